@@ -0,0 +1,10 @@
+// builds the addon with cargo and puts it next to index.js as freqs.node
+const { execFileSync } = require("child_process");
+const fs = require("fs");
+const path = require("path");
+
+execFileSync("cargo", ["build", "--release", "--manifest-path", path.join(__dirname, "Cargo.toml")], { stdio: "inherit" });
+const names = { win32: "freqs_node.dll", darwin: "libfreqs_node.dylib" };
+const built = names[process.platform] || "libfreqs_node.so";
+// the workspace puts everything in the target directory at the top
+fs.copyFileSync(path.join(__dirname, "..", "target", "release", built), path.join(__dirname, "freqs.node"));
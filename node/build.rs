@@ -0,0 +1,9 @@
+use std::env;
+
+// macos wants to be told the napi_* symbols will be there when loaded
+fn main() {
+    if env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "macos") {
+        println!("cargo:rustc-cdylib-link-arg=-undefined");
+        println!("cargo:rustc-cdylib-link-arg=dynamic_lookup");
+    }
+}
@@ -0,0 +1,9 @@
+export interface Analysis {
+  size: number;
+  counts: number[];
+  entropy: number;
+  printable: number;
+  distinct: number;
+}
+
+export function analyze(input: string | Uint8Array): Analysis;
@@ -0,0 +1,6 @@
+// analyze(pathOrBuffer) counts every byte value of a file, or of a Buffer
+// or Uint8Array, and returns { size, counts, entropy, printable, distinct }.
+// counts has 256 entries, entropy is in bits per byte, printable is the
+// share of printable ascii and whitespace. it runs synchronously, like
+// fs.readFileSync does.
+module.exports = require("./freqs.node");
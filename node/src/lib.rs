@@ -0,0 +1,157 @@
+/*
+    The freqs engine as a node addon, for programs (electron guis and the
+    like) that would otherwise spawn freqs for every file. It's written
+    against the plain N-API C interface, which node has had stable since
+    version 10, so it needs nothing but cargo to build and keeps working
+    across node versions. The counting is freqs' own modules, shared with
+    the command line tool.
+*/
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+#[allow(dead_code)]
+#[path = "../../src/entropy.rs"]
+mod entropy;
+#[allow(dead_code)]
+#[path = "../../src/stats.rs"]
+mod stats;
+
+const CHUNKSIZE: usize = 1024 * 128;
+
+// the few parts of node_api.h needed
+type Env = *mut c_void;
+type Value = *mut c_void;
+type CallbackInfo = *mut c_void;
+type Callback = Option<unsafe extern "C" fn(Env, CallbackInfo) -> Value>;
+type Status = c_int;
+
+const OK: Status = 0;
+const STRING: c_int = 4;    // napi_string, from napi_valuetype
+
+extern "C" {
+    fn napi_create_function(env: Env, name: *const c_char, len: usize, cb: Callback, data: *mut c_void, result: *mut Value) -> Status;
+    fn napi_get_cb_info(env: Env, info: CallbackInfo, argc: *mut usize, argv: *mut Value, this: *mut Value, data: *mut *mut c_void) -> Status;
+    fn napi_typeof(env: Env, value: Value, result: *mut c_int) -> Status;
+    fn napi_is_typedarray(env: Env, value: Value, result: *mut bool) -> Status;
+    fn napi_get_typedarray_info(env: Env, value: Value, kind: *mut c_int, len: *mut usize, data: *mut *mut c_void, buffer: *mut Value, offset: *mut usize) -> Status;
+    fn napi_get_value_string_utf8(env: Env, value: Value, buf: *mut c_char, size: usize, result: *mut usize) -> Status;
+    fn napi_create_object(env: Env, result: *mut Value) -> Status;
+    fn napi_create_array_with_length(env: Env, len: usize, result: *mut Value) -> Status;
+    fn napi_create_double(env: Env, value: f64, result: *mut Value) -> Status;
+    fn napi_set_element(env: Env, object: Value, index: u32, value: Value) -> Status;
+    fn napi_set_named_property(env: Env, object: Value, name: *const c_char, value: Value) -> Status;
+    fn napi_throw_error(env: Env, code: *const c_char, msg: *const c_char) -> Status;
+}
+
+// what node looks for when loading the addon
+#[no_mangle]
+pub unsafe extern "C" fn napi_register_module_v1(env: Env, exports: Value) -> Value {
+    let mut f = ptr::null_mut();
+    let name = b"analyze";
+    napi_create_function(env, name.as_ptr() as *const c_char, name.len(), Some(analyze), ptr::null_mut(), &mut f);
+    set(env, exports, "analyze", f);
+    exports
+}
+
+// analyze(pathOrBuffer)
+unsafe extern "C" fn analyze(env: Env, info: CallbackInfo) -> Value {
+    let mut argc = 1;
+    let mut arg = ptr::null_mut();
+    if napi_get_cb_info(env, info, &mut argc, &mut arg, ptr::null_mut(), ptr::null_mut()) != OK || argc < 1 {
+        return throw(env, "analyze takes a path or a buffer");
+    }
+    let mut counts = [0u64; 256];
+    let len = match input(env, arg) {
+        Some(Input::Path(path)) => match count_file(&path, &mut counts) {
+            Ok(len) => len,
+            Err(e) => return throw(env, &format!("Could not read {}: {}", path, e)),
+        },
+        Some(Input::Bytes(bytes)) => {
+            for b in bytes { counts[*b as usize] += 1; }
+            bytes.len() as u64
+        },
+        None => return throw(env, "analyze takes a path or a buffer"),
+    };
+    result(env, &counts, len)
+}
+
+enum Input<'a> {
+    Path(String),
+    Bytes(&'a [u8]),
+}
+
+// a string is a path, a buffer or any typed array is counted as it is
+unsafe fn input<'a>(env: Env, value: Value) -> Option<Input<'a>> {
+    let mut kind = 0;
+    if napi_typeof(env, value, &mut kind) != OK { return None; }
+    if kind == STRING {
+        let mut len = 0;
+        napi_get_value_string_utf8(env, value, ptr::null_mut(), 0, &mut len);
+        let mut buf = vec![0u8; len + 1];
+        napi_get_value_string_utf8(env, value, buf.as_mut_ptr() as *mut c_char, buf.len(), &mut len);
+        buf.truncate(len);
+        return String::from_utf8(buf).ok().map(Input::Path);
+    }
+    let mut typed = false;
+    if napi_is_typedarray(env, value, &mut typed) != OK || !typed { return None; }
+    let (mut kind, mut len, mut data, mut offset) = (0, 0, ptr::null_mut(), 0);
+    if napi_get_typedarray_info(env, value, &mut kind, &mut len, &mut data, ptr::null_mut(), &mut offset) != OK { return None; }
+    // len is in elements, which are 1 to 8 bytes wide
+    let width = match kind { 0..=2 => 1, 3 | 4 => 2, 5..=7 => 4, _ => 8 };
+    if data.is_null() || len == 0 { return Some(Input::Bytes(&[])); }
+    Some(Input::Bytes(std::slice::from_raw_parts(data as *const u8, len * width)))
+}
+
+fn count_file(path: &str, counts: &mut [u64; 256]) -> io::Result<u64> {
+    let mut f = File::open(path)?;
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    let mut len = 0;
+    loop {
+        let n = f.read(&mut chunk)?;
+        if n == 0 { break; }
+        for b in &chunk[..n] { counts[*b as usize] += 1; }
+        len += n as u64;
+    }
+    Ok(len)
+}
+
+// { size, counts, entropy, printable, distinct }
+unsafe fn result(env: Env, counts: &[u64; 256], len: u64) -> Value {
+    let mut object = ptr::null_mut();
+    napi_create_object(env, &mut object);
+    let mut array = ptr::null_mut();
+    napi_create_array_with_length(env, 256, &mut array);
+    for (byte, c) in counts.iter().enumerate() {
+        napi_set_element(env, array, byte as u32, number(env, *c as f64));
+    }
+    set(env, object, "size", number(env, len as f64));
+    set(env, object, "counts", array);
+    set(env, object, "entropy", number(env, entropy::shannon(counts, len)));
+    set(env, object, "printable", number(env, stats::printable_ratio(counts)));
+    set(env, object, "distinct", number(env, stats::distinct(counts) as f64));
+    object
+}
+
+unsafe fn set(env: Env, object: Value, name: &str, value: Value) {
+    let name = CString::new(name).unwrap();
+    napi_set_named_property(env, object, name.as_ptr(), value);
+}
+
+unsafe fn number(env: Env, value: f64) -> Value {
+    let mut v = ptr::null_mut();
+    napi_create_double(env, value, &mut v);
+    v
+}
+
+// throws a js Error, the return value is ignored by node then
+unsafe fn throw(env: Env, message: &str) -> Value {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    napi_throw_error(env, ptr::null(), message.as_ptr());
+    ptr::null_mut()
+}
@@ -0,0 +1,82 @@
+/*
+    --list-ads: the alternate data streams of files on NTFS, one summary
+    each, named file:stream the way Windows opens them. Streams are where
+    the browser's Zone.Identifier goes, and also somewhere to hide a
+    payload that dir and Explorer don't show, so a stream with a lot in it
+    or packed-looking contents is worth a closer look. Any stream can be
+    analyzed on its own the same way, file.txt:name as the input. The
+    file's own contents, the unnamed stream, aren't listed.
+*/
+
+use std::io;
+
+use analysis;
+use args::Options;
+use log;
+use report::{self, Format};
+use walk;
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let mut found = Vec::new();
+    for path in walk::expand(&opts.inputs, opts) {
+        match streams(&path) {
+            Ok(names) => found.extend(names.into_iter().map(|name| format!("{}:{}", path, name))),
+            Err(e) => log::warn(&format!("skipping {}: {}", path, e)),
+        }
+    }
+    report::render_summaries(format, &analysis::analyze_files(&found, opts), &[])
+}
+
+// the names of the streams besides the unnamed one, as FindFirstStreamW
+// lists them (:Zone.Identifier:$DATA) without the colons and the type
+#[cfg(windows)]
+fn streams(path: &str) -> io::Result<Vec<String>> {
+    use std::ffi::OsStr;
+    use std::os::raw::c_void;
+    use std::os::windows::ffi::OsStrExt;
+
+    const FIND_STREAM_INFO_STANDARD: u32 = 0;
+    const ERROR_HANDLE_EOF: i32 = 38;
+    const MAX_PATH: usize = 260;
+
+    #[repr(C)]
+    struct FindStreamData {
+        size: i64,
+        name: [u16; MAX_PATH + 36],
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn FindFirstStreamW(path: *const u16, level: u32, data: *mut FindStreamData, flags: u32) -> *mut c_void;
+        fn FindNextStreamW(find: *mut c_void, data: *mut FindStreamData) -> i32;
+        fn FindClose(find: *mut c_void) -> i32;
+    }
+
+    let wide: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
+    let mut data = FindStreamData { size: 0, name: [0; MAX_PATH + 36] };
+    // safe: wide ends in a nul, and data is laid out as WIN32_FIND_STREAM_DATA
+    let find = unsafe { FindFirstStreamW(wide.as_ptr(), FIND_STREAM_INFO_STANDARD, &mut data, 0) };
+    // INVALID_HANDLE_VALUE. no streams at all comes out the same way
+    if find as isize == -1 {
+        let e = io::Error::last_os_error();
+        return if e.raw_os_error() == Some(ERROR_HANDLE_EOF) { Ok(Vec::new()) } else { Err(e) };
+    }
+    let mut names = Vec::new();
+    loop {
+        let len = data.name.iter().position(|c| *c == 0).unwrap_or(data.name.len());
+        let full = String::from_utf16_lossy(&data.name[..len]);
+        let name = full.strip_prefix(':').unwrap_or(&full);
+        // the unnamed one is ::$DATA
+        let name = name.strip_suffix(":$DATA").unwrap_or(name);
+        if !name.is_empty() { names.push(name.to_string()); }
+        if unsafe { FindNextStreamW(find, &mut data) } == 0 { break; }
+    }
+    unsafe { FindClose(find); }
+    Ok(names)
+}
+
+// checked for before getting here
+#[cfg(not(windows))]
+fn streams(_path: &str) -> io::Result<Vec<String>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "alternate data streams are only on Windows"))
+}
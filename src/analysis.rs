@@ -0,0 +1,564 @@
+/*
+    The counting pass itself. Reads a file through a BufReader one chunk at
+    a time, so it works for files of arbitrary size, and hands each chunk to
+    whatever else was asked for along the way (entropy map, profile...).
+*/
+
+use std::cell::Cell;
+use std::fs;
+use std::io::{self, BufReader, SeekFrom, stderr, Write};
+use std::io::prelude::*;
+use std::mem;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use args::Options;
+use cache::Key;
+use decode::Decoder;
+use decompress;
+use digest::{self, Hasher};
+use disk;
+use dispersion::DispersionTracker;
+use dump;
+use entropy::{BlockEntropy, Point, SlidingEntropy};
+use heatmap::{self, Heatmap, HeatmapTracker};
+use hilbert;
+use history;
+use log;
+use regions::{Region, Regions};
+use remote::{self, Remote};
+use resources;
+use rare::{self, Rare, RareTracker};
+use rate::{Rate, RateTracker};
+use runs::{self, RunTracker, Runs};
+use segments::{self, Segment, Segmenter};
+use serial::{self, Port};
+use windows;
+
+const CHUNKSIZE: usize = 1024 * 128;
+// how much of the start of the file is kept, for format signatures
+pub const HEAD_LEN: usize = 512;
+
+pub struct Analysis {
+    pub len: u64,                       // bytes actually counted
+    pub counts: [u64; 256],             // byte occurences
+    pub head: Vec<u8>,                  // first HEAD_LEN bytes
+    pub blocks: Option<Vec<f64>>,       // normalized block entropies for the map
+    pub heatmap: Option<Heatmap>,       // byte counts by block, for --heatmap
+    pub profile: Option<Vec<Point>>,    // entropy profile samples
+    pub regions: Option<Vec<Region>>,   // per block mini reports
+    pub segments: Option<Vec<Segment>>, // where the distribution changes
+    pub runs: Option<Runs>,             // longest runs and padding
+    pub first_last: Option<Vec<(u64, u64)>>, // offsets each byte value is first and last seen at
+    pub dispersion: Option<Vec<Option<f64>>>, // how evenly each byte value is spread
+    pub located: Option<Vec<u64>>,      // offsets of the byte locate is after
+    pub rare: Option<Rare>,             // the rare bytes and what's around them
+    pub rate: Option<Rate>,             // how fast it all came in
+    pub truncated: bool,                // stopped early by --max-bytes or --timeout
+    pub hashes: Vec<(&'static str, String)>, // digests of what was counted, in hex
+}
+
+// where the bytes come from
+enum Input {
+    File(fs::File),
+    Remote(Remote),
+    Serial(Port),
+    Stdin { tee: bool },    // with --tee, passed on to stdout as read
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Input::File(ref mut f) => f.read(buf),
+            Input::Remote(ref mut r) => r.read(buf),
+            Input::Serial(ref mut p) => p.read(buf),
+            Input::Stdin { tee } => {
+                let n = io::stdin().read(buf)?;
+                if tee { io::stdout().write_all(&buf[..n])?; }
+                Ok(n)
+            },
+        }
+    }
+}
+
+// only files seek, streams are skipped through instead
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match *self {
+            Input::File(ref mut f) => f.seek(pos),
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported, "can't seek in a stream")),
+        }
+    }
+}
+
+// the length and counts of every analysis since watch asked to keep them,
+// for its --metrics, so the files aren't counted a second time
+type Seen = Vec<(String, u64, [u64; 256])>;
+static SEEN: Mutex<Option<Seen>> = Mutex::new(None);
+
+pub fn keep_seen() {
+    *SEEN.lock().unwrap() = Some(Vec::new());
+}
+
+pub fn take_seen() -> Seen {
+    SEEN.lock().unwrap().as_mut().map(mem::take).unwrap_or_default()
+}
+
+fn seen(path: &str, a: &Analysis) {
+    if let Some(ref mut seen) = *SEEN.lock().unwrap() { seen.push((path.to_string(), a.len, a.counts)); }
+}
+
+// one per file in multi-file mode
+pub struct Summary {
+    pub path: String,
+    pub analysis: Analysis,
+}
+
+pub fn analyze(path: &str, opts: &Options, progress: bool) -> io::Result<Analysis> {
+    // files that haven't changed since they were cached aren't read again
+    let key = opts.cache_dir.as_ref().and_then(|_| Key::new(path, opts));
+    if let (Some(k), Some(dir)) = (&key, &opts.cache_dir) {
+        if let Some(a) = k.load(dir) {
+            log::info(&format!("{}: from the cache", path));
+            resources::file(true);
+            seen(path, &a);
+            return Ok(a);
+        }
+    }
+    let analysis = count(path, opts, progress)?;
+    if let (Some(k), Some(dir)) = (&key, &opts.cache_dir) { k.store(dir, &analysis); }
+    seen(path, &analysis);
+    Ok(analysis)
+}
+
+fn count(path: &str, opts: &Options, progress: bool) -> io::Result<Analysis> {
+    let progress = progress && log::progress();
+    // fifos, character devices and downloads can't seek, so they're read
+    // straight through. only downloads might say how long they are
+    let spans = spans(opts);
+    let (target, size, stream, from) = if path == "-" {
+        (Input::Stdin { tee: opts.tee }, None, true, 0)
+    } else if opts.serial.as_deref() == Some(path) {
+        // the port has to know when to stop, it blocks on a quiet line
+        let deadline = opts.timeout.map(|s| Instant::now() + Duration::from_secs(s));
+        (Input::Serial(serial::open(path, opts.baud, deadline)?), None, true, 0)
+    } else if remote::is_url(path) {
+        // only what's counted is downloaded, unless it has to be decoded
+        // first. the gaps between several ranges still come down
+        let (first, last) = (spans[0].0, spans[spans.len() - 1].1);
+        let whole = first == 0 && last == u64::MAX;
+        let range = if whole || opts.decompress.is_some() || opts.decode.is_some() { None } else { Some((first, last)) };
+        let r = remote::open(path, range)?;
+        let (length, start) = (r.length, r.start);
+        (Input::Remote(r), length, true, start)
+    } else {
+        let mut f = fs::File::open(windows::long_path(Path::new(path)))?;
+        let meta = f.metadata()?;
+        // block devices seek like files
+        let size = disk::len(&mut f, &meta)?;
+        (Input::File(f), size, size.is_none(), 0)
+    };
+    let mut file_len = size.unwrap_or(0);
+    resources::file(false);
+    match size {
+        Some(size) if !stream => log::info(&format!("{}: opened, {} bytes, seeking to what's counted", path, size)),
+        Some(size) => log::info(&format!("{}: opened as a stream of {} bytes", path, size)),
+        None => log::info(&format!("{}: opened as a stream, read straight through", path)),
+    }
+
+    // set up bufreader, chunks, and byte occurence counts
+    let mut reader = BufReader::with_capacity(CHUNKSIZE, target);
+    let method = match opts.decompress {
+        // only peeked at, so it's still there to be decompressed
+        Some(ref name) => decompress::pick(name, reader.fill_buf()?),
+        None => None,
+    };
+    match method {
+        Some(m) => log::info(&format!("{}: decompressing as {}", path, m.name())),
+        None if opts.decompress.is_some() => log::info(&format!("{}: not compressed, counted as it is", path)),
+        None => {},
+    }
+    if let Some(ref name) = opts.decode { log::info(&format!("{}: decoding as {}", path, name)); }
+
+    // the parts of the input to count. a plain file can seek straight to
+    // them, decoded contents have to be read through and clipped
+    let plain = method.is_none() && opts.decode.is_none();
+    let mut slice = if plain { None } else { Some(Slice { spans: spans.clone(), at: 0 }) };
+    if plain {
+        file_len = spans.iter().map(|&(start, end)| end.min(file_len).saturating_sub(start)).sum();
+    }
+
+    // the size of decompressed input or of most streams isn't known up front
+    let chunks_total = if method.is_none() && size.is_some() { Some(file_len.div_ceil(CHUNKSIZE as u64) as u32) } else { None };
+    let mut chunks_done: u32 = 0;
+    let mut byte_occurences = [0u64; 256];
+    let mut len = 0u64;
+    let mut head = Vec::with_capacity(HEAD_LEN);
+
+    // per block entropies, only tracked if an entropy map was asked for.
+    // block sizes picked from the length go by the compressed length for
+    // compressed input, which only makes for a bigger image or more blocks
+    let mut blocks = opts.hilbert_path.as_ref().map(|_| {
+        let size = opts.hilbert_block.unwrap_or_else(|| hilbert::block_size_for(file_len));
+        BlockEntropy::new(size.max(1) as usize)
+    });
+
+    // byte values by position, only tracked if a heatmap was asked for,
+    // with the block size picked the same way
+    let mut heat = opts.heatmap_path.as_ref().map(|_| {
+        let size = opts.heatmap_block.unwrap_or_else(|| heatmap::block_size_for(file_len));
+        HeatmapTracker::new(size.max(1), opts.heatmap_buckets.unwrap_or(256))
+    });
+
+    // entropy profile, only tracked if a window was given
+    let mut profile = opts.window.map(|w| SlidingEntropy::new(w, opts.step.unwrap_or(w)));
+
+    // block-wise reports, only tracked if a block size was given
+    let mut regions = opts.block_size.map(|size| if opts.content_defined { Regions::content_defined(size) } else { Regions::new(size) });
+
+    // change-point segmentation, only tracked if asked for
+    let mut segmenter = if opts.segments {
+        let threshold = opts.segment_threshold.unwrap_or(segments::DEFAULT_THRESHOLD);
+        Some(Segmenter::new(segments::block_size_for(file_len), threshold))
+    } else { None };
+
+    // runs of the same byte, only tracked if asked for
+    let mut runs = if opts.runs {
+        Some(RunTracker::new(opts.min_padding.unwrap_or(runs::DEFAULT_MIN_PADDING)))
+    } else { None };
+
+    // where each byte value turns up first and last, only tracked if asked
+    // for. none yet is u64::MAX
+    let mut first_last = if opts.first_last { Some(vec![(u64::MAX, 0u64); 256]) } else { None };
+
+    // the gaps between occurrences of each byte value, only tracked if
+    // asked for
+    let mut dispersion = if opts.dispersion { Some(DispersionTracker::new()) } else { None };
+
+    // rare bytes in context, only tracked if asked for
+    let mut rare = opts.context.map(|n| RareTracker::new(n, opts.rare_below.unwrap_or(rare::DEFAULT_BELOW)));
+
+    // bytes and entropy over time, only tracked if asked for
+    let mut rate = opts.rate.map(RateTracker::new);
+
+    // where locate's byte turns up, up to its limit
+    let mut located = opts.locate.map(|(byte, limit)| (byte, limit, Vec::new()));
+
+    // --max-bytes and --timeout stop the count early. the reading loop
+    // checks this too, so a plain file isn't read any further
+    let truncated = Cell::new(false);
+    let deadline = opts.timeout.map(|s| Instant::now() + Duration::from_secs(s));
+
+    // digests over the same chunks, rather than another read
+    let mut hashers: Vec<Hasher> = hashes_wanted(opts).iter().filter_map(|n| Hasher::new(n)).collect();
+
+    // text decoding, after any decompression
+    let mut decoder = opts.decode.as_ref().map(|name| Decoder::new(name));
+
+    {
+        let mut count = |chunk: &[u8]| {
+            let pieces = match slice {
+                Some(ref mut s) => s.clip(chunk),
+                None => vec![chunk],
+            };
+            for chunk in pieces {
+                if truncated.get() { break; }
+                let chunk = match opts.max_bytes {
+                    Some(max) if len + chunk.len() as u64 > max => {
+                        truncated.set(true);
+                        &chunk[..(max - len) as usize]
+                    },
+                    _ => chunk,
+                };
+                if deadline.is_some_and(|d| Instant::now() >= d) { truncated.set(true); }
+
+                // count occurences of each byte in chunk
+                for byte in chunk.iter() { byte_occurences[*byte as usize] += 1; }
+                if head.len() < HEAD_LEN {
+                    let take = (HEAD_LEN - head.len()).min(chunk.len());
+                    head.extend_from_slice(&chunk[..take]);
+                }
+                if let Some(ref mut b) = blocks { b.feed(chunk); }
+                if let Some(ref mut h) = heat { h.feed(chunk); }
+                if let Some(ref mut p) = profile { p.feed(chunk); }
+                if let Some(ref mut r) = regions { r.feed(chunk); }
+                if let Some(ref mut s) = segmenter { s.feed(chunk); }
+                if let Some(ref mut r) = runs { r.feed(chunk); }
+                if let Some(ref mut seen) = first_last {
+                    for (i, byte) in chunk.iter().enumerate() {
+                        let at = len + i as u64;
+                        let s = &mut seen[*byte as usize];
+                        if s.0 == u64::MAX { s.0 = at; }
+                        s.1 = at;
+                    }
+                }
+                if let Some(ref mut d) = dispersion { d.feed(chunk); }
+                if let Some(ref mut r) = rare { r.feed(chunk); }
+                if let Some(ref mut r) = rate { r.feed(chunk); }
+                if let Some((want, limit, ref mut found)) = located {
+                    for (i, _) in chunk.iter().enumerate().filter(|(_, b)| **b == want).take(limit - found.len()) {
+                        found.push(len + i as u64);
+                    }
+                }
+                for h in hashers.iter_mut() { h.update(chunk); }
+                len += chunk.len() as u64;
+                resources::chunk(chunk.len());
+                if dump::requested() { dump::write(opts, &byte_occurences, len); }
+
+                // update and display progress
+                chunks_done += 1;
+                if progress {
+                    match chunks_total {
+                        Some(total) => eprint!("\rprocessed chunk {} / {}", chunks_done, total),
+                        None => eprint!("\rprocessed chunk {}", chunks_done),
+                    }
+                    let _ = stderr().flush();
+                    log::drawn(true);
+                }
+            }
+        };
+
+        let mut feed = |chunk: &[u8]| match decoder {
+            Some(ref mut d) => d.feed(chunk, &mut count),
+            None => count(chunk),
+        };
+
+        match method {
+            Some(m) => decompress::stream(m, &mut reader, &mut feed)?,
+            // break each span of the file into chunks
+            None => {
+                let mut at = from;  // where a stream has been read up to
+                for &(start, end) in &spans {
+                    if truncated.get() { break; }
+                    if stream {
+                        // skipped by reading, the spans are in order
+                        if start > at { log::debug(&format!("{}: reading past {} bytes to {:#x}", path, start - at, start)); }
+                        at += io::copy(&mut (&mut reader).take(start - at), &mut io::sink())?;
+                        if at < start { break; }
+                    } else {
+                        if start > 0 { log::debug(&format!("{}: seeking to {:#x}", path, start)); }
+                        reader.seek(SeekFrom::Start(start))?;
+                    }
+                    let mut part = (&mut reader).take(end - start);
+                    while !truncated.get() {
+                        // process next chunk if any
+                        // a download that fails halfway shows up here
+                        let length_of_chunk = {
+                            let chunk = part.fill_buf()?;
+                            if !chunk.is_empty() { feed(chunk); }
+                            // return length of chunk done
+                            chunk.len()
+                        };
+
+                        // we're done with this chunk
+                        if length_of_chunk == 0 { break; } else { part.consume(length_of_chunk); }
+                        at += length_of_chunk as u64;
+                    }
+                }
+            },
+        }
+        if let Some(d) = decoder.take() { d.finish(&mut count)?; }
+    }
+    // what wasn't counted still goes through, for the rest of the pipeline
+    if opts.tee { io::copy(&mut reader, &mut io::sink())?; }
+
+    if progress {
+        eprintln!("\ndone!");
+        log::drawn(false);
+    }
+    if truncated.get() { log::debug(&format!("{}: stopped early after {} bytes", path, len)); }
+
+    // offsets in the reports are from the start of the input, not the
+    // slice. with several ranges they're into the ranges put end to end
+    let base = if spans.len() == 1 { spans[0].0 } else { 0 };
+    let mut profile = profile.map(|p| p.finish());
+    let mut regions = regions.map(|r| r.finish());
+    let mut segments = segmenter.map(|s| s.finish());
+    let mut runs = runs.map(|r| r.finish());
+    let mut heat = heat.map(|h| h.finish());
+    if let Some(ref mut h) = heat { h.start = base; }
+    for p in profile.iter_mut().flatten() { p.offset += base; }
+    for r in regions.iter_mut().flatten() { r.offset += base; }
+    for s in segments.iter_mut().flatten() { s.offset += base; }
+    for r in runs.iter_mut().flat_map(|r| r.longest.iter_mut()) { r.1 += base; }
+    let mut rare = rare.map(|r| r.finish(&byte_occurences));
+    for s in rare.iter_mut().flat_map(|r| r.samples.iter_mut()) { s.offset += base; }
+    let mut rate = rate.map(|r| r.finish());
+    for i in rate.iter_mut().flat_map(|r| r.intervals.iter_mut()) { i.offset += base; }
+    let mut located = located.map(|(_, _, found)| found);
+    for o in located.iter_mut().flatten() { *o += base; }
+    for s in first_last.iter_mut().flatten().filter(|s| s.0 != u64::MAX) {
+        s.0 += base;
+        s.1 += base;
+    }
+
+    Ok(Analysis {
+        len,
+        counts: byte_occurences,
+        head,
+        blocks: blocks.map(|b| b.finish()),
+        heatmap: heat,
+        profile,
+        regions,
+        segments,
+        runs,
+        first_last,
+        dispersion: dispersion.map(|d| d.finish()),
+        located,
+        rare,
+        rate,
+        truncated: truncated.get(),
+        hashes: hashers.into_iter().map(|h| (h.name(), h.finish())).collect(),
+    })
+}
+
+// the --hash digests, and sha256 for runs recorded in the history
+pub fn hashes_wanted(opts: &Options) -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = digest::NAMES.iter().cloned().filter(|n| opts.hashes.iter().any(|h| h == n)).collect();
+    if history::store(opts).is_some() && !names.contains(&"sha256") { names.insert(0, "sha256"); }
+    names
+}
+
+// the stretches of the input to count, as start and end offsets, sorted
+// and with overlaps merged so nothing gets counted twice
+pub fn spans(opts: &Options) -> Vec<(u64, u64)> {
+    if opts.ranges.is_empty() {
+        let start = opts.offset.unwrap_or(0);
+        return vec![(start, opts.length.map_or(u64::MAX, |l| start.saturating_add(l)))];
+    }
+    let mut ranges = opts.ranges.clone();
+    ranges.sort();
+    let mut spans: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges {
+        match spans.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => spans.push((start, end)),
+        }
+    }
+    spans
+}
+
+// picks the spans out of a stream as it goes by
+struct Slice {
+    spans: Vec<(u64, u64)>,
+    at: u64,        // offset of the next byte in the stream
+}
+
+impl Slice {
+    fn clip<'a>(&mut self, chunk: &'a [u8]) -> Vec<&'a [u8]> {
+        let (chunk_start, chunk_end) = (self.at, self.at + chunk.len() as u64);
+        self.at = chunk_end;
+        self.spans.iter()
+            .filter_map(|&(start, end)| {
+                let (from, to) = (start.max(chunk_start), end.min(chunk_end));
+                if from < to { Some(&chunk[(from - chunk_start) as usize..(to - chunk_start) as usize]) } else { None }
+            })
+            .collect()
+    }
+}
+
+// one summary per range of each file, for --per-range. files that can't be
+// read are skipped with a note, as in a batch.
+pub fn analyze_ranges(paths: &[String], opts: &Options) -> Vec<Summary> {
+    let mut summaries = Vec::new();
+    for path in paths {
+        for &(start, end) in &opts.ranges {
+            let one = Options { offset: Some(start), length: Some(end - start), ranges: Vec::new(), ..opts.clone() };
+            match analyze(path, &one, false) {
+                Ok(analysis) => summaries.push(Summary { path: format!("{}@{:#x}-{:#x}", path, start, end), analysis }),
+                Err(e) => log::warn(&format!("skipping {}: {}", path, e)),
+            }
+        }
+    }
+    summaries
+}
+
+// analyzes a batch of files, showing progress over the whole batch. files
+// that can't be read are skipped (with a note) rather than ending the run.
+pub fn analyze_files(paths: &[String], opts: &Options) -> Vec<Summary> {
+    batch(paths, opts, |a| a).into_iter()
+        .map(|(path, analysis)| Summary { path, analysis })
+        .collect()
+}
+
+// the same, spread over a thread per core, keeping only what map makes of
+// each analysis so big batches don't have to hold on to every result.
+// results come back in the order of paths.
+pub fn batch<T, F>(paths: &[String], opts: &Options, map: F) -> Vec<(String, T)>
+    where T: Send, F: Fn(Analysis) -> T + Sync
+{
+    let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(paths.len()).max(1);
+    let next = AtomicUsize::new(0);
+    resources::threads(workers);
+    log::info(&format!("{} files, {} at a time", paths.len(), workers));
+    let progress = log::progress();
+    let mut results: Vec<Option<T>> = (0..paths.len()).map(|_| None).collect();
+    // progress goes by the sizes up front, streams and all count as nothing
+    let sizes: Vec<u64> = paths.iter().map(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(0)).collect();
+    let total: u64 = sizes.iter().sum();
+
+    thread::scope(|scope| {
+        // each file is sent once when it's started, and again with its result
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let (tx, next, map) = (tx.clone(), &next, &map);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= paths.len() { break; }
+                if tx.send((i, None)).is_err() { break; }
+                if tx.send((i, Some(analyze(&paths[i], opts, false).map(map)))).is_err() { break; }
+            });
+        }
+        drop(tx);
+
+        let (mut files_done, mut bytes_done, mut shown) = (0, 0, 0usize);
+        for (i, result) in rx.iter() {
+            match result {
+                // just started, shown as the current file
+                None => {},
+                Some(result) => {
+                    files_done += 1;
+                    bytes_done += sizes[i];
+                    match result {
+                        Ok(t) => results[i] = Some(t),
+                        Err(e) => {
+                            log::warn(&format!("skipping {}: {}", paths[i], e));
+                            shown = 0;
+                        },
+                    }
+                },
+            }
+            if !progress { continue; }
+            let line = format!(
+                "analyzed file {} / {}, {} / {}, {}",
+                files_done, paths.len(), size_text(bytes_done), size_text(total), paths[i]
+            );
+            // blanked out past the end, the last line may have been longer
+            let length = line.chars().count();
+            eprint!("\r{}{}", line, " ".repeat(shown.saturating_sub(length)));
+            shown = length;
+            let _ = stderr().flush();
+            log::drawn(true);
+        }
+    });
+    if progress {
+        eprintln!("\ndone!");
+        log::drawn(false);
+    }
+
+    paths.iter().cloned().zip(results)
+        .filter_map(|(path, t)| t.map(|t| (path, t)))
+        .collect()
+}
+
+// a size for people, 1.5M rather than 1572864
+fn size_text(n: u64) -> String {
+    let units = [("G", 1u64 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+    match units.iter().find(|(_, size)| n >= *size) {
+        Some((unit, size)) => format!("{:.1}{}", n as f64 / *size as f64, unit),
+        None => n.to_string(),
+    }
+}
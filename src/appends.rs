@@ -0,0 +1,55 @@
+/*
+    Runs added to the same -o file one after another, with --append. Every
+    appended run starts with a marker line, the first one too, saying when
+    it was made and with which arguments, so the runs can still be told
+    apart; freqs report split takes the file apart again into one file per
+    run.
+*/
+
+use std::fs;
+
+use args::Options;
+use history;
+use outfile;
+use snapshot;
+
+const MARKER: &str = "=== freqs run";
+
+pub fn marker(args: &[String]) -> String {
+    format!("{} {}: freqs {} ===", MARKER, history::timestamp(history::now()), args[1..].join(" "))
+}
+
+fn is_marker(line: &str) -> bool {
+    line.starts_with(MARKER) && line.ends_with(" ===")
+}
+
+// freqs report split <file>: out.txt becomes out.1.txt, out.2.txt...
+pub fn split(opts: &Options) -> Vec<String> {
+    let path = &opts.inputs[1];
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+
+    // whatever comes before the first marker was there before --append
+    let mut runs: Vec<(Option<&str>, Vec<&str>)> = vec![(None, Vec::new())];
+    for line in text.lines() {
+        if is_marker(line) {
+            runs.push((Some(line), Vec::new()));
+        } else {
+            runs.last_mut().unwrap().1.push(line);
+        }
+    }
+    if runs[0].1.is_empty() { runs.remove(0); }
+
+    let mut lines = vec![String::from("")];
+    if runs.len() < 2 {
+        lines.push(format!("  {} has a single run, nothing to split", path));
+        return lines;
+    }
+    for (n, (marker, run)) in runs.iter().enumerate() {
+        let out = snapshot::numbered(path, n as u32 + 1);
+        let body: String = run.iter().map(|l| format!("{}\n", l)).collect();
+        if let Err(e) = outfile::write(&out, body.as_bytes(), opts.force) { panic!("Could not write {}: {}", out, e); }
+        let about = marker.map_or(String::from("from before --append"), |m| m[MARKER.len()..m.len() - 4].trim().to_string());
+        lines.push(format!("  {}: {}", out, about));
+    }
+    lines
+}
@@ -0,0 +1,274 @@
+/*
+    --archive-entries: for zip and tar archives, one summary per member
+    rather than for the archive as a whole. Members are streamed straight
+    from the archive (deflated zip entries through the inflate module), so
+    nothing gets extracted to disk. Entries are named archive:member.
+*/
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+use analysis::{Analysis, Summary, HEAD_LEN};
+use args::Options;
+use inflate;
+use log;
+use report::{self, Format};
+use walk;
+
+const CHUNKSIZE: usize = 1024 * 128;
+// the end of central directory record is 22 bytes, plus a comment of up to 64K
+const EOCD_SEARCH: u64 = 22 + 0xffff;
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let mut summaries = Vec::new();
+    for path in walk::expand(&opts.inputs, opts) {
+        match entries(&path) {
+            Ok(Some(mut found)) => summaries.append(&mut found),
+            Ok(None) => log::warn(&format!("skipping {}: not a zip or tar archive", path)),
+            Err(e) => log::warn(&format!("skipping {}: {}", path, e)),
+        }
+    }
+    report::render_summaries(format, &summaries, &[])
+}
+
+// one summary per member of the archive at path, or None if it isn't one
+pub fn entries(path: &str) -> io::Result<Option<Vec<Summary>>> {
+    let mut f = fs::File::open(path)?;
+    let mut head = [0u8; 512];
+    let filled = read_full(&mut f, &mut head)?;
+    f.seek(SeekFrom::Start(0))?;
+
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        zip(path, f).map(Some)
+    } else if filled == 512 && tar_checksum_ok(&head) {
+        tar(path, BufReader::with_capacity(CHUNKSIZE, f)).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+// byte counts for one member as it streams past
+struct Tally {
+    len: u64,
+    counts: [u64; 256],
+    head: Vec<u8>,
+}
+
+impl Tally {
+    fn new() -> Tally { Tally { len: 0, counts: [0u64; 256], head: Vec::new() } }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        for byte in chunk { self.counts[*byte as usize] += 1; }
+        if self.head.len() < HEAD_LEN {
+            let take = (HEAD_LEN - self.head.len()).min(chunk.len());
+            self.head.extend_from_slice(&chunk[..take]);
+        }
+        self.len += chunk.len() as u64;
+    }
+
+    fn summary(self, archive: &str, name: &str) -> Summary {
+        Summary {
+            path: format!("{}:{}", archive, name),
+            analysis: Analysis {
+                len: self.len,
+                counts: self.counts,
+                head: self.head,
+                blocks: None,
+                heatmap: None,
+                profile: None,
+                regions: None,
+                segments: None,
+                runs: None,
+                first_last: None,
+                dispersion: None,
+                located: None,
+                rare: None,
+                rate: None,
+                truncated: false,
+                hashes: Vec::new(),
+            },
+        }
+    }
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u64)
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u64)
+}
+
+// reads until buf is full or the input ends, returning how much was read
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// passes the next len bytes of input to feed, a chunk at a time
+fn stream<R: BufRead>(input: &mut R, mut len: u64, feed: &mut dyn FnMut(&[u8])) -> io::Result<()> {
+    while len > 0 {
+        let chunk = input.fill_buf()?;
+        if chunk.is_empty() { return Err(bad("archive ends in the middle of a member")); }
+        let take = (chunk.len() as u64).min(len) as usize;
+        feed(&chunk[..take]);
+        input.consume(take);
+        len -= take as u64;
+    }
+    Ok(())
+}
+
+// zips are read through their central directory, which has the real sizes
+// even for entries streamed into the archive with a data descriptor
+fn zip(path: &str, mut f: fs::File) -> io::Result<Vec<Summary>> {
+    let file_len = f.metadata()?.len();
+    let tail_start = file_len.saturating_sub(EOCD_SEARCH);
+    f.seek(SeekFrom::Start(tail_start))?;
+    let mut tail = Vec::new();
+    f.by_ref().read_to_end(&mut tail)?;
+
+    let eocd = (0..tail.len().saturating_sub(21)).rev()
+        .find(|&i| tail[i..].starts_with(b"PK\x05\x06"))
+        .ok_or_else(|| bad("no zip central directory"))?;
+    let (dir_len, dir_at) = match (u32_at(&tail, eocd + 12), u32_at(&tail, eocd + 16)) {
+        (Some(len), Some(at)) => (len, at),
+        _ => return Err(bad("truncated zip central directory")),
+    };
+    if dir_at == 0xffff_ffff { return Err(bad("zip64 archives aren't supported")); }
+
+    f.seek(SeekFrom::Start(dir_at))?;
+    let mut dir = Vec::new();
+    f.by_ref().take(dir_len).read_to_end(&mut dir)?;
+
+    let mut summaries = Vec::new();
+    let mut at = 0;
+    while dir[at..].starts_with(b"PK\x01\x02") {
+        let field = |offset| u16_at(&dir, at + offset).ok_or_else(|| bad("truncated zip central directory"));
+        let (flags, method, name_len) = (field(8)?, field(10)?, field(28)? as usize);
+        let skip = name_len + field(30)? as usize + field(32)? as usize;
+        let compressed = u32_at(&dir, at + 20).unwrap_or(0);
+        let local_at = u32_at(&dir, at + 42).unwrap_or(0);
+        let name = match dir.get(at + 46..at + 46 + name_len) {
+            Some(n) => String::from_utf8_lossy(n).into_owned(),
+            None => return Err(bad("truncated zip central directory")),
+        };
+        at += 46 + skip;
+        if at > dir.len() { return Err(bad("truncated zip central directory")); }
+
+        if name.ends_with('/') { continue; }
+        if flags & 1 != 0 {
+            log::warn(&format!("skipping {}:{}: encrypted", path, name));
+            continue;
+        }
+
+        // the data starts after the local header, whose extra field can
+        // differ from the central directory's
+        f.seek(SeekFrom::Start(local_at))?;
+        let mut local = [0u8; 30];
+        read_full(&mut f, &mut local)?;
+        if !local.starts_with(b"PK\x03\x04") { return Err(bad("bad zip local header")); }
+        let data_at = local_at + 30 + u16_at(&local, 26).unwrap_or(0) + u16_at(&local, 28).unwrap_or(0);
+        f.seek(SeekFrom::Start(data_at))?;
+
+        let mut tally = Tally::new();
+        let mut data = BufReader::with_capacity(CHUNKSIZE, f.by_ref().take(compressed));
+        match method {
+            0 => stream(&mut data, compressed, &mut |chunk| tally.feed(chunk))?,
+            8 => inflate::inflate(&mut data, &mut |chunk| tally.feed(chunk))?,
+            m => {
+                log::warn(&format!("skipping {}:{}: compression method {} isn't supported", path, name, m));
+                continue;
+            },
+        }
+        summaries.push(tally.summary(path, &name));
+    }
+    Ok(summaries)
+}
+
+// old style tars have no magic, but every header carries a checksum
+fn tar_checksum_ok(header: &[u8]) -> bool {
+    let sum: u64 = header.iter().enumerate()
+        .map(|(i, b)| if (148..156).contains(&i) { b' ' as u64 } else { *b as u64 })
+        .sum();
+    octal(&header[148..156]) == Some(sum)
+}
+
+fn octal(field: &[u8]) -> Option<u64> {
+    let text = String::from_utf8_lossy(field);
+    let digits = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(digits, 8).ok()
+}
+
+// sizes too big for octal are stored as big endian binary, flagged by the
+// top bit of the first byte
+fn tar_size(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        Some(field[1..].iter().fold(0u64, |n, b| (n << 8) | *b as u64))
+    } else {
+        octal(field)
+    }
+}
+
+fn tar<R: BufRead>(path: &str, mut input: R) -> io::Result<Vec<Summary>> {
+    let mut summaries = Vec::new();
+    // a name given by a preceding gnu long name or pax header
+    let mut long_name: Option<String> = None;
+    let mut header = [0u8; 512];
+
+    loop {
+        if read_full(&mut input, &mut header)? < 512 || header.iter().all(|b| *b == 0) { break; }
+        if !tar_checksum_ok(&header) { return Err(bad("bad tar header checksum")); }
+        let size = tar_size(&header[124..136]).ok_or_else(|| bad("bad tar member size"))?;
+        let padding = (512 - size % 512) % 512;
+
+        match header[156] {
+            b'0' | b'\0' | b'7' => {
+                let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+                let mut tally = Tally::new();
+                stream(&mut input, size, &mut |chunk| tally.feed(chunk))?;
+                summaries.push(tally.summary(path, &name));
+            },
+            kind @ b'L' | kind @ b'x' => {
+                let mut data = Vec::new();
+                stream(&mut input, size, &mut |chunk| data.extend_from_slice(chunk))?;
+                long_name = if kind == b'L' {
+                    Some(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())
+                } else {
+                    pax_path(&data).or(long_name)
+                };
+            },
+            // directories, links, devices... have nothing to count
+            _ => stream(&mut input, size, &mut |_| ())?,
+        }
+        stream(&mut input, padding, &mut |_| ())?;
+    }
+    Ok(summaries)
+}
+
+fn tar_name(header: &[u8]) -> String {
+    let field = |range: &[u8]| {
+        let end = range.iter().position(|b| *b == 0).unwrap_or(range.len());
+        String::from_utf8_lossy(&range[..end]).into_owned()
+    };
+    let name = field(&header[0..100]);
+    // ustar splits long names into a prefix and the name proper
+    let prefix = if &header[257..262] == b"ustar" { field(&header[345..500]) } else { String::new() };
+    if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+}
+
+// the path record of a pax extended header, made of "<len> <key>=<value>\n"
+fn pax_path(data: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(data).lines()
+        .filter_map(|record| record.split_once(' ').map(|(_, kv)| kv))
+        .find_map(|kv| kv.strip_prefix("path=").map(String::from))
+}
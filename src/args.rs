@@ -0,0 +1,1626 @@
+/*
+    Command line parsing. Everything ends up in an Options; validating the
+    combination of options is left to main.
+*/
+
+// subcommands, recognized only as the first argument
+const COMMANDS: [&str; 28] = ["diff", "check", "merge", "similarity", "cluster", "nearest", "classify", "carve", "watch", "serve", "history", "trend", "report", "pid", "fips", "nist", "random", "samples", "keycheck", "find", "fingerprint", "fuzzy", "calc", "test", "model", "generate", "locate", "repl"];
+
+// how often --snapshot-every reports
+#[derive(Clone, Copy, PartialEq)]
+pub enum Every {
+    Seconds(u64),
+    Bytes(u64),
+}
+
+#[derive(Clone)]
+pub struct Options {
+    pub help: bool,                     // usage
+    pub command: Option<String>,        // subcommand, if any
+    pub inputs: Vec<String>,            // infiles / directories
+    pub out_path: Option<String>,       // outfile
+    pub hilbert_path: Option<String>,   // entropy map image
+    pub hilbert_block: Option<u64>,     // bytes per pixel in the map
+    pub heatmap_path: Option<String>,   // byte values by position, png or csv
+    pub heatmap_block: Option<u64>,     // bytes per row of the heatmap
+    pub heatmap_buckets: Option<usize>, // columns of the heatmap
+    pub format_name: String,            // output format
+    pub window: Option<usize>,          // entropy profile window
+    pub step: Option<usize>,            // entropy profile step
+    pub bars: bool,                     // bar column in the text table
+    pub ci: Option<f64>,                // confidence level of byte intervals, in percent
+    pub save_path: Option<String>,      // saved counts
+    pub baseline: Option<String>,       // saved counts to check against
+    pub max_divergence: Option<f64>,    // allowed drift from the baseline
+    pub significance: Option<f64>,      // p-value randomness tests pass at
+    pub gof_name: String,               // goodness of fit statistic
+    pub samples_name: String,           // what samples writes the file as
+    pub clusters: Option<usize>,        // k for cluster
+    pub top: Option<usize>,             // how many matches nearest shows
+    pub duplicates: bool,               // duplicates report in multi-file mode
+    pub confirm: bool,                  // confirm duplicates by hashing
+    pub git_modified: bool,             // only the files git has as changed
+    pub git_staged: bool,               // or just the staged ones
+    pub respect_gitignore: bool,        // skip what .gitignore leaves out
+    pub hidden: bool,                   // but not hidden files
+    pub max_depth: Option<usize>,       // levels to descend into directories
+    pub follow_symlinks: bool,          // into linked directories too
+    pub same_filesystem: bool,          // without crossing onto another filesystem
+    pub includes: Vec<String>,          // only the files matching one of these
+    pub excludes: Vec<String>,          // and none of these
+    pub triage: bool,                   // triage table instead of a report
+    pub packed_above: Option<f64>,      // triage thresholds, in bits per byte
+    pub encrypted_above: Option<f64>,
+    pub block_size: Option<u64>,        // block-wise reports every this many bytes
+    pub content_defined: bool,          // or about that many, cut by content
+    pub segments: bool,                 // change-point segmentation
+    pub segment_threshold: Option<f64>,
+    pub sections: bool,                 // per section tables for executables
+    pub partitions: bool,               // per partition tables for disks
+    pub stride: Option<usize>,          // per column tables, for records this long
+    pub record_size: Option<usize>,     // the same over whole records, with a layout
+    pub fields: Vec<String>,            // columns of delimited text to count values of
+    pub delimiter: Option<String>,
+    pub payloads: bool,                 // packet payloads of a capture, not the file
+    pub audio: bool,                    // pcm samples of a wav, not its bytes
+    pub pixels: bool,                   // channel values of an image, not its bytes
+    pub per_flow: bool,                 // one table row per flow
+    pub port: Option<u16>,              // only packets to or from this port
+    pub protocol: Option<String>,       // and of this protocol
+    pub archive_entries: bool,          // one summary per archive member
+    pub list_ads: bool,                 // one summary per alternate data stream
+    pub decompress: Option<String>,     // compression to undo before counting
+    pub decode: Option<String>,         // text encoding to undo before counting
+    pub offset: Option<u64>,            // where to start counting
+    pub length: Option<u64>,            // and how much to count
+    pub ranges: Vec<(u64, u64)>,        // or just these, start to end
+    pub per_range: bool,                // a summary per range, not the total
+    pub runs: bool,                     // longest runs and padding
+    pub min_padding: Option<u64>,
+    pub first_last: bool,               // where each byte value turns up first and last
+    pub dispersion: bool,               // how evenly each byte value is spread
+    pub context: Option<usize>,         // bytes either side of the rare ones
+    pub rare_below: Option<u64>,        // and what counts as rare
+    pub limit: Option<usize>,           // how many offsets locate lists
+    pub locate: Option<(u8, usize)>,    // the byte it's after, set by locate itself
+    pub huffman: bool,                  // the optimal prefix code for the counts
+    pub huffman_table: bool,            // and the codes themselves
+    pub min_entropy: Option<f64>,       // carve thresholds
+    pub min_size: Option<u64>,          // which is also the smallest file to walk into
+    pub max_size: Option<u64>,          // and the largest
+    pub sort_by: Option<String>,        // the order of the table of files
+    pub entropy_above: Option<f64>,     // what find is looking for
+    pub like: Option<String>,           // or what it's looking for files like
+    pub fingerprint: bool,              // a short string per file, and nothing else
+    pub fuzzy: bool,                    // or a similarity hash
+    pub normalize: bool,                // calc gives shares, not counts
+    pub order: Option<usize>,           // context length of a markov model
+    pub chars: bool,                    // of utf-8 characters, not bytes
+    pub model_path: Option<String>,     // the model generate draws from
+    pub from: Option<String>,           // or the file to make one of
+    pub match_path: Option<String>,     // or saved counts to match
+    pub sampled: bool,                  // only as far as chance has it
+    pub bytes: Option<u64>,             // how much it generates
+    pub seed: Option<String>,           // and from where, checked to be a number
+    pub extract_dir: Option<String>,    // where carve writes regions out
+    pub follow: bool,                   // keep counting what gets appended
+    pub snapshot_every: Option<Every>,  // intermediate reports while counting a stream
+    pub dump_path: Option<String>,      // where SIGUSR1 writes the counts so far
+    pub listen: Option<String>,         // address serve listens on
+    pub grpc: bool,                     // serve over grpc instead
+    pub root: Option<String>,           // where serve may read files clients name
+    pub metrics_addr: Option<String>,   // where to publish prometheus metrics
+    pub control_path: Option<String>,   // unix socket taking commands
+    pub checkpoint_path: Option<String>, // progress saved every so often
+    pub resume: bool,                   // start from the checkpoint
+    pub max_bytes: Option<u64>,         // stop counting after this many bytes
+    pub timeout: Option<u64>,           // or after this many seconds
+    pub rate: Option<u64>,              // seconds per interval of the rate report
+    pub serial: Option<String>,         // a serial device to listen to, in place of inputs
+    pub baud: Option<u32>,              // and the speed to set it to
+    pub cache_dir: Option<String>,      // where per-file results are kept
+    pub history_path: Option<String>,   // where each run gets recorded
+    pub history_file: Option<String>,   // history only for this file
+    pub since: Option<String>,          // and only runs after this
+    pub previous: bool,                 // diff against the cached result
+    pub tag: Option<String>,            // recorded in the history with each run
+    pub metadata: bool,                 // header with what was analyzed and when
+    pub hashes: Vec<String>,            // digests to compute while counting
+    pub force: bool,                    // replace output files already there
+    pub append: bool,                   // add to the outfile instead
+    pub tee: bool,                      // pass stdin on to stdout while counting
+    pub summary_line: bool,             // one bare line per file
+    pub stats: bool,                    // what the run cost, on stderr
+    pub verbose: u8,                    // -v, more with -vv
+    pub quiet: bool,                    // no warnings or progress
+    pub log_json: bool,                 // messages on stderr as json lines
+}
+
+pub fn parse(args: &[String]) -> Options {
+    let mut opts = Options {
+        help: false,
+        command: None,
+        inputs: Vec::new(),
+        out_path: None,
+        hilbert_path: None,
+        hilbert_block: None,
+        heatmap_path: None,
+        heatmap_block: None,
+        heatmap_buckets: None,
+        format_name: String::from("text"),
+        window: None,
+        step: None,
+        bars: false,
+        ci: None,
+        save_path: None,
+        baseline: None,
+        max_divergence: None,
+        significance: None,
+        gof_name: String::from("chi2"),
+        samples_name: String::from("dieharder"),
+        clusters: None,
+        top: None,
+        duplicates: false,
+        confirm: false,
+        git_modified: false,
+        git_staged: false,
+        respect_gitignore: false,
+        hidden: false,
+        max_depth: None,
+        follow_symlinks: false,
+        same_filesystem: false,
+        includes: Vec::new(),
+        excludes: Vec::new(),
+        triage: false,
+        packed_above: None,
+        encrypted_above: None,
+        block_size: None,
+        content_defined: false,
+        segments: false,
+        segment_threshold: None,
+        sections: false,
+        partitions: false,
+        stride: None,
+        record_size: None,
+        fields: Vec::new(),
+        delimiter: None,
+        payloads: false,
+        audio: false,
+        pixels: false,
+        per_flow: false,
+        port: None,
+        protocol: None,
+        archive_entries: false,
+        list_ads: false,
+        decompress: None,
+        decode: None,
+        offset: None,
+        length: None,
+        ranges: Vec::new(),
+        per_range: false,
+        runs: false,
+        min_padding: None,
+        first_last: false,
+        dispersion: false,
+        context: None,
+        rare_below: None,
+        limit: None,
+        locate: None,
+        huffman: false,
+        huffman_table: false,
+        min_entropy: None,
+        min_size: None,
+        max_size: None,
+        sort_by: None,
+        entropy_above: None,
+        like: None,
+        fingerprint: false,
+        fuzzy: false,
+        normalize: false,
+        order: None,
+        chars: false,
+        model_path: None,
+        from: None,
+        match_path: None,
+        sampled: false,
+        bytes: None,
+        seed: None,
+        extract_dir: None,
+        follow: false,
+        snapshot_every: None,
+        dump_path: None,
+        listen: None,
+        grpc: false,
+        root: None,
+        metrics_addr: None,
+        control_path: None,
+        checkpoint_path: None,
+        resume: false,
+        max_bytes: None,
+        timeout: None,
+        rate: None,
+        serial: None,
+        baud: None,
+        cache_dir: None,
+        history_path: None,
+        history_file: None,
+        since: None,
+        previous: false,
+        tag: None,
+        metadata: false,
+        hashes: Vec::new(),
+        force: false,
+        append: false,
+        tee: false,
+        stats: false,
+        verbose: 0,
+        quiet: false,
+        log_json: false,
+        summary_line: false,
+    };
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_ref() {
+            "-h" => opts.help = true,
+            "--bars" => opts.bars = true,
+            "--ci" => {
+                opts.ci = Some(args.get(i + 1).and_then(|n| n.trim_end_matches('%').parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--duplicates" => opts.duplicates = true,
+            "--triage" => opts.triage = true,
+            "--packed-above" => {
+                opts.packed_above = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--encrypted-above" => {
+                opts.encrypted_above = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--confirm" => { opts.duplicates = true; opts.confirm = true; },
+            "--git-modified" => opts.git_modified = true,
+            "--git-staged" => opts.git_staged = true,
+            "--respect-gitignore" => opts.respect_gitignore = true,
+            "--hidden" => opts.hidden = true,
+            "--max-depth" => {
+                opts.max_depth = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--follow-symlinks" => opts.follow_symlinks = true,
+            "--same-filesystem" => opts.same_filesystem = true,
+            "--include" => {
+                if let Some(pattern) = args.get(i + 1) { opts.includes.push(pattern.clone()); }
+                i += 1;
+            },
+            "--exclude" => {
+                if let Some(pattern) = args.get(i + 1) { opts.excludes.push(pattern.clone()); }
+                i += 1;
+            },
+            "-o" => { opts.out_path = args.get(i + 1).cloned(); i += 1; },
+            "--hilbert" => { opts.hilbert_path = args.get(i + 1).cloned(); i += 1; },
+            "--hilbert-block" => {
+                opts.hilbert_block = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--heatmap" => { opts.heatmap_path = args.get(i + 1).cloned(); i += 1; },
+            "--heatmap-block" => {
+                opts.heatmap_block = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--heatmap-buckets" => {
+                opts.heatmap_buckets = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--format" => {
+                if let Some(name) = args.get(i + 1) { opts.format_name = name.clone(); }
+                i += 1;
+            },
+            "--entropy-window" => {
+                opts.window = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--entropy-step" => {
+                opts.step = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--block-size" => {
+                opts.block_size = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(0));
+                i += 1;
+            },
+            "--content-defined" => opts.content_defined = true,
+            "--segments" => opts.segments = true,
+            "--sections" => opts.sections = true,
+            "--partitions" => opts.partitions = true,
+            "--stride" => {
+                opts.stride = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--field" => {
+                if let Some(names) = args.get(i + 1) { opts.fields.extend(names.split(',').map(String::from)); }
+                i += 1;
+            },
+            "--delimiter" => { opts.delimiter = args.get(i + 1).cloned(); i += 1; },
+            "--payloads" => opts.payloads = true,
+            "--audio" => opts.audio = true,
+            "--pixels" => opts.pixels = true,
+            "--per-flow" => { opts.payloads = true; opts.per_flow = true; },
+            "--port" => {
+                opts.port = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--protocol" => { opts.protocol = args.get(i + 1).cloned(); i += 1; },
+            "--record-size" => {
+                opts.record_size = Some(args.get(i + 1).and_then(|n| parse_size(n)).map_or(0, |n| n as usize));
+                i += 1;
+            },
+            "--archive-entries" => opts.archive_entries = true,
+            "--list-ads" => opts.list_ads = true,
+            "--segment-threshold" => {
+                opts.segments = true;
+                opts.segment_threshold = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--decompress" => { opts.decompress = args.get(i + 1).cloned(); i += 1; },
+            "--decode" => { opts.decode = args.get(i + 1).cloned(); i += 1; },
+            // one that doesn't parse is kept as u64::MAX, which no file
+            // gets to, and reported as a usage problem
+            "--offset" => {
+                opts.offset = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(u64::MAX));
+                i += 1;
+            },
+            "--length" => {
+                opts.length = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(u64::MAX));
+                i += 1;
+            },
+            // a range that doesn't parse is kept as an empty one, which
+            // gets reported as a usage problem
+            "--range" => {
+                opts.ranges.push(args.get(i + 1).and_then(|r| parse_range(r)).unwrap_or((0, 0)));
+                i += 1;
+            },
+            "--per-range" => opts.per_range = true,
+            "--runs" => opts.runs = true,
+            "--min-padding" => {
+                opts.runs = true;
+                opts.min_padding = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(0));
+                i += 1;
+            },
+            "--first-last" => opts.first_last = true,
+            "--dispersion" => opts.dispersion = true,
+            "--context" => {
+                opts.context = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--rare-below" => {
+                opts.rare_below = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--limit" => {
+                opts.limit = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--huffman" => opts.huffman = true,
+            "--huffman-table" => {
+                opts.huffman = true;
+                opts.huffman_table = true;
+            },
+            "--min-entropy" => {
+                opts.min_entropy = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            // like --offset, a size that doesn't parse is kept as u64::MAX
+            // and reported
+            "--min-size" => {
+                opts.min_size = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(u64::MAX));
+                i += 1;
+            },
+            "--sort-by" => { opts.sort_by = args.get(i + 1).cloned(); i += 1; },
+            "--max-size" => {
+                opts.max_size = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(u64::MAX));
+                i += 1;
+            },
+            "--extract" => { opts.extract_dir = args.get(i + 1).cloned(); i += 1; },
+            "--follow" => opts.follow = true,
+            // like a bad range, a bad interval is kept as an empty one
+            "--snapshot-every" => {
+                opts.snapshot_every = Some(args.get(i + 1).and_then(|n| parse_every(n)).unwrap_or(Every::Bytes(0)));
+                i += 1;
+            },
+            "--listen" => { opts.listen = args.get(i + 1).cloned(); i += 1; },
+            "--grpc" => opts.grpc = true,
+            "--root" => { opts.root = args.get(i + 1).cloned(); i += 1; },
+            "--metrics" => { opts.metrics_addr = args.get(i + 1).cloned(); i += 1; },
+            "--control" => { opts.control_path = args.get(i + 1).cloned(); i += 1; },
+            "--checkpoint" => { opts.checkpoint_path = args.get(i + 1).cloned(); i += 1; },
+            "--resume" => opts.resume = true,
+            // a limit that doesn't parse is kept as 0 and reported, rather
+            // than counting on without one
+            "--max-bytes" => {
+                opts.max_bytes = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(0));
+                i += 1;
+            },
+            "--timeout" | "--duration" => {
+                opts.timeout = Some(args.get(i + 1).and_then(|n| parse_seconds(n)).unwrap_or(0));
+                i += 1;
+            },
+            "--rate" => {
+                opts.rate = Some(args.get(i + 1).and_then(|n| parse_seconds(n)).unwrap_or(0));
+                i += 1;
+            },
+            "--serial" => { opts.serial = args.get(i + 1).cloned(); i += 1; },
+            "--baud" => {
+                opts.baud = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--history" => { opts.history_path = args.get(i + 1).cloned(); i += 1; },
+            "--file" => { opts.history_file = args.get(i + 1).cloned(); i += 1; },
+            "--since" => { opts.since = args.get(i + 1).cloned(); i += 1; },
+            "--hash" => {
+                if let Some(names) = args.get(i + 1) { opts.hashes = names.split(',').map(String::from).collect(); }
+                i += 1;
+            },
+            "--force" => opts.force = true,
+            "--tee" => opts.tee = true,
+            "--stats" => opts.stats = true,
+            "-v" => opts.verbose += 1,
+            "-vv" => opts.verbose += 2,
+            "-q" => opts.quiet = true,
+            "--log-json" => opts.log_json = true,
+            "--summary-line" => opts.summary_line = true,
+            "--append" => opts.append = true,
+            "--metadata" => opts.metadata = true,
+            "--tag" => { opts.tag = args.get(i + 1).cloned(); i += 1; },
+            "--previous" => opts.previous = true,
+            "--cache" => { opts.cache_dir = args.get(i + 1).cloned(); i += 1; },
+            "--dump-to" => { opts.dump_path = args.get(i + 1).cloned(); i += 1; },
+            "--save" => { opts.save_path = args.get(i + 1).cloned(); i += 1; },
+            "--baseline" => { opts.baseline = args.get(i + 1).cloned(); i += 1; },
+            "--max-divergence" => {
+                opts.max_divergence = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--entropy-above" => {
+                opts.entropy_above = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--fingerprint" => opts.fingerprint = true,
+            "--fuzzy" => opts.fuzzy = true,
+            "--normalize" => opts.normalize = true,
+            "--like" => { opts.like = args.get(i + 1).cloned(); i += 1; },
+            "--significance" => {
+                opts.significance = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(f64::NAN));
+                i += 1;
+            },
+            "--gof" => {
+                if let Some(name) = args.get(i + 1) { opts.gof_name = name.clone(); }
+                i += 1;
+            },
+            "--as" => {
+                if let Some(name) = args.get(i + 1) { opts.samples_name = name.clone(); }
+                i += 1;
+            },
+            "--clusters" => {
+                opts.clusters = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            "--order" => {
+                opts.order = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(usize::MAX));
+                i += 1;
+            },
+            "--chars" => opts.chars = true,
+            "--model" => { opts.model_path = args.get(i + 1).cloned(); i += 1; },
+            "--from" => { opts.from = args.get(i + 1).cloned(); i += 1; },
+            "--match" => { opts.match_path = args.get(i + 1).cloned(); i += 1; },
+            "--sampled" => opts.sampled = true,
+            "--bytes" => {
+                opts.bytes = Some(args.get(i + 1).and_then(|n| parse_size(n)).unwrap_or(0));
+                i += 1;
+            },
+            "--seed" => {
+                opts.seed = args.get(i + 1).cloned();
+                i += 1;
+            },
+            "--top" => {
+                opts.top = Some(args.get(i + 1).and_then(|n| n.parse().ok()).unwrap_or(0));
+                i += 1;
+            },
+            arg if i == 1 && COMMANDS.contains(&arg) => opts.command = Some(String::from(arg)),
+            arg => opts.inputs.push(String::from(arg)),
+        };
+        i += 1;
+    }
+    // --tee reads stdin, without having to say so
+    if opts.tee && opts.inputs.is_empty() { opts.inputs.push(String::from("-")); }
+    opts
+}
+
+// a byte count, either in hex (0x1f400) or decimal with an optional binary
+// unit suffix: 512, 64K, 1M, 2G
+pub fn parse_size(text: &str) -> Option<u64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).ok();
+    }
+    let (digits, unit) = match text.char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&text[..i], 1 << 10),
+        Some((i, 'm')) | Some((i, 'M')) => (&text[..i], 1 << 20),
+        Some((i, 'g')) | Some((i, 'G')) => (&text[..i], 1 << 30),
+        _ => (text, 1),
+    };
+    digits.parse::<u64>().ok().and_then(|n| n.checked_mul(unit))
+}
+
+// start-end, as in 0x1000-0x2000. the end is exclusive.
+pub fn parse_range(text: &str) -> Option<(u64, u64)> {
+    let (start, end) = text.split_once('-')?;
+    Some((parse_size(start)?, parse_size(end)?))
+}
+
+// a duration in seconds: 30s, 5min, 1h, or just a number of seconds
+pub fn parse_seconds(text: &str) -> Option<u64> {
+    let units = [("min", 60), ("s", 1), ("h", 60 * 60)];
+    for (unit, seconds) in units {
+        if let Some(n) = text.strip_suffix(unit) {
+            return n.parse::<u64>().ok().and_then(|n| n.checked_mul(seconds));
+        }
+    }
+    text.parse().ok()
+}
+
+// an interval of time (30s, 5min, 1h) or of bytes, as any other size
+pub fn parse_every(text: &str) -> Option<Every> {
+    if text.ends_with('s') || text.ends_with("min") || text.ends_with('h') {
+        return parse_seconds(text).map(Every::Seconds);
+    }
+    parse_size(text).map(Every::Bytes)
+}
+
+pub const USAGE: &str = "
+Usage:
+    freqs <path to file>
+        performs analysis on target file,
+        then prints results as stdout.
+
+    freqs
+        run with nothing in a terminal, asks
+        what to analyze, what to make of it and
+        where to put it, then shows the command
+        that does the same.
+
+    freqs <http:// or https:// url>
+        fetches the url with curl and counts
+        the body as it comes down, without
+        saving it first. progress goes by the
+        content-length, if the server sends
+        one. urls can be mixed with files.
+        with --offset, --length or --range
+        only that part is downloaded.
+
+    freqs <s3://bucket/key>
+        the same for an object in s3, when
+        freqs is built with --features s3.
+        requests are signed with the keys in
+        AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY
+        and AWS_SESSION_TOKEN, for the region in
+        AWS_REGION. AWS_ENDPOINT_URL points it at
+        another store that speaks s3.
+
+    freqs <path to target file> -o <outfile>
+        performs analysis on target file,
+        then prints results to outfile. if
+        o flag is specified with no outfile,
+        prints to stdout instead. an outfile
+        that's already there is only replaced
+        with --force.
+
+    --append
+        adds the results to the end of the -o
+        outfile instead of replacing it. each
+        run added starts with a line marking
+        when it was made and with which
+        arguments.
+
+    freqs report split <outfile>
+        takes an outfile with several --append
+        runs in it apart again, into a file per run next to
+        it (out.1.txt, out.2.txt...).
+
+    freqs <file or directory> <file or directory>...
+        performs analysis on every file given,
+        descending into directories, then prints
+        one summary line per file: its size,
+        entropy, and a sparkline of its byte
+        histogram in 16 buckets (0x00-0x0f,
+        0x10-0x1f, ...). csv and json formats
+        give the bucket counts instead.
+
+    --summary-line
+        prints exactly one line per file, even
+        for a single one, and nothing else: the
+        path, size, entropy, how many distinct
+        byte values occur, the most common one
+        and the mean, standard deviation,
+        skewness and kurtosis of the byte
+        values, tab separated (or csv, or a json
+        object per line). for find ... | xargs
+        freqs --summary-line, whose batches add
+        up to one table.
+
+    --fingerprint
+        prints a short fingerprint of each file's
+        byte distribution instead (fq1: and 88
+        characters of base64), to store and
+        compare later with fingerprint compare.
+        close distributions give close strings,
+        but only compare can say how close.
+
+    --fuzzy
+        prints a similarity hash of each file
+        instead, like TLSH: files that share most
+        of their content get hashes that differ a
+        little, where a digest would be entirely
+        different. for matching a sample against
+        known ones with fuzzy compare. files
+        under 64 bytes get none.
+
+    --duplicates
+        with several files, reports groups of
+        files with the same size and byte
+        histogram instead, which are likely
+        duplicates of each other.
+
+    --confirm
+        like --duplicates, but also hashes the
+        files in each group (sha256) to confirm
+        they really are identical. only files
+        that are likely duplicates get hashed.
+
+    --git-modified
+        analyzes the files git has as changed
+        since the last commit instead, staged
+        or not, along with new files it isn't
+        ignoring. any files or directories given
+        only narrow it down. for pre-commit
+        hooks and the like.
+
+    --git-staged
+        the same, for only what's staged.
+
+    --respect-gitignore
+        when descending into directories, skips
+        what .gitignore files (and the ones above,
+        up to the top of the repository) leave
+        out, as well as hidden files. files named
+        on the command line are always analyzed.
+
+    --hidden
+        with --respect-gitignore, still goes into
+        hidden files and directories, but never
+        .git itself.
+
+    --max-depth <n>
+        descends at most n levels into a
+        directory, 1 being only the files right
+        in it.
+
+    --follow-symlinks
+        goes into directories behind symlinks as
+        well, which are otherwise only followed
+        when named on the command line. a link
+        back to a directory already being walked
+        is skipped, so cycles end there.
+
+    --same-filesystem
+        doesn't descend into directories on
+        another filesystem than the one each
+        input is on, like mounts under it.
+
+    --include <pattern>
+        when descending into directories, only
+        analyzes the files matching the pattern.
+        a pattern without a / goes by the name
+        (*.bin), one with a / by the path below
+        the input, at any depth (fw/*.img). can be
+        given several times, any of them will do.
+
+    --exclude <pattern>
+        the other way around, skips files and
+        whole directories matching the pattern
+        (node_modules/**, or just node_modules).
+        can be given several times too, and wins
+        over --include.
+
+    --sort-by <path|size|entropy|distinct|printable>
+        orders the table of files, one row each
+        with its size, entropy, distinct byte
+        values, most common byte, share of
+        printable bytes and the moments of the
+        byte values (mean, standard deviation,
+        skewness and excess kurtosis). biggest
+        first, except for paths. files come in
+        path order without it.
+
+    --archive-entries
+        for zip and tar archives, prints one
+        summary line per member instead, named
+        archive:member. members are read straight
+        from the archive, nothing is extracted.
+
+    --list-ads
+        on windows, prints one summary line per
+        ntfs alternate data stream of the files
+        instead, named file:stream, for finding
+        what's hidden alongside them. a single
+        stream can be analyzed like any file, as
+        file.txt:Zone.Identifier.
+
+    --triage
+        prints one line per file instead, for
+        going through directories of samples:
+        entropy, share of printable bytes, how
+        many distinct byte values occur, and a
+        verdict: encrypted/compressed, packed,
+        text or plain.
+
+    --packed-above <bits>
+        entropy (bits per byte) from which triage
+        calls a file packed. defaults to 7.0.
+
+    --encrypted-above <bits>
+        entropy from which triage calls a file
+        encrypted/compressed, provided nearly all
+        byte values occur. defaults to 7.9.
+
+    --follow
+        like tail -f: after the end of the file,
+        keeps counting whatever gets appended to
+        it, redrawing the byte table as it goes.
+        for watching live logs. a single file
+        only, and no other reports; starts over
+        if the file gets truncated. stop with
+        ctrl-c.
+
+    --snapshot-every <interval>
+        for long streams, like stdin (given as -)
+        or a device: prints the byte table so far
+        every so often (30s, 5min, 1h), or every
+        so many bytes (100M), and keeps counting.
+        with -o, snapshots go to numbered files
+        next to the outfile (out.1.txt, out.2.txt
+        ...) and the final table to the outfile.
+
+    --max-bytes <bytes>
+        stops counting each input after this many
+        bytes, for devices like /dev/urandom and
+        pipes that never end. results are marked
+        as truncated when the input had more.
+
+    --timeout <duration>
+        stops counting each input after this long
+        (30s, 5min, 1h, or a number of seconds),
+        marking the results as truncated.
+
+    --rate <duration>
+        adds how fast the input came in to the
+        results, for pipes, devices and downloads:
+        the bytes read in every interval this long
+        (10s, 1min, or a number of seconds), how
+        many a second that makes, and their
+        entropy. intervals where nothing came in
+        are still there, so stalls stand out.
+
+    --serial <device>
+        counts the bytes arriving on a serial
+        line (/dev/ttyUSB0) instead of reading
+        files, for checking what a sensor or a
+        debug port sends. the port is set to raw
+        mode first, and what it already had
+        buffered is dropped. needs --duration or
+        --max-bytes to know when to stop.
+
+    --baud <rate>
+        the speed to set the serial port to, a
+        standard rate like 9600 or 115200. the
+        port keeps its current speed without it.
+
+    --duration <duration>
+        how long to listen with --serial (30s,
+        5min, 1h). the same as --timeout.
+
+    --cache <dir>
+        keeps the results for each file in this
+        directory, and reuses them as long as
+        the file's size and modification time
+        stay the same. re-running over a big
+        directory only reads what's new or was
+        changed. results with maps, profiles,
+        regions, segments, runs or --timeout
+        aren't kept.
+
+    --checkpoint <state.json>
+        for huge files: saves the counts so far,
+        and how far they go, to this file every
+        30 seconds (and at the end). only the
+        byte table is made, for a single file.
+
+    --resume
+        with --checkpoint, carries on from where
+        the checkpoint says the last run got to,
+        rather than starting over. refuses to if
+        the file has changed since.
+
+    --dump-to <file>
+        sending a running freqs SIGUSR1 (kill
+        -USR1 <pid>) makes it write the byte
+        table counted so far to stderr, without
+        stopping. works for a single file, with
+        --follow and with --snapshot-every. with
+        this option the table goes to the file
+        instead, replacing what was there.
+
+    --metrics <address:port>
+        with --follow, --snapshot-every or watch,
+        serves prometheus metrics at /metrics on
+        this address (127.0.0.1:9100, say): the
+        size, entropy and throughput of each
+        source, the share of nul, printable,
+        control and high bytes, and the counts of
+        0x00, 0x0a, 0x20 and 0xff. serve has
+        /metrics on its own address.
+
+    --control <socket>
+        with --follow or --snapshot-every, takes
+        commands on a unix socket at this path,
+        one per line, each answered with a reply
+        ending in an empty line: status (bytes
+        and entropy so far), snapshot (the byte
+        table so far), reset (starts counting
+        from zero) and stop (finishes as if the
+        input had ended). e.g.
+        echo status | nc -U <socket>
+
+    freqs watch <file, directory or pattern>...
+        runs the analysis again whenever one of
+        the files changes, and redraws the
+        results. takes the same options as the
+        default command. patterns like
+        'logs/*.bin' or 'data/**/*.csv' (quoted)
+        are matched again on every change, so new
+        files get picked up. with -o, the outfile
+        is rewritten each time. stop with ctrl-c.
+
+    freqs serve [--listen <address:port>]
+        runs a small http service for other
+        tools to get data analyzed by, answering
+        in json. POST /analyses with the data as
+        the body, or POST /analyses?path=<file>
+        to analyze a file on the server, when
+        it's run with --root (read with the
+        server's permissions, and with options
+        like --decompress applied). GET
+        /analyses lists earlier results and GET
+        /analyses/<id> fetches one; the latest
+        1000 are kept, in memory. listens on
+        127.0.0.1:8080 by default, and handles
+        up to 64 connections at once.
+
+    --root <dir>
+        lets clients of serve (and serve --grpc)
+        name files on the server, as long as
+        they're regular
+        files in this directory or below it.
+        without it, only data sent along is
+        analyzed.
+
+    freqs serve --grpc [--listen <address:port>]
+        the same over grpc (plain-text http/2),
+        for pipelines wanting typed answers.
+        Analyze takes a stream of Chunks, each
+        with some of the data or the path of a
+        file on the server (with --root), and
+        answers with the counts, entropy,
+        signature and classification. the
+        schema is in proto/freqs.proto in the
+        source.
+
+    freqs diff <file a> <file b>
+        compares the byte distributions of two
+        files side by side: counts, percentages,
+        and the change from a to b for every
+        byte, then the chi-square statistic (do
+        both files come from the same
+        distribution?) and the jensen-shannon
+        divergence (0 for identical distributions,
+        1 for disjoint ones).
+
+    freqs diff --previous <file> --cache <dir>
+        compares a file to the last result of it
+        in the cache, to see what changed in it
+        since the last run: the bytes whose share
+        changed the most come first, 10 of them
+        or as many as --top says. this run is
+        cached in turn, for the next time.
+
+    freqs similarity <file a> <file b>
+        how alike the byte distributions of two
+        files are, regardless of their sizes: the
+        cosine similarity, the jensen-shannon
+        distance, and the histogram intersection
+        (the share of the distributions that
+        overlaps).
+
+    freqs test <file a> <file b>
+        tests whether the byte distributions of
+        two files differ by more than chance, by
+        chi-square and G-tests of their counts:
+        each statistic and p-value, then whether
+        they differ at the --significance level
+        (0.01 unless given) by the --gof test.
+        big files differ significantly over the
+        slightest thing, so the effect size goes
+        with it: cramer's v and the total
+        variation distance. exits with status 1
+        if they differ.
+
+    freqs locate <byte> <file>
+        lists the offsets a byte value (0x7f, or
+        127) turns up at in the file, as hex,
+        the first 100 of them or as many as
+        --limit says, then how often it occurs
+        in all. --offset, --range and the like
+        narrow it down as they do the report.
+
+    --limit <n>
+        how many offsets locate lists.
+
+    freqs classify <file>
+        scores the file's byte distribution
+        against bundled profiles of common
+        content (random/encrypted, english text,
+        source code, x86-64 code, jpeg, zip, pdf)
+        and reports the best matches. confidence
+        is relative to the other profiles; a
+        file unlike all of them still gets a best
+        match. the start of the file is checked
+        for a format signature too, and the
+        verdict combines both, pointing out when
+        they disagree.
+
+    freqs fips <file>
+        runs the fips 140-2 tests for random
+        number generators (monobit, poker, runs
+        and long run) on the first 20,000 bits of
+        the file, and on every 20,000 bit block
+        of it to see how many fail; a few do even
+        for good random data. exits with status 1
+        if the first block fails a test or more
+        than 0.2% of the blocks fail one.
+
+    freqs nist <file>
+        runs some of the nist sp 800-22 tests for
+        random number generators on the whole
+        file: frequency, block frequency (in 128
+        bit blocks), runs, approximate entropy
+        (with patterns of up to 10 bits) and
+        cumulative sums, both ways. each gives a
+        p-value and passes at 0.01 or above;
+        exits with status 1 if one fails. needs
+        at least 128 bytes.
+
+    freqs random <file>
+        all of the above in one report, along
+        with a chi-square test of the byte
+        counts against a uniform distribution
+        and wald-wolfowitz runs tests of the
+        bits and of bytes above and below the
+        median, the statistic being the z-score:
+        each test's statistic, p-value and
+        result, then an overall verdict. exits
+        with status 1 if a test fails. with a
+        dozen tests, random data fails one at
+        0.01 about one time in ten.
+
+    freqs random --block-size <size> <file>
+        tests the byte counts of each block on
+        their own instead (chi-square, or G with
+        --gof g), and prints a map of which
+        blocks pass and the ones that fail, to
+        find the parts of a stream that stop
+        looking random. blocks of at least 1280
+        bytes keep the chi-square honest. exits
+        with status 1 if clearly more blocks
+        fail than chance explains.
+
+    freqs keycheck <file>
+        checks a key, seed or token for the
+        usual weaknesses: under 128 bits, one
+        byte value throughout, ascii only (hex
+        or base64 text should be decoded first),
+        counting up or down, a repeating pattern,
+        and bytes repeated more often than random
+        ones would be. prints a min-entropy
+        estimate, from the most common byte, and
+        exits with status 1 if a check fails.
+        up to 1M; bigger files are for random.
+
+    freqs find --entropy-above <bits> <paths...>
+        goes through files and directories and
+        lists only the files with more entropy
+        than this, in bits per byte, the highest
+        first. --entropy-above 7.9 finds what's
+        encrypted or well compressed, to sweep a
+        share for ransomware damage or binaries
+        for packed ones.
+
+    freqs find --like <file> <paths...>
+        ranks the files by how close their byte
+        distribution is to this file's, the way
+        nearest does, to find other copies or
+        variants of a known blob. --top sets how
+        many are shown (10), and --entropy-above
+        can narrow the files down first.
+
+    freqs fingerprint compare <a> <b>
+        scores how alike two --fingerprint
+        strings are, from 0 to 1 (the same), and
+        gives their hellinger distance. either
+        can be a file instead, which gets its
+        fingerprint made on the spot.
+
+    freqs fuzzy compare <a> <b>
+        scores how alike two --fuzzy hashes are,
+        from 0 (no more than unrelated data) to
+        100 (the same), and gives the distance
+        they're scored from. either can be a
+        file instead.
+
+    freqs samples <file>
+        writes the file out as numbers for rng
+        test suites to read, in 32 bit little
+        endian words (a short last one is left
+        out). to run dieharder on them:
+        freqs samples data.bin -o data.txt
+        dieharder -g 202 -f data.txt -a
+        though dieharder -g 201 reads the raw
+        file just as well.
+
+    --as <dieharder or uniform>
+        what samples writes: dieharder's
+        file_input format, the words in decimal
+        under its header (the default), or each
+        word as a uniform in [0, 1), one a line,
+        for TestU01's text files and the like.
+
+    --significance <level>
+        the p-value below which nist and random
+        fail a test, and test calls two files
+        different. defaults to 0.01.
+
+    --gof <chi2 or g>
+        the goodness of fit statistic diff, check
+        and random report, and the one test
+        decides by: pearson's chi-square
+        (the default) or the likelihood-ratio G
+        test, which is more trustworthy when many
+        byte values only turn up a few times, as
+        in small files.
+
+    freqs cluster <file or directory>...
+        groups files with similar byte
+        distributions together (k-means over
+        their normalized histograms) and prints
+        the members of each group, biggest first.
+
+    --clusters <k>
+        how many groups cluster makes. defaults
+        to the square root of half the number of
+        files.
+
+    freqs history [--file <file>] [--since <date>]
+        lists the runs recorded in the history,
+        oldest first: when each file was analyzed,
+        its size, entropy and sha256. --file only
+        lists the runs of one file, --since only
+        those after a date (2026-10-14, in utc,
+        optionally with a time like T09:30) or
+        after some time ago (12h, 7d).
+
+    --history <file>
+        records a summary of every file analyzed
+        (the time, its sha256, size and entropy)
+        in this file, and is where freqs history
+        looks them up. defaults to the
+        FREQS_HISTORY environment variable, if
+        it's set. results cut short by
+        --max-bytes or --timeout aren't recorded.
+
+    --tag <name>
+        recorded in the history with each file of
+        the run, for freqs trend to follow a set
+        of files (like nightly backups under new
+        names) by.
+
+    freqs trend <file or tag>
+        how the runs of a file in the history,
+        or of all files recorded with the tag,
+        changed over time: size, entropy and its
+        change since the file's run before, and
+        the shares of printable, nul and high
+        (0x80 and up) bytes. gnuplot and vega
+        chart entropy over time.
+
+    freqs nearest <query file> <file or directory>...
+        ranks the files of a corpus by how alike
+        their byte distributions are to the query
+        file's (by jensen-shannon distance),
+        closest first.
+
+    --top <n>
+        how many matches nearest and find --like
+        show, or values --field lists. defaults
+        to 10.
+
+    freqs carve <file or directory>...
+        lists the regions of each file with high
+        entropy, which is where encrypted or
+        compressed payloads hide. files are
+        scanned in 1K blocks, so regions start
+        and end on block boundaries.
+
+    --min-entropy <bits>
+        entropy from which carve counts a block
+        as part of a region. defaults to 7.5.
+
+    --min-size <bytes>
+        smallest region carve reports. defaults
+        to 4K. everywhere else, skips the files
+        smaller than this when descending into
+        directories. the summary lists them.
+
+    --max-size <bytes>
+        the same for files larger than this, to
+        keep a few huge images out of a run.
+
+    --extract <directory>
+        makes carve also write every region to a
+        file of its own in the directory, named
+        after the file and the region's offsets.
+
+    freqs pid <process id>
+        on linux, reports every readable region
+        of a running process's memory on its own:
+        where it's mapped, its size, permissions,
+        entropy and what file it came from.
+        regions that are writable and executable,
+        executable but from no file, or packed
+        (see --packed-above) are pointed out.
+        needs the permissions a debugger would.
+
+    freqs check --baseline <counts.freq> <file or directory>...
+        compares every file given against counts
+        saved earlier with --save, and exits with
+        status 1 if any of them drifted too far
+        from the baseline. drift is measured as
+        jensen-shannon divergence, see diff.
+
+    --max-divergence <0-1>
+        how much drift check allows. defaults to
+        0.05.
+
+    freqs merge <counts.freq>... -o <total.freq>
+        sums up count files saved with --save,
+        e.g. from shards of a dataset analyzed
+        separately. the result is a count file
+        again, printed to stdout or written to
+        the outfile (which is replaced, not
+        appended to).
+
+    freqs calc <a.freq> - <b.freq> ...
+        adds (+) and subtracts (-) count files,
+        and scales the result by a number ('*' 2
+        or x 2, / 2), left to right. for taking
+        a known header's bytes out of a file's
+        counts, say. the result is a count file
+        again, like merge's. subtracting more of
+        a byte than there is is an error.
+
+    --normalize
+        makes calc print the share of each byte
+        value instead, as a probability, in
+        text, csv or json.
+
+    freqs model <file>... -o <model.json>
+        a markov model of the files: for each
+        run of --order bytes, how often it came
+        up and the probability of each byte that
+        followed it, as json, one context a
+        line. the format is described at the top
+        of src/model.rs. for generators and
+        classifiers of your own to read.
+
+    --order <n>
+        how many symbols model's contexts are, 0
+        to 4. 0 is the plain distribution, 1
+        (the default) bigrams, 2 trigrams.
+
+    --chars
+        makes model work on the characters of
+        utf-8 text instead of bytes, each a
+        code point.
+
+    freqs generate --model <model.json> --bytes <size>
+        random data drawn from a model made with
+        model, each byte (or character) given
+        the ones before it, for test fixtures
+        that look like the real thing. written
+        to stdout or the outfile as it is.
+
+    freqs generate --from <file> --bytes <size>
+        the same, from a model of the file made
+        on the spot, to the --order given.
+
+    freqs generate --match <counts.freq> --bytes <size>
+        random data with the byte counts of a
+        file saved with --save, scaled to the
+        size given (or the same size), in no
+        particular order: the counts come out
+        exactly so, for benchmark inputs with
+        a known distribution.
+
+    --sampled
+        makes generate --match draw each byte on
+        its own instead, so the counts only
+        match as closely as chance has it.
+
+    --seed <n>
+        starts generate from this seed, so the
+        same data comes out every time.
+
+    freqs repl
+        analyses one after another in the same
+        session, a command a line: analyze
+        <path>, top [n], compare <a> <b>,
+        classify [path], model <model.json>,
+        generate <size>, files, forget [path],
+        format <name>, help and quit. files are
+        kept once analyzed, and only read again
+        if they've changed, and a model stays
+        loaded. the options given apply to every
+        file.
+
+    freqs <path to target file> --hilbert <image.png>
+        additionally writes an entropy map of the
+        file as a png. the file is split into
+        blocks laid out along a hilbert curve,
+        each coloured by its entropy, from black
+        (constant) through blue and magenta to
+        white (random-looking).
+
+    --hilbert-block <bytes>
+        block size for the entropy map. by default
+        it is picked so the image fits in 512x512.
+
+    freqs <path to target file> --heatmap <image.png>
+        additionally writes a heatmap of where in
+        the file each byte value turns up: a row
+        per block of the file, top to bottom, a
+        column per byte value, brighter the more
+        often it's there. a path ending in .csv
+        gets the matrix of counts instead, with
+        the offset of each block first.
+
+    --heatmap-block <bytes>
+        block size for the heatmap. by default it
+        is picked so there are at most 512 rows.
+
+    --heatmap-buckets <n>
+        columns for the heatmap, each a run of
+        neighbouring byte values: 1, 2, 4 and so
+        on up to 256, the default.
+
+    --entropy-window <bytes>
+        adds an entropy profile to the results:
+        the entropy (bits per byte) of a window
+        of this many bytes, sampled as it slides
+        along the file.
+
+    --entropy-step <bytes>
+        how far the window moves between samples.
+        defaults to the window size, so windows
+        don't overlap.
+
+    --block-size <bytes>
+        adds a report per block of this many bytes
+        (1M, say): the entropy of the block, how
+        many distinct byte values it has, and its
+        most common bytes. text, csv and json only.
+
+    --content-defined
+        cuts the --block-size blocks where the
+        content says instead, with a rolling
+        hash, so they're only that size on
+        average (from a quarter of it to four
+        times). an insertion early on changes
+        only the blocks around it rather than
+        shifting every one after it, so two
+        versions of a file can be lined up.
+
+    --segments
+        adds a list of segments: stretches of the
+        file with a similar byte distribution, with
+        their offsets, sizes and entropy. a new one
+        starts wherever the distribution changes
+        noticeably. text, csv and json only.
+
+    --segment-threshold <distance>
+        how different (as jensen-shannon distance,
+        0 to 1) a block must be from the segment so
+        far to start a new one. defaults to 0.45;
+        lower finds more boundaries.
+
+    --runs
+        adds the longest run of each of the most
+        common bytes, with the offset it starts
+        at, and how much of the file is padding:
+        runs of 0x00 or 0xff of at least 512
+        bytes. text, csv and json only.
+
+    --min-padding <bytes>
+        how long a run of 0x00 or 0xff has to be
+        to count as padding.
+
+    --first-last
+        adds the offsets each byte value first
+        and last turns up at to the byte table,
+        to find where a rare one lives. text,
+        csv and json only.
+
+    --dispersion
+        adds how evenly each byte value is
+        spread through the file to the byte
+        table: the coefficient of variation of
+        the gaps between one and the next. near
+        1 is scattered at random, over 1.5 is
+        clustered in places, under 0.5 is even,
+        as with a delimiter in fixed size
+        records. text, csv and json only.
+
+    --context <bytes>
+        adds where the rare byte values are,
+        the first 3 times each turns up, with
+        that many bytes either side (up to 64)
+        in hex and as text, like a line of a
+        hex dump. text, csv and json only.
+
+    --rare-below <count>
+        what --context counts as rare: byte
+        values that turn up fewer times than
+        this. defaults to 10.
+
+    --huffman
+        adds the huffman code a static coder
+        would build from the counts: the bits
+        per byte it comes to, how far that is
+        over the entropy, the size of the file
+        coded with it and the shortest and
+        longest codes. text, csv and json only.
+
+    --huffman-table
+        the same, with every byte value's code
+        length and canonical code, shortest
+        first.
+
+    --sections
+        for an executable (elf, pe or mach-o),
+        reports each section (.text, .data,
+        .rsrc...) on its own instead: its offset,
+        size, entropy and byte counts. text
+        output shows a sparkline in place of the
+        counts.
+
+    --partitions
+        for a disk image or a block device
+        (/dev/sdb), reads the mbr or gpt
+        partition table and reports each
+        partition the same way, along with the
+        space outside them and the share of it
+        that's zeros, to check a wipe or an image.
+
+    --stride <n>
+        counts the bytes at each offset mod n on
+        their own, a table for every column of
+        n byte records or n interleaved channels,
+        with each column's entropy, how many
+        values it takes and its most common one.
+        columns that never change are marked
+        constant, ones with only printable ascii
+        text, and ones close to as varied as
+        bytes get random. n goes up to 65536.
+
+    --record-size <bytes>
+        the same for a file of fixed size
+        records, leaving out a short last one,
+        and then a guess at the record's layout:
+        neighbouring columns of the same kind
+        put together into fields, with the bytes
+        of the constant ones, to start working
+        out an unknown binary format.
+
+    --field <n or name>
+        for csv and other delimited text, counts
+        the values of field n (from 1) instead of
+        the bytes: how many rows, how many
+        different values, how many are empty and
+        the most common ones (see --top). give it
+        more than once, or a list like 1,3, for
+        several fields. a name picks the field
+        out of the first row, the header.
+
+    --delimiter <char>
+        what separates the fields. defaults to a
+        tab for .tsv files and a comma otherwise;
+        tab works too.
+
+    --payloads
+        for a pcap or pcapng capture, counts the
+        payloads the packets carry, past their
+        ethernet, ip and tcp or udp headers,
+        instead of the capture file.
+
+    --per-flow
+        the same, but a line for each flow (both
+        directions of a conversation) with its
+        packets, bytes, entropy and share of text,
+        marking the ones that look encrypted.
+
+    --port <n>
+        only counts packets to or from port n.
+
+    --protocol <tcp|udp|icmp|icmpv6|sctp|n>
+        only counts packets of this protocol, or
+        of ip protocol number n.
+
+    --audio
+        for a wav file, counts the 8, 16, 24 or
+        32 bit pcm samples instead of the bytes,
+        a line for each channel with its range,
+        dc offset (as a share of full scale), rms
+        level in dbfs and how many samples are
+        clipped at full scale. samples are binned
+        by their top 8 bits.
+
+    --pixels
+        for a png, bmp, pgm or ppm image, decodes
+        it and counts the values of each channel
+        (gray, or red, green and blue, and alpha)
+        instead of the bytes, which for a png are
+        mostly deflate output. a line for each
+        channel, and its whole table with csv or
+        json. 16 bit samples are cut to their top
+        8 bits.
+
+    --offset <bytes>
+        starts counting this far into the input
+        instead of at the start. like all sizes,
+        it can be given in hex (0x1f400) or with
+        a K, M or G suffix (64K).
+
+    --length <bytes>
+        counts only this many bytes, from the
+        start or from --offset. with --decompress
+        or --decode, both apply to the decoded
+        contents.
+
+    --range <start-end>
+        counts only the bytes from start up to
+        (not including) end, as in 0x1000-0x2000.
+        can be given several times, in which case
+        the totals cover all the ranges together.
+        offsets in profiles and block reports are
+        then into the ranges put end to end.
+
+    --per-range
+        with --range, prints one summary line per
+        range (for each input) instead of the
+        totals.
+
+    --decompress <gz|xz|zst|bz2|auto>
+        counts the decompressed contents of the
+        input instead of its compressed bytes.
+        auto goes by each file's signature and
+        reads anything uncompressed as is. gz is
+        built in, the others need the xz, zstd or
+        bzip2 tools installed.
+
+    --decode <base64|hex|url>
+        decodes the input before counting, for
+        encoded payloads from logs, json and the
+        like. whitespace is skipped in base64
+        and hex; url decoding turns %xx escapes
+        into bytes and + into spaces. happens
+        after --decompress.
+
+    --format <text|csv|json|gnuplot|vega>
+        output format of the results. defaults to
+        text. gnuplot emits the data along with a
+        script plotting the histogram (and entropy
+        profile, if any), for gnuplot -p <file>.
+        vega emits the same charts as a vega-lite
+        spec with the data inlined.
+
+    --save <counts.freq>
+        additionally saves the counts to a file,
+        for use as a baseline with check.
+
+    --bars
+        adds a column of bars to the text table,
+        scaled to the most common byte, so the
+        table shows relative magnitudes at a
+        glance.
+
+    --ci <level>
+        adds each byte's percentage and its
+        wilson confidence interval at this level
+        (like 95) to the byte table, and to diff,
+        so a difference between small samples
+        can be told from noise.
+
+    --hash <sha256,md5,crc32,adler32>
+        computes these digests of each file in
+        the same pass as counting, rather than
+        reading it again just to record which
+        file exactly was analyzed. they go in the
+        report, or at the end of each summary
+        line. with --decompress, --decode or
+        ranges, they're of what was counted.
+
+    --force
+        replaces output files that are already
+        there: the outfile, --save, entropy
+        maps, snapshots and carved regions.
+        without it freqs stops rather than
+        overwrite them. every output file is
+        written to a temporary file first and
+        moved in place once complete, so an
+        interrupted run never leaves half of one.
+
+    --tee
+        reads stdin (-), and passes it on to
+        stdout unchanged while counting it, so
+        freqs can sit in the middle of a
+        pipeline. the report goes to stderr, or
+        to the -o outfile.
+
+    --metadata
+        starts the report with the file's path,
+        size and modification time, and when it
+        was analyzed, in every format, so a saved
+        report says what it is about.
+
+    --stats
+        prints what the run took to stderr at the
+        end: wall and cpu time, peak memory, the
+        threads used, and the bytes and chunks
+        read and how fast, for comparing ways of
+        running the same thing.
+
+    -v, -vv
+        says more on stderr about what's going
+        on: with -v, how each input is opened
+        and read (seeking or as a stream, from
+        the cache, decompressed...), the files
+        written along the way and the requests
+        serve answers, with -vv
+        also what's skipped over, and where
+        counting stopped early.
+
+    -q
+        leaves out the progress counters and the
+        warnings about inputs that were skipped.
+
+    --log-json
+        writes the messages on stderr as a line
+        of json each, with the time and level,
+        for collecting from batch runs. there
+        are no progress counters then.
+";
@@ -0,0 +1,110 @@
+/*
+    --cache <dir>: the results of each file are kept in a directory of
+    their own, to be reused as long as the file's size and modification
+    time are the same, so re-running over a big directory only reads
+    what's new or changed. Only plain counts are kept (the byte table, the
+    head for detecting types and the digests), one small text file per
+    input.
+*/
+
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use analysis::{self, Analysis};
+use args::Options;
+use digest::{self, Sha256};
+use log;
+
+pub struct Key {
+    path: String,       // canonical
+    settings: String,   // options that change what's counted
+    size: u64,
+    mtime: u64,         // in nanoseconds
+    hashes: Vec<&'static str>, // digests the result needs
+}
+
+impl Key {
+    // none for streams, and for options that track more than the cache keeps
+    pub fn new(path: &str, opts: &Options) -> Option<Key> {
+        if opts.hilbert_path.is_some() || opts.heatmap_path.is_some() || opts.window.is_some() || opts.block_size.is_some()
+            || opts.segments || opts.runs || opts.first_last || opts.dispersion || opts.context.is_some() || opts.rate.is_some() || opts.locate.is_some() || opts.timeout.is_some() {
+            return None;
+        }
+        let meta = fs::metadata(path).ok().filter(|m| m.is_file())?;
+        let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64;
+        let settings = format!(
+            "{:?} {:?} {:?} {:?}",
+            opts.decompress, opts.decode, analysis::spans(opts), opts.max_bytes
+        );
+        let path = fs::canonicalize(path).ok()?.to_string_lossy().into_owned();
+        Some(Key { path, settings, size: meta.len(), mtime, hashes: analysis::hashes_wanted(opts) })
+    }
+
+    // one entry per file and settings, named after both
+    fn entry(&self, dir: &str) -> String {
+        let mut hash = Sha256::new();
+        hash.update(self.path.as_bytes());
+        hash.update(b"\n");
+        hash.update(self.settings.as_bytes());
+        Path::new(dir).join(format!("{}.cache", &digest::hex(&hash.finish())[..32])).to_string_lossy().into_owned()
+    }
+
+    pub fn load(&self, dir: &str) -> Option<Analysis> {
+        self.read(dir, false).map(|(a, _)| a)
+    }
+
+    // whatever was cached last for the file, even if it has changed since,
+    // and when that version of it was modified (in seconds)
+    pub fn previous(&self, dir: &str) -> Option<(Analysis, u64)> {
+        self.read(dir, true).map(|(a, mtime)| (a, mtime / 1_000_000_000))
+    }
+
+    fn read(&self, dir: &str, any_version: bool) -> Option<(Analysis, u64)> {
+        let text = fs::read_to_string(self.entry(dir)).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != "freqs cache 1" { return None; }
+        let mut field = |name: &str| lines.next()?.strip_prefix(name)?.strip_prefix(' ').map(String::from);
+        if field("path")? != self.path || field("settings")? != self.settings { return None; }
+        let size: u64 = field("size")?.parse().ok()?;
+        let mtime: u64 = field("mtime")?.parse().ok()?;
+        if !any_version && (size != self.size || mtime != self.mtime) { return None; }
+        let len = field("len")?.parse().ok()?;
+        let truncated = field("truncated")? == "1";
+        let head = unhex(&field("head")?)?;
+        // entries made without the digests wanted are counted again
+        let hashes: Vec<(&'static str, String)> = field("hashes")?.split(' ')
+            .filter_map(|h| h.split_once('='))
+            .filter_map(|(name, value)| digest::NAMES.iter().find(|n| **n == name).map(|n| (*n, value.to_string())))
+            .collect();
+        if !any_version && !self.hashes.iter().all(|n| hashes.iter().any(|(h, _)| h == n)) { return None; }
+        let parsed: Vec<u64> = field("counts")?.split(' ').map(|c| c.parse().ok()).collect::<Option<_>>()?;
+        if parsed.len() != 256 { return None; }
+        let mut counts = [0u64; 256];
+        counts.copy_from_slice(&parsed);
+        Some((Analysis { len, counts, head, blocks: None, heatmap: None, profile: None, regions: None, segments: None, runs: None, first_last: None, dispersion: None, located: None, rare: None, rate: None, truncated, hashes }, mtime))
+    }
+
+    // a cache that can't be written to only costs time, so it's not fatal.
+    // written next to the entry and moved over it, for parallel runs
+    pub fn store(&self, dir: &str, a: &Analysis) {
+        let counts: Vec<String> = a.counts.iter().map(|c| c.to_string()).collect();
+        let hashes: Vec<String> = a.hashes.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        let text = format!(
+            "freqs cache 1\npath {}\nsettings {}\nsize {}\nmtime {}\nlen {}\ntruncated {}\nhead {}\nhashes {}\ncounts {}\n",
+            self.path, self.settings, self.size, self.mtime, a.len, a.truncated as u8, digest::hex(&a.head),
+            hashes.join(" "), counts.join(" ")
+        );
+        let entry = self.entry(dir);
+        let tmp = format!("{}.tmp", entry);
+        let written = fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&tmp, text))
+            .and_then(|_| fs::rename(&tmp, &entry));
+        if let Err(e) = written { log::warn(&format!("could not write to cache {}: {}", dir, e)); }
+    }
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) { return None; }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
@@ -0,0 +1,108 @@
+/*
+    freqs calc: arithmetic on count files saved with --save. Files are
+    added and subtracted, and the running result scaled by a number, left
+    to right, so a.freq - header.freq takes a known header's bytes back
+    out of a file's counts. The result is a count file again, or with
+    --normalize the share of each byte value.
+*/
+
+use std::convert::TryFrom;
+
+use args::Options;
+use freqfile;
+use report::{json_str, Format};
+
+pub enum Step {
+    Add(String),
+    Subtract(String),
+    Scale(f64),
+}
+
+const HINT: &str = "calc takes count files joined by + and -, then * or / by a number, like a.freq - b.freq";
+
+// the first file, and what's done to it
+pub fn parse(tokens: &[String]) -> Result<(String, Vec<Step>), String> {
+    let first = match tokens.first() {
+        Some(t) if !is_operator(t) => t.clone(),
+        _ => return Err(String::from(HINT)),
+    };
+    let mut steps = Vec::new();
+    let mut rest = tokens[1..].iter();
+    while let Some(op) = rest.next() {
+        let operand = rest.next().filter(|t| !is_operator(t)).ok_or_else(|| String::from(HINT))?;
+        let number = operand.parse::<f64>().ok().filter(|n| n.is_finite() && *n >= 0.0);
+        steps.push(match (op.as_str(), number) {
+            ("+", _) => Step::Add(operand.clone()),
+            ("-", _) => Step::Subtract(operand.clone()),
+            ("*" | "x", Some(n)) => Step::Scale(n),
+            ("/", Some(n)) if n > 0.0 => Step::Scale(1.0 / n),
+            ("*" | "x" | "/", _) => return Err(format!("Can't scale by {}, try a positive number", operand)),
+            _ => return Err(String::from(HINT)),
+        });
+    }
+    Ok((first, steps))
+}
+
+fn is_operator(token: &str) -> bool {
+    matches!(token, "+" | "-" | "*" | "x" | "/")
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let (first, steps) = match parse(&opts.inputs) {
+        Ok(p) => p,
+        Err(e) => panic!("{}", e),
+    };
+    // signed and wide, so a subtraction can be checked once it's done
+    let mut counts: Vec<i128> = load(&first).iter().map(|c| *c as i128).collect();
+    for step in &steps {
+        match *step {
+            Step::Add(ref path) => for (c, n) in counts.iter_mut().zip(load(path).iter()) { *c += *n as i128; },
+            Step::Subtract(ref path) => {
+                for (byte, (c, n)) in counts.iter_mut().zip(load(path).iter()).enumerate() {
+                    *c -= *n as i128;
+                    if *c < 0 { panic!("Byte {:02x} goes below zero, {} has more of it than there is to take away", byte, path); }
+                }
+            },
+            Step::Scale(k) => for c in counts.iter_mut() { *c = (*c as f64 * k).round() as i128; },
+        }
+    }
+    let mut result = [0u64; 256];
+    for (r, c) in result.iter_mut().zip(counts.iter()) {
+        *r = match u64::try_from(*c) {
+            Ok(c) => c,
+            Err(_) => panic!("Counts overflow in calc"),
+        };
+    }
+    if opts.normalize { normalized(format, &result) } else { freqfile::render(&result) }
+}
+
+fn load(path: &str) -> [u64; 256] {
+    match freqfile::load(path) {
+        Ok(c) => c,
+        Err(e) => panic!("Could not load {}: {}", path, e),
+    }
+}
+
+// each byte value's share of the total, the ones that occur
+fn normalized(format: Format, counts: &[u64; 256]) -> Vec<String> {
+    let total: u64 = counts.iter().sum();
+    if total == 0 { panic!("Nothing left to normalize, the counts add up to 0"); }
+    let shares: Vec<(usize, f64)> = counts.iter().enumerate()
+        .filter(|&(_, c)| *c != 0)
+        .map(|(byte, c)| (byte, *c as f64 / total as f64))
+        .collect();
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("byte,probability")];
+            for (byte, p) in &shares { lines.push(format!("{},{:.9}", byte, p)); }
+            lines
+        },
+        Format::Json => {
+            let bytes: Vec<String> = shares.iter()
+                .map(|(byte, p)| format!("    {{\"byte\": {}, \"hex\": {}, \"probability\": {:.9}}}", byte, json_str(&format!("{:02x}", byte)), p))
+                .collect();
+            vec![format!("{{\n  \"total\": {},\n  \"bytes\": [\n{}\n  ]\n}}", total, bytes.join(",\n"))]
+        },
+        _ => shares.iter().map(|(byte, p)| format!("  {:02x}  {:.9}", byte, p)).collect(),
+    }
+}
@@ -0,0 +1,165 @@
+/*
+    freqs carve: finds stretches of a file with entropy above a threshold,
+    which is where embedded encrypted or compressed payloads sit, and lists
+    them (optionally writing each one out to its own file). The file is
+    scanned a block at a time, and runs of high entropy blocks that add up
+    to at least the minimum size make a region.
+*/
+
+use std::fs;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use args::Options;
+use entropy;
+use log;
+use outfile;
+use report::{csv_field, json_str, Format};
+use stats;
+use walk;
+
+pub const DEFAULT_MIN_ENTROPY: f64 = 7.5;
+pub const DEFAULT_MIN_SIZE: u64 = 4096;
+// granularity of the scan, and so of region boundaries
+const BLOCK: usize = 1024;
+
+struct Carved {
+    path: String,
+    offset: u64,
+    len: u64,
+    entropy: f64,
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let min_entropy = opts.min_entropy.unwrap_or(DEFAULT_MIN_ENTROPY);
+    let min_size = opts.min_size.unwrap_or(DEFAULT_MIN_SIZE);
+
+    let mut carved = Vec::new();
+    for path in walk::expand(&opts.inputs, opts) {
+        match scan(&path, min_entropy, min_size) {
+            Ok(mut found) => carved.append(&mut found),
+            Err(e) => log::warn(&format!("skipping {}: {}", path, e)),
+        }
+    }
+
+    if let Some(ref dir) = opts.extract_dir {
+        for c in &carved {
+            if let Err(e) = extract(c, dir, opts.force) {
+                panic!("Could not extract {} at {:#x}: {}", c.path, c.offset, e);
+            }
+        }
+    }
+    render(format, &carved, min_entropy, min_size)
+}
+
+// entropy of a block, corrected for how few bytes a block has. a 1K block
+// of random bytes only reaches ~7.8 bits by the plain formula, the
+// miller-madow correction brings it back near 8 so thresholds mean the
+// same as they do for whole files.
+fn block_entropy(counts: &[u64; 256], len: usize) -> f64 {
+    let distinct = stats::distinct(counts) as f64;
+    let bias = (distinct - 1.0).max(0.0) / (2.0 * len as f64 * std::f64::consts::LN_2);
+    (entropy::shannon(counts, len as u64) + bias).min(8.0)
+}
+
+fn scan(path: &str, min_entropy: f64, min_size: u64) -> io::Result<Vec<Carved>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut found = Vec::new();
+    let mut block = vec![0u8; BLOCK];
+    let mut offset = 0u64;
+    // the region being built: where it starts and its counts so far
+    let mut region: Option<(u64, [u64; 256])> = None;
+
+    loop {
+        let n = read_block(&mut reader, &mut block)?;
+        let mut counts = [0u64; 256];
+        for byte in &block[..n] { counts[*byte as usize] += 1; }
+        let high = n > 0 && block_entropy(&counts, n) >= min_entropy;
+
+        if high {
+            let r = region.get_or_insert((offset, [0u64; 256]));
+            for (total, c) in r.1.iter_mut().zip(counts.iter()) { *total += *c; }
+        } else if let Some((start, totals)) = region.take() {
+            let len = offset - start;
+            if len >= min_size {
+                found.push(Carved { path: path.to_string(), offset: start, len, entropy: entropy::shannon(&totals, len) });
+            }
+        }
+        offset += n as u64;
+        if n == 0 { break; }
+    }
+    Ok(found)
+}
+
+// fills the block unless the file ends first, returning how much was read
+fn read_block<R: Read>(input: &mut R, block: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match input.read(&mut block[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// writes a region to <dir>/<file name>.<start>-<end>.bin
+fn extract(c: &Carved, dir: &str, force: bool) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let name = Path::new(&c.path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let out_path = Path::new(dir).join(format!("{}.{:x}-{:x}.bin", name, c.offset, c.offset + c.len));
+
+    let mut f = fs::File::open(&c.path)?;
+    f.seek(SeekFrom::Start(c.offset))?;
+    let mut out = outfile::create(&out_path, force)?;
+    io::copy(&mut f.take(c.len), &mut out)?;
+    out.finish()?;
+    log::info(&format!("wrote {}", out_path.display()));
+    Ok(())
+}
+
+fn render(format: Format, carved: &[Carved], min_entropy: f64, min_size: u64) -> Vec<String> {
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("path,offset,end,length,entropy")];
+            for c in carved {
+                lines.push(format!(
+                    "{},{},{},{},{:.6}",
+                    csv_field(&c.path), c.offset, c.offset + c.len, c.len, c.entropy
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let regions: Vec<String> = carved.iter()
+                .map(|c| format!(
+                    "    {{\"path\": {}, \"offset\": {}, \"end\": {}, \"length\": {}, \"entropy\": {:.6}}}",
+                    json_str(&c.path), c.offset, c.offset + c.len, c.len, c.entropy
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"min_entropy\": {},", min_entropy),
+                format!("  \"min_size\": {},", min_size),
+                format!("  \"regions\": [\n{}\n  ]", regions.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![format!(
+                "  {} regions of at least {} bytes above {} bits per byte",
+                carved.len(), min_size, min_entropy
+            )];
+            if carved.is_empty() { return lines; }
+            let width = carved.iter().map(|c| c.path.chars().count()).max().unwrap_or(0).max(4);
+            lines.push(format!("  {:<w$}  {:<10}  {:<10}  {:>12}  entropy", "path", "offset", "end", "length", w = width));
+            for c in carved {
+                lines.push(format!(
+                    "  {:<w$}  {:#010x}  {:#010x}  {:>12}  {:.4}",
+                    c.path, c.offset, c.offset + c.len, c.len, c.entropy, w = width
+                ));
+            }
+            lines
+        },
+    }
+}
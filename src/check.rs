@@ -0,0 +1,90 @@
+/*
+    freqs check: compares files against a saved baseline, and fails (exit
+    status 1) if any of them drifted further from it than allowed. Drift is
+    measured as the jensen-shannon divergence between the distributions.
+*/
+
+use analysis;
+use args::Options;
+use freqfile;
+use report::{csv_field, json_str, Format};
+use stats::{self, Gof};
+use walk;
+
+pub const DEFAULT_MAX_DIVERGENCE: f64 = 0.05;
+
+struct Outcome {
+    path: String,
+    divergence: f64,
+    statistic: f64,     // of --gof
+    degrees_of_freedom: usize,
+}
+
+// the lines to print, and whether every file stayed within the threshold
+pub fn run(opts: &Options, format: Format) -> (Vec<String>, bool) {
+    let baseline_path = opts.baseline.as_ref().unwrap();
+    let baseline = match freqfile::load(baseline_path) {
+        Ok(c) => c,
+        Err(e) => panic!("Could not load baseline {}: {}", baseline_path, e),
+    };
+    let base_p = stats::proportions(&baseline);
+    let max = opts.max_divergence.unwrap_or(DEFAULT_MAX_DIVERGENCE);
+    let gof = Gof::parse(&opts.gof_name).unwrap_or(Gof::ChiSquare);
+
+    let mut outcomes = Vec::new();
+    for path in walk::expand(&opts.inputs, opts) {
+        let a = match analysis::analyze(&path, opts, false) {
+            Ok(a) => a,
+            Err(_) => panic!("Could not open {}. Bad file or path?", path),
+        };
+        let (statistic, df) = gof.homogeneity(&baseline, &a.counts);
+        outcomes.push(Outcome {
+            divergence: stats::js_divergence(&base_p, &stats::proportions(&a.counts)),
+            statistic,
+            degrees_of_freedom: df,
+            path,
+        });
+    }
+    let ok = outcomes.iter().all(|o| o.divergence <= max);
+
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![format!("path,divergence,{},degrees_of_freedom,max_divergence,ok", gof.key())];
+            for o in &outcomes {
+                lines.push(format!(
+                    "{},{:.6},{:.6},{},{},{}",
+                    csv_field(&o.path), o.divergence, o.statistic, o.degrees_of_freedom, max, o.divergence <= max
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let files: Vec<String> = outcomes.iter()
+                .map(|o| format!(
+                    "    {{\"path\": {}, \"divergence\": {:.6}, \"{}\": {:.6}, \"degrees_of_freedom\": {}, \"ok\": {}}}",
+                    json_str(&o.path), o.divergence, gof.key(), o.statistic, o.degrees_of_freedom, o.divergence <= max
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"baseline\": {},", json_str(baseline_path)),
+                format!("  \"max_divergence\": {},", max),
+                format!("  \"files\": [\n{}\n  ],", files.join(",\n")),
+                format!("  \"ok\": {}", ok),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![format!("  baseline: {} (max divergence {})", baseline_path, max)];
+            for o in &outcomes {
+                lines.push(format!(
+                    "  {}  {}: divergence {:.6} ({} {:.4}, {} degrees of freedom)",
+                    if o.divergence <= max { "ok   " } else { "DRIFT" },
+                    o.path, o.divergence, gof.label(), o.statistic, o.degrees_of_freedom
+                ));
+            }
+            lines
+        },
+    };
+    (lines, ok)
+}
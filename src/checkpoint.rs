@@ -0,0 +1,129 @@
+/*
+    --checkpoint and --resume, for images so big that counting them takes
+    hours. Every so often the counts so far and the offset they run up to
+    are written to the checkpoint file; after an interruption, --resume
+    picks up from there instead of starting over. The checkpoint records
+    the file's size and modification time too, so it isn't applied to a
+    file that has changed since.
+*/
+
+use std::fs;
+use std::io::{stderr, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use analysis;
+use args::Options;
+use disk;
+use dump;
+use log;
+use outfile;
+use report::{self, json_str, Format};
+
+const CHUNKSIZE: usize = 1024 * 128;
+const INTERVAL: Duration = Duration::from_secs(30);
+
+struct State {
+    offset: u64,        // of the next byte to count
+    len: u64,
+    counts: [u64; 256],
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let checkpoint = opts.checkpoint_path.as_ref().unwrap();
+    let mut f = fs::File::open(path).unwrap_or_else(|_| panic!("Could not open file. Bad file or path?"));
+    let meta = f.metadata().unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+    let mtime = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map_or(0, |d| d.as_nanos() as u64);
+    // a block device's metadata has no length, it has to be seeked to
+    let size = match disk::len(&mut f, &meta) {
+        Ok(Some(size)) => size,
+        Ok(None) => panic!("--checkpoint needs a file or a disk to pick up again in, {} is neither", path),
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    };
+    let (start, end) = analysis::spans(opts)[0];
+    let end = end.min(size);
+
+    let mut state = State { offset: start, len: 0, counts: [0u64; 256] };
+    if opts.resume {
+        match fs::read_to_string(checkpoint) {
+            Ok(text) => match load(&text, path, size, mtime, end) {
+                Some(s) => {
+                    log::info(&format!("resuming at {:#x}, {} bytes already counted", s.offset, s.len));
+                    state = s;
+                },
+                None => panic!("Checkpoint {} is for another file, or the file has changed since", checkpoint),
+            },
+            Err(_) => log::info(&format!("no checkpoint at {} yet, starting from the beginning", checkpoint)),
+        }
+    }
+
+    f.seek(SeekFrom::Start(state.offset)).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+    let mut reader = BufReader::with_capacity(CHUNKSIZE, f);
+    let mut saved = Instant::now();
+    while state.offset < end {
+        let chunk = reader.fill_buf().unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+        if chunk.is_empty() { break; }
+        let take = (chunk.len() as u64).min(end - state.offset) as usize;
+        for byte in &chunk[..take] { state.counts[*byte as usize] += 1; }
+        reader.consume(take);
+        state.offset += take as u64;
+        state.len += take as u64;
+
+        if dump::requested() { dump::write(opts, &state.counts, state.len); }
+        if saved.elapsed() >= INTERVAL {
+            save(checkpoint, path, size, mtime, end, &state);
+            saved = Instant::now();
+        }
+        if log::progress() {
+            eprint!("\rcounted {} / {} bytes", state.offset - start, end - start);
+            let _ = stderr().flush();
+            log::drawn(true);
+        }
+    }
+    // a finished checkpoint resumes straight to the results
+    save(checkpoint, path, size, mtime, end, &state);
+    if log::progress() {
+        eprintln!("\ndone!");
+        log::drawn(false);
+    }
+
+    report::render_counts(format, &state.counts, opts.bars)
+}
+
+// replaced the way outfile replaces anything, so an interruption halfway
+// through writing doesn't lose the previous one
+fn save(checkpoint: &str, path: &str, size: u64, mtime: u64, end: u64, state: &State) {
+    let counts: Vec<String> = state.counts.iter().map(|c| c.to_string()).collect();
+    let text = format!(
+        "{{\n  \"path\": {},\n  \"size\": {},\n  \"mtime\": {},\n  \"end\": {},\n  \"offset\": {},\n  \"len\": {},\n  \"counts\": [{}]\n}}\n",
+        json_str(path), size, mtime, end, state.offset, state.len, counts.join(", ")
+    );
+    if let Err(e) = outfile::write(checkpoint, text.as_bytes(), true) {
+        panic!("Could not write checkpoint {}: {}", checkpoint, e);
+    }
+}
+
+// the state a checkpoint saved, if it was made for this very file and span
+fn load(text: &str, path: &str, size: u64, mtime: u64, end: u64) -> Option<State> {
+    if value(text, "path")? != json_str(path) { return None; }
+    let number = |key: &str| value(text, key).and_then(|v| v.parse::<u64>().ok());
+    if number("size")? != size || number("mtime")? != mtime || number("end")? != end { return None; }
+
+    let list = value(text, "counts")?;
+    let parsed: Vec<u64> = list.trim_start_matches('[').trim_end_matches(']')
+        .split(',')
+        .map(|c| c.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let mut counts = [0u64; 256];
+    if parsed.len() != 256 { return None; }
+    counts.copy_from_slice(&parsed);
+    Some(State { offset: number("offset")?, len: number("len")?, counts })
+}
+
+// the raw text of a top level value, as save writes them: one per line
+fn value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let prefix = format!("\"{}\": ", key);
+    text.lines()
+        .find_map(|line| line.trim().strip_prefix(prefix.as_str()))
+        .map(|v| v.trim_end_matches(','))
+}
@@ -0,0 +1,141 @@
+/*
+    freqs classify: a quick "what is this blob?" answer. The file's byte
+    distribution is scored against each of the bundled content profiles,
+    closest first. Confidence is relative: it says how much better one
+    profile fits than the others, not how well any of them fits.
+
+    The start of the file is also checked for a format signature, and the
+    two are combined into a single verdict. A signature is trusted over the
+    distribution (which can't tell a jpeg from an encrypted blob), but a
+    signature the distribution disagrees with gets pointed out, since that
+    usually means a damaged file or a header that's lying.
+*/
+
+use analysis::{self, Analysis};
+use args::Options;
+use magic::{self, Signature};
+use profiles::PROFILES;
+use report::{csv_field, json_str, Format};
+use stats;
+
+// how sharply confidence falls off with distance. smaller is sharper.
+const TEMPERATURE: f64 = 0.02;
+// how far behind the best match a signature's profile can be and still be
+// taken as agreeing with the distribution
+const AGREE_MARGIN: f64 = 0.1;
+
+pub struct Score {
+    pub name: &'static str,
+    pub distance: f64,      // jensen-shannon distance to the profile
+    pub confidence: f64,
+}
+
+// every profile, best match first
+pub fn score(counts: &[u64; 256]) -> Vec<Score> {
+    let p = stats::proportions(counts);
+    let distances: Vec<f64> = PROFILES.iter()
+        .map(|profile| stats::js_divergence(&p, &profile.proportions()).sqrt())
+        .collect();
+
+    // softmax over negative distances
+    let best = distances.iter().cloned().fold(f64::INFINITY, f64::min);
+    let weights: Vec<f64> = distances.iter().map(|d| (-(d - best) / TEMPERATURE).exp()).collect();
+    let total: f64 = weights.iter().sum();
+
+    let mut scores: Vec<Score> = PROFILES.iter().zip(distances.iter().zip(weights.iter()))
+        .map(|(profile, (d, w))| Score { name: profile.name, distance: *d, confidence: w / total })
+        .collect();
+    scores.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    scores
+}
+
+pub struct Verdict {
+    pub kind: String,
+    pub basis: String,  // what the verdict rests on
+}
+
+pub fn verdict(scores: &[Score], signature: Option<&Signature>) -> Verdict {
+    let best = &scores[0];
+    match signature {
+        Some(sig) => {
+            let agrees = sig.profile.map(|name| {
+                scores.iter().any(|s| s.name == name && s.distance <= best.distance + AGREE_MARGIN)
+            });
+            let basis = match agrees {
+                Some(true) => String::from("signature and distribution agree"),
+                Some(false) => format!(
+                    "signature only; the distribution looks more like {}, so the file may be damaged or mislabeled",
+                    best.name
+                ),
+                None => String::from("signature"),
+            };
+            Verdict { kind: String::from(sig.name), basis }
+        },
+        None => Verdict {
+            kind: String::from(best.name),
+            basis: format!("distribution only, {:.1}% confidence", best.confidence * 100.0),
+        },
+    }
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let a = match analysis::analyze(path, opts, true) {
+        Ok(a) => a,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    if a.len == 0 { panic!("Nothing to classify, {} is empty", path); }
+    render(format, path, &a)
+}
+
+// the verdict on an analysis that isn't empty, for the repl too
+pub fn render(format: Format, path: &str, a: &Analysis) -> Vec<String> {
+    let scores = score(&a.counts);
+    let signature = magic::detect(&a.head);
+    let v = verdict(&scores, signature);
+    let signature_name = signature.map(|s| s.name);
+
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("profile,distance,confidence")];
+            for s in &scores { lines.push(format!("{},{:.6},{:.6}", s.name, s.distance, s.confidence)); }
+            lines.push(String::from(""));
+            lines.push(String::from("verdict,basis,signature"));
+            lines.push(format!("{},{},{}", csv_field(&v.kind), csv_field(&v.basis), signature_name.unwrap_or("")));
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = scores.iter()
+                .map(|s| format!(
+                    "    {{\"profile\": {}, \"distance\": {:.6}, \"confidence\": {:.6}}}",
+                    json_str(s.name), s.distance, s.confidence
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"path\": {},", json_str(path)),
+                format!("  \"verdict\": {},", json_str(&v.kind)),
+                format!("  \"basis\": {},", json_str(&v.basis)),
+                format!("  \"signature\": {},", signature_name.map_or(String::from("null"), json_str)),
+                format!("  \"best_profile\": {},", json_str(scores[0].name)),
+                format!("  \"scores\": [\n{}\n  ]", entries.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  {}: {} ({})", path, v.kind, v.basis),
+                String::from(""),
+                format!("  signature: {}", signature_name.unwrap_or("none")),
+                format!("  distribution: most likely {} ({:.1}% confidence)", scores[0].name, scores[0].confidence * 100.0),
+                String::from(""),
+                format!("  {:<20}  {:>8}  {:>10}", "profile", "distance", "confidence"),
+            ];
+            for s in &scores {
+                lines.push(format!("  {:<20}  {:>8.4}  {:>9.1}%", s.name, s.distance, s.confidence * 100.0));
+            }
+            lines
+        },
+    }
+}
@@ -0,0 +1,136 @@
+/*
+    freqs cluster: groups files by how alike their byte distributions are,
+    using k-means over the normalized histograms. Handy for triaging a dump
+    of unlabeled files: text ends up apart from compressed data, which ends
+    up apart from executables, and so on.
+*/
+
+use analysis::{self, Summary};
+use args::Options;
+use entropy;
+use report::{csv_field, json_str, Format};
+use rng::Rng;
+use stats;
+use walk;
+
+// the same seed every run, so the same files give the same clusters
+const SEED: u64 = 0x0066_7265_7173; // "freqs"
+const MAX_ROUNDS: usize = 100;
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let summaries = analysis::analyze_files(&walk::expand(&opts.inputs, opts), opts);
+    let points: Vec<[f64; 256]> = summaries.iter().map(|s| stats::proportions(&s.analysis.counts)).collect();
+    let k = opts.clusters.unwrap_or_else(|| default_k(points.len())).min(points.len()).max(1);
+    let assignment = kmeans(&points, k, &mut Rng::new(SEED));
+
+    // biggest clusters first, empty ones dropped
+    let mut clusters: Vec<Vec<&Summary>> = (0..k)
+        .map(|c| summaries.iter().zip(assignment.iter()).filter(|&(_, a)| *a == c).map(|(s, _)| s).collect())
+        .filter(|members: &Vec<&Summary>| !members.is_empty())
+        .collect();
+    clusters.sort_by_key(|members| std::cmp::Reverse(members.len()));
+
+    render(format, &clusters)
+}
+
+// rule of thumb when no number of clusters is given
+fn default_k(n: usize) -> usize {
+    ((n as f64 / 2.0).sqrt().round() as usize).max(1)
+}
+
+fn distance(a: &[f64; 256], b: &[f64; 256]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+fn nearest(point: &[f64; 256], centroids: &[[f64; 256]]) -> (usize, f64) {
+    centroids.iter()
+        .map(|c| distance(point, c))
+        .enumerate()
+        .fold((0, f64::INFINITY), |best, (i, d)| if d < best.1 { (i, d) } else { best })
+}
+
+// plain k-means with k-means++ seeding. returns the cluster of each point.
+fn kmeans(points: &[[f64; 256]], k: usize, rng: &mut Rng) -> Vec<usize> {
+    if points.is_empty() { return Vec::new(); }
+
+    // seeding: each new centroid is picked with odds proportional to its
+    // squared distance from the nearest centroid so far
+    let mut centroids = vec![points[rng.below(points.len() as u64) as usize]];
+    while centroids.len() < k {
+        let weights: Vec<f64> = points.iter().map(|p| nearest(p, &centroids).1).collect();
+        let total: f64 = weights.iter().sum();
+        if total == 0.0 { break; } // fewer distinct points than clusters
+        let mut target = rng.next_f64() * total;
+        let mut pick = points.len() - 1;
+        for (i, w) in weights.iter().enumerate() {
+            if target < *w { pick = i; break; }
+            target -= w;
+        }
+        centroids.push(points[pick]);
+    }
+
+    let mut assignment = vec![usize::MAX; points.len()];
+    for _ in 0..MAX_ROUNDS {
+        let next: Vec<usize> = points.iter().map(|p| nearest(p, &centroids).0).collect();
+        if next == assignment { break; }
+        assignment = next;
+
+        // move each centroid to the mean of its members, leaving it be if
+        // it lost all of them
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&[f64; 256]> = points.iter().zip(assignment.iter())
+                .filter(|&(_, a)| *a == c)
+                .map(|(p, _)| p)
+                .collect();
+            if members.is_empty() { continue; }
+            let mut mean = [0f64; 256];
+            for m in &members {
+                for (acc, v) in mean.iter_mut().zip(m.iter()) { *acc += v; }
+            }
+            for v in mean.iter_mut() { *v /= members.len() as f64; }
+            *centroid = mean;
+        }
+    }
+    assignment
+}
+
+fn mean_entropy(members: &[&Summary]) -> f64 {
+    let sum: f64 = members.iter().map(|s| entropy::shannon(&s.analysis.counts, s.analysis.len)).sum();
+    sum / members.len() as f64
+}
+
+fn render(format: Format, clusters: &[Vec<&Summary>]) -> Vec<String> {
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("path,cluster")];
+            for (c, members) in clusters.iter().enumerate() {
+                for s in members { lines.push(format!("{},{}", csv_field(&s.path), c + 1)); }
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = clusters.iter().enumerate()
+                .map(|(c, members)| {
+                    let files: Vec<String> = members.iter().map(|s| json_str(&s.path)).collect();
+                    format!(
+                        "    {{\"cluster\": {}, \"mean_entropy\": {:.6}, \"files\": [{}]}}",
+                        c + 1, mean_entropy(members), files.join(", ")
+                    )
+                })
+                .collect();
+            vec![format!("{{\n  \"clusters\": [\n{}\n  ]\n}}", entries.join(",\n"))]
+        },
+        _ => {
+            let mut lines = Vec::new();
+            for (c, members) in clusters.iter().enumerate() {
+                lines.push(String::from(""));
+                lines.push(format!(
+                    "  cluster {} ({} files, mean entropy {:.4})",
+                    c + 1, members.len(), mean_entropy(members)
+                ));
+                for s in members { lines.push(format!("    {}", s.path)); }
+            }
+            lines
+        },
+    }
+}
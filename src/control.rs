@@ -0,0 +1,112 @@
+/*
+    --control <socket>: a unix socket for scripting long running counts
+    (--follow and --snapshot-every) without signals or scraping the
+    terminal. Clients send one command per line and get a reply ending in
+    an empty line: status, snapshot (the byte table so far), reset (start
+    counting from zero again) and stop (finish up as if the input ended).
+*/
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use args::Options;
+use entropy;
+use report::{self, Format};
+
+pub struct Control {
+    path: String,
+    shared: Arc<Mutex<Shared>>,
+}
+
+struct Shared {
+    len: u64,
+    counts: [u64; 256],
+    started: Instant,
+    reset: bool,
+    stop: bool,
+}
+
+impl Control {
+    pub fn open(path: &str, opts: &Options) -> Control {
+        let shared = Arc::new(Mutex::new(Shared { len: 0, counts: [0u64; 256], started: Instant::now(), reset: false, stop: false }));
+        listen(path, opts.bars, shared.clone());
+        Control { path: path.to_string(), shared }
+    }
+
+    // called by the counting loop between chunks: hands over the counts
+    // so far, and clears them if a reset came in. true if told to stop
+    pub fn sync(&self, counts: &mut [u64; 256], len: &mut u64) -> bool {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.reset {
+            *counts = [0u64; 256];
+            *len = 0;
+            shared.reset = false;
+        }
+        shared.counts = *counts;
+        shared.len = *len;
+        shared.stop
+    }
+}
+
+// the socket goes away with the count it controls
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn reply(command: &str, bars: bool, shared: &Mutex<Shared>) -> String {
+    let mut shared = shared.lock().unwrap();
+    match command {
+        "status" => format!(
+            "bytes {}\nentropy {:.4}\nrunning {}s\n",
+            shared.len, entropy::shannon(&shared.counts, shared.len), shared.started.elapsed().as_secs()
+        ),
+        // without the table's leading blank line, which would end the reply
+        "snapshot" => report::render_counts(Format::Text, &shared.counts, bars).iter()
+            .skip_while(|l| l.is_empty())
+            .map(|l| format!("{}\n", l))
+            .collect(),
+        "reset" => {
+            shared.counts = [0u64; 256];
+            shared.len = 0;
+            shared.reset = true;
+            String::from("ok\n")
+        },
+        "stop" => { shared.stop = true; String::from("ok\n") },
+        _ => String::from("unknown command, try status, snapshot, reset or stop\n"),
+    }
+}
+
+#[cfg(unix)]
+fn listen(path: &str, bars: bool, shared: Arc<Mutex<Shared>>) {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    // a socket left behind by an earlier run is in the way of binding
+    if fs::symlink_metadata(path).map(|m| m.file_type().is_socket()).unwrap_or(false) {
+        let _ = fs::remove_file(path);
+    }
+    let listener = UnixListener::bind(path).unwrap_or_else(|e| panic!("Could not open control socket {}: {}", path, e));
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(|s| s.ok()) {
+            let mut out = &stream;
+            for line in BufReader::new(&stream).lines() {
+                let command = match line {
+                    Ok(l) => l.trim().to_string(),
+                    Err(_) => break,
+                };
+                if command.is_empty() { continue; }
+                if writeln!(out, "{}", reply(&command, bars, &shared)).is_err() || command == "stop" { break; }
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn listen(_path: &str, _bars: bool, _shared: Arc<Mutex<Shared>>) {
+    panic!("--control needs unix domain sockets, which this system doesn't have");
+}
@@ -0,0 +1,136 @@
+/*
+    --decode: undoes a text encoding before counting, for payloads lifted
+    out of logs, json or captures. The decoders work on the stream a chunk
+    at a time, like everything else, and sit after --decompress, so an
+    encoded payload inside a gzipped log works too.
+*/
+
+use std::io;
+
+pub const KINDS: [&str; 3] = ["base64", "hex", "url"];
+
+pub struct Decoder {
+    kind: Kind,
+    bits: u32,          // partial base64 group or hex digit
+    nbits: u32,
+    pending: Vec<u8>,   // a percent escape split over chunks
+    offset: u64,        // of the next input byte, for errors
+    error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Base64,
+    Hex,
+    Url,
+}
+
+impl Decoder {
+    pub fn new(name: &str) -> Decoder {
+        let kind = match name {
+            "base64" => Kind::Base64,
+            "hex" => Kind::Hex,
+            _ => Kind::Url,
+        };
+        Decoder { kind, bits: 0, nbits: 0, pending: Vec::new(), offset: 0, error: None }
+    }
+
+    // decodes a chunk, passing on what came out of it. after an error the
+    // rest of the input is ignored, finish reports it
+    pub fn feed(&mut self, chunk: &[u8], out: &mut dyn FnMut(&[u8])) {
+        if self.error.is_some() { return; }
+        let mut decoded = Vec::with_capacity(chunk.len());
+        for byte in chunk {
+            match self.kind {
+                Kind::Base64 => self.base64(*byte, &mut decoded),
+                Kind::Hex => self.hex(*byte, &mut decoded),
+                Kind::Url => self.url(*byte, &mut decoded),
+            }
+            if self.error.is_some() { break; }
+            self.offset += 1;
+        }
+        if !decoded.is_empty() { out(&decoded); }
+    }
+
+    pub fn finish(mut self, out: &mut dyn FnMut(&[u8])) -> io::Result<()> {
+        if self.error.is_none() && self.kind == Kind::Hex && self.nbits != 0 {
+            self.error = Some(String::from("odd number of hex digits"));
+        }
+        // a lone % or %x at the very end stays as it was
+        if !self.pending.is_empty() { out(&self.pending); }
+        match self.error {
+            Some(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(()),
+        }
+    }
+
+    fn invalid(&mut self, byte: u8) {
+        self.error = Some(format!("invalid {} byte {:#04x} at offset {}", self.name(), byte, self.offset));
+    }
+
+    fn name(&self) -> &str {
+        match self.kind { Kind::Base64 => "base64", Kind::Hex => "hex", Kind::Url => "url" }
+    }
+
+    // both the standard and the url safe alphabet. padding ends a group, so
+    // concatenated padded blocks decode fine
+    fn base64(&mut self, byte: u8, decoded: &mut Vec<u8>) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => { self.bits = 0; self.nbits = 0; return; },
+            b' ' | b'\t' | b'\r' | b'\n' => return,
+            _ => return self.invalid(byte),
+        };
+        self.bits = (self.bits << 6) | value as u32;
+        self.nbits += 6;
+        if self.nbits >= 8 {
+            self.nbits -= 8;
+            decoded.push((self.bits >> self.nbits) as u8);
+            self.bits &= (1 << self.nbits) - 1;
+        }
+    }
+
+    fn hex(&mut self, byte: u8, decoded: &mut Vec<u8>) {
+        let value = match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'f' => byte - b'a' + 10,
+            b'A'..=b'F' => byte - b'A' + 10,
+            b' ' | b'\t' | b'\r' | b'\n' => return,
+            _ => return self.invalid(byte),
+        };
+        if self.nbits == 0 {
+            self.bits = value as u32;
+            self.nbits = 4;
+        } else {
+            decoded.push((self.bits << 4) as u8 | value);
+            self.nbits = 0;
+        }
+    }
+
+    // percent escapes, and + for a space as in form data. anything that
+    // isn't a proper escape is passed through as is
+    fn url(&mut self, byte: u8, decoded: &mut Vec<u8>) {
+        if self.pending.is_empty() {
+            match byte {
+                b'%' => self.pending.push(byte),
+                b'+' => decoded.push(b' '),
+                _ => decoded.push(byte),
+            }
+            return;
+        }
+        if !byte.is_ascii_hexdigit() {
+            decoded.append(&mut self.pending);
+            return self.url(byte, decoded);
+        }
+        self.pending.push(byte);
+        if self.pending.len() == 3 {
+            let text = String::from_utf8_lossy(&self.pending[1..]).into_owned();
+            decoded.push(u8::from_str_radix(&text, 16).unwrap());
+            self.pending.clear();
+        }
+    }
+}
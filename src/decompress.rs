@@ -0,0 +1,203 @@
+/*
+    --decompress: counts what's inside a compressed file rather than the
+    compressed bytes, which all look alike. gzip is decoded here, on top of
+    the inflate module; xz, zstd and bzip2 are streamed through the system's
+    own decompressor, which has to be installed for those.
+*/
+
+use std::io::{self, BufRead, Read};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use digest::Crc32;
+use inflate;
+use magic;
+
+pub const METHODS: [&str; 5] = ["gz", "xz", "zst", "bz2", "auto"];
+
+const CHUNKSIZE: usize = 1024 * 128;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Method {
+    Gzip,
+    Xz,
+    Zstd,
+    Bzip2,
+}
+
+impl Method {
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Gzip => "gzip",
+            Method::Xz => "xz",
+            Method::Zstd => "zstd",
+            Method::Bzip2 => "bzip2",
+        }
+    }
+}
+
+// the method to use for a file starting with head, None meaning it's read
+// as is. auto goes by the file's signature and leaves anything else alone.
+pub fn pick(name: &str, head: &[u8]) -> Option<Method> {
+    match name {
+        "gz" => Some(Method::Gzip),
+        "xz" => Some(Method::Xz),
+        "zst" => Some(Method::Zstd),
+        "bz2" => Some(Method::Bzip2),
+        _ => match magic::detect(head).map(|s| s.name) {
+            Some("gzip compressed") => Some(Method::Gzip),
+            Some("xz compressed") => Some(Method::Xz),
+            Some("zstd compressed") => Some(Method::Zstd),
+            Some("bzip2 compressed") => Some(Method::Bzip2),
+            _ => None,
+        },
+    }
+}
+
+// decompresses input, passing the output to feed a chunk at a time
+pub fn stream<R: BufRead + Send>(method: Method, input: &mut R, feed: &mut dyn FnMut(&[u8])) -> io::Result<()> {
+    match method {
+        Method::Gzip => gunzip(input, feed),
+        Method::Xz => external("xz", input, feed),
+        Method::Zstd => external("zstd", input, feed),
+        Method::Bzip2 => external("bzip2", input, feed),
+    }
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+// rfc 1952. a file can hold several members back to back, which decompress
+// to their concatenation
+fn gunzip<R: BufRead>(input: &mut R, feed: &mut dyn FnMut(&[u8])) -> io::Result<()> {
+    let mut members = 0;
+    loop {
+        // like gzip itself, ignore anything after the last member
+        let more = input.fill_buf()?.starts_with(b"\x1f\x8b");
+        if !more && members > 0 { return Ok(()); }
+
+        let mut header = [0u8; 10];
+        input.read_exact(&mut header)?;
+        if !header.starts_with(b"\x1f\x8b\x08") { return Err(bad("not gzip data")); }
+        let flags = header[3];
+        if flags & 0x04 != 0 {
+            let mut len = [0u8; 2];
+            input.read_exact(&mut len)?;
+            io::copy(&mut input.by_ref().take(u16::from_le_bytes(len) as u64), &mut io::sink())?;
+        }
+        // original name and comment, both nul terminated
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 { input.read_until(0, &mut Vec::new())?; }
+        }
+        if flags & 0x02 != 0 { input.read_exact(&mut [0u8; 2])?; }
+
+        let mut crc = Crc32::new();
+        let mut size = 0u32;
+        inflate::inflate(input, &mut |chunk| {
+            crc.update(chunk);
+            size = size.wrapping_add(chunk.len() as u32);
+            feed(chunk);
+        })?;
+
+        let mut trailer = [0u8; 8];
+        input.read_exact(&mut trailer)?;
+        let expected_crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let expected_size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc.finish() != expected_crc || size != expected_size {
+            return Err(bad("gzip checksum mismatch, the file is damaged"));
+        }
+        members += 1;
+    }
+}
+
+// the input is piped in rather than named, so pipes and devices work too
+fn external<R: Read + Send>(tool: &str, input: &mut R, feed: &mut dyn FnMut(&[u8])) -> io::Result<()> {
+    let mut child = match Command::new(tool).arg("-dc")
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()
+    {
+        Ok(c) => c,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} isn't installed, it's needed to decompress this", tool)));
+        },
+        Err(e) => return Err(e),
+    };
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut out = child.stdout.take().unwrap();
+    thread::scope(|scope| {
+        // fed from a thread of its own, or the tool could stall on output
+        // nobody is reading yet
+        let writer = scope.spawn(move || io::copy(input, &mut stdin));
+        let mut chunk = vec![0u8; CHUNKSIZE];
+        loop {
+            let n = out.read(&mut chunk)?;
+            if n == 0 { break; }
+            feed(&chunk[..n]);
+        }
+        match writer.join().unwrap() {
+            // the tool quitting early is for its exit status to explain
+            Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Ok(()),
+        }
+    })?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} failed ({})", tool, status)));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        gunzip(&mut &data[..], &mut |chunk| out.extend_from_slice(chunk))?;
+        Ok(out)
+    }
+
+    // "one " and "two" gzipped on their own, one after the other
+    const MEMBERS: [u8; 47] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0xcf, 0x4b, 0x55, 0x00, 0x00,
+        0x49, 0x61, 0xac, 0x23, 0x04, 0x00, 0x00, 0x00,
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x2b, 0x29, 0xcf, 0x07, 0x00, 0x66,
+        0x8a, 0xca, 0x11, 0x03, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn members_back_to_back() {
+        assert_eq!(run(&MEMBERS).unwrap(), b"one two");
+        assert_eq!(run(&MEMBERS[..24]).unwrap(), b"one ");
+        // like gzip, what follows the last member is left alone
+        assert_eq!(run(&[&MEMBERS[..], b"junk"].concat()).unwrap(), b"one two");
+    }
+
+    #[test]
+    fn truncated() {
+        // cut off after the first member, or a byte into the second, which
+        // is too little to tell from junk
+        for len in (0..MEMBERS.len()).filter(|len| *len != 24 && *len != 25) {
+            assert!(run(&MEMBERS[..len]).is_err(), "cut off at {}", len);
+        }
+    }
+
+    #[test]
+    fn damaged() {
+        let mut crc = MEMBERS;
+        crc[16] ^= 1;
+        assert_eq!(run(&crc).unwrap_err().to_string(), "gzip checksum mismatch, the file is damaged");
+        let mut size = MEMBERS;
+        size[44] ^= 1;
+        assert!(run(&size).is_err());
+        assert_eq!(run(b"not gzip at all").unwrap_err().to_string(), "not gzip data");
+        for i in 10..MEMBERS.len() {
+            let mut damaged = MEMBERS;
+            damaged[i] ^= 0x40;
+            let _ = run(&damaged);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+/*
+    freqs diff: compares the byte distributions of two files side by side,
+    byte by byte and overall. With --previous, a file against the last
+    result the cache has of it, most changed bytes first: what changed in
+    it since the last run?
+*/
+
+use analysis::{self, Analysis};
+use args::Options;
+use cache::Key;
+use entropy;
+use history;
+use report::{byte_label, csv_field, json_str, Format};
+use special;
+use stats::{self, Gof};
+
+// how many of the most changed bytes --previous shows
+const DEFAULT_TOP: usize = 10;
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    if opts.previous { return previous(opts, format); }
+    let (path_a, path_b) = (&opts.inputs[0], &opts.inputs[1]);
+    let a = open(path_a, opts);
+    let b = open(path_b, opts);
+    render(format, opts, path_a, &a, path_b, &b, None)
+}
+
+fn previous(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let dir = opts.cache_dir.as_ref().unwrap();
+    let key = Key::new(path, opts).unwrap_or_else(|| panic!("Could not open {}. Bad file or path?", path));
+    let (before, mtime) = key.previous(dir)
+        .unwrap_or_else(|| panic!("Nothing in the cache at {} for {} yet, it needs a run with --cache first", dir, path));
+    // counting it again caches it too, so the next --previous starts here
+    let now = open(path, opts);
+    let then = format!("{} as of {}", path, history::timestamp(mtime));
+    render(format, opts, &then, &before, path, &now, Some(opts.top.unwrap_or(DEFAULT_TOP)))
+}
+
+fn open(path: &str, opts: &Options) -> Analysis {
+    match analysis::analyze(path, opts, true) {
+        Ok(a) => a,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    }
+}
+
+// one row per byte present in either file
+struct Row {
+    byte: u8,
+    a: u64,
+    b: u64,
+    pct_a: f64,
+    pct_b: f64,
+    ci_a: Option<(f64, f64)>,
+    ci_b: Option<(f64, f64)>,
+}
+
+// with most_changed, only that many rows, the biggest changes in share first
+fn render(format: Format, opts: &Options, path_a: &str, a: &Analysis, path_b: &str, b: &Analysis, most_changed: Option<usize>) -> Vec<String> {
+    let gof = Gof::parse(&opts.gof_name).unwrap_or(Gof::ChiSquare);
+    // with --ci, the wilson interval of each share, in percent
+    let z = opts.ci.map(|level| special::normal_quantile(1.0 - (1.0 - level / 100.0) / 2.0));
+    let (total_a, total_b) = (a.counts.iter().sum(), b.counts.iter().sum());
+    let interval = |count: u64, total: u64| z.map(|z| {
+        let (low, high) = stats::wilson(count, total, z);
+        (low * 100.0, high * 100.0)
+    });
+    let p = stats::proportions(&a.counts);
+    let q = stats::proportions(&b.counts);
+    let (chi, df) = gof.homogeneity(&a.counts, &b.counts);
+    let js = stats::js_divergence(&p, &q);
+    let entropy_a = entropy::shannon(&a.counts, a.len);
+    let entropy_b = entropy::shannon(&b.counts, b.len);
+
+    let mut rows: Vec<Row> = (0..256)
+        .filter(|i| a.counts[*i] != 0 || b.counts[*i] != 0)
+        .map(|i| Row {
+            byte: i as u8,
+            a: a.counts[i],
+            b: b.counts[i],
+            pct_a: p[i] * 100.0,
+            pct_b: q[i] * 100.0,
+            ci_a: interval(a.counts[i], total_a),
+            ci_b: interval(b.counts[i], total_b),
+        })
+        .collect();
+    if let Some(n) = most_changed {
+        rows.sort_by(|x, y| (y.pct_b - y.pct_a).abs().total_cmp(&(x.pct_b - x.pct_a).abs()));
+        rows.truncate(n);
+    }
+
+    match format {
+        Format::Csv => {
+            let mut header = String::from("byte,label,a_count,a_percent,b_count,b_percent,delta,delta_percent");
+            if z.is_some() { header.push_str(",a_ci_low,a_ci_high,b_ci_low,b_ci_high"); }
+            let mut lines = vec![header];
+            for r in &rows {
+                let mut line = format!(
+                    "{},{},{},{:.6},{},{:.6},{},{:.6}",
+                    r.byte, csv_field(&byte_label(r.byte)), r.a, r.pct_a, r.b, r.pct_b,
+                    r.b as i64 - r.a as i64, r.pct_b - r.pct_a
+                );
+                if let (Some(ca), Some(cb)) = (r.ci_a, r.ci_b) {
+                    line.push_str(&format!(",{:.6},{:.6},{:.6},{:.6}", ca.0, ca.1, cb.0, cb.1));
+                }
+                lines.push(line);
+            }
+            lines.push(String::from(""));
+            lines.push(String::from("metric,value"));
+            lines.push(format!("{},{:.6}", gof.key(), chi));
+            lines.push(format!("degrees_of_freedom,{}", df));
+            lines.push(format!("js_divergence,{:.6}", js));
+            lines
+        },
+        Format::Json => {
+            let bytes: Vec<String> = rows.iter()
+                .map(|r| format!(
+                    "    {{\"byte\": {}, \"label\": {}, \"a\": {}, \"b\": {}, \"a_percent\": {:.6}, \"b_percent\": {:.6}, \"delta\": {}, \"delta_percent\": {:.6}{}}}",
+                    r.byte, json_str(&byte_label(r.byte)), r.a, r.b, r.pct_a, r.pct_b,
+                    r.b as i64 - r.a as i64, r.pct_b - r.pct_a,
+                    match (r.ci_a, r.ci_b) {
+                        (Some(ca), Some(cb)) => format!(", \"a_ci\": [{:.6}, {:.6}], \"b_ci\": [{:.6}, {:.6}]", ca.0, ca.1, cb.0, cb.1),
+                        _ => String::new(),
+                    }
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"a\": {{\"path\": {}, \"size\": {}, \"entropy\": {:.6}}},", json_str(path_a), a.len, entropy_a),
+                format!("  \"b\": {{\"path\": {}, \"size\": {}, \"entropy\": {:.6}}},", json_str(path_b), b.len, entropy_b),
+                format!("  \"bytes\": [\n{}\n  ],", bytes.join(",\n")),
+                format!("  \"{}\": {:.6},", gof.key(), chi),
+                format!("  \"degrees_of_freedom\": {},", df),
+                format!("  \"js_divergence\": {:.6}", js),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let label_width = rows.iter().map(|r| byte_label(r.byte).chars().count()).max().unwrap_or(0).max(5);
+            let mut lines = vec![
+                String::from(""),
+                format!("  a: {} ({} bytes, entropy {:.4})", path_a, a.len, entropy_a),
+                format!("  b: {} ({} bytes, entropy {:.4})", path_b, b.len, entropy_b),
+                String::from(""),
+                format!(
+                    "  {:<3}  {:<w$}  {:>12}  {:>9}  {:>12}  {:>9}  {:>13}  {:>10}{}",
+                    "hex", "label", "a count", "a %", "b count", "b %", "delta", "delta %",
+                    opts.ci.map_or(String::new(), |level| format!("  {:>19}  {:>19}", format!("a {}% interval", level), format!("b {}% interval", level))),
+                    w = label_width
+                ),
+            ];
+            let range = |ci: Option<(f64, f64)>| ci.map_or(String::new(), |(low, high)| format!("  {:>19}", format!("{:.4}-{:.4}%", low, high)));
+            for r in &rows {
+                lines.push(format!(
+                    "  {:<3}  {:<w$}  {:>12}  {:>8.4}%  {:>12}  {:>8.4}%  {:>+13}  {:>+9.4}%{}{}",
+                    format!("{:x}", r.byte), byte_label(r.byte), r.a, r.pct_a, r.b, r.pct_b,
+                    r.b as i64 - r.a as i64, r.pct_b - r.pct_a, range(r.ci_a), range(r.ci_b), w = label_width
+                ));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}: {:.4} ({} degrees of freedom)", gof.label(), chi, df));
+            lines.push(format!("  js divergence: {:.6} bits", js));
+            lines
+        },
+    }
+}
@@ -0,0 +1,331 @@
+/*
+    Checksums and digests, for binary output formats and for telling files
+    apart. All of them are incremental, so they can be fed one chunk at a
+    time.
+*/
+
+pub struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        // standard reflected crc32 (as used by png, zip, gzip...)
+        let mut table = [0u32; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+        Crc32 { table, crc: 0xffff_ffff }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.crc = self.table[((self.crc ^ *byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 { self.crc ^ 0xffff_ffff }
+}
+
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Adler32 { Adler32 { a: 1, b: 0 } }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.a = (self.a + *byte as u32) % 65521;
+            self.b = (self.b + self.a) % 65521;
+        }
+    }
+
+    pub fn finish(&self) -> u32 { (self.b << 16) | self.a }
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    filled: usize,
+    len: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Sha256 {
+        Sha256 {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+            ],
+            block: [0u8; 64],
+            filled: 0,
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let take = (64 - self.filled).min(data.len());
+            self.block[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            if self.filled == 64 {
+                let block = self.block;
+                self.compress(&block);
+                self.filled = 0;
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; 32] {
+        // pad with a 1 bit, zeros, then the length in bits
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.filled != 56 { self.update(&[0]); }
+        self.update(&bits.to_be_bytes());
+
+        let mut out = [0u8; 32];
+        for (chunk, word) in out.chunks_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let mut v = self.state;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[i]).wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+            v = [t1.wrapping_add(t2), v[0], v[1], v[2], v[3].wrapping_add(t1), v[4], v[5], v[6]];
+        }
+        for (s, x) in self.state.iter_mut().zip(v.iter()) { *s = s.wrapping_add(*x); }
+    }
+}
+
+pub fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// per round shift amounts, and the sines table of rfc 1321
+const MD5_S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const MD5_K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+// broken for security, but still what a lot of records of files go by
+pub struct Md5 {
+    state: [u32; 4],
+    block: [u8; 64],
+    filled: usize,
+    len: u64,
+}
+
+impl Md5 {
+    pub fn new() -> Md5 {
+        Md5 { state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476], block: [0u8; 64], filled: 0, len: 0 }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let take = (64 - self.filled).min(data.len());
+            self.block[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            if self.filled == 64 {
+                let block = self.block;
+                self.compress(&block);
+                self.filled = 0;
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; 16] {
+        // same padding as sha256, but the length goes little endian
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.filled != 56 { self.update(&[0]); }
+        self.update(&bits.to_le_bytes());
+
+        let mut out = [0u8; 16];
+        for (chunk, word) in out.chunks_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut m = [0u32; 16];
+        for (i, word) in block.chunks(4).enumerate() {
+            m[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(MD5_K[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_S[i]));
+        }
+        for (s, x) in self.state.iter_mut().zip([a, b, c, d].iter()) { *s = s.wrapping_add(*x); }
+    }
+}
+
+// the digests --hash can compute, by name
+pub const NAMES: [&str; 4] = ["sha256", "md5", "crc32", "adler32"];
+
+pub enum Hasher {
+    Sha256(Sha256),
+    Md5(Md5),
+    Crc32(Box<Crc32>),  // the table makes it big
+    Adler32(Adler32),
+}
+
+impl Hasher {
+    pub fn new(name: &str) -> Option<Hasher> {
+        match name {
+            "sha256" => Some(Hasher::Sha256(Sha256::new())),
+            "md5" => Some(Hasher::Md5(Md5::new())),
+            "crc32" => Some(Hasher::Crc32(Box::new(Crc32::new()))),
+            "adler32" => Some(Hasher::Adler32(Adler32::new())),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Hasher::Sha256(_) => "sha256",
+            Hasher::Md5(_) => "md5",
+            Hasher::Crc32(_) => "crc32",
+            Hasher::Adler32(_) => "adler32",
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::Sha256(ref mut h) => h.update(data),
+            Hasher::Md5(ref mut h) => h.update(data),
+            Hasher::Crc32(ref mut h) => h.update(data),
+            Hasher::Adler32(ref mut h) => h.update(data),
+        }
+    }
+
+    // in hex, the checksums as 8 digits like crc32 and cksum tools print them
+    pub fn finish(self) -> String {
+        match self {
+            Hasher::Sha256(h) => hex(&h.finish()),
+            Hasher::Md5(h) => hex(&h.finish()),
+            Hasher::Crc32(h) => format!("{:08x}", h.finish()),
+            Hasher::Adler32(h) => format!("{:08x}", h.finish()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // by name, and fed in pieces of the given size
+    fn digest(name: &str, data: &[u8], piece: usize) -> String {
+        let mut h = Hasher::new(name).unwrap();
+        for chunk in data.chunks(piece.max(1)) { h.update(chunk); }
+        h.finish()
+    }
+
+    #[test]
+    fn known_answers() {
+        let million = vec![b'a'; 1_000_000];
+        let vectors: [(&[u8], [&str; 4]); 3] = [
+            (b"", [
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "d41d8cd98f00b204e9800998ecf8427e", "00000000", "00000001",
+            ]),
+            (b"abc", [
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "900150983cd24fb0d6963f7d28e17f72", "352441c2", "024d0127",
+            ]),
+            (&million, [
+                "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0",
+                "7707d6ae4e027c70eea2a935c2296f21", "dc25bfbc", "15d870f9",
+            ]),
+        ];
+        for (data, answers) in vectors.iter() {
+            for (name, answer) in NAMES.iter().zip(answers.iter()) {
+                assert_eq!(digest(name, data, data.len()), *answer, "{} of {} bytes", name, data.len());
+            }
+        }
+    }
+
+    #[test]
+    fn pieces_dont_matter() {
+        let million = vec![b'a'; 1_000_000];
+        for piece in [1, 55, 63, 64, 65, 4096] {
+            // a byte at a time takes a while, less of it will do
+            let data = if piece == 1 { &million[..1000] } else { &million[..] };
+            for name in NAMES.iter() {
+                assert_eq!(digest(name, data, piece), digest(name, data, data.len()), "{} in pieces of {}", name, piece);
+            }
+        }
+    }
+
+    #[test]
+    fn padding_into_a_second_block() {
+        // 56 bytes leave no room for the length in the first block
+        let data = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(digest("sha256", data, data.len()), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+        assert_eq!(digest("md5", data, data.len()), "8215ef0796a20bcaaae116d3876c664a");
+    }
+}
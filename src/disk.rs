@@ -0,0 +1,277 @@
+/*
+    Block devices and disk images. A device doesn't say how big it is the
+    way a file does, so that's found by seeking to its end. --partitions
+    reads the MBR or GPT partition table and counts each partition on its
+    own, and the space outside of them too, which on a wiped disk should
+    be all zeros (or all noise) like the rest.
+*/
+
+use std::fs::{self, Metadata};
+use std::io::{self, Seek, SeekFrom};
+
+use args::Options;
+use entropy;
+use report::{csv_field, json_str, sparkline, Format};
+use sections::{count_range, read_at};
+
+const SECTOR: u64 = 512;
+
+pub struct Partition {
+    pub name: String,           // its number, or unpartitioned
+    pub kind: String,
+    pub label: String,          // gpt only
+    pub offset: u64,
+    pub size: u64,
+}
+
+struct Counted {
+    partition: Partition,
+    counts: [u64; 256],
+}
+
+// the size of a file or a block device, None for streams
+pub fn len(f: &mut fs::File, meta: &Metadata) -> io::Result<Option<u64>> {
+    if meta.is_file() { return Ok(Some(meta.len())); }
+    if !is_device(meta) { return Ok(None); }
+    let len = f.seek(SeekFrom::End(0))?;
+    f.seek(SeekFrom::Start(0))?;
+    Ok(Some(len))
+}
+
+#[cfg(unix)]
+fn is_device(meta: &Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    meta.file_type().is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_device(_: &Metadata) -> bool { false }
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let mut f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let disk_len = match f.metadata().and_then(|m| len(&mut f, &m)) {
+        Ok(Some(l)) => l,
+        Ok(None) => panic!("{} isn't a file or a block device", path),
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    };
+    let (table, mut partitions) = match find(&mut f, disk_len) {
+        Ok(Some(found)) => found,
+        Ok(None) => panic!("{} has no MBR or GPT partition table", path),
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    };
+
+    // what's between and around the partitions, tables included
+    partitions.retain(|p| p.size > 0 && p.offset < disk_len);
+    for p in &mut partitions { p.size = p.size.min(disk_len - p.offset); }
+    partitions.sort_by_key(|p| p.offset);
+    let mut gaps = Vec::new();
+    let mut at = 0;
+    for p in &partitions {
+        if p.offset > at { gaps.push((at, p.offset - at)); }
+        at = at.max(p.offset + p.size);
+    }
+    if disk_len > at { gaps.push((at, disk_len - at)); }
+    for (offset, size) in gaps {
+        partitions.push(Partition { name: String::from("-"), kind: String::from("unpartitioned"), label: String::new(), offset, size });
+    }
+    partitions.sort_by_key(|p| p.offset);
+
+    let mut counted = Vec::new();
+    for partition in partitions {
+        let counts = match count_range(&mut f, partition.offset, partition.size) {
+            Ok(c) => c,
+            Err(e) => panic!("Could not read partition {}: {}", partition.name, e),
+        };
+        counted.push(Counted { partition, counts });
+    }
+    render(format, table, &counted)
+}
+
+// the kind of table and its partitions, or None without one
+pub fn find(f: &mut fs::File, disk_len: u64) -> io::Result<Option<(&'static str, Vec<Partition>)>> {
+    let mbr = read_at(f, 0, SECTOR)?;
+    if mbr.len() < SECTOR as usize || mbr[510..512] != [0x55, 0xaa] { return Ok(None); }
+    let entries: Vec<(u8, u8, u64, u64)> = (0..4)
+        .map(|i| {
+            let e = &mbr[446 + i * 16..462 + i * 16];
+            (e[0], e[4], u32le(&e[8..]) as u64, u32le(&e[12..]) as u64)
+        })
+        .collect();
+    // a boot sector without a table (a bare fat filesystem) has code there,
+    // which rarely passes for boot flags
+    if entries.iter().any(|e| e.0 != 0 && e.0 != 0x80) || entries.iter().all(|e| e.1 == 0) { return Ok(None); }
+
+    // a protective mbr, the real table follows
+    if entries.iter().any(|e| e.1 == 0xee) {
+        for sector in [512, 4096] {
+            if let Some(partitions) = gpt(f, sector)? { return Ok(Some(("gpt", partitions))); }
+        }
+    }
+
+    let mut partitions = Vec::new();
+    for (i, &(_, kind, start, sectors)) in entries.iter().enumerate() {
+        if kind == 0 { continue; }
+        if [0x05, 0x0f, 0x85].contains(&kind) {
+            logical(f, start, disk_len, &mut partitions)?;
+            continue;
+        }
+        partitions.push(Partition { name: (i + 1).to_string(), kind: mbr_kind(kind), label: String::new(), offset: start * SECTOR, size: sectors * SECTOR });
+    }
+    Ok(Some(("mbr", partitions)))
+}
+
+// logical partitions, in a chain of boot records through an extended one.
+// they're numbered from 5 on, like linux does
+fn logical(f: &mut fs::File, extended: u64, disk_len: u64, partitions: &mut Vec<Partition>) -> io::Result<()> {
+    let mut at = extended;
+    let mut n = 5;
+    // a chain that loops or runs off the disk just ends there
+    while at * SECTOR < disk_len && n < 5 + 128 {
+        let ebr = read_at(f, at * SECTOR, SECTOR)?;
+        if ebr.len() < SECTOR as usize || ebr[510..512] != [0x55, 0xaa] { break; }
+        let (kind, start, sectors) = (ebr[446 + 4], u32le(&ebr[446 + 8..]) as u64, u32le(&ebr[446 + 12..]) as u64);
+        if kind != 0 {
+            partitions.push(Partition { name: n.to_string(), kind: mbr_kind(kind), label: String::new(), offset: (at + start) * SECTOR, size: sectors * SECTOR });
+            n += 1;
+        }
+        let next = u32le(&ebr[462 + 8..]) as u64;
+        if next == 0 { break; }
+        at = extended + next;
+    }
+    Ok(())
+}
+
+fn gpt(f: &mut fs::File, sector: u64) -> io::Result<Option<Vec<Partition>>> {
+    let header = read_at(f, sector, 92)?;
+    if header.len() < 92 || !header.starts_with(b"EFI PART") { return Ok(None); }
+    let (table, count, size) = (u64le(&header[72..]), u32le(&header[80..]) as u64, u32le(&header[84..]) as u64);
+    if size < 128 || count > 4096 { return Ok(None); }
+    let entries = read_at(f, table * sector, count * size)?;
+    let mut partitions = Vec::new();
+    for (i, e) in entries.chunks_exact(size as usize).enumerate() {
+        if e[..16].iter().all(|b| *b == 0) { continue; }
+        let (first, last) = (u64le(&e[32..]), u64le(&e[40..]));
+        if last < first { continue; }
+        let name: Vec<u16> = e[56..128].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).take_while(|c| *c != 0).collect();
+        partitions.push(Partition {
+            name: (i + 1).to_string(),
+            kind: gpt_kind(&guid(&e[..16])),
+            label: String::from_utf16_lossy(&name),
+            offset: first * sector,
+            size: (last - first + 1) * sector,
+        });
+    }
+    Ok(Some(partitions))
+}
+
+fn u32le(b: &[u8]) -> u32 { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) }
+
+fn u64le(b: &[u8]) -> u64 { u32le(b) as u64 | (u32le(&b[4..]) as u64) << 32 }
+
+// the first three fields are little endian, the rest as they are
+fn guid(b: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}",
+        u32le(b), u16::from_le_bytes([b[4], b[5]]), u16::from_le_bytes([b[6], b[7]]), b[8], b[9],
+        b[10..16].iter().map(|x| format!("{:02X}", x)).collect::<String>()
+    )
+}
+
+fn gpt_kind(guid: &str) -> String {
+    let known = match guid {
+        "C12A7328-F81F-11D2-BA4B-00A0C93EC93B" => "efi system",
+        "21686148-6449-6E6F-744E-656564454649" => "bios boot",
+        "0FC63DAF-8483-4772-8E79-3D69D8477DE4" => "linux",
+        "0657FD6D-A4AB-43C4-84E5-0933C84B4F4F" => "linux swap",
+        "E6D6D379-F507-44C2-A23C-238F2A3DF928" => "linux lvm",
+        "A19D880F-05FC-4D3B-A006-743F0F84911E" => "linux raid",
+        "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7" => "windows data",
+        "E3C9E316-0B5C-4DB8-817D-F92DF00215AE" => "windows reserved",
+        "DE94BBA4-06D1-4D40-A16A-BFD50179D6AC" => "windows recovery",
+        "7C3457EF-0000-11AA-AA11-00306543ECAC" => "apfs",
+        "48465300-0000-11AA-AA11-00306543ECAC" => "hfs+",
+        _ => return guid.to_lowercase(),
+    };
+    known.to_string()
+}
+
+fn mbr_kind(kind: u8) -> String {
+    let known = match kind {
+        0x01 | 0x04 | 0x06 | 0x0e => "fat",
+        0x0b | 0x0c => "fat32",
+        0x07 => "ntfs/exfat",
+        0x82 => "linux swap",
+        0x83 => "linux",
+        0x8e => "linux lvm",
+        0xfd => "linux raid",
+        0xa5 => "freebsd",
+        0xa6 => "openbsd",
+        0xaf => "hfs+",
+        0xef => "efi system",
+        _ => return format!("type {:#04x}", kind),
+    };
+    known.to_string()
+}
+
+fn render(format: Format, table: &str, counted: &[Counted]) -> Vec<String> {
+    let entropy = |c: &Counted| entropy::shannon(&c.counts, c.partition.size);
+    // wiped space is all zeros
+    let zeros = |c: &Counted| if c.partition.size == 0 { 0.0 } else { c.counts[0] as f64 / c.partition.size as f64 };
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("partition,type,label,offset,size,entropy,zeros")];
+            for c in counted {
+                let p = &c.partition;
+                lines.push(format!(
+                    "{},{},{},{},{},{:.6},{:.6}",
+                    csv_field(&p.name), csv_field(&p.kind), csv_field(&p.label), p.offset, p.size, entropy(c), zeros(c)
+                ));
+            }
+            // then every partition's counts, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("partition,offset,byte,count"));
+            for c in counted {
+                for (byte, n) in c.counts.iter().enumerate() {
+                    if *n != 0 { lines.push(format!("{},{},{},{}", csv_field(&c.partition.name), c.partition.offset, byte, n)); }
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let partitions: Vec<String> = counted.iter()
+                .map(|c| {
+                    let p = &c.partition;
+                    let bytes: Vec<String> = c.counts.iter().enumerate()
+                        .filter(|&(_, n)| *n != 0)
+                        .map(|(byte, n)| format!("{{\"byte\": {}, \"count\": {}}}", byte, n))
+                        .collect();
+                    format!(
+                        "    {{\"partition\": {}, \"type\": {}, \"label\": {}, \"offset\": {}, \"size\": {}, \"entropy\": {:.6}, \"zeros\": {:.6}, \"bytes\": [{}]}}",
+                        json_str(&p.name), json_str(&p.kind), json_str(&p.label), p.offset, p.size, entropy(c), zeros(c), bytes.join(", ")
+                    )
+                })
+                .collect();
+            vec![format!("{{\n  \"table\": \"{}\",\n  \"partitions\": [\n{}\n  ]\n}}", table, partitions.join(",\n"))]
+        },
+        _ => {
+            let width = counted.iter().map(|c| c.partition.kind.chars().count()).max().unwrap_or(0).max(4);
+            let mut lines = vec![
+                format!("  {} partition table", table),
+                format!("  {:>4}  {:<w$}  {:<14}  {:>14}  entropy    zeros", "#", "type", "offset", "size", w = width),
+            ];
+            for c in counted {
+                let p = &c.partition;
+                lines.push(format!(
+                    "  {:>4}  {:<w$}  {:#014x}  {:>14}  {:>7.4}  {:>6.2}%  {}{}",
+                    p.name, p.kind, p.offset, p.size, entropy(c), zeros(c) * 100.0, sparkline(&c.counts),
+                    if p.label.is_empty() { String::new() } else { format!("  {}", p.label) }, w = width
+                ));
+            }
+            lines
+        },
+    }
+}
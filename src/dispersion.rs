@@ -0,0 +1,54 @@
+/*
+    --dispersion: how evenly each byte value is spread through the file,
+    as the coefficient of variation of the gaps between one occurrence and
+    the next. Scattered at random, the gaps come out about as varied as
+    they are long, near 1. Well above that, the value comes in bunches,
+    all in one region or a few; well below, it keeps turning up at regular
+    intervals, like a delimiter in fixed size records.
+*/
+
+// the coefficient of variation beyond which the value counts as bunched
+// up, and below which as regular
+pub const CLUSTERED_ABOVE: f64 = 1.5;
+pub const EVEN_BELOW: f64 = 0.5;
+
+pub struct DispersionTracker {
+    last: Vec<u64>,     // offset of the latest occurrence, per byte value
+    seen: Vec<bool>,
+    gaps: Vec<(u64, f64, f64)>,  // count, mean and sum of squares (welford's)
+    offset: u64,        // of the next byte
+}
+
+impl DispersionTracker {
+    pub fn new() -> DispersionTracker {
+        DispersionTracker { last: vec![0; 256], seen: vec![false; 256], gaps: vec![(0, 0.0, 0.0); 256], offset: 0 }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for byte in chunk {
+            let b = *byte as usize;
+            if self.seen[b] {
+                let gap = (self.offset - self.last[b]) as f64;
+                let (ref mut n, ref mut mean, ref mut m2) = self.gaps[b];
+                *n += 1;
+                let delta = gap - *mean;
+                *mean += delta / *n as f64;
+                *m2 += delta * (gap - *mean);
+            }
+            self.seen[b] = true;
+            self.last[b] = self.offset;
+            self.offset += 1;
+        }
+    }
+
+    // per byte value, none for those with fewer than two gaps to compare
+    pub fn finish(self) -> Vec<Option<f64>> {
+        self.gaps.iter()
+            .map(|&(n, mean, m2)| if n < 2 { None } else { Some((m2 / n as f64).sqrt() / mean) })
+            .collect()
+    }
+}
+
+pub fn label(cv: f64) -> &'static str {
+    if cv > CLUSTERED_ABOVE { "clustered" } else if cv < EVEN_BELOW { "even" } else { "random" }
+}
@@ -0,0 +1,58 @@
+/*
+    Peeking at long runs: sending the process SIGUSR1 (kill -USR1 <pid>)
+    makes it write out the byte table counted so far, to stderr or to the
+    file given with --dump-to, and carry on. The signal handler only sets a
+    flag; the counting loops check it between chunks.
+*/
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use args::Options;
+use entropy;
+use log;
+use outfile;
+use report::{self, Format};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+// whether a dump was asked for since the last call
+pub fn requested() -> bool {
+    REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+pub fn write(opts: &Options, counts: &[u64; 256], len: u64) {
+    let format = Format::parse(&opts.format_name).unwrap_or(Format::Text);
+    let mut lines = report::render_counts(format, counts, opts.bars);
+    if format == Format::Text {
+        lines.insert(0, format!("  {} bytes so far, entropy {:.4}", len, entropy::shannon(counts, len)));
+    }
+    let text: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+    match opts.dump_path {
+        // replaced each time, so it always holds the latest counts
+        Some(ref path) => if let Err(e) = outfile::write(path, text.as_bytes(), true) { log::warn(&format!("Could not write {}: {}", path, e)); },
+        // on a line of its own, past any progress counter
+        None => eprint!("\n{}", text),
+    }
+}
+
+#[cfg(unix)]
+pub fn install() {
+    use std::os::raw::c_int;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const SIGUSR1: c_int = 10;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const SIGUSR1: c_int = 30;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+    extern "C" fn on_signal(_: c_int) {
+        REQUESTED.store(true, Ordering::SeqCst);
+    }
+    // safe: the handler does nothing but store to an atomic
+    unsafe { signal(SIGUSR1, on_signal); }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
@@ -0,0 +1,127 @@
+/*
+    Duplicate detection for multi-file mode. Files can only be identical if
+    their sizes and byte histograms are, and those come for free with the
+    analysis, so only the few files sharing both need hashing to confirm.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader};
+use std::io::prelude::*;
+
+use analysis::Summary;
+use digest::{self, Sha256};
+use log;
+use report::{csv_field, json_str, Format};
+
+struct Group<'a> {
+    size: u64,
+    members: Vec<&'a Summary>,
+    sha256: Option<String>,     // only when confirmed
+}
+
+pub fn render(format: Format, summaries: &[Summary], confirm: bool) -> Vec<String> {
+    let groups = find(summaries, confirm);
+    let redundant: u64 = groups.iter().map(|g| g.size * (g.members.len() as u64 - 1)).sum();
+    let files: usize = groups.iter().map(|g| g.members.len()).sum();
+
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("group,size,path,sha256")];
+            for (n, g) in groups.iter().enumerate() {
+                for s in &g.members {
+                    lines.push(format!(
+                        "{},{},{},{}",
+                        n + 1, g.size, csv_field(&s.path), g.sha256.as_deref().unwrap_or("")
+                    ));
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = groups.iter()
+                .map(|g| {
+                    let paths: Vec<String> = g.members.iter().map(|s| json_str(&s.path)).collect();
+                    format!(
+                        "    {{\"size\": {}, \"confirmed\": {}, \"sha256\": {}, \"files\": [{}]}}",
+                        g.size, confirm,
+                        g.sha256.as_ref().map_or(String::from("null"), |h| json_str(h)),
+                        paths.join(", ")
+                    )
+                })
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"redundant_bytes\": {},", redundant),
+                format!("  \"groups\": [\n{}\n  ]", entries.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!(
+                    "  {} groups of {} duplicates ({} files, {} redundant bytes)",
+                    groups.len(), if confirm { "confirmed" } else { "likely" }, files, redundant
+                ),
+            ];
+            for g in &groups {
+                lines.push(String::from(""));
+                match g.sha256 {
+                    Some(ref h) => lines.push(format!("  {} bytes, {} files, sha256 {}", g.size, g.members.len(), h)),
+                    None => lines.push(format!("  {} bytes, {} files", g.size, g.members.len())),
+                }
+                for s in &g.members { lines.push(format!("    {}", s.path)); }
+            }
+            lines
+        },
+    }
+}
+
+// groups of two or more files with the same size and histogram, largest
+// files first. with confirm, groups are split up by content hash as well.
+fn find(summaries: &[Summary], confirm: bool) -> Vec<Group<'_>> {
+    let mut by_histogram: HashMap<(u64, [u64; 256]), Vec<&Summary>> = HashMap::new();
+    for s in summaries {
+        by_histogram.entry((s.analysis.len, s.analysis.counts)).or_default().push(s);
+    }
+
+    let mut groups = Vec::new();
+    for ((size, _), members) in by_histogram {
+        if members.len() < 2 { continue; }
+        if !confirm {
+            groups.push(Group { size, members, sha256: None });
+            continue;
+        }
+
+        let mut by_hash: HashMap<String, Vec<&Summary>> = HashMap::new();
+        for s in members {
+            match hash_file(&s.path) {
+                Ok(h) => by_hash.entry(h).or_default().push(s),
+                Err(e) => log::warn(&format!("skipping {}: {}", s.path, e)),
+            }
+        }
+        for (h, members) in by_hash {
+            if members.len() > 1 { groups.push(Group { size, members, sha256: Some(h) }); }
+        }
+    }
+
+    for g in groups.iter_mut() { g.members.sort_by(|a, b| a.path.cmp(&b.path)); }
+    groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.members[0].path.cmp(&b.members[0].path)));
+    groups
+}
+
+fn hash_file(path: &str) -> io::Result<String> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut sha = Sha256::new();
+    loop {
+        let length_of_chunk = {
+            let chunk = reader.fill_buf()?;
+            sha.update(chunk);
+            chunk.len()
+        };
+        if length_of_chunk == 0 { break; }
+        reader.consume(length_of_chunk);
+    }
+    Ok(digest::hex(&sha.finish()))
+}
@@ -0,0 +1,124 @@
+/*
+    Shannon entropy, over a whole table of counts, over consecutive fixed
+    size blocks of a stream, and over a window sliding along a stream.
+*/
+
+// entropy in bits per byte of a table of byte counts
+pub fn shannon(counts: &[u64; 256], total: u64) -> f64 {
+    if total == 0 { return 0.0; }
+    counts.iter()
+        .filter(|c| **c != 0)
+        .map(|c| {
+            // written this way round so a single byte value comes out as 0
+            // rather than -0
+            let p = *c as f64 / total as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+// splits a stream into fixed size blocks and records the entropy of each,
+// normalized to 0..1 against the most a block of that size could reach.
+pub struct BlockEntropy {
+    block_size: usize,
+    counts: [u64; 256],
+    filled: usize,
+    entropies: Vec<f64>,
+}
+
+impl BlockEntropy {
+    pub fn new(block_size: usize) -> BlockEntropy {
+        BlockEntropy {
+            block_size,
+            counts: [0u64; 256],
+            filled: 0,
+            entropies: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for byte in chunk {
+            self.counts[*byte as usize] += 1;
+            self.filled += 1;
+            if self.filled == self.block_size { self.end_block(); }
+        }
+    }
+
+    // flushes a trailing partial block, if any, and hands back the results
+    pub fn finish(mut self) -> Vec<f64> {
+        if self.filled > 0 { self.end_block(); }
+        self.entropies
+    }
+
+    fn end_block(&mut self) {
+        let max = (self.filled.min(256) as f64).log2();
+        let e = shannon(&self.counts, self.filled as u64);
+        self.entropies.push(if max > 0.0 { e / max } else { 0.0 });
+        self.counts = [0u64; 256];
+        self.filled = 0;
+    }
+}
+
+// one sample of an entropy profile: the window starting at offset
+pub struct Point {
+    pub offset: u64,
+    pub len: usize,
+    pub bits: f64,
+}
+
+// entropy of a window sliding over the stream, sampled every step bytes.
+// unlike BlockEntropy the windows can overlap, so the last window's bytes
+// are kept around in a ring to be taken back out of the counts.
+pub struct SlidingEntropy {
+    window: usize,
+    step: usize,
+    ring: Vec<u8>,
+    counts: [u64; 256],
+    seen: u64,
+    points: Vec<Point>,
+}
+
+impl SlidingEntropy {
+    pub fn new(window: usize, step: usize) -> SlidingEntropy {
+        SlidingEntropy {
+            window,
+            step,
+            ring: vec![0u8; window],
+            counts: [0u64; 256],
+            seen: 0,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let window = self.window as u64;
+        for byte in chunk {
+            let slot = (self.seen % window) as usize;
+            if self.seen >= window { self.counts[self.ring[slot] as usize] -= 1; }
+            self.ring[slot] = *byte;
+            self.counts[*byte as usize] += 1;
+            self.seen += 1;
+
+            if self.seen >= window && (self.seen - window).is_multiple_of(self.step as u64) {
+                self.sample();
+            }
+        }
+    }
+
+    // if the stream didn't end on a sample, the last window still gets one,
+    // so the tail of the file isn't missing from the profile
+    pub fn finish(mut self) -> Vec<Point> {
+        let covered = self.points.last().map_or(0, |p| p.offset + p.len as u64);
+        if self.seen > covered { self.sample(); }
+        self.points
+    }
+
+    fn sample(&mut self) {
+        let len = self.seen.min(self.window as u64);
+        self.points.push(Point {
+            offset: self.seen - len,
+            len: len as usize,
+            bits: shannon(&self.counts, len),
+        });
+    }
+}
@@ -0,0 +1,193 @@
+/*
+    --field: for delimited text, counts the values of a column instead of
+    the bytes, the most common of them and how many different ones there
+    are, to get a quick feel for a csv or tsv file. Quoted fields can hold
+    delimiters, doubled quotes and line breaks. Fields go by number from 1,
+    or by name, which takes the first row as the header.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, Read};
+
+use args::Options;
+use report::{csv_field, json_str, Format};
+
+const DEFAULT_TOP: usize = 10;
+
+struct Field {
+    name: String,
+    index: usize,
+    values: HashMap<String, u64>,
+    empty: u64,
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let delimiter = delimiter(opts);
+    let mut records = Records { bytes: BufReader::new(f).bytes(), delimiter, done: false };
+    let by_name = opts.fields.iter().any(|f| f.parse::<usize>().is_err());
+    let header = if by_name { records.next().unwrap_or_default() } else { Vec::new() };
+
+    let mut fields: Vec<Field> = opts.fields.iter()
+        .map(|f| {
+            let index = match f.parse::<usize>() {
+                Ok(n) => n - 1,
+                Err(_) => match header.iter().position(|h| h == f) {
+                    Some(i) => i,
+                    None => panic!("{} has no field {} in its header", path, f),
+                },
+            };
+            let name = header.get(index).cloned().unwrap_or_else(|| format!("field {}", index + 1));
+            Field { name, index, values: HashMap::new(), empty: 0 }
+        })
+        .collect();
+
+    let mut rows = 0;
+    for record in records {
+        rows += 1;
+        for field in fields.iter_mut() {
+            match record.get(field.index) {
+                Some(v) if !v.is_empty() => *field.values.entry(v.clone()).or_insert(0) += 1,
+                _ => field.empty += 1,
+            }
+        }
+    }
+    render(format, rows, &fields, opts.top.unwrap_or(DEFAULT_TOP))
+}
+
+// given, or tabs for a .tsv file and commas otherwise
+fn delimiter(opts: &Options) -> u8 {
+    match opts.delimiter.as_deref() {
+        Some("tab") | Some("\\t") => b'\t',
+        Some(d) => d.as_bytes()[0],
+        None if opts.inputs[0].to_lowercase().ends_with(".tsv") => b'\t',
+        None => b',',
+    }
+}
+
+// the rows of the file, split into fields
+struct Records<R: Read> {
+    bytes: std::io::Bytes<BufReader<R>>,
+    delimiter: u8,
+    done: bool,
+}
+
+// blank lines are skipped
+impl<R: Read> Iterator for Records<R> {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Vec<String>> {
+        loop {
+            match self.record() {
+                Some(r) if r.len() == 1 && r[0].is_empty() => continue,
+                r => return r,
+            }
+        }
+    }
+}
+
+impl<R: Read> Records<R> {
+    fn record(&mut self) -> Option<Vec<String>> {
+        if self.done { return None; }
+        let (mut record, mut field) = (Vec::new(), Vec::new());
+        let (mut quoted, mut after_quote, mut any) = (false, false, false);
+        loop {
+            let b = match self.bytes.next() {
+                Some(Ok(b)) => b,
+                Some(Err(e)) => panic!("Could not read the file: {}", e),
+                None => {
+                    self.done = true;
+                    if !any { return None; }
+                    break;
+                },
+            };
+            any = true;
+            if quoted {
+                if b == b'"' { quoted = false; after_quote = true; } else { field.push(b); }
+            } else if b == b'"' {
+                // a doubled quote inside quotes is a quote
+                if after_quote { field.push(b); }
+                quoted = true;
+                after_quote = false;
+            } else if b == self.delimiter {
+                record.push(String::from_utf8_lossy(&field).into_owned());
+                field.clear();
+                after_quote = false;
+            } else if b == b'\n' {
+                break;
+            } else if b != b'\r' {
+                field.push(b);
+                after_quote = false;
+            }
+        }
+        record.push(String::from_utf8_lossy(&field).into_owned());
+        Some(record)
+    }
+}
+
+// the top most common values, ties in order
+fn ranked(f: &Field, top: usize) -> Vec<(&String, &u64)> {
+    let mut values: Vec<(&String, &u64)> = f.values.iter().collect();
+    values.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    values.truncate(top);
+    values
+}
+
+fn render(format: Format, rows: u64, fields: &[Field], top: usize) -> Vec<String> {
+    let percent = |n: u64| if rows == 0 { 0.0 } else { n as f64 / rows as f64 * 100.0 };
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("field,rows,distinct,empty")];
+            for f in fields {
+                lines.push(format!("{},{},{},{}", csv_field(&f.name), rows, f.values.len(), f.empty));
+            }
+            // then each field's most common values, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("field,value,count,percent"));
+            for f in fields {
+                for (value, n) in ranked(f, top) {
+                    lines.push(format!("{},{},{},{:.4}", csv_field(&f.name), csv_field(value), n, percent(*n)));
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = fields.iter()
+                .map(|f| {
+                    let values: Vec<String> = ranked(f, top).iter()
+                        .map(|(value, n)| format!("{{\"value\": {}, \"count\": {}, \"percent\": {:.4}}}", json_str(value), n, percent(**n)))
+                        .collect();
+                    format!(
+                        "    {{\"field\": {}, \"distinct\": {}, \"empty\": {}, \"top\": [{}]}}",
+                        json_str(&f.name), f.values.len(), f.empty, values.join(", ")
+                    )
+                })
+                .collect();
+            vec![format!("{{\n  \"rows\": {},\n  \"fields\": [\n{}\n  ]\n}}", rows, entries.join(",\n"))]
+        },
+        _ => {
+            let mut lines = Vec::new();
+            for f in fields {
+                lines.push(format!("  {}: {} rows, {} distinct values, {} empty", f.name, rows, f.values.len(), f.empty));
+                let values = ranked(f, top);
+                // line breaks in a value would break the table
+                let values: Vec<(String, &u64)> = values.into_iter().map(|(v, n)| (v.replace('\n', "\\n").replace('\r', "\\r"), n)).collect();
+                let width = values.iter().map(|(v, _)| v.chars().count()).max().unwrap_or(0).clamp(5, 40);
+                for (value, n) in values {
+                    let shown: String = if value.chars().count() > width {
+                        value.chars().take(width - 3).chain("...".chars()).collect()
+                    } else { value };
+                    lines.push(format!("  {:<w$}  {:>12}  {:>7.2}%", shown, n, percent(*n), w = width));
+                }
+                lines.push(String::from(""));
+            }
+            lines.pop();
+            lines
+        },
+    }
+}
@@ -0,0 +1,83 @@
+/*
+    find: goes through a tree and lists only the files that stand out,
+    for sweeps where the whole table would be too much to read. With
+    --entropy-above, the files over that many bits per byte, the most
+    random first, which is what encrypted or packed files look like. With
+    --like, the files ranked by how close they are to a reference file,
+    as nearest does it.
+*/
+
+use analysis;
+use args::Options;
+use entropy;
+use nearest;
+use report::{csv_field, json_str, Format};
+use stats;
+use walk;
+
+struct Hit {
+    path: String,
+    size: u64,
+    entropy: f64,
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    if let Some(ref reference) = opts.like { return like(opts, format, reference); }
+    let files = walk::expand(&opts.inputs, opts);
+    let above = opts.entropy_above.unwrap_or(0.0);
+    let mut hits: Vec<Hit> = analysis::batch(&files, opts, |a| (a.len, entropy::shannon(&a.counts, a.len)))
+        .into_iter()
+        .filter(|(_, (_, entropy))| *entropy > above)
+        .map(|(path, (size, entropy))| Hit { path, size, entropy })
+        .collect();
+    hits.sort_by(|a, b| b.entropy.total_cmp(&a.entropy).then(a.path.cmp(&b.path)));
+    render(format, &hits, above, files.len())
+}
+
+// --entropy-above still narrows the files down first
+fn like(opts: &Options, format: Format, reference: &str) -> Vec<String> {
+    let query = match analysis::analyze(reference, opts, false) {
+        Ok(a) => stats::proportions(&a.counts),
+        Err(_) => panic!("Could not open {}. Bad file or path?", reference),
+    };
+    let files: Vec<String> = walk::expand(&opts.inputs, opts).into_iter()
+        .filter(|p| !walk::same_file(p, reference))
+        .collect();
+    let above = opts.entropy_above.unwrap_or(-1.0);
+    let fingerprints = analysis::batch(&files, opts, |a| (entropy::shannon(&a.counts, a.len), stats::proportions(&a.counts)))
+        .into_iter()
+        .filter(|(_, (entropy, _))| *entropy > above)
+        .map(|(path, (_, p))| (path, p))
+        .collect();
+    let ranked = nearest::rank(&query, fingerprints, opts.top.unwrap_or(nearest::DEFAULT_TOP));
+    nearest::render(format, reference, &ranked)
+}
+
+fn render(format: Format, hits: &[Hit], above: f64, searched: usize) -> Vec<String> {
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("path,size,entropy")];
+            for h in hits { lines.push(format!("{},{},{:.6}", csv_field(&h.path), h.size, h.entropy)); }
+            lines
+        },
+        Format::Json => {
+            let files: Vec<String> = hits.iter()
+                .map(|h| format!("    {{\"path\": {}, \"size\": {}, \"entropy\": {:.6}}}", json_str(&h.path), h.size, h.entropy))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"entropy_above\": {},", above),
+                format!("  \"searched\": {},", searched),
+                format!("  \"files\": [\n{}\n  ]", files.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let width = hits.iter().map(|h| h.path.chars().count()).max().unwrap_or(0).max(4);
+            let mut lines = vec![format!("  {:<w$}  {:>12}  {:>7}", "path", "size", "entropy", w = width)];
+            for h in hits { lines.push(format!("  {:<w$}  {:>12}  {:>7.4}", h.path, h.size, h.entropy, w = width)); }
+            lines.push(format!("  {} of {} files above {} bits per byte", hits.len(), searched, above));
+            lines
+        },
+    }
+}
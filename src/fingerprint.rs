@@ -0,0 +1,130 @@
+/*
+    --fingerprint: the byte distribution squeezed into a short string that
+    can go in a database column and be compared later without the file.
+    The 256 byte values are summed into 64 buckets of 4, and each bucket
+    keeps the square root of its share in a byte, which leaves more room
+    for the rare ones. A version goes in front, so the encoding can change.
+
+    freqs fingerprint compare takes two of them (or files, which get one
+    made on the spot) and scores how alike they are, 1 being the same.
+*/
+
+use analysis;
+use args::Options;
+use report::{csv_field, json_str, Format};
+use walk;
+
+pub const VERSION: &str = "fq1";
+const BUCKETS: usize = 64;
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(counts: &[u64; 256]) -> String {
+    let total: u64 = counts.iter().sum();
+    let mut buckets = [0u8; BUCKETS];
+    for (i, b) in buckets.iter_mut().enumerate() {
+        let n: u64 = counts[i * 4..i * 4 + 4].iter().sum();
+        if total > 0 { *b = (255.0 * (n as f64 / total as f64).sqrt()).round() as u8; }
+    }
+    format!("{}:{}", VERSION, base64(&buckets))
+}
+
+// the square roots of the bucket shares, scaled back to add up to one
+// squared. none if it isn't a fingerprint of this version
+pub fn decode(text: &str) -> Option<[f64; BUCKETS]> {
+    let data = text.strip_prefix(VERSION)?.strip_prefix(':')?;
+    let bytes = unbase64(data)?;
+    if bytes.len() != BUCKETS { return None; }
+    let mut roots = [0.0; BUCKETS];
+    for (r, b) in roots.iter_mut().zip(bytes.iter()) { *r = *b as f64 / 255.0; }
+    let norm = roots.iter().map(|r| r * r).sum::<f64>().sqrt();
+    if norm > 0.0 { for r in roots.iter_mut() { *r /= norm; } }
+    Some(roots)
+}
+
+// the bhattacharyya coefficient of the bucket shares. two empty files are
+// the same, an empty one and another aren't alike at all
+pub fn similarity(a: &[f64; BUCKETS], b: &[f64; BUCKETS]) -> f64 {
+    let (empty_a, empty_b) = (a.iter().all(|r| *r == 0.0), b.iter().all(|r| *r == 0.0));
+    if empty_a || empty_b { return if empty_a && empty_b { 1.0 } else { 0.0 }; }
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f64>().min(1.0)
+}
+
+// one line per file, like sha256sum
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let files = walk::expand(&opts.inputs, opts);
+    let prints = analysis::batch(&files, opts, |a| encode(&a.counts));
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("path,fingerprint")];
+            for (path, print) in &prints { lines.push(format!("{},{}", csv_field(path), print)); }
+            lines
+        },
+        Format::Json => {
+            let files: Vec<String> = prints.iter()
+                .map(|(path, print)| format!("    {{\"path\": {}, \"fingerprint\": {}}}", json_str(path), json_str(print)))
+                .collect();
+            vec![format!("{{\n  \"files\": [\n{}\n  ]\n}}", files.join(",\n"))]
+        },
+        _ => prints.iter().map(|(path, print)| format!("{}  {}", print, path)).collect(),
+    }
+}
+
+// freqs fingerprint compare <a> <b>
+pub fn compare(opts: &Options, format: Format) -> Vec<String> {
+    let (a, b) = (&opts.inputs[1], &opts.inputs[2]);
+    let (print_a, print_b) = (fingerprint_of(a, opts), fingerprint_of(b, opts));
+    let (roots_a, roots_b) = (decode(&print_a).unwrap(), decode(&print_b).unwrap());
+    let s = similarity(&roots_a, &roots_b);
+    let hellinger = (1.0 - s).max(0.0).sqrt();
+    match format {
+        Format::Csv => vec![
+            String::from("a,b,similarity,hellinger_distance"),
+            format!("{},{},{:.6},{:.6}", csv_field(&print_a), csv_field(&print_b), s, hellinger),
+        ],
+        Format::Json => vec![format!(
+            "{{\n  \"a\": {},\n  \"b\": {},\n  \"similarity\": {:.6},\n  \"hellinger_distance\": {:.6}\n}}",
+            json_str(&print_a), json_str(&print_b), s, hellinger
+        )],
+        _ => vec![
+            format!("  a           {}", print_a),
+            format!("  b           {}", print_b),
+            format!("  similarity  {:.4}, hellinger distance {:.4}", s, hellinger),
+        ],
+    }
+}
+
+// either one already, or a file to make one of
+fn fingerprint_of(text: &str, opts: &Options) -> String {
+    if decode(text).is_some() { return text.to_string(); }
+    if text.starts_with(&format!("{}:", VERSION)) { panic!("{} isn't a valid fingerprint", text); }
+    match analysis::analyze(text, opts, false) {
+        Ok(a) => encode(&a.counts),
+        Err(_) => panic!("Could not open {}. Bad file or path?", text),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for group in bytes.chunks(3) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() { text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char); } else { text.push('='); }
+        }
+    }
+    text
+}
+
+fn unbase64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let (mut bits, mut nbits, mut bytes) = (0u32, 0, Vec::new());
+    for c in text.bytes() {
+        bits = (bits << 6) | ALPHABET.iter().position(|a| *a == c)? as u32;
+        nbits += 6;
+        if nbits >= 8 {
+            nbits -= 8;
+            bytes.push((bits >> nbits) as u8);
+            bits &= (1 << nbits) - 1;
+        }
+    }
+    Some(bytes)
+}
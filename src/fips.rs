@@ -0,0 +1,195 @@
+/*
+    freqs fips: the FIPS 140-2 statistical tests for random number
+    generators (monobit, poker, runs and long run), to sanity check rng
+    output. They're defined on 20,000 bits, so they're run on the first
+    20,000 bits of the file and then on every 20,000 bit block of it, to
+    see how often they fail. Bits are taken most significant first.
+*/
+
+use std::fs;
+use std::io::{self, BufReader, Read};
+
+use args::Options;
+use report::{csv_field, json_str, Format};
+
+pub const BLOCK_BYTES: usize = 2500;
+pub const TESTS: [&str; 4] = ["monobit", "poker", "runs", "long run"];
+const MONOBIT: (u32, u32) = (9725, 10275);
+const POKER: (f64, f64) = (2.16, 46.17);
+// runs of 1 to 5 bits and 6 or more, the same for runs of zeros and ones
+const RUNS: [(u32, u32); 6] = [(2315, 2685), (1114, 1386), (527, 723), (240, 384), (103, 209), (103, 209)];
+const LONG_RUN: u32 = 26;
+// random data fails a block now and then, so the whole file only fails a
+// test when more blocks do than that explains
+const MAX_FAILING: f64 = 0.002;
+
+pub struct Block {
+    pub ones: u32,
+    pub poker: f64,
+    pub runs: [[u32; 6]; 2],    // of zeros and of ones, by length
+    pub longest: u32,
+}
+
+pub struct Fips {
+    pub first: Block,
+    pub blocks: u64,
+    pub failed: [u64; 4],       // blocks failing each test
+}
+
+impl Block {
+    pub fn new(bytes: &[u8]) -> Block {
+        let mut nibbles = [0u32; 16];
+        let mut runs = [[0u32; 6]; 2];
+        let (mut ones, mut longest, mut run, mut last) = (0, 0, 0, 0);
+        for b in bytes {
+            ones += b.count_ones();
+            nibbles[(b >> 4) as usize] += 1;
+            nibbles[(b & 0xf) as usize] += 1;
+            for i in (0..8).rev() {
+                let bit = b >> i & 1;
+                if run > 0 && bit != last {
+                    runs[last as usize][run.min(6) as usize - 1] += 1;
+                    run = 0;
+                }
+                last = bit;
+                run += 1;
+                longest = longest.max(run);
+            }
+        }
+        if run > 0 { runs[last as usize][run.min(6) as usize - 1] += 1; }
+        let segments = (bytes.len() * 2) as f64;
+        let squares: f64 = nibbles.iter().map(|f| (*f as f64).powi(2)).sum();
+        Block { ones, poker: 16.0 / segments * squares - segments, runs, longest }
+    }
+
+    // in the order of TESTS
+    pub fn passed(&self) -> [bool; 4] {
+        [
+            self.ones > MONOBIT.0 && self.ones < MONOBIT.1,
+            self.poker > POKER.0 && self.poker < POKER.1,
+            self.runs_out() == 0,
+            self.longest < LONG_RUN,
+        ]
+    }
+
+    // how many of the 12 run counts are out of bounds
+    fn runs_out(&self) -> usize {
+        self.runs.iter()
+            .flat_map(|r| r.iter().zip(RUNS.iter()))
+            .filter(|(n, (lo, hi))| *n < lo || *n > hi)
+            .count()
+    }
+}
+
+impl Fips {
+    pub fn stream_passed(&self) -> [bool; 4] {
+        let limit = (self.blocks as f64 * MAX_FAILING).max(1.0);
+        let mut passed = [true; 4];
+        for (p, f) in passed.iter_mut().zip(self.failed.iter()) { *p = *f as f64 <= limit; }
+        passed
+    }
+
+    pub fn ok(&self) -> bool {
+        self.first.passed().iter().chain(self.stream_passed().iter()).all(|p| *p)
+    }
+}
+
+// a last block short of 20,000 bits is left out
+pub fn test(path: &str) -> io::Result<Option<Fips>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut buf = vec![0u8; BLOCK_BYTES];
+    let mut first = None;
+    let (mut blocks, mut failed) = (0, [0u64; 4]);
+    loop {
+        let mut got = 0;
+        while got < BLOCK_BYTES {
+            let n = reader.read(&mut buf[got..])?;
+            if n == 0 { break; }
+            got += n;
+        }
+        if got < BLOCK_BYTES { break; }
+        let block = Block::new(&buf);
+        for (f, p) in failed.iter_mut().zip(block.passed().iter()) {
+            if !p { *f += 1; }
+        }
+        blocks += 1;
+        if first.is_none() { first = Some(block); }
+    }
+    Ok(first.map(|first| Fips { first, blocks, failed }))
+}
+
+// the lines to print, and whether every test passed
+pub fn run(opts: &Options, format: Format) -> (Vec<String>, bool) {
+    let path = &opts.inputs[0];
+    let fips = match test(path) {
+        Ok(Some(f)) => f,
+        Ok(None) => panic!("The tests need at least 20,000 bits (2,500 bytes), {} is shorter", path),
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    let ok = fips.ok();
+    let (first, stream) = (fips.first.passed(), fips.stream_passed());
+    let b = &fips.first;
+    let statistics = [
+        format!("{}", b.ones),
+        format!("{:.2}", b.poker),
+        format!("{}", b.runs_out()),
+        format!("{}", b.longest),
+    ];
+
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("test,first_block_statistic,first_block_passed,blocks,blocks_failed,passed")];
+            for (i, test) in TESTS.iter().enumerate() {
+                lines.push(format!(
+                    "{},{},{},{},{},{}",
+                    csv_field(test), statistics[i], first[i], fips.blocks, fips.failed[i], first[i] && stream[i]
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let tests: Vec<String> = TESTS.iter().enumerate()
+                .map(|(i, test)| format!(
+                    "    {{\"test\": {}, \"first_block_statistic\": {}, \"first_block_passed\": {}, \"blocks_failed\": {}, \"passed\": {}}}",
+                    json_str(test), statistics[i], first[i], fips.failed[i], first[i] && stream[i]
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"path\": {},", json_str(path)),
+                format!("  \"blocks\": {},", fips.blocks),
+                format!("  \"runs\": {{\"zeros\": {:?}, \"ones\": {:?}}},", b.runs[0], b.runs[1]),
+                format!("  \"tests\": [\n{}\n  ],", tests.join(",\n")),
+                format!("  \"passed\": {}", ok),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let verdict = |p: bool| if p { "pass" } else { "FAIL" };
+            let details = [
+                format!("{} ones, passes between {} and {}", b.ones, MONOBIT.0, MONOBIT.1),
+                format!("{:.2}, passes between {} and {}", b.poker, POKER.0, POKER.1),
+                format!("{} of 12 run counts out of bounds", b.runs_out()),
+                format!("longest {} bits, {} or more fails", b.longest, LONG_RUN),
+            ];
+            let mut lines = vec![
+                String::from(""),
+                format!("  fips 140-2 tests of {}", path),
+                String::from(""),
+                String::from("  first 20,000 bits:"),
+            ];
+            for (i, test) in TESTS.iter().enumerate() {
+                lines.push(format!("  {:<10}  {}  {}", test, verdict(first[i]), details[i]));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  all {} blocks of 20,000 bits:", fips.blocks));
+            for (i, test) in TESTS.iter().enumerate() {
+                lines.push(format!("  {:<10}  {}  {} failed", test, verdict(stream[i]), fips.failed[i]));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}", if ok { "passed" } else { "FAILED" }));
+            lines
+        },
+    };
+    (lines, ok)
+}
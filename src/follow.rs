@@ -0,0 +1,78 @@
+/*
+    --follow: like tail -f, keeps the file open after reaching its end and
+    counts whatever gets appended, redrawing the totals every so often. A
+    live log can be watched this way without starting over from byte zero
+    each time. If the file gets truncated, counting starts again. It only
+    ends when stopped through --control (or killed).
+*/
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use args::Options;
+use control::Control;
+use dump;
+use metrics;
+use entropy;
+use report::{self, Format};
+use watch;
+
+const CHUNKSIZE: usize = 1024 * 128;
+// how often the results are redrawn while bytes keep coming
+const REFRESH: Duration = Duration::from_secs(1);
+// how long to wait at the end of the file before looking again
+const IDLE: Duration = Duration::from_millis(200);
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let board = opts.metrics_addr.as_ref().map(|addr| metrics::listen(addr));
+    let path = &opts.inputs[0];
+    let control = opts.control_path.as_ref().map(|p| Control::open(p, opts));
+    let mut f = fs::File::open(path).unwrap_or_else(|_| panic!("Could not open file. Bad file or path?"));
+    let mut counts = [0u64; 256];
+    let mut len = 0u64;
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    // how much had been counted when the results were last drawn
+    let mut shown: Option<u64> = None;
+    let mut drawn = Instant::now();
+
+    loop {
+        let n = f.read(&mut chunk).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+        for byte in &chunk[..n] { counts[*byte as usize] += 1; }
+        len += n as u64;
+        if let Some(ref c) = control {
+            // the table on screen is already up to date
+            if c.sync(&mut counts, &mut len) { return Vec::new(); }
+        }
+        if let Some(ref b) = board { b.update(path, len, &counts); }
+
+        if n == 0 && fs::metadata(path).map(|m| m.len() < len).unwrap_or(false) {
+            f.seek(SeekFrom::Start(0)).unwrap_or_else(|e| panic!("Could not read {}: {}", path, e));
+            counts = [0u64; 256];
+            len = 0;
+        }
+        // at the end of the file there's no point waiting to redraw
+        if shown != Some(len) && (n == 0 || shown.is_none() || drawn.elapsed() >= REFRESH) {
+            watch::show(opts, &render(opts, format, &counts, len));
+            shown = Some(len);
+            drawn = Instant::now();
+        }
+        if dump::requested() { dump::write(opts, &counts, len); }
+        if n == 0 { thread::sleep(IDLE); }
+    }
+}
+
+fn render(opts: &Options, format: Format, counts: &[u64; 256], len: u64) -> Vec<String> {
+    if len == 0 {
+        return vec![format!("  following {}, nothing to count yet", opts.inputs[0])];
+    }
+    let mut lines = report::render_counts(format, counts, opts.bars);
+    if format == Format::Text {
+        lines.insert(0, format!(
+            "  following {}: {} bytes so far, entropy {:.4}",
+            opts.inputs[0], len, entropy::shannon(counts, len)
+        ));
+    }
+    lines
+}
@@ -0,0 +1,69 @@
+/*
+    Saved count files (.freq). A small text format so they can be diffed,
+    inspected and versioned alongside whatever they describe:
+
+        freqs counts 1
+        total 5341
+        0a 152
+        20 1442
+        ...
+
+    The first line names the format and its version. Then comes the total
+    number of bytes counted, then one line per byte that occured, as hex
+    byte value and count. Bytes that never occured are left out.
+*/
+
+use std::fs;
+use std::io::{self, Write};
+
+use outfile;
+
+const MAGIC: &str = "freqs counts";
+const VERSION: u32 = 1;
+
+pub fn save(path: &str, counts: &[u64; 256], force: bool) -> io::Result<()> {
+    let mut f = outfile::create(path, force)?;
+    for line in render(counts) { writeln!(f, "{}", line)?; }
+    f.finish()
+}
+
+pub fn render(counts: &[u64; 256]) -> Vec<String> {
+    let total: u64 = counts.iter().sum();
+    let mut lines = vec![format!("{} {}", MAGIC, VERSION), format!("total {}", total)];
+    for (byte, c) in counts.iter().enumerate() {
+        if *c != 0 { lines.push(format!("{:02x} {}", byte, c)); }
+    }
+    lines
+}
+
+pub fn load(path: &str) -> Result<[u64; 256], String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut lines = text.lines();
+
+    match lines.next().and_then(|l| l.strip_prefix(MAGIC)) {
+        Some(v) if v.trim().parse() == Ok(VERSION) => (),
+        Some(v) => return Err(format!("unsupported version {}", v.trim())),
+        None => return Err(String::from("not a freqs count file")),
+    }
+
+    let total: u64 = match lines.next().and_then(|l| l.strip_prefix("total ")) {
+        Some(n) => n.trim().parse().map_err(|_| String::from("bad total"))?,
+        None => return Err(String::from("missing total")),
+    };
+
+    let mut counts = [0u64; 256];
+    for (n, line) in lines.enumerate() {
+        let bad = || format!("bad count on line {}", n + 3);
+        let mut fields = line.split_whitespace();
+        let byte = fields.next().and_then(|b| u8::from_str_radix(b, 16).ok()).ok_or_else(bad)?;
+        let count = fields.next().and_then(|c| c.parse().ok()).ok_or_else(bad)?;
+        if fields.next().is_some() { return Err(bad()); }
+        counts[byte as usize] = count;
+    }
+
+    let sum = counts.iter().try_fold(0u64, |sum, c| sum.checked_add(*c));
+    if sum != Some(total) {
+        return Err(format!("counts don't add up to the total of {}", total));
+    }
+    Ok(counts)
+}
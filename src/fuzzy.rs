@@ -0,0 +1,174 @@
+/*
+    --fuzzy: a locality sensitive hash in the manner of TLSH, so that files
+    which share most of their content get hashes that differ only a little.
+    A 5 byte window slides over the file and six triplets from each window
+    are hashed into 128 buckets. Each bucket is then kept as 2 bits, which
+    quarter it falls in among all the buckets, along with the length and
+    the spread of the counts. Unlike a digest, changing a few bytes only
+    moves a few buckets.
+
+    freqs fuzzy compare scores two of them (or files) from 0 to 100, 0
+    being about as far apart as unrelated data gets, and 100 the same.
+*/
+
+use std::fs;
+use std::io::{self, Read};
+
+use args::Options;
+use log;
+use report::{csv_field, json_str, Format};
+use walk;
+
+pub const VERSION: &str = "fz1";
+// too little to fill the buckets in any meaningful way
+pub const MIN_LEN: u64 = 64;
+const BUCKETS: usize = 128;
+const CHUNKSIZE: usize = 1024 * 128;
+// the triplets of the window that get hashed, newest byte first
+const TRIPLETS: [(usize, usize, usize, u8); 6] = [(0, 1, 2, 2), (0, 1, 3, 3), (0, 2, 3, 5), (0, 2, 4, 7), (0, 1, 4, 11), (0, 3, 4, 13)];
+// the distance two hashes of random data land at, on average
+const UNRELATED: f64 = 208.0;
+
+struct Hash {
+    length: u8,     // log2 of the length, in quarters
+    ratios: u8,     // first and second quartile, in sixteenths of the third
+    body: [u8; BUCKETS / 4],
+}
+
+// none if the file is too short
+fn hash_file(path: &str) -> io::Result<Option<Hash>> {
+    let mut f = fs::File::open(path)?;
+    let mut buckets = [0u64; BUCKETS];
+    let mut window = [0u8; 5];
+    let mut len = 0u64;
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    loop {
+        let n = f.read(&mut chunk)?;
+        if n == 0 { break; }
+        for byte in &chunk[..n] {
+            window.rotate_right(1);
+            window[0] = *byte;
+            len += 1;
+            if len < 5 { continue; }
+            for &(a, b, c, salt) in &TRIPLETS {
+                buckets[bucket(salt, window[a], window[b], window[c])] += 1;
+            }
+        }
+    }
+    if len < MIN_LEN { return Ok(None); }
+
+    let mut sorted = buckets;
+    sorted.sort_unstable();
+    let (q1, q2, q3) = (sorted[BUCKETS / 4 - 1], sorted[BUCKETS / 2 - 1], sorted[BUCKETS * 3 / 4 - 1]);
+    let mut body = [0u8; BUCKETS / 4];
+    for (i, n) in buckets.iter().enumerate() {
+        let code = if *n <= q1 { 0 } else if *n <= q2 { 1 } else if *n <= q3 { 2 } else { 3 };
+        body[i / 4] |= code << (2 * (i % 4));
+    }
+    let sixteenths = |q: u64| (q * 15).checked_div(q3).unwrap_or(0) as u8;
+    let length = ((len as f64).log2() * 4.0).round().min(255.0) as u8;
+    Ok(Some(Hash { length, ratios: sixteenths(q1) << 4 | sixteenths(q2), body }))
+}
+
+// fnv-1a over the salt and the three bytes
+fn bucket(salt: u8, a: u8, b: u8, c: u8) -> usize {
+    let mut h: u32 = 0x811c9dc5;
+    for x in [salt, a, b, c] { h = (h ^ x as u32).wrapping_mul(0x01000193); }
+    (h >> 25) as usize
+}
+
+fn encode(h: &Hash) -> String {
+    let mut text = format!("{}:{:02x}{:02x}", VERSION, h.length, h.ratios);
+    for b in h.body.iter() { text.push_str(&format!("{:02x}", b)); }
+    text
+}
+
+fn decode(text: &str) -> Option<Hash> {
+    let hex = text.strip_prefix(VERSION)?.strip_prefix(':')?;
+    if hex.len() != 2 * (2 + BUCKETS / 4) || !hex.is_ascii() { return None; }
+    let bytes: Option<Vec<u8>> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect();
+    let bytes = bytes?;
+    let mut body = [0u8; BUCKETS / 4];
+    body.copy_from_slice(&bytes[2..]);
+    Some(Hash { length: bytes[0], ratios: bytes[1], body })
+}
+
+// buckets a whole quarter range apart count double, as TLSH has it, and
+// the header adds how far apart the lengths and spreads are
+fn distance(a: &Hash, b: &Hash) -> u32 {
+    let mut d = 0;
+    for i in 0..BUCKETS {
+        let (x, y) = ((a.body[i / 4] >> (2 * (i % 4))) & 3, (b.body[i / 4] >> (2 * (i % 4))) & 3);
+        d += match x.abs_diff(y) { 3 => 6, diff => diff as u32 };
+    }
+    d += a.length.abs_diff(b.length) as u32;
+    d += (a.ratios >> 4).abs_diff(b.ratios >> 4) as u32 + (a.ratios & 15).abs_diff(b.ratios & 15) as u32;
+    d
+}
+
+fn similarity(distance: u32) -> u32 {
+    (100.0 * (1.0 - distance as f64 / UNRELATED)).round().max(0.0) as u32
+}
+
+// one line per file, like sha256sum. files too short for a hash get a -
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let hashes: Vec<(String, Option<String>)> = walk::expand(&opts.inputs, opts).into_iter()
+        .filter_map(|path| match hash_file(&path) {
+            Ok(h) => Some((path, h.map(|h| encode(&h)))),
+            Err(e) => {
+                log::warn(&format!("skipping {}: {}", path, e));
+                None
+            },
+        })
+        .collect();
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("path,fuzzy")];
+            for (path, hash) in &hashes { lines.push(format!("{},{}", csv_field(path), hash.as_deref().unwrap_or(""))); }
+            lines
+        },
+        Format::Json => {
+            let files: Vec<String> = hashes.iter()
+                .map(|(path, hash)| format!(
+                    "    {{\"path\": {}, \"fuzzy\": {}}}",
+                    json_str(path), hash.as_ref().map_or(String::from("null"), |h| json_str(h))
+                ))
+                .collect();
+            vec![format!("{{\n  \"files\": [\n{}\n  ]\n}}", files.join(",\n"))]
+        },
+        _ => hashes.iter().map(|(path, hash)| format!("{}  {}", hash.as_deref().unwrap_or("-"), path)).collect(),
+    }
+}
+
+// freqs fuzzy compare <a> <b>
+pub fn compare(opts: &Options, format: Format) -> Vec<String> {
+    let (a, b) = (hash_of(&opts.inputs[1]), hash_of(&opts.inputs[2]));
+    let d = distance(&a, &b);
+    let (text_a, text_b) = (encode(&a), encode(&b));
+    match format {
+        Format::Csv => vec![
+            String::from("a,b,similarity,distance"),
+            format!("{},{},{},{}", text_a, text_b, similarity(d), d),
+        ],
+        Format::Json => vec![format!(
+            "{{\n  \"a\": {},\n  \"b\": {},\n  \"similarity\": {},\n  \"distance\": {}\n}}",
+            json_str(&text_a), json_str(&text_b), similarity(d), d
+        )],
+        _ => vec![
+            format!("  a           {}", text_a),
+            format!("  b           {}", text_b),
+            format!("  similarity  {} of 100, distance {}", similarity(d), d),
+        ],
+    }
+}
+
+// either one already, or a file to hash
+fn hash_of(text: &str) -> Hash {
+    if let Some(h) = decode(text) { return h; }
+    if text.starts_with(&format!("{}:", VERSION)) { panic!("{} isn't a valid fuzzy hash", text); }
+    match hash_file(text) {
+        Ok(Some(h)) => h,
+        Ok(None) => panic!("{} is too short for a fuzzy hash, it takes at least {} bytes", text, MIN_LEN),
+        Err(_) => panic!("Could not open {}. Bad file or path?", text),
+    }
+}
@@ -0,0 +1,213 @@
+/*
+    freqs generate: random data in the likeness of real data, for test
+    fixtures that look the part. With --model, drawn from a model written
+    by freqs model; with --from, from a model of that file made on the
+    spot, to the --order (and --chars) given. Each symbol is drawn given
+    the ones before it, and whenever that leads to a context the model
+    never saw anything follow, it starts over at a context picked by how
+    common it was. --bytes says how much, and --seed makes it the same
+    every time.
+
+    With --match, the byte counts of a file saved with --save are matched
+    instead, with no regard to order: scaled to --bytes (the same total if
+    not given) and dealt out at random, so the counts come out exactly so,
+    or with --sampled, each byte drawn on its own, so they only match as
+    far as chance has it.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use args::Options;
+use freqfile;
+use model::{self, Context, Model};
+use outfile;
+use rng::Rng;
+
+const CHUNKSIZE: usize = 1024 * 64;
+
+// writes straight to the outfile or stdout, like samples
+pub fn run(opts: &Options) {
+    if let Some(ref path) = opts.match_path { return matched(opts, path); }
+    let model = match (&opts.model_path, &opts.from) {
+        (Some(path), _) => model::load(path).unwrap_or_else(|e| panic!("Could not load model {}: {}", path, e)),
+        (_, Some(path)) => {
+            if fs::metadata(path).is_err() { panic!("Could not open {}. Bad file or path?", path); }
+            model::learn(std::slice::from_ref(path), opts.order.unwrap_or(model::DEFAULT_ORDER), opts.chars)
+        },
+        _ => unreachable!(),
+    };
+    if model.contexts.is_empty() { panic!("The model has nothing to go on, it was made from less than --order symbols"); }
+    let mut rng = Rng::new(seed(opts));
+    let bytes = opts.bytes.unwrap();
+    output(opts, |out| write(&model, bytes, &mut rng, out));
+}
+
+fn matched(opts: &Options, path: &str) {
+    let counts = freqfile::load(path).unwrap_or_else(|e| panic!("Could not load {}: {}", path, e));
+    let total: u64 = counts.iter().sum();
+    if total == 0 { panic!("Nothing to match, {} has no bytes counted", path); }
+    let mut rng = Rng::new(seed(opts));
+    let bytes = opts.bytes.unwrap_or(total);
+    if opts.sampled {
+        output(opts, |out| write_sampled(&counts, bytes, &mut rng, out));
+    } else {
+        output(opts, |out| write_exact(&scaled(&counts, bytes), &mut rng, out));
+    }
+}
+
+fn output<F: FnMut(&mut dyn Write) -> io::Result<()>>(opts: &Options, mut write: F) {
+    let written = match opts.out_path {
+        Some(ref path) => outfile::create(path, opts.force).and_then(|mut out| {
+            write(&mut out)?;
+            out.finish()
+        }),
+        None => write(&mut BufWriter::new(io::stdout().lock())),
+    };
+    match written {
+        // the reader, like head -c, has had enough
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(e) => panic!("Could not write the generated data: {}", e),
+        Ok(()) => (),
+    }
+}
+
+// the --seed given, checked to be a number already, or else the clock
+fn seed(opts: &Options) -> u64 {
+    opts.seed.as_ref().and_then(|s| s.parse().ok()).unwrap_or_else(clock)
+}
+
+// a seed that differs from run to run
+pub fn clock() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+// the running totals of some weights, to draw from with a binary search
+fn cumulative<I: Iterator<Item = f64>>(weights: I) -> Vec<f64> {
+    weights.scan(0.0, |total, w| { *total += w; Some(*total) }).collect()
+}
+
+fn draw(cumulative: &[f64], rng: &mut Rng) -> usize {
+    let r = rng.next_f64() * cumulative[cumulative.len() - 1];
+    cumulative.partition_point(|c| *c <= r).min(cumulative.len() - 1)
+}
+
+// at most that many bytes: with --chars, a character that doesn't fit in
+// what's left ends it a little short
+pub fn write(model: &Model, bytes: u64, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+    let index: HashMap<&[u32], (&Context, Vec<f64>)> = model.contexts.iter()
+        .map(|c| (c.symbols.as_slice(), (c, cumulative(c.next.iter().map(|(_, w)| *w)))))
+        .collect();
+    // contexts never seen still get a chance to start over at
+    let starts = cumulative(model.contexts.iter().map(|c| c.count.max(1) as f64));
+
+    let mut buf: Vec<u8> = Vec::with_capacity(CHUNKSIZE + 4);
+    let mut left = bytes;
+    let mut recent: Vec<u32> = Vec::new();
+    let emit = |symbol: u32, buf: &mut Vec<u8>, left: &mut u64| -> bool {
+        let mut encoded = [0u8; 4];
+        let piece: &[u8] = if model.chars {
+            char::from_u32(symbol).unwrap_or('\u{fffd}').encode_utf8(&mut encoded).as_bytes()
+        } else {
+            encoded[0] = symbol as u8;
+            &encoded[..1]
+        };
+        if piece.len() as u64 > *left { return false; }
+        buf.extend_from_slice(piece);
+        *left -= piece.len() as u64;
+        true
+    };
+    'generating: while left > 0 {
+        let symbol = match index.get(recent.as_slice()) {
+            Some((c, weights)) if recent.len() == model.order => c.next[draw(weights, rng)].0,
+            _ => {
+                // a fresh start, which goes out as it is
+                recent = model.contexts[draw(&starts, rng)].symbols.clone();
+                for s in &recent {
+                    if !emit(*s, &mut buf, &mut left) { break 'generating; }
+                }
+                continue;
+            },
+        };
+        if !emit(symbol, &mut buf, &mut left) { break; }
+        if model.order > 0 {
+            recent.remove(0);
+            recent.push(symbol);
+        }
+        if buf.len() >= CHUNKSIZE {
+            out.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    out.write_all(&buf)?;
+    out.flush()
+}
+
+// the counts scaled to a new total. rounding down leaves it a few short,
+// which go to the bytes with the biggest remainders, so it adds up exactly
+fn scaled(counts: &[u64; 256], bytes: u64) -> [u64; 256] {
+    let total: u64 = counts.iter().sum();
+    let mut target = [0u64; 256];
+    let mut remainders = Vec::new();
+    for (byte, c) in counts.iter().enumerate() {
+        let exact = *c as u128 * bytes as u128;
+        target[byte] = (exact / total as u128) as u64;
+        remainders.push((exact % total as u128, byte));
+    }
+    let short = bytes - target.iter().sum::<u64>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for &(_, byte) in remainders.iter().take(short as usize) { target[byte] += 1; }
+    target
+}
+
+// drawn without putting back, so every byte comes out as often as it's due.
+// what's left of each is kept in a fenwick tree, for finding the nth byte
+// still to go without going through all 256
+fn write_exact(counts: &[u64; 256], rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+    let mut tree = [0u64; 257];
+    for (byte, c) in counts.iter().enumerate() {
+        let mut i = byte + 1;
+        while i <= 256 { tree[i] += c; i += i & i.wrapping_neg(); }
+    }
+    let mut remaining: u64 = counts.iter().sum();
+    let mut buf: Vec<u8> = Vec::with_capacity(CHUNKSIZE);
+    while remaining > 0 {
+        let mut r = rng.below(remaining);
+        let mut pos = 0;
+        let mut step = 256;
+        while step > 0 {
+            if pos + step <= 256 && tree[pos + step] <= r {
+                pos += step;
+                r -= tree[pos];
+            }
+            step /= 2;
+        }
+        // pos is the byte, one less than its place in the tree
+        let mut i = pos + 1;
+        while i <= 256 { tree[i] -= 1; i += i & i.wrapping_neg(); }
+        remaining -= 1;
+        buf.push(pos as u8);
+        if buf.len() >= CHUNKSIZE {
+            out.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    out.write_all(&buf)?;
+    out.flush()
+}
+
+fn write_sampled(counts: &[u64; 256], bytes: u64, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+    let weights = cumulative(counts.iter().map(|c| *c as f64));
+    let mut buf: Vec<u8> = Vec::with_capacity(CHUNKSIZE);
+    for _ in 0..bytes {
+        buf.push(draw(&weights, rng) as u8);
+        if buf.len() >= CHUNKSIZE {
+            out.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    out.write_all(&buf)?;
+    out.flush()
+}
@@ -0,0 +1,41 @@
+/*
+    --git-modified and --git-staged: the files to analyze are the ones git
+    says have changed, instead of the inputs, which only narrow it down to
+    some paths. Modified takes in everything that differs from the last
+    commit, staged or not, and new files git isn't ignoring; staged only
+    what's in the index. Files deleted in the change have nothing to count.
+*/
+
+use std::io;
+use std::process::Command;
+
+use args::Options;
+
+pub fn changed(opts: &Options) -> Vec<String> {
+    // outside a repository git diff goes on to compare the paths instead
+    let mut queries: Vec<Vec<&str>> = vec![
+        vec!["rev-parse", "--is-inside-work-tree"],
+        vec!["diff", "--cached", "--name-only", "-z", "--relative", "--diff-filter=d"],
+    ];
+    if opts.git_modified {
+        queries.push(vec!["diff", "--name-only", "-z", "--relative", "--diff-filter=d"]);
+        queries.push(vec!["ls-files", "--others", "--exclude-standard", "-z"]);
+    }
+    let mut files = Vec::new();
+    for query in queries {
+        let out = match Command::new("git").args(&query).arg("--").args(&opts.inputs).output() {
+            Ok(o) => o,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => panic!("git isn't installed, it's needed for --git-modified and --git-staged"),
+            Err(e) => panic!("Could not run git: {}", e),
+        };
+        if !out.status.success() {
+            panic!("git {} failed: {}", query[0], String::from_utf8_lossy(&out.stderr).trim());
+        }
+        if query[0] == "rev-parse" { continue; }
+        // paths are relative to where freqs was run from
+        files.extend(out.stdout.split(|&b| b == 0).filter(|p| !p.is_empty()).map(|p| String::from_utf8_lossy(p).into_owned()));
+    }
+    files.sort();
+    files.dedup();
+    files
+}
@@ -0,0 +1,145 @@
+/*
+    Glob patterns for inputs. * and ? match within a path component,
+    [abc], [a-z] and [!a-z] match one character of a set, and a component
+    that is just ** matches any number of directories, so that data, **
+    and *.csv put together find every csv below data. Like in the shell,
+    wildcards don't match a leading dot.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use args::Options;
+use walk;
+
+pub fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+// the files the inputs name now: patterns are matched against what exists,
+// plain paths are expanded like any other input but dropped if missing
+pub fn expand(inputs: &[String], opts: &Options) -> Vec<String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if is_pattern(input) {
+            files.extend(matching(input));
+        } else if fs::metadata(input).is_ok() {
+            files.extend(walk::expand(std::slice::from_ref(input), opts));
+        }
+    }
+    files.dedup();
+    files
+}
+
+// the files a pattern matches, sorted
+pub fn matching(pattern: &str) -> Vec<String> {
+    let (base, components) = split(pattern);
+    let mut found = Vec::new();
+    visit(&base, &components, &mut found);
+    found.sort();
+    // matches below the current directory are named like ls would
+    found.iter().map(|p| p.strip_prefix(".").unwrap_or(p).to_string_lossy().into_owned()).collect()
+}
+
+// the directory a pattern starts from, which is the part before the first
+// wildcard, and the components to match below it
+pub fn split(pattern: &str) -> (PathBuf, Vec<String>) {
+    let mut base = PathBuf::new();
+    if pattern.starts_with('/') { base.push("/"); }
+    // C:/ rather than C:, which is wherever that drive was left at
+    let mut rest = pattern;
+    if cfg!(windows) && pattern.get(1..3) == Some(":/") {
+        base.push(&pattern[..3]);
+        rest = &pattern[3..];
+    }
+    let mut components = Vec::new();
+    for part in rest.split('/').filter(|p| !p.is_empty()) {
+        if components.is_empty() && !is_pattern(part) {
+            base.push(part);
+        } else {
+            components.push(part.to_string());
+        }
+    }
+    if base.as_os_str().is_empty() { base.push("."); }
+    (base, components)
+}
+
+fn visit(dir: &Path, components: &[String], found: &mut Vec<PathBuf>) {
+    let (first, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            if dir.is_file() { found.push(dir.to_path_buf()); }
+            return;
+        },
+    };
+    let entries: Vec<(String, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+            .map(|e| (e.file_name().to_string_lossy().into_owned(), e.path()))
+            .collect(),
+        Err(_) => return,
+    };
+    let dir_named = |path: &Path| fs::symlink_metadata(path).map(|m| m.is_dir()).unwrap_or(false);
+
+    if first == "**" {
+        // no directories at all, or one more and still inside the **
+        visit(dir, rest, found);
+        for (name, path) in &entries {
+            if !name.starts_with('.') && dir_named(path) { visit(path, components, found); }
+        }
+        return;
+    }
+    for (name, path) in &entries {
+        if name.starts_with('.') && !first.starts_with('.') { continue; }
+        if !matches(first, name) { continue; }
+        if rest.is_empty() {
+            if path.is_file() { found.push(path.clone()); }
+        } else if dir_named(path) {
+            visit(path, rest, found);
+        }
+    }
+}
+
+// whether a single path component matches a pattern
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_from(&pattern, &name)
+}
+
+fn match_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| match_from(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && match_from(&pattern[1..], &name[1..]),
+        Some('[') if name.is_empty() => false,
+        Some('[') => match class(&pattern[1..], name[0]) {
+            Some((matched, len)) => matched && match_from(&pattern[1 + len..], &name[1..]),
+            // an unclosed [ is just a [
+            None => name[0] == '[' && match_from(&pattern[1..], &name[1..]),
+        },
+        Some(c) => name.first() == Some(c) && match_from(&pattern[1..], &name[1..]),
+    }
+}
+
+// whether c is in the set of a [...], given what follows the [, and how
+// many characters the set took up including the ]. None if it's unclosed
+fn class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    let start = if negated { 1 } else { 0 };
+    // a ] right at the start is part of the set
+    let end = start + 1 + pattern.get(start + 1..)?.iter().position(|m| *m == ']')?;
+    let members = &pattern[start..end];
+
+    let mut i = 0;
+    let mut found = false;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            found |= members[i] <= c && c <= members[i + 2];
+            i += 3;
+        } else {
+            found |= members[i] == c;
+            i += 1;
+        }
+    }
+    Some((found != negated, end + 1))
+}
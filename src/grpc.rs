@@ -0,0 +1,495 @@
+/*
+    freqs serve --grpc: the service over grpc instead, for pipelines that
+    want a typed schema rather than json. It's plain-text http/2 (h2c, with
+    prior knowledge, like grpc's insecure channels) with just enough of the
+    protocol for unary answers to streamed uploads. The schema is in
+    proto/freqs.proto; messages are encoded and decoded by hand here.
+    Files on the server are only read under --root, as with serve.
+*/
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::thread;
+
+use analysis::{self, HEAD_LEN};
+use args::Options;
+use classify;
+use entropy;
+use hpack::{self, Decoder};
+use log;
+use magic;
+use serve::{self, Slot, DEFAULT_LISTEN, TIMEOUT};
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const METHOD: &str = "/freqs.Freqs/Analyze";
+const CHUNKSIZE: usize = 1024 * 128;
+// the http/2 default, which is never raised
+const MAX_FRAME: usize = 16384;
+// grpc's usual limit
+const MAX_MESSAGE: usize = 4 * 1024 * 1024;
+// a header block, over however many CONTINUATION frames. grpc's calls only
+// need a few hundred bytes of it
+const MAX_HEADER_BLOCK: usize = 64 * 1024;
+
+// frame types and flags
+const DATA: u8 = 0;
+const HEADERS: u8 = 1;
+const RST_STREAM: u8 = 3;
+const SETTINGS: u8 = 4;
+const PING: u8 = 6;
+const GOAWAY: u8 = 7;
+const WINDOW_UPDATE: u8 = 8;
+const CONTINUATION: u8 = 9;
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+// http/2 error codes
+const PROTOCOL_ERROR: u32 = 0x1;
+const FRAME_SIZE_ERROR: u32 = 0x6;
+const COMPRESSION_ERROR: u32 = 0x9;
+const ENHANCE_YOUR_CALM: u32 = 0xb;
+
+// grpc status codes
+const INVALID_ARGUMENT: u32 = 3;
+const NOT_FOUND: u32 = 5;
+const PERMISSION_DENIED: u32 = 7;
+const RESOURCE_EXHAUSTED: u32 = 8;
+const UNIMPLEMENTED: u32 = 12;
+
+// type, flags, stream and payload
+type Frame = (u8, u8, u32, Vec<u8>);
+
+struct Call {
+    path: String,
+    pending: Vec<u8>,       // the part of a message that has come so far
+    file: Option<String>,   // a file on the server, instead of the data
+    len: u64,
+    counts: [u64; 256],
+    head: Vec<u8>,
+    failed: Option<(u32, String)>,
+}
+
+pub fn run(opts: &Options) -> ! {
+    let addr = opts.listen.as_deref().unwrap_or(DEFAULT_LISTEN);
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| panic!("Could not listen on {}: {}", addr, e));
+    log::info(&format!("listening for grpc on {}", addr));
+
+    let open = Arc::new(AtomicUsize::new(0));
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(_) => continue,
+        };
+        // past serve's MAX_CONNECTIONS, dropped before the preface
+        let slot = match Slot::take(&open) {
+            Some(slot) => slot,
+            None => continue,
+        };
+        let opts = opts.clone();
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = connection(stream, &opts) { log::warn(&format!("connection failed: {}", e)); }
+        });
+    }
+}
+
+fn connection(stream: TcpStream, opts: &Options) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut input = BufReader::with_capacity(CHUNKSIZE, &stream);
+    let mut out = &stream;
+    let mut preface = [0u8; 24];
+    input.read_exact(&mut preface)?;
+    if preface[..] != PREFACE[..] {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not http/2 with prior knowledge"));
+    }
+    frame(&mut out, SETTINGS, 0, 0, &[])?;
+
+    let mut decoder = Decoder::new();
+    let mut calls: HashMap<u32, Call> = HashMap::new();
+    // a header block still waiting on CONTINUATION frames: its stream, the
+    // flags it started with, and the block so far
+    let mut block: Option<(u32, u8, Vec<u8>)> = None;
+    loop {
+        let (kind, flags, id, payload) = match read_frame(&mut input)? {
+            Some(f) => f,
+            None => return Ok(()),
+        };
+        if payload.len() > MAX_FRAME { return goaway(&mut out, FRAME_SIZE_ERROR); }
+        if block.is_some() && kind != CONTINUATION { return goaway(&mut out, PROTOCOL_ERROR); }
+        match kind {
+            HEADERS => {
+                let mut fragment = match unpad(flags, &payload) {
+                    Some(f) => f,
+                    None => return goaway(&mut out, PROTOCOL_ERROR),
+                };
+                if flags & PRIORITY != 0 {
+                    if fragment.len() < 5 { return goaway(&mut out, PROTOCOL_ERROR); }
+                    fragment = &fragment[5..];
+                }
+                block = Some((id, flags, fragment.to_vec()));
+            },
+            CONTINUATION => match block {
+                Some((started, _, ref mut b)) if started == id => {
+                    if b.len() + payload.len() > MAX_HEADER_BLOCK { return goaway(&mut out, ENHANCE_YOUR_CALM); }
+                    b.extend_from_slice(&payload);
+                },
+                _ => return goaway(&mut out, PROTOCOL_ERROR),
+            },
+            DATA => {
+                // give back the window right away, the data's counted as it comes
+                if !payload.is_empty() {
+                    window_update(&mut out, 0, payload.len())?;
+                    if flags & END_STREAM == 0 { window_update(&mut out, id, payload.len())?; }
+                }
+                let data = match unpad(flags, &payload) {
+                    Some(d) => d,
+                    None => return goaway(&mut out, PROTOCOL_ERROR),
+                };
+                if let Some(call) = calls.get_mut(&id) { call.feed(data); }
+                if flags & END_STREAM != 0 {
+                    if let Some(call) = calls.remove(&id) { respond(&mut out, id, call, opts)?; }
+                }
+            },
+            SETTINGS if flags & ACK == 0 => frame(&mut out, SETTINGS, ACK, 0, &[])?,
+            PING if flags & ACK == 0 => frame(&mut out, PING, ACK, 0, &payload)?,
+            RST_STREAM => { calls.remove(&id); },
+            GOAWAY => return Ok(()),
+            // priorities, window updates (answers are small) and anything new
+            _ => {},
+        }
+
+        if (kind == HEADERS || kind == CONTINUATION) && flags & END_HEADERS != 0 {
+            let (id, first_flags, bytes) = block.take().unwrap();
+            let headers = match decoder.decode(&bytes) {
+                Ok(h) => h,
+                Err(_) => return goaway(&mut out, COMPRESSION_ERROR),
+            };
+            // headers on a call already going are the client's trailers
+            calls.entry(id).or_insert_with(|| Call {
+                path: headers.iter().find(|(name, _)| name == ":path").map_or(String::new(), |(_, v)| v.clone()),
+                pending: Vec::new(), file: None, len: 0, counts: [0; 256], head: Vec::new(), failed: None,
+            });
+            if first_flags & END_STREAM != 0 {
+                if let Some(call) = calls.remove(&id) { respond(&mut out, id, call, opts)?; }
+            }
+        }
+    }
+}
+
+impl Call {
+    // takes in DATA, counting each Chunk as soon as it's whole
+    fn feed(&mut self, data: &[u8]) {
+        if self.failed.is_some() { return; }
+        self.pending.extend_from_slice(data);
+        while self.pending.len() >= 5 {
+            let len = u32::from_be_bytes([self.pending[1], self.pending[2], self.pending[3], self.pending[4]]) as usize;
+            if len > MAX_MESSAGE {
+                self.failed = Some((RESOURCE_EXHAUSTED, format!("messages are limited to {} bytes", MAX_MESSAGE)));
+                return;
+            }
+            if self.pending.len() < 5 + len { return; }
+            if self.pending[0] != 0 {
+                self.failed = Some((UNIMPLEMENTED, String::from("compressed messages aren't supported")));
+                return;
+            }
+            let fields = match fields(&self.pending[5..5 + len]) {
+                Ok(f) => f,
+                Err(e) => { self.failed = Some((INVALID_ARGUMENT, String::from(e))); return; },
+            };
+            for (field, value) in fields {
+                match field {
+                    1 => {
+                        for byte in value { self.counts[*byte as usize] += 1; }
+                        if self.head.len() < HEAD_LEN {
+                            let n = (HEAD_LEN - self.head.len()).min(value.len());
+                            self.head.extend_from_slice(&value[..n]);
+                        }
+                        self.len += value.len() as u64;
+                    },
+                    2 => self.file = Some(String::from_utf8_lossy(value).into_owned()),
+                    _ => {},
+                }
+            }
+            self.pending.drain(..5 + len);
+        }
+    }
+}
+
+fn respond<W: Write>(out: &mut W, id: u32, call: Call, opts: &Options) -> io::Result<()> {
+    let failed = if call.path != METHOD {
+        Some((UNIMPLEMENTED, format!("no method {}, there's only {}", call.path, METHOD)))
+    } else if call.failed.is_some() {
+        call.failed
+    } else if !call.pending.is_empty() {
+        Some((INVALID_ARGUMENT, String::from("the stream ended partway through a message")))
+    } else {
+        None
+    };
+    let result = match (failed, call.file) {
+        (Some(f), _) => Err(f),
+        (None, Some(path)) => match serve::local_path(opts, &path) {
+            Ok(local) => match analysis::analyze(&local, opts, false) {
+                Ok(a) => Ok((a.len, a.counts, a.head)),
+                Err(e) => Err((NOT_FOUND, format!("Could not read {}: {}", path, e))),
+            },
+            Err(e) => Err((PERMISSION_DENIED, e)),
+        },
+        (None, None) => Ok((call.len, call.counts, call.head)),
+    };
+    let (len, counts, head) = match result {
+        Ok(r) => r,
+        Err((status, message)) => {
+            log::info(&format!("{} {}", call.path, status));
+            // a trailers-only answer
+            let (status, message) = (status.to_string(), escape(&message));
+            return headers(out, id, END_STREAM, &[
+                (":status", "200"), ("content-type", "application/grpc"), ("grpc-status", &status), ("grpc-message", &message),
+            ]);
+        },
+    };
+    log::info(&format!("{} 0", call.path));
+
+    let message = analysis_message(len, &counts, &head);
+    let mut body = vec![0];
+    body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    body.extend_from_slice(&message);
+    headers(out, id, 0, &[(":status", "200"), ("content-type", "application/grpc")])?;
+    for part in body.chunks(MAX_FRAME) { frame(out, DATA, 0, id, part)?; }
+    headers(out, id, END_STREAM, &[("grpc-status", "0")])
+}
+
+// Analysis, as in proto/freqs.proto
+fn analysis_message(len: u64, counts: &[u64; 256], head: &[u8]) -> Vec<u8> {
+    let mut m = Vec::new();
+    put_varint(&mut m, 1 << 3);
+    put_varint(&mut m, len);
+    let mut packed = Vec::new();
+    for c in counts.iter() { put_varint(&mut packed, *c); }
+    put_bytes(&mut m, 2, &packed);
+    put_double(&mut m, 3, entropy::shannon(counts, len));
+    let signature = magic::detect(head);
+    if let Some(sig) = signature { put_bytes(&mut m, 4, sig.name.as_bytes()); }
+    // there's nothing to score an empty stream against
+    if len > 0 {
+        let scores = classify::score(counts);
+        let v = classify::verdict(&scores, signature);
+        put_bytes(&mut m, 5, v.kind.as_bytes());
+        put_bytes(&mut m, 6, v.basis.as_bytes());
+        for s in &scores {
+            let mut score = Vec::new();
+            put_bytes(&mut score, 1, s.name.as_bytes());
+            put_double(&mut score, 2, s.distance);
+            put_double(&mut score, 3, s.confidence);
+            put_bytes(&mut m, 7, &score);
+        }
+    }
+    m
+}
+
+// the length-delimited fields of a message, which is all a Chunk has; the
+// rest are skipped over
+fn fields(message: &[u8]) -> Result<Vec<(u64, &[u8])>, &'static str> {
+    const BAD: &str = "malformed Chunk message";
+    let mut found = Vec::new();
+    let mut at = 0;
+    while at < message.len() {
+        let key = varint(message, &mut at).ok_or(BAD)?;
+        let skip = match key & 7 {
+            0 => { varint(message, &mut at).ok_or(BAD)?; 0 },
+            1 => 8,
+            2 => {
+                let len = varint(message, &mut at).ok_or(BAD)? as usize;
+                let value = message.get(at..at.checked_add(len).ok_or(BAD)?).ok_or(BAD)?;
+                found.push((key >> 3, value));
+                len
+            },
+            5 => 4,
+            _ => return Err(BAD),
+        };
+        at += skip;
+        if at > message.len() { return Err(BAD); }
+    }
+    Ok(found)
+}
+
+fn varint(message: &[u8], at: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let b = *message.get(*at)?;
+        *at += 1;
+        value |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 { return Some(value); }
+    }
+    None
+}
+
+fn put_varint(m: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        m.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    m.push(value as u8);
+}
+
+fn put_bytes(m: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    put_varint(m, field << 3 | 2);
+    put_varint(m, bytes.len() as u64);
+    m.extend_from_slice(bytes);
+}
+
+fn put_double(m: &mut Vec<u8>, field: u64, value: f64) {
+    put_varint(m, field << 3 | 1);
+    m.extend_from_slice(&value.to_le_bytes());
+}
+
+// grpc-message is percent-encoded outside printable ascii
+fn escape(message: &str) -> String {
+    let mut escaped = String::new();
+    for b in message.bytes() {
+        if (0x20..0x7f).contains(&b) && b != b'%' { escaped.push(b as char); } else { escaped.push_str(&format!("%{:02X}", b)); }
+    }
+    escaped
+}
+
+// None at the end of the connection between frames
+fn read_frame<R: BufRead>(input: &mut R) -> io::Result<Option<Frame>> {
+    if input.fill_buf()?.is_empty() { return Ok(None); }
+    let mut head = [0u8; 9];
+    input.read_exact(&mut head)?;
+    let len = (head[0] as usize) << 16 | (head[1] as usize) << 8 | head[2] as usize;
+    let id = u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff;
+    // a frame too big gets refused, but its length is trusted no further
+    if len > MAX_FRAME { return Ok(Some((head[3], head[4], id, vec![0; len]))); }
+    let mut payload = vec![0u8; len];
+    input.read_exact(&mut payload)?;
+    Ok(Some((head[3], head[4], id, payload)))
+}
+
+fn unpad(flags: u8, payload: &[u8]) -> Option<&[u8]> {
+    if flags & PADDED == 0 { return Some(payload); }
+    let pad = *payload.first()? as usize;
+    payload.get(1..payload.len().checked_sub(pad)?)
+}
+
+fn frame<W: Write>(out: &mut W, kind: u8, flags: u8, id: u32, payload: &[u8]) -> io::Result<()> {
+    let len = payload.len() as u32;
+    let mut head = vec![(len >> 16) as u8, (len >> 8) as u8, len as u8, kind, flags];
+    head.extend_from_slice(&id.to_be_bytes());
+    head.extend_from_slice(payload);
+    out.write_all(&head)?;
+    out.flush()
+}
+
+fn headers<W: Write>(out: &mut W, id: u32, flags: u8, fields: &[(&str, &str)]) -> io::Result<()> {
+    frame(out, HEADERS, flags | END_HEADERS, id, &hpack::encode(fields))
+}
+
+fn window_update<W: Write>(out: &mut W, id: u32, n: usize) -> io::Result<()> {
+    frame(out, WINDOW_UPDATE, 0, id, &(n as u32).to_be_bytes())
+}
+
+fn goaway<W: Write>(out: &mut W, code: u32) -> io::Result<()> {
+    let mut payload = vec![0u8; 4];
+    payload.extend_from_slice(&code.to_be_bytes());
+    frame(out, GOAWAY, 0, 0, &payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use args;
+
+    fn raw(kind: u8, flags: u8, id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        frame(&mut out, kind, flags, id, payload).unwrap();
+        out
+    }
+
+    #[test]
+    fn frames_read_back() {
+        let data = [raw(PING, 0, 0, b"12345678"), raw(DATA, END_STREAM, 3, &[])].concat();
+        let mut input = &data[..];
+        assert_eq!(read_frame(&mut input).unwrap(), Some((PING, 0, 0, b"12345678".to_vec())));
+        assert_eq!(read_frame(&mut input).unwrap(), Some((DATA, END_STREAM, 3, Vec::new())));
+        assert_eq!(read_frame(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn truncated_frames() {
+        let data = raw(HEADERS, END_HEADERS, 1, &[0x82, 0x86]);
+        for len in 1..data.len() {
+            let e = read_frame(&mut &data[..len]).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof, "cut off at {}", len);
+        }
+    }
+
+    #[test]
+    fn oversized_frames_arent_read() {
+        // a length of 16M, and nothing after the head to back it up
+        let head = [0xff, 0xff, 0xff, DATA, 0, 0, 0, 0, 1];
+        let mut input = &head[..];
+        let (kind, _, _, payload) = read_frame(&mut input).unwrap().unwrap();
+        assert_eq!((kind, payload.len()), (DATA, 0xff_ffff));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(unpad(0, b"\x02ab"), Some(&b"\x02ab"[..]));
+        assert_eq!(unpad(PADDED, b"\x02abcd"), Some(&b"ab"[..]));
+        assert_eq!(unpad(PADDED, b"\x00"), Some(&b""[..]));
+        assert_eq!(unpad(PADDED, b""), None);
+        // more padding than there is frame
+        assert_eq!(unpad(PADDED, b"\x05ab"), None);
+    }
+
+    // what a connection sent back up to its GOAWAY, when sent data after
+    // the preface
+    fn goaway_code(data: &[u8]) -> Option<u32> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let opts = args::parse(&[String::from("freqs"), String::from("serve"), String::from("--grpc")]);
+        let handle = thread::spawn(move || connection(server, &opts));
+        // the server can hang up before it's all been read
+        let _ = client.write_all(&[PREFACE, data].concat());
+        let mut input = BufReader::new(client);
+        let mut code = None;
+        while let Ok(Some((kind, _, _, payload))) = read_frame(&mut input) {
+            if kind == GOAWAY { code = Some(u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]])); break; }
+        }
+        drop(input);
+        let _ = handle.join();
+        code
+    }
+
+    #[test]
+    fn oversized_frame_goes_away() {
+        let head = [0x00, 0x40, 0x01, PING, 0, 0, 0, 0, 0];
+        assert_eq!(goaway_code(&head), Some(FRAME_SIZE_ERROR));
+    }
+
+    #[test]
+    fn endless_header_block_goes_away() {
+        let mut data = raw(HEADERS, 0, 1, &[0x82]);
+        let fragment = vec![0x82; MAX_FRAME];
+        for _ in 0..MAX_HEADER_BLOCK / MAX_FRAME + 1 { data.extend(raw(CONTINUATION, 0, 1, &fragment)); }
+        assert_eq!(goaway_code(&data), Some(ENHANCE_YOUR_CALM));
+    }
+
+    #[test]
+    fn broken_framing_goes_away() {
+        // a CONTINUATION with no HEADERS before it
+        assert_eq!(goaway_code(&raw(CONTINUATION, END_HEADERS, 1, &[0x82])), Some(PROTOCOL_ERROR));
+        // a HEADERS frame all padding
+        assert_eq!(goaway_code(&raw(HEADERS, END_HEADERS | PADDED, 1, &[0x09])), Some(PROTOCOL_ERROR));
+        // and a header block that doesn't decode
+        assert_eq!(goaway_code(&raw(HEADERS, END_HEADERS, 1, &[0x80])), Some(COMPRESSION_ERROR));
+    }
+}
@@ -0,0 +1,110 @@
+/*
+    Run with nothing at all in a terminal, freqs asks what to do instead
+    of saying there aren't enough arguments: what to analyze, which kind of
+    report, in what format, and where to put it. The answers make up the
+    same arguments as anything typed out, which are shown before running,
+    so next time they can be.
+*/
+
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const MODES: [(&str, &str); 5] = [
+    ("the full report, a table of every byte value", ""),
+    ("a line per file, for a directory", "--summary-line"),
+    ("triage, entropy and file types for going through samples", "--triage"),
+    ("an entropy map of the file, as a png", "--hilbert"),
+    ("whether it looks random, with the usual tests", "random"),
+];
+const FORMATS: [&str; 3] = ["text", "csv", "json"];
+
+// only where someone can answer
+pub fn available() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+// the arguments to run with, none if the questions were left unanswered
+pub fn ask(program: &str) -> Option<Vec<String>> {
+    let mut input = io::stdin().lock();
+    println!("No arguments, so some questions instead. Leave the first one empty to stop, or run freqs -h for all there is.");
+
+    let path = loop {
+        let path = prompt(&mut input, "\nFile or directory to analyze: ")?;
+        if path.is_empty() { return None; }
+        if fs::metadata(&path).is_ok() { break path; }
+        println!("There's nothing at {}.", path);
+    };
+
+    println!("\nWhat should it make?");
+    for (i, (what, _)) in MODES.iter().enumerate() { println!("  {}) {}", i + 1, what); }
+    let mode = choose(&mut input, MODES.len())?;
+
+    let mut args = vec![program.to_string()];
+    match MODES[mode].1 {
+        "" => args.push(path),
+        "random" => args.extend([String::from("random"), path]),
+        "--hilbert" => {
+            let (image, replaced) = destination(&mut input, "\nWrite the image to [map.png]: ", "map.png")?;
+            args.extend([path, String::from("--hilbert"), image]);
+            if replaced { args.push(String::from("--force")); }
+        },
+        flag => args.extend([path, flag.to_string()]),
+    }
+
+    println!("\nIn what format?");
+    for (i, f) in FORMATS.iter().enumerate() { println!("  {}) {}", i + 1, f); }
+    let format = choose(&mut input, FORMATS.len())?;
+    if format > 0 { args.extend([String::from("--format"), FORMATS[format].to_string()]); }
+
+    let (out, replaced) = destination(&mut input, "\nSave it to a file (leave empty to show it here): ", "")?;
+    if !out.is_empty() { args.extend([String::from("-o"), out]); }
+    if replaced && !args.iter().any(|a| a == "--force") { args.push(String::from("--force")); }
+
+    let shown: Vec<String> = args.iter().map(|a| quoted(a)).collect();
+    println!("\nRunning {}\n", shown.join(" "));
+    Some(args)
+}
+
+// a line, trimmed. none at the end of input
+fn prompt(input: &mut dyn BufRead, question: &str) -> Option<String> {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+// one of the numbered choices, the first if left empty
+fn choose(input: &mut dyn BufRead, choices: usize) -> Option<usize> {
+    loop {
+        let answer = prompt(input, &format!("Pick 1 to {} [1]: ", choices))?;
+        if answer.is_empty() { return Some(0); }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=choices).contains(&n) => return Some(n - 1),
+            _ => println!("That isn't one of them."),
+        }
+    }
+}
+
+// a file to write, and whether it's one already there to replace. asked
+// again if it's there and shouldn't be
+fn destination(input: &mut dyn BufRead, question: &str, default: &str) -> Option<(String, bool)> {
+    loop {
+        let path = prompt(input, question)?;
+        let path = if path.is_empty() { default.to_string() } else { path };
+        if path.is_empty() || fs::metadata(&path).is_err() { return Some((path, false)); }
+        let answer = prompt(input, &format!("{} is already there, replace it? [y/N]: ", path))?;
+        if answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") { return Some((path, true)); }
+    }
+}
+
+// as it would be typed into a shell
+fn quoted(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
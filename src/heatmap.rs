@@ -0,0 +1,120 @@
+/*
+    --heatmap: which parts of the file hold which kinds of bytes. The file
+    is split into blocks, one row each from top to bottom, and every row
+    counts the byte values in its block, in --heatmap-buckets columns of
+    neighbouring values (all 256 by default). Written as a png, brighter
+    for more, on a log scale so a few common values don't wash out the
+    rest; or, for a path ending in .csv, as the matrix of counts itself,
+    one row per block.
+*/
+
+use std::io::{self, Write};
+
+use outfile;
+use png;
+
+// most rows we'll produce when picking the block size ourselves
+pub const MAX_ROWS: u64 = 512;
+// the image is this wide however many buckets, each getting an equal share
+const WIDTH: usize = 256;
+
+// colour stops from none to the most of any cell
+const GRADIENT: [(f64, (u8, u8, u8)); 5] = [
+    (0.0, (0x00, 0x00, 0x00)),
+    (0.25, (0x30, 0x10, 0x80)), // violet
+    (0.5, (0xc0, 0x20, 0x40)),  // red
+    (0.75, (0xff, 0xa0, 0x00)), // orange
+    (1.0, (0xff, 0xff, 0xe0)),  // near white
+];
+
+pub struct Heatmap {
+    pub start: u64,            // offset of the first block
+    pub block_size: u64,
+    pub buckets: usize,
+    pub rows: Vec<Vec<u64>>,   // per block: a count per bucket
+}
+
+// picks a block size so the whole file fits in MAX_ROWS rows
+pub fn block_size_for(file_len: u64) -> u64 {
+    file_len.div_ceil(MAX_ROWS).max(1)
+}
+
+pub fn is_csv(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".csv")
+}
+
+pub struct HeatmapTracker {
+    block_size: u64,
+    shift: u32,                // from byte value to bucket
+    filled: u64,
+    rows: Vec<Vec<u64>>,
+}
+
+impl HeatmapTracker {
+    // buckets is a power of two, 1 to 256
+    pub fn new(block_size: u64, buckets: usize) -> HeatmapTracker {
+        HeatmapTracker { block_size, shift: 8 - buckets.trailing_zeros(), filled: 0, rows: Vec::new() }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for byte in chunk {
+            if self.filled == 0 { self.rows.push(vec![0; 256 >> self.shift]); }
+            let row = self.rows.len() - 1;
+            self.rows[row][(*byte >> self.shift) as usize] += 1;
+            self.filled += 1;
+            if self.filled == self.block_size { self.filled = 0; }
+        }
+    }
+
+    pub fn finish(self) -> Heatmap {
+        Heatmap { start: 0, block_size: self.block_size, buckets: 256 >> self.shift, rows: self.rows }
+    }
+}
+
+fn colour(t: f64) -> (u8, u8, u8) {
+    for pair in GRADIENT.windows(2) {
+        let (lo, c0) = pair[0];
+        let (hi, c1) = pair[1];
+        if t <= hi {
+            let t = (t - lo) / (hi - lo);
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+            return (mix(c0.0, c1.0), mix(c0.1, c1.1), mix(c0.2, c1.2));
+        }
+    }
+    GRADIENT[GRADIENT.len() - 1].1
+}
+
+pub fn render(map: &Heatmap, path: &str, force: bool) -> io::Result<()> {
+    if is_csv(path) { render_csv(map, path, force) } else { render_png(map, path, force) }
+}
+
+fn render_png(map: &Heatmap, path: &str, force: bool) -> io::Result<()> {
+    let most = map.rows.iter().flatten().copied().max().unwrap_or(0);
+    let scale = ((most + 1) as f64).ln();
+    let wide = WIDTH / map.buckets;
+    // an empty file still makes an image, of one blank row
+    let height = map.rows.len().max(1);
+    let mut pixels = Vec::with_capacity(WIDTH * height * 3);
+    for row in &map.rows {
+        for c in row {
+            let (r, g, b) = if *c == 0 { (0, 0, 0) } else { colour(((c + 1) as f64).ln() / scale) };
+            for _ in 0..wide { pixels.extend_from_slice(&[r, g, b]); }
+        }
+    }
+    pixels.resize(WIDTH * height * 3, 0);
+    png::write_rgb(path, WIDTH as u32, height as u32, &pixels, force)
+}
+
+// offset of the block, then the counts of the buckets, named by the first
+// byte value in each
+fn render_csv(map: &Heatmap, path: &str, force: bool) -> io::Result<()> {
+    let mut f = outfile::create(path, force)?;
+    let wide = 256 / map.buckets;
+    let columns: Vec<String> = (0..map.buckets).map(|b| format!("{:#04x}", b * wide)).collect();
+    writeln!(f, "offset,{}", columns.join(","))?;
+    for (i, row) in map.rows.iter().enumerate() {
+        let counts: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+        writeln!(f, "{},{}", map.start + i as u64 * map.block_size, counts.join(","))?;
+    }
+    f.finish()
+}
@@ -0,0 +1,110 @@
+/*
+    Entropy map export. Blocks of the file are laid out along a Hilbert
+    curve, which keeps neighbouring offsets next to each other in the image,
+    so contiguous regions of the file (headers, code, packed data...) show
+    up as contiguous blobs of colour.
+*/
+
+use std::io;
+use std::mem;
+
+use png;
+
+// largest image side we'll produce when picking the block size ourselves
+pub const MAX_SIDE: u64 = 512;
+// blocks smaller than this don't have a meaningful entropy
+pub const MIN_BLOCK: u64 = 64;
+// largest image side at all, whatever --hilbert-block asks for. 48MB of
+// pixels
+pub const MAX_IMAGE_SIDE: u64 = 4096;
+
+// unused pixels past the end of the file
+const BACKGROUND: (u8, u8, u8) = (0x20, 0x20, 0x20);
+
+// colour stops from low to high entropy
+const GRADIENT: [(f64, (u8, u8, u8)); 5] = [
+    (0.0, (0x00, 0x00, 0x00)), // constant / padding
+    (0.4, (0x1e, 0x3c, 0xc8)), // blue, text and structured data
+    (0.7, (0xc8, 0x28, 0xa0)), // magenta, code
+    (0.9, (0xff, 0x8c, 0x00)), // orange
+    (1.0, (0xff, 0xff, 0xc8)), // near white, compressed or encrypted
+];
+
+// picks a block size so the whole file fits in a MAX_SIDE * MAX_SIDE image
+pub fn block_size_for(file_len: u64) -> u64 {
+    let pixels = MAX_SIDE * MAX_SIDE;
+    MIN_BLOCK.max(file_len.div_ceil(pixels))
+}
+
+// the side of the square image that blocks many pixels fit in, a power of
+// two
+pub fn side_for(blocks: u64) -> u64 {
+    let mut side = 1;
+    while side * side < blocks { side *= 2; }
+    side
+}
+
+// maps a distance along the curve to x, y on a side * side grid. side must
+// be a power of two.
+pub fn d2xy(side: u32, d: u32) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        // rotate the quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+// entropy is expected to already be normalized to 0..1
+fn colour(e: f64) -> (u8, u8, u8) {
+    let e = e.clamp(0.0, 1.0);
+    for pair in GRADIENT.windows(2) {
+        let (lo, c0) = pair[0];
+        let (hi, c1) = pair[1];
+        if e <= hi {
+            let t = (e - lo) / (hi - lo);
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+            return (mix(c0.0, c1.0), mix(c0.1, c1.1), mix(c0.2, c1.2));
+        }
+    }
+    GRADIENT[GRADIENT.len() - 1].1
+}
+
+// one pixel per block entropy, written out as a png
+pub fn render(entropies: &[f64], path: &str, force: bool) -> io::Result<()> {
+    // a stream of no known length can't be checked for this beforehand
+    let side = side_for(entropies.len() as u64);
+    if side > MAX_IMAGE_SIDE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "it would be {0}x{0} pixels, more than {1}x{1}; try a bigger --hilbert-block", side, MAX_IMAGE_SIDE
+        )));
+    }
+    let side = side as u32;
+
+    let mut pixels = Vec::with_capacity(side as usize * side as usize * 3);
+    for _ in 0..(side as usize * side as usize) {
+        pixels.extend_from_slice(&[BACKGROUND.0, BACKGROUND.1, BACKGROUND.2]);
+    }
+    for (d, e) in entropies.iter().enumerate() {
+        let (x, y) = d2xy(side, d as u32);
+        let (r, g, b) = colour(*e);
+        let at = (y as usize * side as usize + x as usize) * 3;
+        pixels[at..at + 3].copy_from_slice(&[r, g, b]);
+    }
+
+    png::write_rgb(path, side, side, &pixels, force)
+}
@@ -0,0 +1,215 @@
+/*
+    The history: a summary of every file analyzed (when, its hash, size
+    and entropy), appended to a local file given with --history or in
+    FREQS_HISTORY, so earlier results can be looked up again with freqs
+    history. One run per line, tab separated, newest last: the time, hash,
+    size, entropy and path, then name=value fields with the shares of a
+    few classes of bytes and the --tag, if any.
+
+        freqs history 1
+        1791977753  2c26b46b...  5341  4.817200  /data/a.bin  nul=0.001...
+*/
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use analysis::Analysis;
+use args::{self, Options};
+use entropy;
+use log;
+use metrics;
+use report::{csv_field, json_str, Format};
+
+const HEADER: &str = "freqs history 1";
+
+pub struct Run {
+    pub when: u64,          // seconds since the epoch
+    pub sha256: String,
+    pub size: u64,
+    pub entropy: f64,
+    pub path: String,
+    pub printable: Option<f64>,     // shares of printable, nul and high bytes
+    pub nul: Option<f64>,
+    pub high: Option<f64>,
+    pub tag: Option<String>,
+}
+
+// where runs are recorded, if anywhere
+pub fn store(opts: &Options) -> Option<String> {
+    opts.history_path.clone().or_else(|| env::var("FREQS_HISTORY").ok().filter(|p| !p.is_empty()))
+}
+
+// paths are kept absolute, so runs from anywhere are found again
+pub fn canonical(path: &str) -> String {
+    fs::canonicalize(path).map_or(path.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+// results cut short don't describe the file, so they're left out. like
+// the cache, a history that can't be written to isn't fatal
+pub fn record(opts: &Options, results: &[(&str, &Analysis)]) {
+    let store = match store(opts) {
+        Some(s) => s,
+        None => return,
+    };
+    let when = now();
+    let mut text = String::new();
+    for (path, a) in results.iter().filter(|(_, a)| !a.truncated) {
+        if let Some((_, hash)) = a.hashes.iter().find(|(name, _)| *name == "sha256") {
+            let path = canonical(path).replace(['\t', '\n'], " ");
+            text.push_str(&format!("{}\t{}\t{}\t{:.6}\t{}", when, hash, a.len, entropy::shannon(&a.counts, a.len), path));
+            for (class, share) in metrics::classes(&a.counts) {
+                if class != "control" { text.push_str(&format!("\t{}={:.6}", class, share)); }
+            }
+            if let Some(ref tag) = opts.tag { text.push_str(&format!("\ttag={}", tag.replace(['\t', '\n'], " "))); }
+            text.push('\n');
+        }
+    }
+    let fresh = fs::metadata(&store).map(|m| m.len() == 0).unwrap_or(true);
+    if fresh { text.insert_str(0, &format!("{}\n", HEADER)); }
+    let written = OpenOptions::new().append(true).create(true).open(&store)
+        .and_then(|mut f| f.write_all(text.as_bytes()));
+    if let Err(e) = written { log::warn(&format!("could not record history in {}: {}", store, e)); }
+}
+
+// every run recorded, oldest first
+pub fn load(store: &str) -> Vec<Run> {
+    let text = match fs::read_to_string(store) {
+        Ok(t) => t,
+        Err(_) => return Vec::new(),
+    };
+    let mut lines = text.lines();
+    if lines.next().is_some_and(|l| l != HEADER) {
+        panic!("{} isn't a freqs history", store);
+    }
+    lines.filter_map(|line| {
+        let mut fields = line.split('\t');
+        let mut run = Run {
+            when: fields.next()?.parse().ok()?,
+            sha256: fields.next()?.to_string(),
+            size: fields.next()?.parse().ok()?,
+            entropy: fields.next()?.parse().ok()?,
+            path: fields.next()?.to_string(),
+            printable: None,
+            nul: None,
+            high: None,
+            tag: None,
+        };
+        // the rest are optional, and ones from later versions are skipped
+        for (name, value) in fields.filter_map(|f| f.split_once('=')) {
+            match name {
+                "printable" => run.printable = value.parse().ok(),
+                "nul" => run.nul = value.parse().ok(),
+                "high" => run.high = value.parse().ok(),
+                "tag" => run.tag = Some(value.to_string()),
+                _ => {},
+            }
+        }
+        Some(run)
+    }).collect()
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let store = store(opts).unwrap();
+    let file = opts.history_file.as_ref().map(|f| canonical(f));
+    let since = opts.since.as_ref().and_then(|s| parse_since(s)).unwrap_or(0);
+    let runs: Vec<Run> = load(&store).into_iter()
+        .filter(|r| file.as_ref().is_none_or(|f| &r.path == f) && r.when >= since)
+        .collect();
+    render(format, &runs)
+}
+
+fn render(format: Format, runs: &[Run]) -> Vec<String> {
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("time,path,sha256,size,entropy,tag")];
+            for r in runs {
+                lines.push(format!(
+                    "{},{},{},{},{:.6},{}",
+                    timestamp(r.when), csv_field(&r.path), r.sha256, r.size, r.entropy, csv_field(r.tag.as_deref().unwrap_or(""))
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let items: Vec<String> = runs.iter()
+                .map(|r| format!(
+                    "    {{\"time\": {}, \"path\": {}, \"sha256\": {}, \"size\": {}, \"entropy\": {:.6}, \"tag\": {}}}",
+                    json_str(&timestamp(r.when)), json_str(&r.path), json_str(&r.sha256), r.size, r.entropy,
+                    r.tag.as_deref().map_or(String::from("null"), json_str)
+                ))
+                .collect();
+            vec![String::from("{"), format!("  \"runs\": [\n{}\n  ]", items.join(",\n")), String::from("}")]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  {:<20}  {:>12}  {:>7}  {:<16}  path", "time", "size", "entropy", "sha256"),
+            ];
+            for r in runs {
+                lines.push(format!(
+                    "  {:<20}  {:>12}  {:>7.4}  {:<16}  {}",
+                    timestamp(r.when), r.size, r.entropy, r.sha256.get(..16).unwrap_or(&r.sha256), r.path
+                ));
+            }
+            if runs.is_empty() { lines.push(String::from("  no runs to list")); }
+            lines
+        },
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// a date (2026-10-14, or 2026-10-14T09:30 and with seconds, in utc) or
+// how long ago (30min, 12h, 7d), as seconds since the epoch
+pub fn parse_since(text: &str) -> Option<u64> {
+    if let Some(days) = text.strip_suffix('d').and_then(|d| d.parse::<u64>().ok()) {
+        return now().checked_sub(days.checked_mul(24 * 60 * 60)?);
+    }
+    // bare numbers are dates here (or nonsense), not seconds
+    if let Some(ago) = Some(text).filter(|t| !t.bytes().all(|b| b.is_ascii_digit())).and_then(args::parse_seconds) {
+        return now().checked_sub(ago);
+    }
+    let (date, time) = text.split_once(['T', ' ']).unwrap_or((text, "00:00"));
+    let date: Vec<u64> = date.split('-').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    let time: Vec<u64> = time.trim_end_matches('Z').split(':').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    if date.len() != 3 || !(2..=3).contains(&time.len()) { return None; }
+    let (y, m, d) = (date[0], date[1], date[2]);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || y < 1970 || time[0] > 23 || time[1] > 59 || time.get(2).is_some_and(|s| *s > 59) {
+        return None;
+    }
+    Some(days_from_civil(y, m, d) * 86400 + time[0] * 3600 + time[1] * 60 + time.get(2).unwrap_or(&0))
+}
+
+// 2026-10-14T09:30:00Z
+pub fn timestamp(secs: u64) -> String {
+    let (y, m, d) = civil_from_days(secs / 86400);
+    let rest = secs % 86400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, m, d, rest / 3600, rest % 3600 / 60, rest % 60)
+}
+
+// days between the epoch and a date, and back, in the proleptic gregorian
+// calendar. after howard hinnant's algorithms, for dates from 1970 on
+fn days_from_civil(y: u64, m: u64, d: u64) -> u64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (if m <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, m, d)
+}
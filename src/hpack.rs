@@ -0,0 +1,348 @@
+/*
+    HPACK, the header compression http/2 uses, for the grpc server. Only
+    what a server needs: decoding the headers clients send, with the
+    static and dynamic tables and huffman coded strings, and encoding its
+    own few headers plainly.
+*/
+
+use std::collections::VecDeque;
+
+// the dynamic table size http/2 starts with, and all we allow
+const TABLE_SIZE: usize = 4096;
+const TRUNCATED: &str = "header block cut short";
+const EOS: u16 = 256;
+
+const STATIC: [(&str, &str); 61] = [
+    (":authority", ""), (":method", "GET"), (":method", "POST"), (":path", "/"),
+    (":path", "/index.html"), (":scheme", "http"), (":scheme", "https"), (":status", "200"),
+    (":status", "204"), (":status", "206"), (":status", "304"), (":status", "400"),
+    (":status", "404"), (":status", "500"), ("accept-charset", ""), ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""), ("accept-ranges", ""), ("accept", ""), ("access-control-allow-origin", ""),
+    ("age", ""), ("allow", ""), ("authorization", ""), ("cache-control", ""),
+    ("content-disposition", ""), ("content-encoding", ""), ("content-language", ""), ("content-length", ""),
+    ("content-location", ""), ("content-range", ""), ("content-type", ""), ("cookie", ""),
+    ("date", ""), ("etag", ""), ("expect", ""), ("expires", ""),
+    ("from", ""), ("host", ""), ("if-match", ""), ("if-modified-since", ""),
+    ("if-none-match", ""), ("if-range", ""), ("if-unmodified-since", ""), ("last-modified", ""),
+    ("link", ""), ("location", ""), ("max-forwards", ""), ("proxy-authenticate", ""),
+    ("proxy-authorization", ""), ("range", ""), ("referer", ""), ("refresh", ""),
+    ("retry-after", ""), ("server", ""), ("set-cookie", ""), ("strict-transport-security", ""),
+    ("transfer-encoding", ""), ("user-agent", ""), ("vary", ""), ("via", ""),
+    ("www-authenticate", ""),
+];
+
+// the huffman code from rfc 7541, by code length. it's a canonical code, so
+// the lengths are all it takes: codes are handed out in order of length,
+// and within a length in order of symbol
+const HUFFMAN: [(u32, &[u16]); 21] = [
+    (5, &[48, 49, 50, 97, 99, 101, 105, 111, 115, 116]),
+    (6, &[32, 37, 45, 46, 47, 51, 52, 53, 54, 55, 56, 57, 61, 65, 95, 98, 100, 102, 103, 104, 108, 109, 110, 112, 114, 117]),
+    (7, &[58, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 89, 106, 107, 113, 118, 119, 120, 121, 122]),
+    (8, &[38, 42, 44, 59, 88, 90]),
+    (10, &[33, 34, 40, 41, 63]),
+    (11, &[39, 43, 124]),
+    (12, &[35, 62]),
+    (13, &[0, 36, 64, 91, 93, 126]),
+    (14, &[94, 125]),
+    (15, &[60, 96, 123]),
+    (19, &[92, 195, 208]),
+    (20, &[128, 130, 131, 162, 184, 194, 224, 226]),
+    (21, &[153, 161, 167, 172, 176, 177, 179, 209, 216, 217, 227, 229, 230]),
+    (22, &[129, 132, 133, 134, 136, 146, 154, 156, 160, 163, 164, 169, 170, 173, 178, 181, 185, 186, 187, 189, 190, 196, 198, 228, 232, 233]),
+    (23, &[1, 135, 137, 138, 139, 140, 141, 143, 147, 149, 150, 151, 152, 155, 157, 158, 165, 166, 168, 174, 175, 180, 182, 183, 188, 191, 197, 231, 239]),
+    (24, &[9, 142, 144, 145, 148, 159, 171, 206, 215, 225, 236, 237]),
+    (25, &[199, 207, 234, 235]),
+    (26, &[192, 193, 200, 201, 202, 205, 210, 213, 218, 219, 238, 240, 242, 243, 255]),
+    (27, &[203, 204, 211, 212, 214, 221, 222, 223, 241, 244, 245, 246, 247, 248, 250, 251, 252, 253, 254]),
+    (28, &[2, 3, 4, 5, 6, 7, 8, 11, 12, 14, 15, 16, 17, 18, 19, 20, 21, 23, 24, 25, 26, 27, 28, 29, 30, 31, 127, 220, 249]),
+    (30, &[10, 13, 22, 256]),
+];
+
+pub struct Decoder {
+    table: VecDeque<(String, String)>,  // the dynamic table, newest first
+    size: usize,
+    max: usize,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder { table: VecDeque::new(), size: 0, max: TABLE_SIZE }
+    }
+
+    // a whole header block, with any CONTINUATION frames put back on
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<(String, String)>, &'static str> {
+        let mut headers = Vec::new();
+        let mut at = 0;
+        while at < block.len() {
+            let first = block[at];
+            if first & 0x80 != 0 {
+                let index = integer(block, &mut at, 7)?;
+                headers.push(self.get(index)?);
+            } else if first & 0x40 != 0 {
+                let header = self.literal(block, &mut at, 6)?;
+                self.insert(header.clone());
+                headers.push(header);
+            } else if first & 0x20 != 0 {
+                let max = integer(block, &mut at, 5)?;
+                if max > TABLE_SIZE { return Err("header table size over the limit"); }
+                self.max = max;
+                self.evict();
+            } else {
+                // without indexing and never indexed, the same to a decoder
+                headers.push(self.literal(block, &mut at, 4)?);
+            }
+        }
+        Ok(headers)
+    }
+
+    fn get(&self, index: usize) -> Result<(String, String), &'static str> {
+        match index {
+            0 => Err("header index 0"),
+            1..=61 => Ok((String::from(STATIC[index - 1].0), String::from(STATIC[index - 1].1))),
+            _ => self.table.get(index - 62).cloned().ok_or("header index past the table"),
+        }
+    }
+
+    fn literal(&self, block: &[u8], at: &mut usize, prefix: u32) -> Result<(String, String), &'static str> {
+        let index = integer(block, at, prefix)?;
+        let name = if index == 0 { string(block, at)? } else { self.get(index)?.0 };
+        Ok((name, string(block, at)?))
+    }
+
+    fn insert(&mut self, header: (String, String)) {
+        self.size += 32 + header.0.len() + header.1.len();
+        self.table.push_front(header);
+        self.evict();
+    }
+
+    // an entry bigger than the whole table just empties it
+    fn evict(&mut self) {
+        while self.size > self.max {
+            let (name, value) = match self.table.pop_back() {
+                Some(h) => h,
+                None => { self.size = 0; break; },
+            };
+            self.size -= 32 + name.len() + value.len();
+        }
+    }
+}
+
+// integers have a prefix of some bits in the first byte, and spill over
+// into 7 more bits a byte when they don't fit
+fn integer(block: &[u8], at: &mut usize, prefix: u32) -> Result<usize, &'static str> {
+    let mask = (1usize << prefix) - 1;
+    let mut value = *block.get(*at).ok_or(TRUNCATED)? as usize & mask;
+    *at += 1;
+    if value < mask { return Ok(value); }
+    let mut shift = 0;
+    loop {
+        let b = *block.get(*at).ok_or(TRUNCATED)?;
+        *at += 1;
+        if shift > 28 { return Err("header integer too large"); }
+        value += ((b & 0x7f) as usize) << shift;
+        shift += 7;
+        if b & 0x80 == 0 { return Ok(value); }
+    }
+}
+
+fn string(block: &[u8], at: &mut usize) -> Result<String, &'static str> {
+    let huffman_coded = *block.get(*at).ok_or(TRUNCATED)? & 0x80 != 0;
+    let len = integer(block, at, 7)?;
+    let bytes = block.get(*at..at.saturating_add(len)).ok_or(TRUNCATED)?;
+    *at += len;
+    let bytes = if huffman_coded { huffman(bytes)? } else { bytes.to_vec() };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn huffman(bytes: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut out = Vec::with_capacity(bytes.len() * 8 / 5);
+    let (mut code, mut len) = (0u32, 0u32);
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            code = code << 1 | (byte >> bit & 1) as u32;
+            len += 1;
+            match symbol(code, len) {
+                Some(EOS) => return Err("end of string code in a header"),
+                Some(s) => { out.push(s as u8); code = 0; len = 0; },
+                None if len >= 30 => return Err("bad huffman code in a header"),
+                None => {},
+            }
+        }
+    }
+    // what's left over is padding, the start of the end of string code
+    if len > 7 || code != (1 << len) - 1 { return Err("bad huffman padding in a header"); }
+    Ok(out)
+}
+
+// the codes of each length carry on from the last code of the length before
+fn symbol(code: u32, len: u32) -> Option<u16> {
+    let (mut first, mut prev) = (0u32, 0);
+    for &(l, symbols) in HUFFMAN.iter() {
+        if l > len { return None; }
+        first <<= l - prev;
+        if l == len { return code.checked_sub(first).and_then(|i| symbols.get(i as usize)).cloned(); }
+        first += symbols.len() as u32;
+        prev = l;
+    }
+    None
+}
+
+// headers going out are literals that stay out of the table, so there's
+// no table to keep in step with the client's, and aren't huffman coded
+pub fn encode(headers: &[(&str, &str)]) -> Vec<u8> {
+    let mut block = Vec::new();
+    for (name, value) in headers {
+        block.push(0);
+        put_string(&mut block, name);
+        put_string(&mut block, value);
+    }
+    block
+}
+
+fn put_string(block: &mut Vec<u8>, s: &str) {
+    let mut len = s.len();
+    if len < 0x7f {
+        block.push(len as u8);
+    } else {
+        block.push(0x7f);
+        len -= 0x7f;
+        while len >= 0x80 {
+            block.push((len & 0x7f) as u8 | 0x80);
+            len >>= 7;
+        }
+        block.push(len as u8);
+    }
+    block.extend_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let digits: Vec<u8> = text.bytes().filter(|b| b.is_ascii_hexdigit()).collect();
+        digits.chunks(2).map(|d| u8::from_str_radix(std::str::from_utf8(d).unwrap(), 16).unwrap()).collect()
+    }
+
+    fn decoded(d: &mut Decoder, block: &str) -> Vec<(String, String)> {
+        d.decode(&hex(block)).unwrap()
+    }
+
+    fn headers(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+    }
+
+    // rfc 7541 c.1
+    #[test]
+    fn integers() {
+        assert_eq!(integer(&[0x0a], &mut 0, 5), Ok(10));
+        assert_eq!(integer(&[0x1f, 0x9a, 0x0a], &mut 0, 5), Ok(1337));
+        assert_eq!(integer(&[0x2a], &mut 0, 8), Ok(42));
+        assert_eq!(integer(&[0x1f, 0x9a], &mut 0, 5), Err(TRUNCATED));
+        assert_eq!(integer(&[0x1f, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01], &mut 0, 5), Err("header integer too large"));
+    }
+
+    // c.2
+    #[test]
+    fn header_fields() {
+        let mut d = Decoder::new();
+        assert_eq!(decoded(&mut d, "400a 6375 7374 6f6d 2d6b 6579 0d63 7573 746f 6d2d 6865 6164 6572"), headers(&[("custom-key", "custom-header")]));
+        assert_eq!(d.size, 55);
+        let mut d = Decoder::new();
+        assert_eq!(decoded(&mut d, "040c 2f73 616d 706c 652f 7061 7468"), headers(&[(":path", "/sample/path")]));
+        assert_eq!(decoded(&mut d, "1008 7061 7373 776f 7264 0673 6563 7265 74"), headers(&[("password", "secret")]));
+        assert_eq!(decoded(&mut d, "82"), headers(&[(":method", "GET")]));
+        assert!(d.table.is_empty());
+    }
+
+    const REQUESTS: [[(&str, &str); 5]; 3] = [
+        [(":method", "GET"), (":scheme", "http"), (":path", "/"), (":authority", "www.example.com"), ("", "")],
+        [(":method", "GET"), (":scheme", "http"), (":path", "/"), (":authority", "www.example.com"), ("cache-control", "no-cache")],
+        [(":method", "GET"), (":scheme", "https"), (":path", "/index.html"), (":authority", "www.example.com"), ("custom-key", "custom-value")],
+    ];
+
+    fn requests(blocks: [&str; 3]) {
+        let mut d = Decoder::new();
+        for (block, expected) in blocks.iter().zip(REQUESTS.iter()) {
+            let expected: Vec<_> = expected.iter().filter(|(n, _)| !n.is_empty()).cloned().collect();
+            assert_eq!(decoded(&mut d, block), headers(&expected));
+        }
+        assert_eq!(d.size, 164);
+    }
+
+    // c.3 and c.4, the same requests on one connection
+    #[test]
+    fn requests_plain() {
+        requests([
+            "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+            "8286 84be 5808 6e6f 2d63 6163 6865",
+            "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+        ]);
+    }
+
+    #[test]
+    fn requests_huffman() {
+        requests([
+            "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+            "8286 84be 5886 a8eb 1064 9cbf",
+            "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+        ]);
+    }
+
+    fn responses(blocks: [&str; 3]) {
+        // these go with a table of 256, which leaves room to see entries evicted
+        let mut d = Decoder { max: 256, ..Decoder::new() };
+        let first = [(":status", "302"), ("cache-control", "private"), ("date", "Mon, 21 Oct 2013 20:13:21 GMT"), ("location", "https://www.example.com")];
+        assert_eq!(decoded(&mut d, blocks[0]), headers(&first));
+        assert_eq!(d.size, 222);
+        let second = [(":status", "307"), ("cache-control", "private"), ("date", "Mon, 21 Oct 2013 20:13:21 GMT"), ("location", "https://www.example.com")];
+        assert_eq!(decoded(&mut d, blocks[1]), headers(&second));
+        assert_eq!(d.size, 222);
+        let third = [
+            (":status", "200"), ("cache-control", "private"), ("date", "Mon, 21 Oct 2013 20:13:22 GMT"), ("location", "https://www.example.com"),
+            ("content-encoding", "gzip"), ("set-cookie", "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1"),
+        ];
+        assert_eq!(decoded(&mut d, blocks[2]), headers(&third));
+        assert_eq!(d.size, 215);
+        assert_eq!(d.table.len(), 3);
+    }
+
+    // c.5 and c.6
+    #[test]
+    fn responses_plain() {
+        responses([
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+            "4803 3330 37c1 c0bf",
+            "88c1 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3220 474d 54c0 5a04 677a 6970 7738 666f 6f3d 4153 444a 4b48 514b 425a 584f 5157 454f 5049 5541 5851 5745 4f49 553b 206d 6178 2d61 6765 3d33 3630 303b 2076 6572 7369 6f6e 3d31",
+        ]);
+    }
+
+    #[test]
+    fn responses_huffman() {
+        responses([
+            "4882 6402 5885 aec3 771a 4b61 96d0 7abe 9410 54d4 44a8 2005 9504 0b81 66e0 82a6 2d1b ff6e 919d 29ad 1718 63c7 8f0b 97c8 e9ae 82ae 43d3",
+            "4883 640e ffc1 c0bf",
+            "88c1 6196 d07a be94 1054 d444 a820 0595 040b 8166 e084 a62d 1bff c05a 839b d9ab 77ad 94e7 821d d7f2 e6c7 b335 dfdf cd5b 3960 d5af 2708 7f36 72c1 ab27 0fb5 291f 9587 3160 65c0 03ed 4ee5 b106 3d50 07",
+        ]);
+    }
+
+    #[test]
+    fn bad_blocks() {
+        let bad = |block: &[u8]| Decoder::new().decode(block).unwrap_err();
+        assert_eq!(bad(&[0x80]), "header index 0");
+        assert_eq!(bad(&[0xbe]), "header index past the table");
+        assert_eq!(bad(&[0x3f, 0xe2, 0x1f]), "header table size over the limit");
+        assert_eq!(bad(&[0x40, 0x0a, b'c', b'u']), TRUNCATED);
+        // padding that isn't the start of the end of string code
+        assert_eq!(bad(&[0x04, 0x81, 0x00]), "bad huffman padding in a header");
+        // the end of string code itself, all 30 ones
+        assert_eq!(bad(&[0x04, 0x84, 0xff, 0xff, 0xff, 0xff]), "end of string code in a header");
+    }
+
+    #[test]
+    fn encoded_headers_decode() {
+        let long = "x".repeat(300);
+        let list = [(":status", "200"), ("content-type", "application/grpc"), ("grpc-message", long.as_str())];
+        assert_eq!(Decoder::new().decode(&encode(&list)).unwrap(), headers(&list));
+    }
+}
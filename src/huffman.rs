@@ -0,0 +1,95 @@
+/*
+    --huffman: the optimal prefix code for the file's byte counts, the one
+    a static huffman coder would build: how many bits each byte value gets,
+    how many bits per byte that comes to on average, set against the
+    entropy it can't beat, and how big the file would be coded with it.
+    --huffman-table adds the code itself, canonical as deflate assigns it,
+    so it can be rebuilt from the lengths alone.
+*/
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use entropy;
+
+pub struct Huffman {
+    pub lengths: [u32; 256],    // bits per byte value, 0 for the absent ones
+    pub bits: u64,              // the whole file, coded
+    pub bits_per_byte: f64,
+    pub entropy: f64,
+    pub table: bool,            // the codes go in the report too
+}
+
+pub fn build(counts: &[u64; 256], table: bool) -> Huffman {
+    let total: u64 = counts.iter().sum();
+    let mut lengths = [0u32; 256];
+    let present = counts.iter().filter(|c| **c != 0).count();
+    if present == 1 {
+        // a code needs a bit even with nothing to tell apart
+        for (l, c) in lengths.iter_mut().zip(counts.iter()) { if *c != 0 { *l = 1; } }
+    } else if present > 1 {
+        // leaves are 0 to 255, merged nodes come after. ties go to the
+        // lower node, so the same counts always make the same code
+        let mut parent = vec![usize::MAX; 511];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = counts.iter().enumerate()
+            .filter(|&(_, c)| *c != 0)
+            .map(|(byte, c)| Reverse((*c, byte)))
+            .collect();
+        let mut next = 256;
+        while heap.len() > 1 {
+            let Reverse((x, a)) = heap.pop().unwrap();
+            let Reverse((y, b)) = heap.pop().unwrap();
+            parent[a] = next;
+            parent[b] = next;
+            heap.push(Reverse((x + y, next)));
+            next += 1;
+        }
+        for (byte, l) in lengths.iter_mut().enumerate() {
+            if counts[byte] == 0 { continue; }
+            let mut node = byte;
+            while parent[node] != usize::MAX {
+                node = parent[node];
+                *l += 1;
+            }
+        }
+    }
+    let bits: u64 = counts.iter().zip(lengths.iter()).map(|(c, l)| c * *l as u64).sum();
+    Huffman {
+        lengths,
+        bits,
+        bits_per_byte: if total == 0 { 0.0 } else { bits as f64 / total as f64 },
+        entropy: entropy::shannon(counts, total),
+        table,
+    }
+}
+
+impl Huffman {
+    pub fn bytes(&self) -> u64 {
+        self.bits.div_ceil(8)
+    }
+
+    pub fn shortest(&self) -> u32 {
+        self.lengths.iter().filter(|l| **l != 0).min().copied().unwrap_or(0)
+    }
+
+    pub fn longest(&self) -> u32 {
+        self.lengths.iter().max().copied().unwrap_or(0)
+    }
+
+    // the byte values in code order, shortest codes first, each with its
+    // canonical code written out in bits
+    pub fn codes(&self) -> Vec<(u8, String)> {
+        let mut order: Vec<u8> = (0..=255u8).filter(|b| self.lengths[*b as usize] != 0).collect();
+        order.sort_by_key(|b| (self.lengths[*b as usize], *b));
+        let (mut code, mut len) = (0u128, 0);
+        let mut codes = Vec::new();
+        for byte in order {
+            let l = self.lengths[byte as usize];
+            code <<= l - len;
+            len = l;
+            codes.push((byte, format!("{:0w$b}", code, w = l as usize)));
+            code += 1;
+        }
+        codes
+    }
+}
@@ -0,0 +1,94 @@
+/*
+    .gitignore rules, for --respect-gitignore. The files are read as the
+    walk goes into each directory, along with the ones above the inputs
+    up to the top of the repository and .git/info/exclude, and later and
+    deeper rules win, like in git. Patterns are matched a component at a
+    time with the same wildcards as glob inputs.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob;
+
+pub struct Rule {
+    base: PathBuf,              // the directory the rule came from
+    components: Vec<String>,
+    anchored: bool,             // had a / in it, so it's from the base
+    dir_only: bool,             // had a / at the end
+    negated: bool,              // !, takes a file back in
+}
+
+// the rules in effect where a walk starts, from the directories above it
+pub fn above(dir: &Path) -> Vec<Rule> {
+    let dir = match fs::canonicalize(dir) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs = Vec::new();
+    if !dir.join(".git").exists() {
+        for d in dir.ancestors().skip(1) {
+            dirs.push(d.to_path_buf());
+            if d.join(".git").exists() { break; }
+        }
+        // not in a repository, then nothing above counts
+        if !dirs.last().is_some_and(|d| d.join(".git").exists()) { dirs.clear(); }
+    }
+    let mut rules = Vec::new();
+    for d in dirs.iter().rev() { read(d, &mut rules); }
+    rules
+}
+
+// adds the rules for a directory being walked into
+pub fn read(dir: &Path, rules: &mut Vec<Rule>) {
+    if dir.join(".git").is_dir() { parse(dir, &dir.join(".git").join("info").join("exclude"), rules); }
+    parse(dir, &dir.join(".gitignore"), rules);
+}
+
+fn parse(base: &Path, file: &Path, rules: &mut Vec<Rule>) {
+    let text = match fs::read_to_string(file) {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+    for line in text.lines() {
+        let mut line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let negated = line.starts_with('!');
+        if negated { line = &line[1..]; }
+        // \# and \! for names that start with them
+        if line.starts_with("\\#") || line.starts_with("\\!") { line = &line[1..]; }
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        let components: Vec<String> = line.split('/').filter(|c| !c.is_empty()).map(String::from).collect();
+        if components.is_empty() { continue; }
+        rules.push(Rule { base: base.to_path_buf(), components, anchored, dir_only, negated });
+    }
+}
+
+// whether the last rule about a path leaves it out
+pub fn ignored(rules: &[Rule], path: &Path, is_dir: bool) -> bool {
+    for rule in rules.iter().rev() {
+        if rule.dir_only && !is_dir { continue; }
+        let rel: Vec<String> = match path.strip_prefix(&rule.base) {
+            Ok(rel) => rel.iter().map(|c| c.to_string_lossy().into_owned()).collect(),
+            Err(_) => continue,
+        };
+        let hit = if rule.anchored {
+            matches(&rule.components, &rel)
+        } else {
+            rel.last().is_some_and(|name| glob::matches(&rule.components[0], name))
+        };
+        if hit { return !rule.negated; }
+    }
+    false
+}
+
+// ** takes in any number of directories
+pub fn matches(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        Some((first, rest)) => !path.is_empty() && glob::matches(first, &path[0]) && matches(rest, &path[1..]),
+    }
+}
@@ -0,0 +1,334 @@
+/*
+    A small deflate decoder (rfc 1951), for reading zip entries and gzip
+    streams without pulling in a dependency. It's modelled on zlib's puff:
+    simple rather than fast. Output is handed over in chunks as it's
+    produced, only the last 32K is kept around for back references.
+*/
+
+use std::io::{self, BufRead};
+
+const WINDOW: usize = 1 << 15;
+// how much output collects before being handed over
+const FLUSH_AT: usize = 1 << 17;
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+// order in which the code length code lengths are stored
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// decompresses one raw deflate stream from input, passing the output to
+// out as it goes. reads no further than the end of the stream, so whatever
+// follows it (a gzip trailer, say) can be read from input afterwards.
+pub fn inflate<R: BufRead>(input: &mut R, out: &mut dyn FnMut(&[u8])) -> io::Result<()> {
+    let mut bits = Bits { input, buf: 0, count: 0 };
+    let mut window = Output { buf: Vec::new(), emitted: 0, out };
+
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => stored(&mut bits, &mut window)?,
+            1 => {
+                let (lit, dist) = fixed();
+                codes(&mut bits, &mut window, &lit, &dist)?;
+            },
+            2 => {
+                let (lit, dist) = dynamic(&mut bits)?;
+                codes(&mut bits, &mut window, &lit, &dist)?;
+            },
+            _ => return Err(bad("bad deflate block type")),
+        }
+        if last { break; }
+    }
+    window.finish();
+    Ok(())
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+// deflate packs its bits starting from the lowest of each byte
+struct Bits<'a, R: 'a> {
+    input: &'a mut R,
+    buf: u32,
+    count: u32,
+}
+
+impl<'a, R: BufRead> Bits<'a, R> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let b = match self.input.fill_buf()?.first() {
+            Some(b) => *b,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "deflate stream ends early")),
+        };
+        self.input.consume(1);
+        Ok(b)
+    }
+
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            self.buf |= (self.byte()? as u32) << self.count;
+            self.count += 8;
+        }
+        let v = self.buf & ((1u32 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Ok(v)
+    }
+}
+
+struct Output<'a> {
+    buf: Vec<u8>,
+    emitted: usize,
+    out: &'a mut dyn FnMut(&[u8]),
+}
+
+impl<'a> Output<'a> {
+    fn push(&mut self, byte: u8) {
+        self.buf.push(byte);
+        if self.buf.len() >= FLUSH_AT + WINDOW { self.flush(); }
+    }
+
+    fn back_reference(&mut self, dist: usize, len: usize) -> io::Result<()> {
+        if dist > self.buf.len() { return Err(bad("deflate distance too far back")); }
+        for _ in 0..len {
+            let b = self.buf[self.buf.len() - dist];
+            self.push(b);
+        }
+        Ok(())
+    }
+
+    // hands over everything new, keeping the window
+    fn flush(&mut self) {
+        (self.out)(&self.buf[self.emitted..]);
+        if self.buf.len() > WINDOW {
+            let excess = self.buf.len() - WINDOW;
+            self.buf.drain(..excess);
+        }
+        self.emitted = self.buf.len();
+    }
+
+    fn finish(&mut self) {
+        if self.emitted < self.buf.len() { (self.out)(&self.buf[self.emitted..]); }
+    }
+}
+
+// canonical huffman code, as the number of codes of each length and the
+// symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for l in lengths { counts[*l as usize] += 1; }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|s| lengths[*s as usize] != 0).collect();
+        symbols.sort_by_key(|s| lengths[*s as usize]);
+        Huffman { counts, symbols }
+    }
+
+    fn decode<R: BufRead>(&self, bits: &mut Bits<R>) -> io::Result<u16> {
+        // codes are stored from their highest bit, one bit at a time
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= bits.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return self.symbols.get((index + code - first) as usize).cloned()
+                    .ok_or_else(|| bad("bad huffman code"));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(bad("bad huffman code"))
+    }
+}
+
+fn stored<R: BufRead>(bits: &mut Bits<R>, window: &mut Output) -> io::Result<()> {
+    // stored blocks start on a byte boundary
+    bits.buf = 0;
+    bits.count = 0;
+    let len = bits.byte()? as u16 | (bits.byte()? as u16) << 8;
+    let nlen = bits.byte()? as u16 | (bits.byte()? as u16) << 8;
+    if len != !nlen { return Err(bad("bad stored block length")); }
+    for _ in 0..len { window.push(bits.byte()?); }
+    Ok(())
+}
+
+fn fixed() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (s, l) in lengths.iter_mut().enumerate() {
+        *l = match s { 0..=143 => 8, 144..=255 => 9, 256..=279 => 7, _ => 8 };
+    }
+    (Huffman::new(&lengths), Huffman::new(&[5u8; 30]))
+}
+
+fn dynamic<R: BufRead>(bits: &mut Bits<R>) -> io::Result<(Huffman, Huffman)> {
+    let nlen = bits.bits(5)? as usize + 257;
+    let ndist = bits.bits(5)? as usize + 1;
+    let ncode = bits.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 { return Err(bad("bad dynamic block counts")); }
+
+    let mut clen = [0u8; 19];
+    for i in 0..ncode { clen[CLEN_ORDER[i]] = bits.bits(3)? as u8; }
+    let clen = Huffman::new(&clen);
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < nlen + ndist {
+        let (value, repeat) = match clen.decode(bits)? {
+            sym @ 0..=15 => (sym as u8, 1),
+            16 => match i {
+                0 => return Err(bad("length repeat with no previous length")),
+                _ => (lengths[i - 1], 3 + bits.bits(2)? as usize),
+            },
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if i + repeat > nlen + ndist { return Err(bad("too many code lengths")); }
+        for l in &mut lengths[i..i + repeat] { *l = value; }
+        i += repeat;
+    }
+    if lengths[256] == 0 { return Err(bad("no end of block code")); }
+    Ok((Huffman::new(&lengths[..nlen]), Huffman::new(&lengths[nlen..])))
+}
+
+fn codes<R: BufRead>(bits: &mut Bits<R>, window: &mut Output, lit: &Huffman, dist: &Huffman) -> io::Result<()> {
+    loop {
+        let sym = lit.decode(bits)? as usize;
+        if sym < 256 {
+            window.push(sym as u8);
+        } else if sym == 256 {
+            return Ok(());
+        } else {
+            let sym = sym - 257;
+            if sym >= 29 { return Err(bad("bad length code")); }
+            let len = LEN_BASE[sym] as usize + bits.bits(LEN_EXTRA[sym] as u32)? as usize;
+            let d = dist.decode(bits)? as usize;
+            if d >= 30 { return Err(bad("bad distance code")); }
+            let d = DIST_BASE[d] as usize + bits.bits(DIST_EXTRA[d] as u32)? as usize;
+            window.back_reference(d, len)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // everything inflate hands over, or what went wrong
+    fn run(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        inflate(&mut &data[..], &mut |chunk| out.extend_from_slice(chunk))?;
+        Ok(out)
+    }
+
+    // the blocks of each kind below come from zlib
+    const FIXED: [u8; 10] = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+    const DYNAMIC: [u8; 48] = [
+        0xe5, 0xcc, 0xb1, 0x11, 0x00, 0x20, 0x08, 0x04, 0xc1, 0x5a, 0x41, 0x04, 0x04, 0xec, 0x3f, 0xf5,
+        0xbf, 0x0e, 0xa3, 0x0b, 0x76, 0xe6, 0x64, 0x2a, 0x7d, 0x2f, 0x99, 0x3a, 0x81, 0xdc, 0x46, 0x4c,
+        0x91, 0x74, 0x84, 0x68, 0xc4, 0x24, 0x0e, 0x71, 0x11, 0x83, 0xd8, 0x44, 0x95, 0x7f, 0x06, 0x0f,
+    ];
+
+    fn dynamic_text() -> Vec<u8> {
+        (0..300u32).map(|i| ((i * 37 + i / 5) % 13 + 97) as u8).collect()
+    }
+
+    #[test]
+    fn stored_block() {
+        assert_eq!(run(&[0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o']).unwrap(), b"hello");
+        // an empty one, then a last one
+        assert_eq!(run(&[0x00, 0x00, 0x00, 0xff, 0xff, 0x01, 0x01, 0x00, 0xfe, 0xff, b'!']).unwrap(), b"!");
+    }
+
+    #[test]
+    fn fixed_block() {
+        assert_eq!((FIXED[0] >> 1) & 3, 1);
+        assert_eq!(run(&FIXED).unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn dynamic_block() {
+        assert_eq!((DYNAMIC[0] >> 1) & 3, 2);
+        assert_eq!(run(&DYNAMIC).unwrap(), dynamic_text());
+    }
+
+    #[test]
+    fn back_references_across_flushes() {
+        // 300000 a's, mostly copies of the byte before
+        let data = [
+            &[0xed, 0xc1, 0x01, 0x0d, 0x00, 0x00, 0x00, 0xc2, 0xa0, 0xac, 0xef, 0x5f, 0xc2, 0x1e, 0x0e, 0x28][..],
+            &[0; 290][..],
+            &[0xf8, 0x31][..],
+        ].concat();
+        let mut chunks = 0;
+        let mut out = Vec::new();
+        inflate(&mut &data[..], &mut |chunk| { chunks += 1; out.extend_from_slice(chunk); }).unwrap();
+        assert!(chunks > 1);
+        assert_eq!(out.len(), 300000);
+        assert!(out.iter().all(|b| *b == b'a'));
+    }
+
+    #[test]
+    fn reads_no_further_than_the_stream() {
+        let data = [&FIXED[..], b"trailer"].concat();
+        let mut input = &data[..];
+        inflate(&mut input, &mut |_| ()).unwrap();
+        assert_eq!(input, b"trailer");
+    }
+
+    #[test]
+    fn truncated() {
+        for stream in [&FIXED[..], &DYNAMIC[..]] {
+            for len in 0..stream.len() {
+                let e = run(&stream[..len]).unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof, "cut off at {}", len);
+            }
+        }
+        assert!(run(&[0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e']).is_err());
+    }
+
+    #[test]
+    fn corrupt() {
+        let bad = |data: &[u8], what: &str| assert_eq!(run(data).unwrap_err().to_string(), what);
+        bad(&[0x07], "bad deflate block type");
+        bad(&[0x01, 0x05, 0x00, 0x00, 0x00], "bad stored block length");
+        // a length of 3 and a distance of 1, with nothing before it
+        bad(&[0x03, 0x02, 0x00], "deflate distance too far back");
+        // fixed codes 286 and 287 are never used
+        bad(&[0x1b, 0x03], "bad length code");
+        // dynamic with 257 + 31 lengths, more than there can be
+        bad(&[0xfd, 0x1f, 0x00], "bad dynamic block counts");
+    }
+
+    #[test]
+    fn damage_is_an_error_not_a_panic() {
+        for stream in [&FIXED[..], &DYNAMIC[..]] {
+            for i in 0..stream.len() {
+                for bit in 0..8 {
+                    let mut damaged = stream.to_vec();
+                    damaged[i] ^= 1 << bit;
+                    let _ = run(&damaged);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+/*
+    freqs keycheck: a look at a short input, a key, seed or token, for the
+    ways key material goes wrong. The statistical tests need far more data
+    than a key has, so this checks for the usual mistakes instead: too few
+    bits, one byte over and over, text where bytes were expected, counting,
+    a pattern repeating, or bytes turning up more often than chance gives.
+*/
+
+use std::fs;
+
+use args::Options;
+use report::{csv_field, json_str, Format};
+use special;
+
+// shorter than 128 bits is short for a key
+const MIN_BYTES: usize = 16;
+// beyond this it's not a key, and random has the tests for it
+const MAX_BYTES: u64 = 1 << 20;
+// how unlikely repeats have to be for random bytes to count against a key
+const UNLIKELY: f64 = 0.001;
+
+struct Check {
+    name: &'static str,
+    passed: bool,
+    detail: String,
+}
+
+// the lines to print, and whether the key passed every check
+pub fn run(opts: &Options, format: Format) -> (Vec<String>, bool) {
+    let path = &opts.inputs[0];
+    let size = match fs::metadata(path) {
+        Ok(m) => m.len(),
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    if size > MAX_BYTES { panic!("keycheck is for keys and tokens, {} is over 1M. try freqs random", path); }
+    let data = match fs::read(path) {
+        Ok(d) => d,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    if data.is_empty() { panic!("Nothing to check, {} is empty", path); }
+
+    let n = data.len();
+    let mut counts = [0u64; 256];
+    for b in &data { counts[*b as usize] += 1; }
+    let (most, most_count) = counts.iter().enumerate().max_by_key(|(_, c)| **c).map(|(b, c)| (b, *c)).unwrap_or((0, 0));
+    let distinct = counts.iter().filter(|c| **c > 0).count();
+    // the most common value estimate, which n bytes can't put above log2(n) a byte
+    let min_entropy = (n as f64 / most_count as f64).log2();
+    let checks = checks(&data, most, most_count);
+    let failed: Vec<&str> = checks.iter().filter(|c| !c.passed).map(|c| c.name).collect();
+    let ok = failed.is_empty();
+    let verdict = if ok {
+        format!("no weaknesses found, about {:.0} bits of min-entropy", min_entropy * n as f64)
+    } else {
+        format!("weak key material: {}", failed.join(", "))
+    };
+
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("check,passed,detail")];
+            for c in &checks { lines.push(format!("{},{},{}", csv_field(c.name), c.passed, csv_field(&c.detail))); }
+            lines.push(String::from(""));
+            lines.push(String::from("bytes,distinct,min_entropy,min_entropy_bits,passed,verdict"));
+            lines.push(format!("{},{},{:.6},{:.2},{},{}", n, distinct, min_entropy, min_entropy * n as f64, ok, csv_field(&verdict)));
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = checks.iter()
+                .map(|c| format!(
+                    "    {{\"check\": {}, \"passed\": {}, \"detail\": {}}}",
+                    json_str(c.name), c.passed, json_str(&c.detail)
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"path\": {},", json_str(path)),
+                format!("  \"bytes\": {},", n),
+                format!("  \"distinct\": {},", distinct),
+                format!("  \"min_entropy\": {:.6},", min_entropy),
+                format!("  \"min_entropy_bits\": {:.2},", min_entropy * n as f64),
+                format!("  \"checks\": [\n{}\n  ],", entries.join(",\n")),
+                format!("  \"passed\": {},", ok),
+                format!("  \"verdict\": {}", json_str(&verdict)),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  key material check of {}", path),
+                String::from(""),
+                format!("  {} bytes, {} distinct, min-entropy {:.3} bits a byte ({:.0} bits)", n, distinct, min_entropy, min_entropy * n as f64),
+                String::from(""),
+            ];
+            for c in &checks {
+                lines.push(format!("  {:<18}  {}  {}", c.name, if c.passed { "pass" } else { "FAIL" }, c.detail));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}", verdict));
+            lines
+        },
+    };
+    (lines, ok)
+}
+
+fn checks(data: &[u8], most: usize, most_count: u64) -> Vec<Check> {
+    let n = data.len();
+    let mut checks = Vec::new();
+
+    checks.push(Check {
+        name: "length",
+        passed: n >= MIN_BYTES,
+        detail: format!("{} bits{}", n * 8, if n >= MIN_BYTES { "" } else { ", under 128" }),
+    });
+
+    let one_value = data.iter().all(|b| *b == data[0]);
+    checks.push(Check {
+        name: "one value",
+        passed: !one_value,
+        detail: if one_value { format!("every byte is {:#04x}", data[0]) } else { String::from("") },
+    });
+
+    // text is usually an encoded key, which should be decoded first
+    let ascii = data.iter().all(|b| (0x20..0x7f).contains(b) || *b == b'\n' || *b == b'\r' || *b == b'\t');
+    let alphabet = if !ascii {
+        String::from("")
+    } else if data.iter().all(|b| b.is_ascii_hexdigit() || b.is_ascii_whitespace()) {
+        String::from("all hex digits, check the decoded bytes instead")
+    } else if data.iter().all(|b| b.is_ascii_alphanumeric() || b"+/=-_\r\n".contains(b)) {
+        String::from("all base64 characters, check the decoded bytes instead")
+    } else {
+        String::from("all printable ascii, a password rather than a key")
+    };
+    checks.push(Check { name: "ascii only", passed: !ascii, detail: alphabet });
+
+    // the same step between every byte, counting up or down
+    let step = if n > 2 { Some(data[1].wrapping_sub(data[0])) } else { None };
+    let counting = step.is_some_and(|s| s != 0 && data.windows(2).all(|w| w[1].wrapping_sub(w[0]) == s));
+    checks.push(Check {
+        name: "counting",
+        passed: !counting,
+        detail: match step {
+            Some(s) if counting => format!("each byte is the last {} {}", if s < 128 { "plus" } else { "minus" }, if s < 128 { s } else { s.wrapping_neg() }),
+            _ => String::from(""),
+        },
+    });
+
+    // the shortest period past a single byte, if the data has one
+    let period = (2..=n / 2).find(|p| (*p..n).all(|i| data[i] == data[i - p])).filter(|_| !one_value);
+    checks.push(Check {
+        name: "repeating pattern",
+        passed: period.is_none(),
+        detail: period.map_or(String::from(""), |p| format!("the first {} bytes repeat", p)),
+    });
+
+    // how likely random bytes are to have a value as common, by a union
+    // bound over the 256 values
+    let p = (256.0 * binomial_tail(n as u64, most_count, 1.0 / 256.0)).min(1.0);
+    checks.push(Check {
+        name: "repeated bytes",
+        passed: p >= UNLIKELY,
+        detail: format!("{:#04x} turns up {} times, random bytes would that often with p {:.6}", most, most_count, p),
+    });
+
+    let mut longest = (1, 0);
+    let mut run = 1;
+    for i in 1..n {
+        run = if data[i] == data[i - 1] { run + 1 } else { 1 };
+        if run > longest.0 { longest = (run, i + 1 - run); }
+    }
+    // a run of r starts at any of n places with chance 256^-(r - 1)
+    let p = (n as f64 * 256f64.powi(1 - longest.0 as i32)).min(1.0);
+    checks.push(Check {
+        name: "longest run",
+        passed: longest.0 < 2 || p >= UNLIKELY,
+        detail: format!("{} of {:#04x} at offset {}", longest.0, data[longest.1], longest.1),
+    });
+    checks
+}
+
+// the chance of at least k successes in n tries of chance p
+fn binomial_tail(n: u64, k: u64, p: f64) -> f64 {
+    let ln_choose = |j: u64| special::ln_gamma(n as f64 + 1.0) - special::ln_gamma(j as f64 + 1.0) - special::ln_gamma((n - j) as f64 + 1.0);
+    let mut sum = 0.0;
+    for j in k..=n {
+        let term = (ln_choose(j) + j as f64 * p.ln() + (n - j) as f64 * (1.0 - p).ln()).exp();
+        sum += term;
+        if term < sum * 1e-17 { break; }
+    }
+    sum
+}
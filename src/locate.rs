@@ -0,0 +1,52 @@
+/*
+    freqs locate: where in the file a byte value turns up, for the count
+    that looks wrong. The offsets come out of the same pass that counts,
+    so --offset, --range, --decompress and the rest apply as they do to
+    the report, and the first --limit of them are listed.
+*/
+
+use analysis;
+use args::Options;
+use report::{json_str, Format};
+
+pub const DEFAULT_LIMIT: usize = 100;
+
+// 0x7f or 127
+pub fn parse_byte(text: &str) -> Option<u8> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let byte = parse_byte(&opts.inputs[0]).unwrap();
+    let path = &opts.inputs[1];
+    let limit = opts.limit.unwrap_or(DEFAULT_LIMIT);
+    let opts = Options { locate: Some((byte, limit)), ..opts.clone() };
+    let a = match analysis::analyze(path, &opts, !opts.tee) {
+        Ok(a) => a,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    let offsets = a.located.unwrap_or_default();
+    let count = a.counts[byte as usize];
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("offset")];
+            for o in &offsets { lines.push(o.to_string()); }
+            lines
+        },
+        Format::Json => {
+            let list: Vec<String> = offsets.iter().map(|o| o.to_string()).collect();
+            vec![format!(
+                "{{\n  \"path\": {},\n  \"byte\": {},\n  \"count\": {},\n  \"offsets\": [{}]\n}}",
+                json_str(path), byte, count, list.join(", ")
+            )]
+        },
+        _ => {
+            let mut lines: Vec<String> = offsets.iter().map(|o| format!("  {:#010x}", o)).collect();
+            lines.push(format!("  {:#04x} turns up {} times in {}, {} shown", byte, count, path, offsets.len()));
+            lines
+        },
+    }
+}
@@ -0,0 +1,91 @@
+/*
+    What happened along the way, on stderr, for working out after the fact
+    why a big batch went the way it did. Warnings (inputs skipped, a cache
+    or history that couldn't be written) always show; -v adds what each
+    input was opened as and how it's read, the files written on the side
+    and what serve answers, -vv the stretches skipped
+    over and where counting stopped early. -q leaves out the warnings and
+    the progress both. With --log-json every message is a line of json
+    of its own:
+
+        {"time": "2026-10-14T09:30:00Z", "level": "info", "message": "..."}
+*/
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use history;
+use report::json_str;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Quiet,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    // from the -v and -q flags
+    pub fn from(verbose: u8, quiet: bool) -> Level {
+        match (quiet, verbose) {
+            (true, _) => Level::Quiet,
+            (_, 0) => Level::Warn,
+            (_, 1) => Level::Info,
+            _ => Level::Debug,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Quiet => "quiet",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+static JSON: AtomicBool = AtomicBool::new(false);
+// a progress counter is on the last line, with no newline after it yet
+static DRAWN: AtomicBool = AtomicBool::new(false);
+
+pub fn init(level: Level, json: bool) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+// progress counters are left out with -q, and with json logs, which they'd
+// only get in the way of
+pub fn progress() -> bool {
+    enabled(Level::Warn) && !JSON.load(Ordering::Relaxed)
+}
+
+// for the counters to say when they've left a line unfinished, so
+// messages in between start on a line of their own
+pub fn drawn(pending: bool) {
+    DRAWN.store(pending, Ordering::Relaxed);
+}
+
+fn write(level: Level, message: &str) {
+    if !enabled(level) { return; }
+    if DRAWN.swap(false, Ordering::Relaxed) { eprintln!(); }
+    if JSON.load(Ordering::Relaxed) {
+        eprintln!(
+            "{{\"time\": {}, \"level\": {}, \"message\": {}}}",
+            json_str(&history::timestamp(history::now())), json_str(level.name()), json_str(message)
+        );
+    } else if level == Level::Warn {
+        eprintln!("{}", message);
+    } else {
+        eprintln!("{}: {}", level.name(), message);
+    }
+}
+
+pub fn warn(message: &str) { write(Level::Warn, message); }
+pub fn info(message: &str) { write(Level::Info, message); }
+pub fn debug(message: &str) { write(Level::Debug, message); }
@@ -0,0 +1,47 @@
+/*
+    File format signatures ("magic numbers") found at fixed offsets near the
+    start of a file. Where a format has an obvious counterpart among the
+    bundled content profiles, it's noted so classify can tell whether the
+    signature and the byte distribution agree.
+*/
+
+pub struct Signature {
+    pub name: &'static str,
+    offset: usize,
+    magic: &'static [u8],
+    pub profile: Option<&'static str>,  // matching content profile, if any
+}
+
+// checked in order, so more specific signatures come before the short,
+// easily matched ones at the end
+pub const SIGNATURES: [Signature; 25] = [
+    Signature { name: "sqlite database", offset: 0, magic: b"SQLite format 3\0", profile: None },
+    Signature { name: "png image", offset: 0, magic: b"\x89PNG\r\n\x1a\n", profile: None },
+    Signature { name: "7z archive", offset: 0, magic: b"7z\xbc\xaf\x27\x1c", profile: Some("random / encrypted") },
+    Signature { name: "xz compressed", offset: 0, magic: b"\xfd7zXZ\0", profile: Some("random / encrypted") },
+    Signature { name: "rar archive", offset: 0, magic: b"Rar!\x1a\x07", profile: Some("random / encrypted") },
+    Signature { name: "gif image", offset: 0, magic: b"GIF87a", profile: None },
+    Signature { name: "gif image", offset: 0, magic: b"GIF89a", profile: None },
+    Signature { name: "pdf document", offset: 0, magic: b"%PDF-", profile: Some("pdf document") },
+    Signature { name: "tar archive", offset: 257, magic: b"ustar", profile: None },
+    Signature { name: "wav audio", offset: 8, magic: b"WAVE", profile: None },
+    Signature { name: "elf executable", offset: 0, magic: b"\x7fELF", profile: Some("x86-64 code") },
+    Signature { name: "mach-o executable", offset: 0, magic: b"\xcf\xfa\xed\xfe", profile: Some("x86-64 code") },
+    Signature { name: "mach-o executable", offset: 0, magic: b"\xce\xfa\xed\xfe", profile: Some("x86-64 code") },
+    Signature { name: "zstd compressed", offset: 0, magic: b"\x28\xb5\x2f\xfd", profile: Some("random / encrypted") },
+    Signature { name: "zip archive", offset: 0, magic: b"PK\x03\x04", profile: Some("zip archive") },
+    Signature { name: "zip archive", offset: 0, magic: b"PK\x05\x06", profile: Some("zip archive") },
+    Signature { name: "pcap capture", offset: 0, magic: b"\xd4\xc3\xb2\xa1", profile: None },
+    Signature { name: "pcap capture", offset: 0, magic: b"\xa1\xb2\xc3\xd4", profile: None },
+    Signature { name: "pcapng capture", offset: 0, magic: b"\x0a\x0d\x0d\x0a", profile: None },
+    Signature { name: "bzip2 compressed", offset: 0, magic: b"BZh", profile: Some("random / encrypted") },
+    Signature { name: "jpeg image", offset: 0, magic: b"\xff\xd8\xff", profile: Some("jpeg image") },
+    Signature { name: "utf-8 text (with bom)", offset: 0, magic: b"\xef\xbb\xbf", profile: Some("english text") },
+    Signature { name: "gzip compressed", offset: 0, magic: b"\x1f\x8b", profile: Some("random / encrypted") },
+    Signature { name: "script", offset: 0, magic: b"#!", profile: Some("source code") },
+    Signature { name: "pe/dos executable", offset: 0, magic: b"MZ", profile: Some("x86-64 code") },
+];
+
+pub fn detect(head: &[u8]) -> Option<&'static Signature> {
+    SIGNATURES.iter().find(|s| head.get(s.offset..s.offset + s.magic.len()) == Some(s.magic))
+}
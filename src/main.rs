@@ -5,163 +5,817 @@
     size.
 */
 
+mod analysis;
+mod appends;
+mod archive;
+mod ads;
+mod args;
+mod cache;
+mod calc;
+mod carve;
+mod check;
+mod checkpoint;
+mod classify;
+mod cluster;
+mod control;
+mod decode;
+mod decompress;
+mod diff;
+mod digest;
+mod disk;
+mod dispersion;
+mod dump;
+mod dupes;
+mod entropy;
+mod fields;
+mod find;
+mod fingerprint;
+mod fips;
+mod follow;
+mod freqfile;
+mod fuzzy;
+mod generate;
+mod git;
+mod glob;
+mod guided;
+mod grpc;
+mod hpack;
+mod heatmap;
+mod hilbert;
+mod history;
+mod huffman;
+mod ignore;
+mod inflate;
+mod keycheck;
+mod locate;
+mod log;
+mod magic;
+mod memory;
+mod merge;
+mod metrics;
+mod model;
+mod nearest;
+mod nist;
+mod outfile;
+mod pcap;
+mod pixels;
+mod png;
+mod profiles;
+mod random;
+mod rare;
+mod rate;
+mod regions;
+mod remote;
+mod repl;
+mod report;
+mod resources;
+mod rng;
+mod runs;
+mod samples;
+#[cfg(feature = "s3")]
+mod s3;
+mod sections;
+mod segments;
+mod serial;
+mod serve;
+mod similarity;
+mod snapshot;
+mod special;
+mod stats;
+mod stride;
+mod test;
+mod trend;
+mod triage;
+mod walk;
+mod wav;
+mod watch;
+mod windows;
+
 use std::{
     env,
     fs,
     panic,
+    process,
     str
 };
-use std::io::{BufReader, stdout, Write};
-use std::io::prelude::*;
+use std::time::{Instant, UNIX_EPOCH};
+
+use analysis::Analysis;
+use args::{Every, Options};
+use report::{Format, Meta, Profile, Regions, Report};
 
 fn main() {
+    let started = Instant::now();
+    windows::init();
+    // any run can be asked for its counts so far, not only one showing progress
+    dump::install();
+
     // collect and parse args
-    let mut h_flag = false;           // usage
-    let mut in_path = None::<String>;  // infile
-    let mut out_path = None::<String>; // outfile
-    let args: Vec<String> = env::args().collect();
-    for (i, argv) in args.iter().enumerate() {
-        if i != 0 {
-            match argv.as_ref() {
-                "-h" => h_flag = true,
-                "-o" => out_path = args.get(i + 1).cloned(),
-                arg => if let Some(prev) = args.get(i - 1) {
-                    if prev != "-o" { in_path = Some(String::from(arg)); }
-                },
-            };
+    let mut args: Vec<String> = env::args().collect();
+    // run bare in a terminal, it asks what to do
+    if args.len() == 1 && guided::available() {
+        match guided::ask(&args[0]) {
+            Some(answers) => args = answers,
+            None => return,
         }
     }
+    let mut opts = args::parse(&args);
+    // no shell expanded the wildcards there. watch matches them itself, and
+    // calc's * is a multiplication
+    if !matches!(opts.command.as_deref(), Some("watch") | Some("calc")) { opts.inputs = windows::expand(&opts.inputs); }
+    let format = Format::parse(&opts.format_name);
+    log::init(log::Level::from(opts.verbose, opts.quiet), opts.log_json);
 
     // exit early if usage option is specified
-    if h_flag { // display usage
-        println!("
-Usage:
-    freqs <path to file>
-        performs analysis on target file,
-        then prints results as stdout.
-
-    freqs <path to target file> -o <outfile>
-        performs analysis on target file,
-        then prints results to outfile. if
-        o flag is specified with no outfile,
-        prints to stdout instead.
-");
-    } else if in_path == None {
-        println!("Not enough arguments. try passing -h");
-    } else { // main execution
+    if opts.help { // display usage
+        println!("{}", args::USAGE);
+    } else if let Some(problem) = usage_problem(&opts, format) {
+        // on stderr and with a status of its own, so a script or a ci check
+        // never takes a mistyped option for a pass
+        eprintln!("{}. try passing -h", problem);
+        process::exit(2);
+    } else if let Some(format) = format { // main execution
+        // on stderr, where it can't get in the way of the results, or fail
+        // itself along with a stdout that's gone
         panic::set_hook(Box::new(|panic_info| {
-            println!("Error: {}\nAborting", panic_info.payload().downcast_ref::<&str>().unwrap());
+            let payload = panic_info.payload();
+            let msg = match payload.downcast_ref::<&str>() {
+                Some(s) => s.to_string(),
+                None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+            eprintln!("Error: {}\nAborting", msg);
         }));
 
-        // set up in and out files
-        let target = match fs::File::open(in_path.clone().unwrap()) {
-                Ok(f) => f,
-                Err(_) => panic!("Could not open file. Bad file or path?")
-        };
+        // what git has as changed takes the place of the inputs
+        if opts.git_modified || opts.git_staged { opts.inputs = git::changed(&opts); }
+        // and so does a serial port
+        if let Some(ref device) = opts.serial { opts.inputs = vec![device.clone()]; }
 
-        let out_file = match out_path {
-            None => None,
-            Some(path) => Some(fs::OpenOptions::new()
-                .write(true)
-                .append(true)
-                .create(true)
-                .open(path)
-                .unwrap()),
+        let mut status = 0;
+        let lines = match opts.command.as_deref() {
+            Some("diff") => diff::run(&opts, format),
+            Some("check") => {
+                let (lines, ok) = check::run(&opts, format);
+                if !ok { status = 1; }
+                lines
+            },
+            Some("fips") => {
+                let (lines, ok) = fips::run(&opts, format);
+                if !ok { status = 1; }
+                lines
+            },
+            Some("nist") => {
+                let (lines, ok) = nist::run(&opts, format);
+                if !ok { status = 1; }
+                lines
+            },
+            Some("random") => {
+                let (lines, ok) = random::run(&opts, format);
+                if !ok { status = 1; }
+                lines
+            },
+            Some("keycheck") => {
+                let (lines, ok) = keycheck::run(&opts, format);
+                if !ok { status = 1; }
+                lines
+            },
+            Some("test") => {
+                let (lines, ok) = test::run(&opts, format);
+                if !ok { status = 1; }
+                lines
+            },
+            Some("samples") => {
+                samples::run(&opts);
+                if opts.stats { resources::print(started); }
+                return;
+            },
+            Some("generate") => {
+                generate::run(&opts);
+                if opts.stats { resources::print(started); }
+                return;
+            },
+            Some("repl") => {
+                repl::run(&opts, format);
+                if opts.stats { resources::print(started); }
+                return;
+            },
+            Some("find") => find::run(&opts, format),
+            Some("fingerprint") => fingerprint::compare(&opts, format),
+            Some("fuzzy") => fuzzy::compare(&opts, format),
+            Some("merge") => merge::run(&opts),
+            Some("calc") => calc::run(&opts, format),
+            Some("locate") => locate::run(&opts, format),
+            Some("model") => model::run(&opts),
+            Some("similarity") => similarity::run(&opts, format),
+            Some("cluster") => cluster::run(&opts, format),
+            Some("nearest") => nearest::run(&opts, format),
+            Some("classify") => classify::run(&opts, format),
+            Some("carve") => carve::run(&opts, format),
+            Some("watch") => watch::run(&opts, format, analyze_inputs),
+            Some("serve") if opts.grpc => grpc::run(&opts),
+            Some("serve") => serve::run(&opts),
+            Some("history") => history::run(&opts, format),
+            Some("trend") => trend::run(&opts, format),
+            Some("report") => appends::split(&opts),
+            Some("pid") => memory::run(&opts, format),
+            None if opts.follow => follow::run(&opts, format),
+            None if opts.snapshot_every.is_some() => snapshot::run(&opts, format),
+            _ => analyze_inputs(&opts, format),
         };
 
-        // set up bufreader, chunks, and byte occurence counts
-        const CHUNKSIZE: usize = 1024 * 128;
-        let mut reader = BufReader::with_capacity(CHUNKSIZE, target);
-        let chunks_total = (fs::metadata(in_path.unwrap()).unwrap().len() / CHUNKSIZE as u64) as u32;
-        let mut chunks_done: u32 = 0;
-        let mut byte_occurences = [0u32; 256];
-
-        // break file into chunks
-        loop {
-            // process next chunk if any
-            let length_of_chunk = {
-                if let Ok(chunk) = reader.fill_buf() {
-                    // count occurences of each byte in chunk
-                    for byte in chunk.iter() { byte_occurences[*byte as usize] += 1; }
-
-                    // return length of chunk done
-                    chunk.len()
-                } else { 0 }
-            };
+        // set up out file. it's replaced, unless this run should be added
+        // to it with --append. every appended run is marked, the first
+        // one too, for report split
 
-            if length_of_chunk == 0 { break; } else {
-                // update and display progress
-                chunks_done += 1;
-                print!("\rprocessed chunk {} / {}", chunks_done, chunks_total);
-                stdout().flush();
-
-                // we're done with this chunk
-                reader.consume(length_of_chunk);
-            }
+        // either save table in file, or else print as stdout
+        match opts.out_path {
+            Some(ref path) => {
+                let mut text = String::new();
+                if opts.append { text.push_str(&format!("{}\n", appends::marker(&args))); }
+                for line in lines { text.push_str(&format!("{}\n", line)); }
+                let written = if opts.append {
+                    outfile::append(path, text.as_bytes())
+                } else {
+                    outfile::write(path, text.as_bytes(), opts.force)
+                };
+                if let Err(e) = written { panic!("Could not write {}: {}", path, e); }
+            },
+            None if opts.tee => for line in lines { eprintln!("{}", line); },
+            None => for line in lines { println!("{}", line); },
         }
 
-        println!("\ndone!");
+        if opts.stats { resources::print(started); }
+        if status != 0 { process::exit(status); }
+    }
+}
 
-        // turn results into table
-        let mut lines = vec![String::from("")];
-        for (byte, byte_count) in byte_occurences.iter().enumerate() {
-            if *byte_count != 0 {
-                lines.push(format!(
-                    "  {0: <3}: {1}: {2}",
-                    format!("{:x}", byte as u8),
-                    byte_count,
-                    match byte as u8 {
-                        // gross-ass to_string()s on all of these because I got
-                        // tired of fucking around trying to get a str from
-                        // format!() to live long enough.
-                        0x00 => "<NULL>".to_string(),
-                        0x01 => "<SOH>".to_string(),
-                        0x02 => "<STX>".to_string(),
-                        0x03 => "<ETX>".to_string(),
-                        0x04 => "<EOT>".to_string(),
-                        0x05 => "<ENQ>".to_string(),
-                        0x06 => "<ACK>".to_string(),
-                        0x07 => "<BEL".to_string(),
-                        0x08 => "<BS>".to_string(),
-                        0x09 => "<TAB>".to_string(),
-                        0x0a => "\\n".to_string(),
-                        0x0b => "<VT>".to_string(),
-                        0x0c => "<FF>".to_string(),
-                        0x0d => "\\r".to_string(),
-                        0x0e => "<SO>".to_string(),
-                        0x0f => "<SI>".to_string(),
-                        0x10 => "<DLE>".to_string(),
-                        0x11 => "<DC1>".to_string(),
-                        0x12 => "<DC2>".to_string(),
-                        0x13 => "<DC3>".to_string(),
-                        0x14 => "<DC4>".to_string(),
-                        0x15 => "<NAK>".to_string(),
-                        0x16 => "<SYN>".to_string(),
-                        0x17 => "<ETB>".to_string(),
-                        0x18 => "<EM>".to_string(),
-                        0x19 => "<SUB>".to_string(),
-                        0x1a => "<SUB>".to_string(),
-                        0x1b => "<ESC>".to_string(),
-                        0x1c => "<FS>".to_string(),
-                        0x1d => "<GS>".to_string(),
-                        0x1e => "<RS>".to_string(),
-                        0x1f => "<US>".to_string(),
-                        0x20 => "<space>".to_string(),
-                        0x7f => "<DEL>".to_string(),
-                        0xa0 => "<non break space>".to_string(),
-                        0xad => "<soft hyphen>".to_string(),
-
-                        b => format!("{}", b as char)
-                    }
-                ));
+// the history has a sha256 computed that only goes in the report if asked for
+fn asked_hashes(opts: &Options, analysis: &mut Analysis) {
+    analysis.hashes.retain(|(name, _)| opts.hashes.iter().any(|h| h == name));
+}
+
+// for --metadata, taken after counting so it's the time of the analysis
+// a url has no metadata here, its size is what came down
+fn metadata(path: &str, fetched: u64) -> Meta {
+    let meta = fs::metadata(path).ok();
+    Meta {
+        path: path.to_string(),
+        size: meta.as_ref().map_or(fetched, |m| m.len()),
+        modified: meta.and_then(|m| m.modified().ok()).and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()),
+        analyzed: history::now(),
+    }
+}
+
+// whether more than one file will be analyzed by the default command. git
+// can come up with any number of them
+fn is_multi(opts: &Options) -> bool {
+    opts.git_modified || opts.git_staged || opts.inputs.len() > 1 || opts.inputs.iter().any(|i| walk::is_dir(i))
+}
+
+// anything wrong with the combination of options given, before doing any work
+fn usage_problem(opts: &Options, format: Option<Format>) -> Option<String> {
+    let chart = format == Some(Format::Gnuplot) || format == Some(Format::Vega);
+    let extras = opts.hilbert_path.is_some() || opts.heatmap_path.is_some() || opts.window.is_some() || opts.save_path.is_some()
+        || opts.block_size.is_some() || opts.segments || opts.runs || opts.first_last || opts.dispersion || opts.context.is_some() || opts.rate.is_some() || opts.metadata;
+    let columns = opts.stride.is_some() || opts.record_size.is_some() || !opts.fields.is_empty() || opts.payloads || opts.audio || opts.pixels;
+    // random takes a block size for its per-block map
+    let random_blocks = opts.command.as_deref() == Some("random") && opts.block_size.is_some()
+        && !(opts.hilbert_path.is_some() || opts.heatmap_path.is_some() || opts.window.is_some() || opts.save_path.is_some() || opts.segments || opts.runs || opts.first_last || opts.dispersion || opts.context.is_some() || opts.rate.is_some() || opts.metadata);
+
+    if format.is_none() {
+        return Some(format!("Unknown format {}", opts.format_name));
+    }
+    // files that would be replaced, caught before doing any work
+    let merging = opts.command.as_deref() == Some("merge");
+    if opts.append && (merging || opts.out_path.is_none()) {
+        return Some(String::from("--append needs an -o outfile, and merged counts can't be added to one"));
+    }
+    if let Some(path) = opts.out_path.as_ref().filter(|p| !opts.force && !opts.append && fs::metadata(p).is_ok()) {
+        return Some(format!("{} is already there, pass --force to replace it or --append to add this run to it", path));
+    }
+    let replaced = [opts.save_path.as_ref(), opts.hilbert_path.as_ref(), opts.heatmap_path.as_ref()];
+    if let Some(path) = replaced.iter().flatten().find(|p| !opts.force && fs::metadata(p).is_ok()) {
+        return Some(format!("{} is already there, pass --force to replace it", path));
+    }
+    if let Some(name) = opts.hashes.iter().find(|h| !digest::NAMES.contains(&h.as_str())) {
+        return Some(format!("Unknown hash {}, try {}", name, digest::NAMES.join(", ")));
+    }
+    if opts.tee && (opts.command.is_some() || opts.inputs != ["-"] || opts.follow || opts.snapshot_every.is_some()
+        || opts.checkpoint_path.is_some() || opts.triage || opts.sections || opts.partitions || opts.archive_entries || opts.list_ads || opts.per_range) {
+        return Some(String::from("--tee only reads stdin, for a plain report"));
+    }
+    if opts.hidden && !opts.respect_gitignore {
+        return Some(String::from("--hidden goes with --respect-gitignore, hidden files are already analyzed without it"));
+    }
+    if opts.window == Some(0) || opts.step == Some(0) {
+        return Some(String::from("Entropy window and step take a number of bytes, at least 1"));
+    }
+    if let Some(ref name) = opts.decompress {
+        if !decompress::METHODS.contains(&name.as_str()) {
+            return Some(format!("Unknown compression {}", name));
+        }
+    }
+    if let Some(ref name) = opts.decode {
+        if !decode::KINDS.contains(&name.as_str()) {
+            return Some(format!("Unknown encoding {}", name));
+        }
+    }
+    if opts.ranges.iter().any(|&(start, end)| end <= start) {
+        return Some(String::from("Ranges look like 0x1000-0x2000, with the end past the start"));
+    }
+    if opts.offset == Some(u64::MAX) || opts.length == Some(u64::MAX) {
+        return Some(String::from("--offset and --length take a number of bytes, like 4096, 0x1000 or 4k"));
+    }
+    if opts.heatmap_block == Some(0) {
+        return Some(String::from("--heatmap-block takes a number of bytes, 1 or more"));
+    }
+    if opts.hilbert_block == Some(0) {
+        return Some(String::from("--hilbert-block takes a number of bytes, 1 or more"));
+    }
+    if let (Some(block), Some(path)) = (opts.hilbert_block, opts.inputs.first().filter(|_| opts.hilbert_path.is_some())) {
+        // as much as is counted of a file, its length known up front
+        let counted = fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| {
+            let after = m.len().saturating_sub(opts.offset.unwrap_or(0));
+            opts.length.map_or(after, |l| l.min(after))
+        });
+        if let Some(len) = counted.filter(|len| hilbert::side_for(len.div_ceil(block)) > hilbert::MAX_IMAGE_SIDE) {
+            let least = len.div_ceil(hilbert::MAX_IMAGE_SIDE * hilbert::MAX_IMAGE_SIDE);
+            return Some(format!(
+                "--hilbert-block {} would make a map of {} bigger than {2}x{2} pixels, try {3} or more",
+                block, path, hilbert::MAX_IMAGE_SIDE, least
+            ));
+        }
+    }
+    if opts.min_size == Some(u64::MAX) || opts.max_size == Some(u64::MAX) {
+        return Some(String::from("--min-size and --max-size take a size, like 4k or 10M"));
+    }
+    if !opts.ranges.is_empty() && (opts.offset.is_some() || opts.length.is_some()) {
+        return Some(String::from("--range can't be combined with --offset or --length"));
+    }
+    if opts.stride.is_some_and(|n| !(2..=stride::MAX_STRIDE).contains(&n)) {
+        return Some(format!("--stride takes a record size from 2 to {} bytes", stride::MAX_STRIDE));
+    }
+    if opts.record_size.is_some_and(|n| !(2..=stride::MAX_STRIDE).contains(&n)) {
+        return Some(format!("--record-size takes a size from 2 to {} bytes", stride::MAX_STRIDE));
+    }
+    if opts.stride.is_some() && opts.record_size.is_some() {
+        return Some(String::from("--stride and --record-size are two takes on the same table, pick one"));
+    }
+    if opts.fields.iter().any(|f| f.is_empty() || f == "0") {
+        return Some(String::from("--field takes a field number from 1, or a name from the header"));
+    }
+    if !opts.fields.is_empty() && (opts.stride.is_some() || opts.record_size.is_some()) {
+        return Some(String::from("--field counts values, not bytes, and doesn't go with --stride or --record-size"));
+    }
+    if opts.delimiter.as_ref().is_some_and(|d| d != "tab" && d != "\\t" && d.len() != 1) {
+        return Some(String::from("--delimiter takes a single character, or tab"));
+    }
+    if (opts.port.is_some() || opts.protocol.is_some()) && !opts.payloads {
+        return Some(String::from("--port and --protocol pick out packets for --payloads and --per-flow"));
+    }
+    if opts.port == Some(0) {
+        return Some(String::from("--port takes a port number from 1 to 65535"));
+    }
+    if let Some(name) = opts.protocol.as_ref().filter(|p| pcap::protocol_number(p).is_none()) {
+        return Some(format!("Unknown --protocol {}, try tcp, udp, icmp, icmpv6, sctp or a number", name));
+    }
+    if opts.payloads && (opts.stride.is_some() || opts.record_size.is_some() || !opts.fields.is_empty()) {
+        return Some(String::from("--payloads counts packets, and doesn't go with --stride, --record-size or --field"));
+    }
+    if opts.audio && (opts.stride.is_some() || opts.record_size.is_some() || !opts.fields.is_empty() || opts.payloads || opts.pixels) {
+        return Some(String::from("--audio counts samples, and doesn't go with --stride, --record-size, --field, --payloads or --pixels"));
+    }
+    if opts.pixels && (opts.stride.is_some() || opts.record_size.is_some() || !opts.fields.is_empty() || opts.payloads) {
+        return Some(String::from("--pixels counts the pixels of an image, and doesn't go with --stride, --record-size, --field or --payloads"));
+    }
+    if opts.delimiter.is_some() && opts.fields.is_empty() {
+        return Some(String::from("--delimiter goes with --field"));
+    }
+    if let Some(key) = opts.sort_by.as_ref().filter(|k| !report::SORT_KEYS.contains(&k.as_str())) {
+        return Some(format!("Can't sort by {}, try {}", key, report::SORT_KEYS.join(", ")));
+    }
+    if opts.sort_by.is_some() && (opts.command.is_some() || !is_multi(opts) || opts.duplicates || opts.summary_line || opts.per_range || opts.triage || opts.archive_entries) {
+        return Some(String::from("--sort-by is for the table of several files"));
+    }
+    if [opts.packed_above, opts.encrypted_above].iter().flatten().any(|e| !(0.0..=8.0).contains(e)) {
+        return Some(String::from("--packed-above and --encrypted-above take bits per byte, from 0 to 8"));
+    }
+    if opts.max_divergence.is_some_and(|d| !(0.0..=1.0).contains(&d)) {
+        return Some(String::from("--max-divergence takes a divergence from 0 to 1, like 0.05"));
+    }
+    if opts.min_padding == Some(0) {
+        return Some(String::from("--min-padding takes a size, 1 byte or more, like 512"));
+    }
+    if opts.segment_threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+        return Some(String::from("--segment-threshold takes a distance from 0 to 1, like 0.45"));
+    }
+    if opts.min_entropy.is_some_and(|e| !(0.0..=8.0).contains(&e)) {
+        return Some(String::from("--min-entropy takes bits per byte, from 0 to 8"));
+    }
+    if opts.entropy_above.is_some_and(|e| !(0.0..=8.0).contains(&e)) {
+        return Some(String::from("--entropy-above takes bits per byte, from 0 to 8"));
+    }
+    if (opts.entropy_above.is_some() || opts.like.is_some()) && opts.command.as_deref() != Some("find") {
+        return Some(String::from("--entropy-above and --like are for find"));
+    }
+    if opts.normalize && opts.command.as_deref() != Some("calc") {
+        return Some(String::from("--normalize is for calc"));
+    }
+    if opts.fingerprint && opts.command.is_some() {
+        return Some(String::from("--fingerprint is for the default command, fingerprint compare takes what it prints"));
+    }
+    if opts.fuzzy && opts.command.is_some() {
+        return Some(String::from("--fuzzy is for the default command, fuzzy compare takes what it prints"));
+    }
+    if opts.fingerprint && opts.fuzzy {
+        return Some(String::from("--fingerprint and --fuzzy are two different lines, pick one"));
+    }
+    if opts.max_depth == Some(0) {
+        return Some(String::from("--max-depth takes a number of levels, from 1"));
+    }
+    if opts.baud.is_some() && opts.serial.is_none() {
+        return Some(String::from("--baud goes with --serial"));
+    }
+    if let Some(baud) = opts.baud.filter(|b| !serial::BAUDS.contains(b)) {
+        return Some(format!("Unsupported --baud {}, try a standard rate like 9600 or 115200", baud));
+    }
+    if opts.serial.is_some() && opts.command.is_some() {
+        return Some(String::from("--serial only makes the plain report"));
+    }
+    if opts.per_range && opts.ranges.is_empty() {
+        return Some(String::from("--per-range needs at least one --range"));
+    }
+    let reshaped = opts.decompress.is_some() || opts.decode.is_some()
+        || opts.offset.is_some() || opts.length.is_some() || !opts.ranges.is_empty();
+    if reshaped && (opts.sections || opts.partitions || opts.archive_entries || columns || opts.command.as_deref() == Some("carve")) {
+        return Some(String::from("--decompress, --decode and byte ranges don't apply to carve, --sections, --partitions, --archive-entries, --stride, --record-size, --field, --payloads, --audio or --pixels"));
+    }
+    if let Some(Every::Seconds(0)) | Some(Every::Bytes(0)) = opts.snapshot_every {
+        return Some(String::from("Snapshot intervals look like 30s, 5min, 1h or 100M"));
+    }
+    let streaming = opts.follow || opts.snapshot_every.is_some() || opts.command.as_deref() == Some("watch");
+    if opts.control_path.is_some() && !(opts.follow || opts.snapshot_every.is_some()) {
+        return Some(String::from("--control is for --follow and --snapshot-every"));
+    }
+    if opts.resume && opts.checkpoint_path.is_none() {
+        return Some(String::from("--resume needs the --checkpoint to resume from"));
+    }
+    if opts.metrics_addr.is_some() && !streaming {
+        return Some(String::from("--metrics is for --follow, --snapshot-every and watch, serve has /metrics already"));
+    }
+    if opts.grpc && opts.command.as_deref() != Some("serve") {
+        return Some(String::from("--grpc is for serve"));
+    }
+    if opts.root.is_some() && opts.command.as_deref() != Some("serve") {
+        return Some(String::from("--root is for serve, the directory clients can name files in"));
+    }
+    if opts.root.as_ref().is_some_and(|r| !walk::is_dir(r)) {
+        return Some(String::from("--root takes a directory"));
+    }
+    if opts.order.is_some_and(|n| n > model::MAX_ORDER) {
+        return Some(format!("--order takes 0 to {} symbols", model::MAX_ORDER));
+    }
+    let generating = opts.command.as_deref() == Some("generate");
+    if (opts.order.is_some() || opts.chars) && !(opts.command.as_deref() == Some("model") || (generating && opts.from.is_some())) {
+        return Some(String::from("--order and --chars are for model and generate --from"));
+    }
+    if (opts.model_path.is_some() || opts.from.is_some() || opts.match_path.is_some() || opts.bytes.is_some() || opts.seed.is_some()) && !generating {
+        return Some(String::from("--model, --from, --match, --bytes and --seed are for generate"));
+    }
+    if opts.top == Some(0) {
+        return Some(String::from("--top takes how many to show, 1 or more"));
+    }
+    if opts.clusters == Some(0) {
+        return Some(String::from("--clusters takes how many clusters, 1 or more"));
+    }
+    if opts.seed.as_ref().is_some_and(|s| s.parse::<u64>().is_err()) {
+        return Some(String::from("--seed takes a whole number, 0 or more"));
+    }
+    if opts.context.is_some_and(|n| !(1..=rare::MAX_CONTEXT).contains(&n)) {
+        return Some(format!("--context takes 1 to {} bytes either side", rare::MAX_CONTEXT));
+    }
+    if opts.rare_below == Some(0) {
+        return Some(String::from("--rare-below takes a count, 1 or more"));
+    }
+    if opts.rare_below.is_some() && opts.context.is_none() {
+        return Some(String::from("--rare-below goes with --context"));
+    }
+    if opts.heatmap_buckets.is_some_and(|n| !n.is_power_of_two() || n > 256) {
+        return Some(String::from("--heatmap-buckets takes a power of two, 1 to 256"));
+    }
+    if (opts.heatmap_block.is_some() || opts.heatmap_buckets.is_some()) && opts.heatmap_path.is_none() {
+        return Some(String::from("--heatmap-block and --heatmap-buckets go with --heatmap"));
+    }
+    if opts.quiet && opts.verbose > 0 {
+        return Some(String::from("-q and -v don't go together"));
+    }
+    if opts.max_bytes == Some(0) {
+        return Some(String::from("--max-bytes takes a size, 1 byte or more, like 1M"));
+    }
+    if opts.timeout == Some(0) {
+        return Some(String::from("--timeout and --duration take a duration, 1s or more, like 30s or 5min"));
+    }
+    if opts.rate == Some(0) {
+        return Some(String::from("--rate takes a duration, 1s or more"));
+    }
+    if opts.limit == Some(0) {
+        return Some(String::from("--limit takes a number of offsets, 1 or more"));
+    }
+    if opts.limit.is_some() && opts.command.as_deref() != Some("locate") {
+        return Some(String::from("--limit is for locate"));
+    }
+    if opts.sampled && opts.match_path.is_none() {
+        return Some(String::from("--sampled is for generate --match"));
+    }
+    if opts.significance.is_some_and(|s| !(s > 0.0 && s < 1.0)) {
+        return Some(String::from("--significance takes a level between 0 and 1, like 0.01"));
+    }
+    if opts.significance.is_some() && !matches!(opts.command.as_deref(), Some("nist") | Some("random") | Some("test")) {
+        return Some(String::from("--significance is for nist, random and test"));
+    }
+    if stats::Gof::parse(&opts.gof_name).is_none() {
+        return Some(format!("Unknown --gof {}, try chi2 or g", opts.gof_name));
+    }
+    if opts.gof_name != "chi2" && !matches!(opts.command.as_deref(), Some("diff") | Some("check") | Some("random") | Some("test")) {
+        return Some(String::from("--gof is for diff, check, random and test"));
+    }
+    if !samples::KINDS.contains(&opts.samples_name.as_str()) {
+        return Some(format!("Unknown --as {}, try dieharder or uniform", opts.samples_name));
+    }
+    if opts.samples_name != "dieharder" && opts.command.as_deref() != Some("samples") {
+        return Some(String::from("--as is for samples"));
+    }
+    if opts.ci.is_some_and(|level| !(level > 0.0 && level < 100.0)) {
+        return Some(String::from("--ci takes a confidence level in percent, like 95"));
+    }
+    if opts.ci.is_some() && (chart || !matches!(opts.command.as_deref(), None | Some("diff"))) {
+        return Some(String::from("--ci is for the byte table and diff, in text, csv or json"));
+    }
+    if opts.block_size == Some(0) {
+        return Some(String::from("--block-size takes a size, 1 byte or more, like 4k"));
+    }
+    if opts.content_defined && (opts.block_size.is_none_or(|s| s < 64) || opts.command.is_some()) {
+        return Some(String::from("--content-defined goes with a --block-size of at least 64 bytes, the average block size of the report"));
+    }
+
+    match opts.command.as_deref() {
+        // watch takes the same options as the default command, but a
+        // pattern can match any number of files
+        Some("watch") => {
+            if opts.follow || opts.snapshot_every.is_some() {
+                return Some(String::from("--follow and --snapshot-every aren't available for watch"));
+            }
+            if opts.git_modified || opts.git_staged {
+                return Some(String::from("--git-modified and --git-staged aren't available for watch"));
+            }
+            if extras && opts.inputs.iter().any(|i| glob::is_pattern(i)) {
+                return Some(String::from("Entropy maps, heatmaps, profiles, block reports, segments, runs, --first-last, --dispersion, --context, --rate, --save and --metadata need a single input file"));
+            }
+            // --metrics goes with watch, checked above
+            usage_problem(&Options { command: None, metrics_addr: None, ..opts.clone() }, format)
+        },
+        Some(command) => {
+            if opts.git_modified || opts.git_staged {
+                return Some(format!("--git-modified and --git-staged aren't available for {}", command));
+            }
+            if extras && !random_blocks {
+                return Some(format!("Entropy maps, heatmaps, profiles, block reports, segments, runs, --first-last, --dispersion, --context, --rate, --save and --metadata aren't available for {}", command));
+            }
+            if opts.triage || opts.sections || opts.partitions || opts.archive_entries || opts.list_ads || columns || opts.per_range || opts.follow || opts.snapshot_every.is_some() || opts.checkpoint_path.is_some() {
+                return Some(format!("--triage, --sections, --partitions, --archive-entries, --list-ads, --stride, --record-size, --field, --payloads, --audio, --pixels, --per-range, --follow, --snapshot-every and --checkpoint aren't available for {}", command));
+            }
+            if opts.huffman && command != "watch" {
+                return Some(format!("--huffman isn't available for {}", command));
+            }
+            // these write a file format of their own
+            let own_format = command == "merge" || (command == "calc" && !opts.normalize) || command == "model" || command == "generate";
+            if (chart && command != "trend") || (own_format && format != Some(Format::Text)) {
+                return Some(format!("Format {} isn't available for {}", opts.format_name, command));
+            }
+            match command {
+                "diff" if opts.previous => {
+                    if opts.inputs.len() != 1 || opts.cache_dir.is_none() || opts.timeout.is_some() {
+                        Some(String::from("diff --previous needs exactly one file and the --cache it was run with (and no --timeout)"))
+                    } else { None }
+                },
+                "diff" | "similarity" | "test" if opts.inputs.len() != 2 => {
+                    Some(format!("{} needs exactly two files", command))
+                },
+                "check" if opts.baseline.is_none() => Some(String::from("check needs a --baseline")),
+                "check" if opts.inputs.is_empty() => Some(String::from("Not enough arguments")),
+                "merge" | "cluster" | "carve" | "find" | "model" if opts.inputs.is_empty() => Some(String::from("Not enough arguments")),
+                "calc" => calc::parse(&opts.inputs).err(),
+                "locate" if opts.inputs.len() != 2 || locate::parse_byte(&opts.inputs[0]).is_none() => {
+                    Some(String::from("try locate <byte> <file>, the byte like 0x7f or 127"))
+                },
+                "generate" if !opts.inputs.is_empty() => Some(String::from("generate doesn't take files, try --from <file>")),
+                "generate" if [opts.model_path.is_some(), opts.from.is_some(), opts.match_path.is_some()].iter().filter(|g| **g).count() != 1 => {
+                    Some(String::from("generate needs one of --model <model.json>, --from <file> or --match <counts.freq>"))
+                },
+                "generate" if opts.bytes == Some(0) || (opts.bytes.is_none() && opts.match_path.is_none()) => {
+                    Some(String::from("generate needs --bytes <size>, like --bytes 1M"))
+                },
+                "fingerprint" | "fuzzy" if opts.inputs.len() != 3 || opts.inputs[0] != "compare" => Some(format!("try {} compare <a> <b>", command)),
+                "find" if opts.entropy_above.is_none() && opts.like.is_none() => Some(String::from("find needs --entropy-above <bits> or --like <file>")),
+                "repl" if !opts.inputs.is_empty() => Some(String::from("repl doesn't take files, they're analyzed from inside it")),
+                "repl" if opts.out_path.is_some() => Some(String::from("repl writes to the terminal, not an outfile")),
+                "serve" if !opts.inputs.is_empty() => Some(String::from("serve doesn't take files, they get sent to it")),
+                "history" if !opts.inputs.is_empty() => Some(String::from("history takes the file to list with --file")),
+                "history" | "trend" if history::store(opts).is_none() => Some(format!("{} needs a --history file, or FREQS_HISTORY set", command)),
+                "report" if opts.inputs.len() != 2 || opts.inputs[0] != "split" => Some(String::from("try report split <file>")),
+                "trend" if opts.inputs.len() != 1 => Some(String::from("trend needs exactly one file or tag")),
+                "history" if opts.since.as_ref().is_some_and(|s| history::parse_since(s).is_none()) => {
+                    Some(String::from("--since takes a date like 2026-10-14 or a time ago like 7d"))
+                },
+                "pid" if opts.inputs.len() != 1 || opts.inputs[0].parse::<u32>().is_err() => Some(String::from("pid needs the id of a process")),
+                "classify" if opts.inputs.len() != 1 => Some(String::from("classify needs exactly one file")),
+                "fips" | "nist" | "random" | "samples" | "keycheck" if opts.inputs.len() != 1 => Some(format!("{} needs exactly one file", command)),
+                "nearest" if opts.inputs.len() < 2 => Some(String::from("nearest needs a query file and a corpus")),
+                _ => None,
+            }
+        },
+        None => {
+            if opts.inputs.is_empty() && !opts.git_modified && !opts.git_staged && opts.serial.is_none() { return Some(String::from("Not enough arguments")); }
+            if opts.serial.is_some() {
+                if !opts.inputs.is_empty() || opts.git_modified || opts.git_staged {
+                    return Some(String::from("--serial reads the port in place of any files"));
+                }
+                if opts.max_bytes.is_none() && opts.timeout.is_none() {
+                    return Some(String::from("--serial needs --duration or --max-bytes to know when to stop"));
+                }
+                if columns || opts.triage || opts.sections || opts.partitions || opts.archive_entries || opts.per_range || opts.summary_line || chart {
+                    return Some(String::from("--serial only makes the plain report"));
+                }
+            }
+            if is_multi(opts) && extras {
+                return Some(String::from("Entropy maps, heatmaps, profiles, block reports, segments, runs, --first-last, --dispersion, --context, --rate, --save and --metadata need a single input file"));
+            }
+            if opts.triage && (extras || chart || opts.duplicates || opts.sections) {
+                return Some(String::from("--triage only prints its table, try it without the other options"));
+            }
+            if opts.archive_entries && (extras || chart || opts.duplicates || opts.triage || opts.sections) {
+                return Some(String::from("--archive-entries only prints its table, try it without the other options"));
+            }
+            if opts.list_ads && !cfg!(windows) {
+                return Some(String::from("--list-ads needs NTFS alternate data streams, which only Windows has"));
+            }
+            if opts.list_ads && (extras || chart || columns || opts.duplicates || opts.triage || opts.sections || opts.partitions || opts.archive_entries
+                || opts.summary_line || opts.per_range || opts.fingerprint || opts.fuzzy || opts.follow || opts.snapshot_every.is_some() || opts.checkpoint_path.is_some() || opts.serial.is_some()) {
+                return Some(String::from("--list-ads only prints its table, try it without the other options"));
+            }
+            if opts.summary_line && (extras || chart || opts.duplicates || opts.triage || opts.sections || opts.partitions || opts.archive_entries || opts.per_range || opts.follow || opts.snapshot_every.is_some()) {
+                return Some(String::from("--summary-line only prints its lines, try it without the other options"));
+            }
+            if (opts.fingerprint || opts.fuzzy) && (extras || chart || columns || opts.duplicates || opts.triage || opts.sections || opts.partitions || opts.archive_entries
+                || opts.summary_line || opts.per_range || opts.follow || opts.snapshot_every.is_some() || opts.checkpoint_path.is_some() || opts.serial.is_some()) {
+                return Some(String::from("--fingerprint and --fuzzy only print their lines, try them without the other options"));
+            }
+            if opts.per_range && (extras || chart || opts.duplicates || opts.triage) {
+                return Some(String::from("--per-range only prints its table, try it without the other options"));
+            }
+            if opts.follow && (is_multi(opts) || extras || reshaped || opts.triage || opts.sections || opts.archive_entries || opts.per_range) {
+                return Some(String::from("--follow needs a single input file, and only keeps the byte table up to date"));
+            }
+            if opts.snapshot_every.is_some() && (is_multi(opts) || extras || reshaped || opts.follow || opts.triage || opts.sections || opts.archive_entries || opts.per_range) {
+                return Some(String::from("--snapshot-every needs a single input, and only prints the byte table"));
+            }
+            let counting_only = extras || opts.decompress.is_some() || opts.decode.is_some() || !opts.ranges.is_empty()
+                || opts.follow || opts.snapshot_every.is_some() || opts.triage || opts.sections || opts.partitions || opts.archive_entries;
+            let limited = opts.max_bytes.is_some() || opts.timeout.is_some();
+            if limited && (opts.follow || opts.snapshot_every.is_some() || opts.checkpoint_path.is_some()) {
+                return Some(String::from("--max-bytes and --timeout don't apply to --follow, --snapshot-every or --checkpoint"));
+            }
+            if opts.checkpoint_path.is_some() && (is_multi(opts) || counting_only) {
+                return Some(String::from("--checkpoint needs a single input file, and only counts bytes (--offset and --length work)"));
+            }
+            if opts.sections && (is_multi(opts) || extras || chart) {
+                return Some(String::from("--sections needs a single input file, and no other reports or charts"));
             }
+            if opts.partitions && (is_multi(opts) || extras || chart || opts.sections || opts.triage || opts.archive_entries) {
+                return Some(String::from("--partitions needs a single disk or image, and no other reports or charts"));
+            }
+            if columns && (is_multi(opts) || extras || chart || opts.sections || opts.partitions || opts.triage || opts.archive_entries
+                || opts.per_range || opts.summary_line || opts.follow || opts.snapshot_every.is_some() || opts.checkpoint_path.is_some()) {
+                return Some(String::from("--stride, --record-size, --field, --payloads, --audio and --pixels need a single input file, and no other reports or charts"));
+            }
+            if !is_multi(opts) && opts.duplicates {
+                return Some(String::from("--duplicates needs several input files or a directory"));
+            }
+            if is_multi(opts) && chart {
+                return Some(format!("Format {} needs a single input file", opts.format_name));
+            }
+            if opts.huffman && (is_multi(opts) || chart || columns || opts.triage || opts.sections || opts.partitions || opts.archive_entries
+                || opts.per_range || opts.summary_line || opts.fingerprint || opts.fuzzy || opts.follow || opts.snapshot_every.is_some() || opts.checkpoint_path.is_some()) {
+                return Some(String::from("--huffman goes with the report of a single file, in text, csv or json"));
+            }
+            if chart && (opts.block_size.is_some() || opts.segments || opts.runs || opts.first_last || opts.dispersion || opts.context.is_some() || opts.rate.is_some()) {
+                return Some(format!("Block reports, segments, runs, --first-last, --dispersion, --context and --rate aren't available in format {}", opts.format_name));
+            }
+            None
+        },
+    }
+}
+
+// the default command: a full report for a single file, or one summary per
+// file when given several
+fn analyze_inputs(opts: &Options, format: Format) -> Vec<String> {
+    if opts.triage {
+        triage::run(opts, format)
+    } else if opts.archive_entries {
+        archive::run(opts, format)
+    } else if opts.list_ads {
+        ads::run(opts, format)
+    } else if opts.sections {
+        sections::run(opts, format)
+    } else if opts.partitions {
+        disk::run(opts, format)
+    } else if let Some(stride) = opts.stride {
+        stride::run(opts, format, stride, false)
+    } else if let Some(size) = opts.record_size {
+        stride::run(opts, format, size, true)
+    } else if !opts.fields.is_empty() {
+        fields::run(opts, format)
+    } else if opts.payloads {
+        pcap::run(opts, format)
+    } else if opts.audio {
+        wav::run(opts, format)
+    } else if opts.pixels {
+        pixels::run(opts, format)
+    } else if opts.fingerprint {
+        fingerprint::run(opts, format)
+    } else if opts.fuzzy {
+        fuzzy::run(opts, format)
+    } else if opts.summary_line {
+        let mut summaries = analysis::analyze_files(&walk::expand(&opts.inputs, opts), opts);
+        let results: Vec<(&str, &Analysis)> = summaries.iter().map(|s| (s.path.as_str(), &s.analysis)).collect();
+        history::record(opts, &results);
+        for s in summaries.iter_mut() { asked_hashes(opts, &mut s.analysis); }
+        report::render_summary_lines(format, &summaries)
+    } else if opts.per_range {
+        report::render_summaries(format, &analysis::analyze_ranges(&walk::expand(&opts.inputs, opts), opts), &[])
+    } else if is_multi(opts) {
+        // one summary per file
+        let (files, skipped) = walk::expand_sized(&opts.inputs, opts);
+        let mut summaries = analysis::analyze_files(&files, opts);
+        if let Some(ref key) = opts.sort_by { report::sort_summaries(&mut summaries, key); }
+        let results: Vec<(&str, &Analysis)> = summaries.iter().map(|s| (s.path.as_str(), &s.analysis)).collect();
+        history::record(opts, &results);
+        for s in summaries.iter_mut() { asked_hashes(opts, &mut s.analysis); }
+        if opts.duplicates {
+            dupes::render(format, &summaries, opts.confirm)
+        } else {
+            report::render_summaries(format, &summaries, &skipped)
         }
+    } else if opts.checkpoint_path.is_some() {
+        checkpoint::run(opts, format)
+    } else {
+        // progress would get mixed up with the report on stderr
+        let mut analysis = match analysis::analyze(&opts.inputs[0], opts, !opts.tee) {
+            Ok(a) => a,
+            // decompressing and decoding can fail in more ways than opening
+            Err(e) if opts.decompress.is_some() || opts.decode.is_some() || remote::is_url(&opts.inputs[0]) || opts.tee || opts.serial.is_some() => panic!("Could not read {}: {}", opts.inputs[0], e),
+            Err(_) => panic!("Could not open file. Bad file or path?")
+        };
+        history::record(opts, &[(&opts.inputs[0], &analysis)]);
+        asked_hashes(opts, &mut analysis);
 
-        // either save table in file, or else print as stdout
-        if let Some(mut f) = out_file {
-            for line in lines {
-                if let Err(e) = writeln!(f, "{}", line) { println!("{}", e); }
+        if let (Some(path), Some(b)) = (opts.hilbert_path.as_ref(), analysis.blocks.as_ref()) {
+            if let Err(e) = hilbert::render(b, path, opts.force) {
+                panic!("Could not write entropy map {}: {}", path, e);
+            }
+        }
+        if let (Some(path), Some(h)) = (opts.heatmap_path.as_ref(), analysis.heatmap.as_ref()) {
+            if let Err(e) = heatmap::render(h, path, opts.force) {
+                panic!("Could not write heatmap {}: {}", path, e);
+            }
+        }
+        if let Some(ref path) = opts.save_path {
+            if let Err(e) = freqfile::save(path, &analysis.counts, opts.force) {
+                panic!("Could not save counts to {}: {}", path, e);
             }
-        } else { for line in lines { println!("{}", line); } }
+        }
+
+        // turn results into table
+        let report = Report {
+            counts: &analysis.counts,
+            meta: if opts.metadata { Some(metadata(&opts.inputs[0], analysis.len)) } else { None },
+            hashes: analysis.hashes,
+            profile: analysis.profile.map(|points| {
+                let w = opts.window.unwrap();
+                Profile { window: w, step: opts.step.unwrap_or(w), points }
+            }),
+            regions: analysis.regions.map(|regions| Regions { block_size: opts.block_size.unwrap(), content_defined: opts.content_defined, regions }),
+            segments: analysis.segments,
+            runs: analysis.runs,
+            first_last: analysis.first_last,
+            dispersion: analysis.dispersion,
+            rare: analysis.rare,
+            rate: analysis.rate,
+            huffman: if opts.huffman { Some(huffman::build(&analysis.counts, opts.huffman_table)) } else { None },
+            truncated: analysis.truncated,
+            bars: opts.bars,
+            ci: opts.ci,
+        };
+        report::render(format, &report)
     }
 }
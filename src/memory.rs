@@ -0,0 +1,135 @@
+/*
+    freqs pid: the memory of a running process on linux, a region at a
+    time as /proc/PID/maps lists them, read through /proc/PID/mem. That
+    takes the same permissions as a debugger would (the same user and
+    ptrace allowed, or root). Executable regions that are writable too, or
+    that came from no file, or that look packed are pointed out, since
+    that's where injected code tends to be.
+*/
+
+use std::fs;
+
+use args::Options;
+use entropy;
+use report::{csv_field, json_str, sparkline, Format};
+use sections::count_range;
+use triage;
+
+struct Region {
+    start: u64,
+    size: u64,
+    perms: String,
+    path: String,
+    counts: Option<[u64; 256]>,     // None if it couldn't be read
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let pid = &opts.inputs[0];
+    let maps = fs::read_to_string(format!("/proc/{}/maps", pid))
+        .unwrap_or_else(|e| panic!("Could not read the memory map of process {}: {}", pid, e));
+    let name = fs::read_to_string(format!("/proc/{}/comm", pid)).map(|n| n.trim().to_string()).unwrap_or_default();
+    let mut mem = fs::File::open(format!("/proc/{}/mem", pid))
+        .unwrap_or_else(|e| panic!("Could not open the memory of process {}: {}", pid, e));
+
+    // 7f0c3a400000-7f0c3a428000 r--p 00000000 08:01 1234   /usr/lib/libc.so.6
+    let mut regions = Vec::new();
+    for line in maps.lines() {
+        let mut fields = line.split_whitespace();
+        let (range, perms) = match (fields.next(), fields.next()) {
+            (Some(r), Some(p)) => (r, p),
+            _ => continue,
+        };
+        let (start, end) = match range.split_once('-').and_then(|(s, e)| Some((u64::from_str_radix(s, 16).ok()?, u64::from_str_radix(e, 16).ok()?))) {
+            Some(r) => r,
+            None => continue,
+        };
+        // regions mapped without read access are skipped, and some that have
+        // it still can't be read ([vvar], device memory)
+        if !perms.starts_with('r') { continue; }
+        let counts = count_range(&mut mem, start, end - start).ok();
+        // the path, if any, is all that's after the fifth field
+        let path = (0..5)
+            .try_fold(line, |rest, _| rest.trim_start().split_once(char::is_whitespace).map(|(_, r)| r))
+            .map_or(String::new(), |r| r.trim().to_string());
+        regions.push(Region { start, size: end - start, perms: perms.to_string(), path, counts });
+    }
+    if regions.is_empty() { panic!("Nothing readable in the memory of process {}", pid); }
+    render(format, pid, &name, &regions, opts.packed_above.unwrap_or(triage::DEFAULT_PACKED_ABOVE))
+}
+
+// why a region is worth a closer look, if it is
+fn notes(r: &Region, entropy: Option<f64>, packed_above: f64) -> Vec<&'static str> {
+    let mut notes = Vec::new();
+    let exec = r.perms.contains('x');
+    if exec && r.perms.contains('w') { notes.push("writable and executable"); }
+    if exec && r.path.is_empty() { notes.push("executable, from no file"); }
+    if exec && entropy.is_some_and(|e| e > packed_above) { notes.push("packed"); }
+    if r.counts.is_none() { notes.push("unreadable"); }
+    notes
+}
+
+fn render(format: Format, pid: &str, name: &str, regions: &[Region], packed_above: f64) -> Vec<String> {
+    let entropy = |r: &Region| r.counts.as_ref().map(|c| entropy::shannon(c, r.size));
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("start,size,perms,path,entropy,notes")];
+            for r in regions {
+                lines.push(format!(
+                    "{:#x},{},{},{},{},{}",
+                    r.start, r.size, r.perms, csv_field(&r.path), entropy(r).map_or(String::new(), |e| format!("{:.6}", e)),
+                    csv_field(&notes(r, entropy(r), packed_above).join("; "))
+                ));
+            }
+            // then every region's counts, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("start,byte,count"));
+            for r in regions {
+                for (byte, n) in r.counts.iter().flat_map(|c| c.iter().enumerate()) {
+                    if *n != 0 { lines.push(format!("{:#x},{},{}", r.start, byte, n)); }
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let items: Vec<String> = regions.iter()
+                .map(|r| {
+                    let bytes: Vec<String> = r.counts.iter().flat_map(|c| c.iter().enumerate())
+                        .filter(|&(_, n)| *n != 0)
+                        .map(|(byte, n)| format!("{{\"byte\": {}, \"count\": {}}}", byte, n))
+                        .collect();
+                    let notes: Vec<String> = notes(r, entropy(r), packed_above).iter().map(|n| json_str(n)).collect();
+                    format!(
+                        "    {{\"start\": {}, \"size\": {}, \"perms\": {}, \"path\": {}, \"entropy\": {}, \"notes\": [{}], \"bytes\": [{}]}}",
+                        r.start, r.size, json_str(&r.perms), json_str(&r.path),
+                        entropy(r).map_or(String::from("null"), |e| format!("{:.6}", e)), notes.join(", "), bytes.join(", ")
+                    )
+                })
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"pid\": {},", pid),
+                format!("  \"name\": {},", json_str(name)),
+                format!("  \"regions\": [\n{}\n  ]", items.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  process {} ({}), {} readable regions", pid, name, regions.len()),
+                String::from(""),
+                format!("  {:<18}  {:>12}  perms  entropy  {:<16}  path", "start", "size", "bytes"),
+            ];
+            for r in regions {
+                let notes = notes(r, entropy(r), packed_above);
+                lines.push(format!(
+                    "  {:#018x}  {:>12}  {:<5}  {:>7}  {:<16}  {}{}",
+                    r.start, r.size, r.perms, entropy(r).map_or(String::from("-"), |e| format!("{:.4}", e)),
+                    r.counts.as_ref().map_or(String::new(), sparkline), r.path,
+                    if notes.is_empty() { String::new() } else { format!("  <- {}", notes.join(", ")) }
+                ));
+            }
+            lines
+        },
+    }
+}
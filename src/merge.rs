@@ -0,0 +1,24 @@
+/*
+    freqs merge: sums up count files saved with --save, e.g. from shards of
+    a dataset analyzed separately, into a single count file.
+*/
+
+use args::Options;
+use freqfile;
+
+pub fn run(opts: &Options) -> Vec<String> {
+    let mut total = [0u64; 256];
+    for path in &opts.inputs {
+        let counts = match freqfile::load(path) {
+            Ok(c) => c,
+            Err(e) => panic!("Could not load {}: {}", path, e),
+        };
+        for (t, c) in total.iter_mut().zip(counts.iter()) {
+            *t = match t.checked_add(*c) {
+                Some(sum) => sum,
+                None => panic!("Counts overflow when merging {}", path),
+            };
+        }
+    }
+    freqfile::render(&total)
+}
@@ -0,0 +1,123 @@
+/*
+    Prometheus metrics, for alerting on drift in data that keeps coming. A
+    Board holds the latest counts of every source being watched and renders
+    them in prometheus' text format: entropy, the share of each class of
+    bytes, throughput and the counts of a few telling bytes, all as gauges.
+    serve has it at /metrics; --follow, --snapshot-every and watch get a
+    listener of their own with --metrics <address:port>.
+*/
+
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use entropy;
+use serve;
+use stats;
+
+// bytes whose counts are published: nul, newline, space and 0xff
+pub const SELECTED: [u8; 4] = [0x00, 0x0a, 0x20, 0xff];
+
+struct Sample {
+    source: String,
+    len: u64,
+    counts: [u64; 256],
+    updated: Instant,
+    rate: f64,      // bytes per second, between the last two updates
+}
+
+pub struct Board {
+    samples: Mutex<Vec<Sample>>,
+}
+
+impl Board {
+    pub fn new() -> Board {
+        Board { samples: Mutex::new(Vec::new()) }
+    }
+
+    pub fn update(&self, source: &str, len: u64, counts: &[u64; 256]) {
+        let now = Instant::now();
+        let mut samples = self.samples.lock().unwrap();
+        match samples.iter_mut().find(|s| s.source == source) {
+            Some(s) => {
+                let seconds = now.duration_since(s.updated).as_secs_f64();
+                if seconds > 0.0 { s.rate = len.saturating_sub(s.len) as f64 / seconds; }
+                s.len = len;
+                s.counts = *counts;
+                s.updated = now;
+            },
+            None => samples.push(Sample { source: source.to_string(), len, counts: *counts, updated: now, rate: 0.0 }),
+        }
+    }
+
+    // sources no longer there, like files gone from a watched directory
+    pub fn retain(&self, keep: &dyn Fn(&str) -> bool) {
+        self.samples.lock().unwrap().retain(|s| keep(&s.source));
+    }
+
+    pub fn render(&self) -> String {
+        let samples = self.samples.lock().unwrap();
+        let mut out = String::new();
+        let mut gauge = |name: &str, help: &str, values: Vec<(String, f64)>| {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+            for (labels, value) in values { out.push_str(&format!("{}{{{}}} {}\n", name, labels, value)); }
+        };
+        let source = |s: &Sample| format!("source=\"{}\"", label(&s.source));
+
+        gauge("freqs_bytes", "Bytes counted.",
+            samples.iter().map(|s| (source(s), s.len as f64)).collect());
+        gauge("freqs_entropy_bits", "Shannon entropy of the bytes counted, in bits per byte.",
+            samples.iter().map(|s| (source(s), entropy::shannon(&s.counts, s.len))).collect());
+        gauge("freqs_throughput_bytes_per_second", "How fast bytes were being counted, between the last two updates.",
+            samples.iter().map(|s| (source(s), s.rate)).collect());
+        gauge("freqs_byte_class_ratio", "Share of the bytes counted in each class.",
+            samples.iter().flat_map(|s| classes(&s.counts).into_iter()
+                .map(move |(class, ratio)| (format!("{},class=\"{}\"", source(s), class), ratio)))
+                .collect());
+        gauge("freqs_byte_count", "Occurences of a few telling byte values.",
+            samples.iter().flat_map(|s| SELECTED.iter()
+                .map(move |b| (format!("{},byte=\"{:#04x}\"", source(s), b), s.counts[*b as usize] as f64)))
+                .collect());
+        out
+    }
+}
+
+// the share of nul, printable, other control and high (0x80 and up) bytes
+pub fn classes(counts: &[u64; 256]) -> Vec<(&'static str, f64)> {
+    let total: u64 = counts.iter().sum();
+    if total == 0 { return vec![("nul", 0.0), ("printable", 0.0), ("control", 0.0), ("high", 0.0)]; }
+    let share = |n: u64| n as f64 / total as f64;
+    let nul = share(counts[0]);
+    let printable = stats::printable_ratio(counts);
+    let high = share(counts[0x80..].iter().sum());
+    vec![("nul", nul), ("printable", printable), ("control", (1.0 - nul - printable - high).max(0.0)), ("high", high)]
+}
+
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// serves the board at /metrics from a thread of its own
+pub fn listen(addr: &str) -> Arc<Board> {
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| panic!("Could not listen on {}: {}", addr, e));
+    let board = Arc::new(Board::new());
+    let shared = board.clone();
+    thread::spawn(move || loop {
+        if let Ok((stream, _)) = listener.accept() {
+            let _ = answer(stream, &shared);
+        }
+    });
+    board
+}
+
+fn answer(stream: TcpStream, board: &Board) -> io::Result<()> {
+    stream.set_read_timeout(Some(serve::TIMEOUT))?;
+    let mut out = &stream;
+    match serve::read_head(&mut BufReader::new(&stream))? {
+        Some(ref r) if r.path == "/metrics" => serve::respond(&mut out, 200, serve::METRICS_TYPE, &board.render()),
+        Some(_) => serve::respond(&mut out, 404, "text/plain", "try /metrics\n"),
+        None => serve::respond(&mut out, 400, "text/plain", "malformed request\n"),
+    }
+}
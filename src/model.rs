@@ -0,0 +1,235 @@
+/*
+    freqs model: a markov model of the files, for generators and
+    classifiers elsewhere to use. For every run of --order symbols seen
+    (the context), how often it came up and the probability of each symbol
+    that came after it. Symbols are bytes, or with --chars the characters
+    of utf-8 text (anything that doesn't decode counts as U+FFFD). Contexts
+    don't carry over from one file to the next.
+
+    The model is written as json, one context per line, like so:
+
+        {
+          "format": "freqs markov",
+          "version": 1,
+          "symbols": "bytes",
+          "order": 2,
+          "contexts": [
+            {"context": [104, 101], "count": 40, "next": [[32, 0.25], [108, 0.75]]},
+            ...
+          ]
+        }
+
+    symbols is "bytes" or "chars", and every symbol is a number, the byte
+    value or the unicode code point. Contexts are in order of their
+    symbols, and each one's next symbols are too; the probabilities of
+    those add up to 1. With order 0 there's a single context, [], which
+    is the plain distribution of the symbols.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+
+use args::Options;
+use log;
+use report::json_str;
+use walk;
+
+pub const FORMAT: &str = "freqs markov";
+pub const VERSION: u32 = 1;
+pub const DEFAULT_ORDER: usize = 1;
+// so a context packs into a u128, 21 bits a symbol
+pub const MAX_ORDER: usize = 4;
+const CHUNKSIZE: usize = 1024 * 128;
+const SYMBOL_BITS: u32 = 21;
+const REPLACEMENT: u32 = 0xfffd;
+
+pub struct Model {
+    pub order: usize,
+    pub chars: bool,
+    pub contexts: Vec<Context>,
+}
+
+pub struct Context {
+    pub symbols: Vec<u32>,
+    pub count: u64,
+    pub next: Vec<(u32, f64)>,  // symbol and weight, in symbol order
+}
+
+pub fn run(opts: &Options) -> Vec<String> {
+    let files = walk::expand(&opts.inputs, opts);
+    render(&learn(&files, opts.order.unwrap_or(DEFAULT_ORDER), opts.chars))
+}
+
+// the transitions of all the files, counted
+pub fn learn(files: &[String], order: usize, chars: bool) -> Model {
+    let mut seen: HashMap<u128, HashMap<u32, u64>> = HashMap::new();
+    let mask = (1u128 << (SYMBOL_BITS as usize * order)) - 1;
+    for path in files {
+        let (mut context, mut filled) = (0u128, 0);
+        let mut count = |symbol: u32| {
+            if filled >= order { *seen.entry(context).or_default().entry(symbol).or_insert(0) += 1; } else { filled += 1; }
+            context = ((context << SYMBOL_BITS) | symbol as u128) & mask;
+        };
+        let read = if chars {
+            let mut utf8 = Utf8::default();
+            symbols(path, |byte| utf8.feed(byte, &mut count)).map(|_| utf8.finish(&mut count))
+        } else {
+            symbols(path, |byte| count(byte as u32))
+        };
+        if let Err(e) = read { log::warn(&format!("skipping {}: {}", path, e)); }
+    }
+
+    let mut contexts: Vec<(u128, HashMap<u32, u64>)> = seen.into_iter().collect();
+    contexts.sort_by_key(|(key, _)| *key);
+    let contexts = contexts.into_iter()
+        .map(|(key, next)| {
+            let mut next: Vec<(u32, u64)> = next.into_iter().collect();
+            next.sort_by_key(|(symbol, _)| *symbol);
+            Context {
+                symbols: (0..order).map(|i| ((key >> (SYMBOL_BITS as usize * (order - 1 - i))) & 0x1fffff) as u32).collect(),
+                count: next.iter().map(|(_, c)| c).sum(),
+                next: next.into_iter().map(|(symbol, c)| (symbol, c as f64)).collect(),
+            }
+        })
+        .collect();
+    Model { order, chars, contexts }
+}
+
+fn symbols<F: FnMut(u8)>(path: &str, mut each: F) -> io::Result<()> {
+    let mut f = fs::File::open(path)?;
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    loop {
+        let n = f.read(&mut chunk)?;
+        if n == 0 { return Ok(()); }
+        for byte in &chunk[..n] { each(*byte); }
+    }
+}
+
+pub fn render(model: &Model) -> Vec<String> {
+    let mut lines = vec![
+        String::from("{"),
+        format!("  \"format\": {},", json_str(FORMAT)),
+        format!("  \"version\": {},", VERSION),
+        format!("  \"symbols\": {},", json_str(if model.chars { "chars" } else { "bytes" })),
+        format!("  \"order\": {},", model.order),
+    ];
+    if model.contexts.is_empty() {
+        lines.push(String::from("  \"contexts\": []"));
+    } else {
+        lines.push(String::from("  \"contexts\": ["));
+        for (i, c) in model.contexts.iter().enumerate() {
+            let symbols: Vec<String> = c.symbols.iter().map(|s| s.to_string()).collect();
+            let total: f64 = c.next.iter().map(|(_, w)| w).sum();
+            let next: Vec<String> = c.next.iter().map(|(s, w)| format!("[{}, {:.9}]", s, w / total)).collect();
+            lines.push(format!(
+                "    {{\"context\": [{}], \"count\": {}, \"next\": [{}]}}{}",
+                symbols.join(", "), c.count, next.join(", "), if i + 1 < model.contexts.len() { "," } else { "" }
+            ));
+        }
+        lines.push(String::from("  ]"));
+    }
+    lines.push(String::from("}"));
+    lines
+}
+
+// a model as render writes it, one context a line
+pub fn load(path: &str) -> Result<Model, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if value(&text, "format") != Some(json_str(FORMAT).as_str()) { return Err(String::from("not a freqs markov model")); }
+    match value(&text, "version") {
+        Some(v) if v.parse() == Ok(VERSION) => (),
+        v => return Err(format!("unsupported version {}", v.unwrap_or("missing"))),
+    }
+    let chars = match value(&text, "symbols") {
+        Some("\"bytes\"") => false,
+        Some("\"chars\"") => true,
+        _ => return Err(String::from("symbols should be bytes or chars")),
+    };
+    let order = value(&text, "order").and_then(|o| o.parse().ok()).filter(|o| *o <= MAX_ORDER)
+        .ok_or_else(|| format!("the order should be 0 to {}", MAX_ORDER))?;
+
+    let mut contexts = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if !line.trim_start().starts_with("{\"context\"") { continue; }
+        let c = context(line).filter(|c| {
+            let fits = |s: &u32| if chars { char::from_u32(*s).is_some() } else { *s < 256 };
+            c.symbols.len() == order && c.symbols.iter().all(fits) && c.next.iter().all(|(s, w)| fits(s) && *w >= 0.0)
+                && c.next.iter().any(|(_, w)| *w > 0.0)
+        });
+        contexts.push(c.ok_or_else(|| format!("bad context on line {}", n + 1))?);
+    }
+    Ok(Model { order, chars, contexts })
+}
+
+// the raw text of a top level value, as render writes them: one per line
+fn value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let prefix = format!("\"{}\": ", key);
+    text.lines()
+        .find_map(|line| line.strip_prefix("  ").and_then(|l| l.strip_prefix(prefix.as_str())))
+        .map(|v| v.trim_end_matches(','))
+}
+
+// {"context": [104, 101], "count": 40, "next": [[32, 0.25], [108, 0.75]]}
+fn context(line: &str) -> Option<Context> {
+    let rest = line.trim().trim_end_matches(',').strip_prefix("{\"context\": [")?;
+    let (symbols, rest) = rest.split_once("], \"count\": ")?;
+    let (count, rest) = rest.split_once(", \"next\": [")?;
+    let next = rest.strip_suffix("]}")?;
+    let symbols = if symbols.is_empty() {
+        Vec::new()
+    } else {
+        symbols.split(", ").map(|s| s.parse().ok()).collect::<Option<Vec<u32>>>()?
+    };
+    let next = next.strip_prefix('[')?.strip_suffix(']')?.split("], [")
+        .map(|pair| {
+            let (symbol, p) = pair.split_once(", ")?;
+            Some((symbol.parse().ok()?, p.parse::<f64>().ok().filter(|p| p.is_finite())?))
+        })
+        .collect::<Option<Vec<(u32, f64)>>>()?;
+    Some(Context { symbols, count: count.parse().ok()?, next })
+}
+
+// utf-8 a byte at a time, since characters run across chunks
+#[derive(Default)]
+struct Utf8 {
+    point: u32,
+    needed: u8,     // continuation bytes still to come
+    least: u32,     // the smallest code point that takes this many bytes
+}
+
+impl Utf8 {
+    fn feed<F: FnMut(u32)>(&mut self, byte: u8, each: &mut F) {
+        if self.needed > 0 {
+            if byte & 0xc0 == 0x80 {
+                self.point = self.point << 6 | (byte & 0x3f) as u32;
+                self.needed -= 1;
+                if self.needed == 0 {
+                    let valid = self.point >= self.least && !(0xd800..0xe000).contains(&self.point) && self.point <= 0x10ffff;
+                    each(if valid { self.point } else { REPLACEMENT });
+                }
+                return;
+            }
+            // cut short, and this byte starts something new
+            self.needed = 0;
+            each(REPLACEMENT);
+        }
+        match byte {
+            0x00..=0x7f => each(byte as u32),
+            0xc0..=0xdf => self.start(byte & 0x1f, 1, 0x80),
+            0xe0..=0xef => self.start(byte & 0x0f, 2, 0x800),
+            0xf0..=0xf7 => self.start(byte & 0x07, 3, 0x10000),
+            _ => each(REPLACEMENT),
+        }
+    }
+
+    fn start(&mut self, bits: u8, needed: u8, least: u32) {
+        self.point = bits as u32;
+        self.needed = needed;
+        self.least = least;
+    }
+
+    fn finish<F: FnMut(u32)>(&mut self, each: &mut F) {
+        if self.needed > 0 { each(REPLACEMENT); }
+    }
+}
@@ -0,0 +1,86 @@
+/*
+    freqs nearest: ranks every file in a corpus by how alike its byte
+    distribution is to a query file's, closest first. Only the normalized
+    histogram (the file's fingerprint) is kept for each corpus file, so big
+    corpora don't need much memory.
+*/
+
+use analysis;
+use args::Options;
+use report::{csv_field, json_str, Format};
+use stats::{self, Similarity};
+use walk;
+
+pub const DEFAULT_TOP: usize = 10;
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let query_path = &opts.inputs[0];
+    let query = match analysis::analyze(query_path, opts, false) {
+        Ok(a) => stats::proportions(&a.counts),
+        Err(_) => panic!("Could not open {}. Bad file or path?", query_path),
+    };
+
+    // the query itself is likely to sit in the corpus too, leave it out
+    let corpus: Vec<String> = walk::expand(&opts.inputs[1..], opts).into_iter()
+        .filter(|p| !walk::same_file(p, query_path))
+        .collect();
+    let fingerprints = analysis::batch(&corpus, opts, |a| stats::proportions(&a.counts));
+
+    let ranked = rank(&query, fingerprints, opts.top.unwrap_or(DEFAULT_TOP));
+    render(format, query_path, &ranked)
+}
+
+// closest (by jensen-shannon distance) first, at most top of them. shared
+// with anything else that needs to search a corpus.
+pub fn rank(query: &[f64; 256], fingerprints: Vec<(String, [f64; 256])>, top: usize) -> Vec<(String, Similarity)> {
+    let mut ranked: Vec<(String, Similarity)> = fingerprints.into_iter()
+        .map(|(path, p)| (path, stats::similarity(query, &p)))
+        .collect();
+    ranked.sort_by(|a, b| a.1.js_distance.total_cmp(&b.1.js_distance));
+    ranked.truncate(top);
+    ranked
+}
+
+pub fn render(format: Format, query_path: &str, ranked: &[(String, Similarity)]) -> Vec<String> {
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("rank,path,js_distance,cosine_similarity,histogram_intersection")];
+            for (n, (path, s)) in ranked.iter().enumerate() {
+                lines.push(format!(
+                    "{},{},{:.6},{:.6},{:.6}",
+                    n + 1, csv_field(path), s.js_distance, s.cosine, s.intersection
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let matches: Vec<String> = ranked.iter().enumerate()
+                .map(|(n, (path, s))| format!(
+                    "    {{\"rank\": {}, \"path\": {}, \"js_distance\": {:.6}, \"cosine_similarity\": {:.6}, \"histogram_intersection\": {:.6}}}",
+                    n + 1, json_str(path), s.js_distance, s.cosine, s.intersection
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"query\": {},", json_str(query_path)),
+                format!("  \"matches\": [\n{}\n  ]", matches.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  query: {}", query_path),
+                String::from(""),
+                format!("  {:>4}  {:>11}  {:>8}  {:>12}  path", "rank", "js distance", "cosine", "intersection"),
+            ];
+            for (n, (path, s)) in ranked.iter().enumerate() {
+                lines.push(format!(
+                    "  {:>4}  {:>11.6}  {:>8.6}  {:>12.6}  {}",
+                    n + 1, s.js_distance, s.cosine, s.intersection, path
+                ));
+            }
+            lines
+        },
+    }
+}
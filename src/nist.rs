@@ -0,0 +1,304 @@
+/*
+    freqs nist: part of the NIST SP 800-22 statistical test suite for
+    random number generators, the frequency, block frequency, runs,
+    approximate entropy and cumulative sums tests, each with its p-value.
+    They're all worked out in one pass over the bits of the file, most
+    significant bit of each byte first, with the suite's usual parameters.
+*/
+
+use std::f64::consts::LN_2;
+use std::fs;
+use std::io::{self, Read};
+
+use args::Options;
+use report::{csv_field, json_str, Format};
+use special;
+
+pub const DEFAULT_SIGNIFICANCE: f64 = 0.01;
+// bits in each block of the block frequency test
+const BLOCK_BITS: u64 = 128;
+// the pattern length of approximate entropy, if the file is big enough
+const PATTERN_BITS: u32 = 10;
+pub const MIN_BYTES: u64 = 128;
+const CHUNKSIZE: usize = 1024 * 128;
+
+pub struct Outcome {
+    pub test: &'static str,
+    pub statistic: f64,
+    pub p: f64,
+}
+
+// everything the tests need, gathered a bit at a time
+pub struct Bits {
+    n: u64,
+    ones: u64,
+    changes: u64,           // places where the next bit differs
+    last: u8,
+    sum: i64,               // of the bits as -1 and +1
+    max: i64,               // of the sums after each bit
+    min: i64,
+    max_before: i64,        // of the sums before each bit, from nothing
+    min_before: i64,
+    block_len: u64,
+    block_fill: u64,
+    block_ones: u64,
+    block_squares: f64,     // sum of (block proportion - 1/2)^2
+    blocks: u64,
+    m: u32,
+    window: usize,          // the last m + 1 bits
+    first: usize,           // the first m bits, to wrap around with
+    short: Vec<u64>,        // overlapping m bit patterns
+    long: Vec<u64>,         // and m + 1 bit ones
+}
+
+impl Bits {
+    pub fn new(block_len: u64, m: u32) -> Bits {
+        Bits {
+            n: 0, ones: 0, changes: 0, last: 0,
+            sum: 0, max: i64::MIN, min: i64::MAX, max_before: 0, min_before: 0,
+            block_len, block_fill: 0, block_ones: 0, block_squares: 0.0, blocks: 0,
+            m, window: 0, first: 0, short: vec![0; 1 << m], long: vec![0; 1 << (m + 1)],
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            for i in (0..8).rev() { self.bit(b >> i & 1); }
+        }
+    }
+
+    pub fn bit(&mut self, bit: u8) {
+        if self.n > 0 && bit != self.last { self.changes += 1; }
+        self.last = bit;
+        self.ones += bit as u64;
+
+        self.max_before = self.max_before.max(self.sum);
+        self.min_before = self.min_before.min(self.sum);
+        self.sum += if bit == 1 { 1 } else { -1 };
+        self.max = self.max.max(self.sum);
+        self.min = self.min.min(self.sum);
+
+        self.block_ones += bit as u64;
+        self.block_fill += 1;
+        if self.block_fill == self.block_len {
+            self.block_squares += (self.block_ones as f64 / self.block_len as f64 - 0.5).powi(2);
+            self.blocks += 1;
+            self.block_fill = 0;
+            self.block_ones = 0;
+        }
+
+        if self.n < self.m as u64 { self.first = self.first << 1 | bit as usize; }
+        self.n += 1;
+        self.pattern(bit, self.n, true);
+    }
+
+    // seen is how many bits have gone through the window
+    fn pattern(&mut self, bit: u8, seen: u64, short: bool) {
+        self.window = (self.window << 1 | bit as usize) & (self.long.len() - 1);
+        if seen > self.m as u64 { self.long[self.window] += 1; }
+        if short && seen >= self.m as u64 { self.short[self.window & ((1 << self.m) - 1)] += 1; }
+    }
+
+    pub fn finish(mut self) -> Vec<Outcome> {
+        let n = self.n as f64;
+        let mut outcomes = Vec::new();
+
+        // frequency
+        let s = self.sum.abs() as f64 / n.sqrt();
+        outcomes.push(Outcome { test: "frequency", statistic: s, p: special::erfc(s / 2f64.sqrt()) });
+
+        // block frequency
+        let chi = 4.0 * self.block_len as f64 * self.block_squares;
+        outcomes.push(Outcome {
+            test: "block frequency", statistic: chi, p: special::igamc(self.blocks as f64 / 2.0, chi / 2.0),
+        });
+
+        // runs, which is only tried when the frequency is close enough
+        let pi = self.ones as f64 / n;
+        let runs = (self.changes + 1) as f64;
+        let p = if (pi - 0.5).abs() >= 2.0 / n.sqrt() {
+            0.0
+        } else {
+            special::erfc((runs - 2.0 * n * pi * (1.0 - pi)).abs() / (2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi)))
+        };
+        outcomes.push(Outcome { test: "runs", statistic: runs, p });
+
+        // approximate entropy. the patterns wrap around to the start
+        let m = self.m;
+        for i in (0..m).rev() {
+            let bit = (self.first >> i & 1) as u8;
+            self.pattern(bit, self.n + (m - i) as u64, i > 0);
+        }
+        let phi = |counts: &[u64]| -> f64 {
+            counts.iter().filter(|c| **c > 0).map(|c| { let p = *c as f64 / n; p * p.ln() }).sum()
+        };
+        let apen = phi(&self.short) - phi(&self.long);
+        let chi = 2.0 * n * (LN_2 - apen);
+        outcomes.push(Outcome { test: "approximate entropy", statistic: chi, p: special::igamc(2f64.powi(m as i32 - 1), chi / 2.0) });
+
+        // cumulative sums, from the start and from the end
+        let forward = self.max.abs().max(self.min.abs());
+        let backward = (self.sum - self.min_before).abs().max((self.sum - self.max_before).abs());
+        outcomes.push(Outcome { test: "cumulative sums forward", statistic: forward as f64, p: cusum_p(self.n as i64, forward) });
+        outcomes.push(Outcome { test: "cumulative sums backward", statistic: backward as f64, p: cusum_p(self.n as i64, backward) });
+        outcomes
+    }
+}
+
+// the suite's sums over the walk's distribution, with its integer division
+fn cusum_p(n: i64, z: i64) -> f64 {
+    if z == 0 { return 1.0; }
+    let root = (n as f64).sqrt();
+    let zf = z as f64;
+    let mut sum1 = 0.0;
+    for k in (-n / z + 1) / 4..=(n / z - 1) / 4 {
+        sum1 += special::normal((4 * k + 1) as f64 * zf / root) - special::normal((4 * k - 1) as f64 * zf / root);
+    }
+    let mut sum2 = 0.0;
+    for k in (-n / z - 3) / 4..=(n / z - 1) / 4 {
+        sum2 += special::normal((4 * k + 3) as f64 * zf / root) - special::normal((4 * k + 1) as f64 * zf / root);
+    }
+    1.0 - sum1 + sum2
+}
+
+// the pattern length for n bits, which the suite keeps below log2(n) - 5
+fn pattern_bits(n: u64) -> u32 {
+    (63 - n.leading_zeros()).saturating_sub(6).clamp(1, PATTERN_BITS)
+}
+
+pub fn test(path: &str) -> io::Result<Option<Vec<Outcome>>> {
+    let mut f = fs::File::open(path)?;
+    let len = f.metadata()?.len();
+    if len < MIN_BYTES { return Ok(None); }
+    let mut bits = Bits::new(BLOCK_BITS, pattern_bits(len * 8));
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    loop {
+        let n = f.read(&mut chunk)?;
+        if n == 0 { break; }
+        bits.feed(&chunk[..n]);
+    }
+    Ok(Some(bits.finish()))
+}
+
+// the lines to print, and whether every test passed
+pub fn run(opts: &Options, format: Format) -> (Vec<String>, bool) {
+    let path = &opts.inputs[0];
+    let outcomes = match test(path) {
+        Ok(Some(o)) => o,
+        Ok(None) => panic!("The tests need at least {} bytes, {} is shorter", MIN_BYTES, path),
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    let significance = opts.significance.unwrap_or(DEFAULT_SIGNIFICANCE);
+    let ok = outcomes.iter().all(|o| o.p >= significance);
+
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("test,statistic,p_value,passed")];
+            for o in &outcomes {
+                lines.push(format!("{},{:.6},{:.6},{}", csv_field(o.test), o.statistic, o.p, o.p >= significance));
+            }
+            lines
+        },
+        Format::Json => {
+            let tests: Vec<String> = outcomes.iter()
+                .map(|o| format!(
+                    "    {{\"test\": {}, \"statistic\": {:.6}, \"p_value\": {:.6}, \"passed\": {}}}",
+                    json_str(o.test), o.statistic, o.p, o.p >= significance
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"path\": {},", json_str(path)),
+                format!("  \"significance\": {},", significance),
+                format!("  \"tests\": [\n{}\n  ],", tests.join(",\n")),
+                format!("  \"passed\": {}", ok),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  nist sp 800-22 tests of {} (significance {})", path, significance),
+                String::from(""),
+                format!("  {:<24}  {:>14}  {:>8}", "test", "statistic", "p-value"),
+            ];
+            for o in &outcomes {
+                lines.push(format!(
+                    "  {:<24}  {:>14.4}  {:>8.6}  {}",
+                    o.test, o.statistic, o.p, if o.p >= significance { "pass" } else { "FAIL" }
+                ));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}", if ok { "passed" } else { "FAILED" }));
+            lines
+        },
+    };
+    (lines, ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the examples from SP 800-22 section 2, with their block length and
+    // pattern length
+    fn outcomes(bits: &str, block_len: u64, m: u32) -> Vec<Outcome> {
+        let mut b = Bits::new(block_len, m);
+        for c in bits.bytes().filter(|c| !c.is_ascii_whitespace()) { b.bit(c - b'0'); }
+        b.finish()
+    }
+
+    fn p(outcomes: &[Outcome], test: &str) -> f64 {
+        outcomes.iter().find(|o| o.test == test).unwrap().p
+    }
+
+    fn close(got: f64, published: f64) {
+        assert!((got - published).abs() < 1e-6, "{} where the suite has {}", got, published);
+    }
+
+    // the first 100 bits of pi, in the longer examples
+    const PI: &str = "11001001000011111101101010100010001000010110100011 00001000110100110001001100011001100010100010111000";
+
+    #[test]
+    fn frequency() {
+        close(p(&outcomes("1011010101", 3, 2), "frequency"), 0.527089);
+        close(p(&outcomes(PI, 10, 2), "frequency"), 0.109599);
+    }
+
+    #[test]
+    fn block_frequency() {
+        let o = outcomes("0110011010", 3, 2);
+        close(o[1].statistic, 1.0);
+        close(p(&o, "block frequency"), 0.801252);
+        let o = outcomes(PI, 10, 2);
+        close(o[1].statistic, 7.2);
+        close(p(&o, "block frequency"), 0.706438);
+    }
+
+    #[test]
+    fn runs() {
+        let o = outcomes("1001101011", 3, 2);
+        assert_eq!(o[2].statistic, 7.0);
+        close(p(&o, "runs"), 0.147232);
+        let o = outcomes(PI, 10, 2);
+        assert_eq!(o[2].statistic, 52.0);
+        close(p(&o, "runs"), 0.500798);
+    }
+
+    #[test]
+    fn approximate_entropy() {
+        close(p(&outcomes("0100110101", 3, 3), "approximate entropy"), 0.261961);
+        close(p(&outcomes(PI, 10, 2), "approximate entropy"), 0.235301);
+    }
+
+    #[test]
+    fn cumulative_sums() {
+        let o = outcomes("1011010111", 3, 2);
+        assert_eq!(o[4].statistic, 4.0);
+        close(p(&o, "cumulative sums forward"), 0.4116588);
+        let o = outcomes(PI, 10, 2);
+        assert_eq!((o[4].statistic, o[5].statistic), (16.0, 19.0));
+        close(p(&o, "cumulative sums forward"), 0.219194);
+        close(p(&o, "cumulative sums backward"), 0.114866);
+    }
+}
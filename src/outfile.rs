@@ -0,0 +1,71 @@
+/*
+    Writing output files. Everything goes to a temporary file next to the
+    one it's for, and is only moved over it once complete, so a crash or a
+    full disk halfway leaves the old file (or none) instead of half a
+    report. Files already there are only replaced with --force.
+*/
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+pub struct Pending {
+    file: BufWriter<File>,
+    tmp: PathBuf,
+    path: PathBuf,
+    done: bool,
+}
+
+pub fn create<P: AsRef<Path>>(path: P, force: bool) -> io::Result<Pending> {
+    let path = path.as_ref().to_path_buf();
+    if !force && path.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "it's already there, pass --force to replace it"));
+    }
+    // the pid keeps two runs writing the same file from mixing
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+    let file = BufWriter::new(File::create(&tmp)?);
+    Ok(Pending { file, tmp, path, done: false })
+}
+
+impl Pending {
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+        fs::rename(&self.tmp, &self.path)?;
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl Write for Pending {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.file.write(buf) }
+    fn flush(&mut self) -> io::Result<()> { self.file.flush() }
+}
+
+// given up on halfway, by an error or a panic
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if !self.done { let _ = fs::remove_file(&self.tmp); }
+    }
+}
+
+pub fn write<P: AsRef<Path>>(path: P, contents: &[u8], force: bool) -> io::Result<()> {
+    let mut out = create(path, force)?;
+    out.write_all(contents)?;
+    out.finish()
+}
+
+// the same for adding to a file: what was there is copied over first, so
+// the file is either as it was or has all of the new part
+pub fn append<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    let mut out = create(&path, true)?;
+    match File::open(&path) {
+        Ok(mut old) => { io::copy(&mut old, &mut out)?; },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e),
+    }
+    out.write_all(contents)?;
+    out.finish()
+}
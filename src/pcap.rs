@@ -0,0 +1,384 @@
+/*
+    --payloads: for packet captures, pcap or pcapng, counts what the
+    packets carry above tcp, udp or whatever else ip has inside, rather
+    than the capture file with all its headers. Either everything together
+    or, with --per-flow, each conversation on its own, which is where
+    encrypted or tunneled traffic shows up. Ethernet (with vlan tags),
+    linux cooked, loopback and raw ip links are understood; ip fragments
+    past the first are left out, having no ports to go by.
+*/
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use args::Options;
+use entropy;
+use report::{self, csv_field, json_str, Format, Report};
+use stats;
+use triage;
+
+// bigger than any real packet, to tell a broken capture from a big one
+const MAX_PACKET: usize = 1 << 24;
+// flows shorter than this can't show the entropy of encrypted data
+const MIN_VERDICT_BYTES: u64 = 4096;
+
+pub const PROTOCOLS: [(&str, u8); 5] = [("tcp", 6), ("udp", 17), ("icmp", 1), ("icmpv6", 58), ("sctp", 132)];
+
+struct Packet<'a> {
+    protocol: u8,
+    src: (IpAddr, u16),
+    dst: (IpAddr, u16),
+    payload: &'a [u8],
+}
+
+// the two ends in order, so both directions are one flow
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Flow {
+    protocol: u8,
+    a: (IpAddr, u16),
+    b: (IpAddr, u16),
+}
+
+struct Counted {
+    flow: Flow,
+    packets: u64,
+    len: u64,
+    counts: [u64; 256],
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let protocol = opts.protocol.as_ref().map(|p| protocol_number(p).unwrap_or(0));
+    let mut flows: HashMap<Flow, Counted> = HashMap::new();
+    let mut total = [0u64; 256];
+    let (mut seen, mut kept) = (0u64, 0u64);
+    let read = frames(BufReader::new(f), |linktype, frame| {
+        seen += 1;
+        let p = match packet(linktype, frame) {
+            Some(p) => p,
+            None => return,
+        };
+        if protocol.is_some_and(|n| n != p.protocol) { return; }
+        if opts.port.is_some_and(|port| p.src.1 != port && p.dst.1 != port) { return; }
+        kept += 1;
+        for b in p.payload { total[*b as usize] += 1; }
+        if opts.per_flow {
+            let (a, b) = if p.src <= p.dst { (p.src, p.dst) } else { (p.dst, p.src) };
+            let flow = Flow { protocol: p.protocol, a, b };
+            let c = flows.entry(flow).or_insert_with(|| Counted { flow, packets: 0, len: 0, counts: [0; 256] });
+            c.packets += 1;
+            c.len += p.payload.len() as u64;
+            for b in p.payload { c.counts[*b as usize] += 1; }
+        }
+    });
+    match read {
+        Ok(true) => (),
+        Ok(false) => panic!("{} isn't a pcap or pcapng capture", path),
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    }
+
+    if opts.per_flow {
+        let mut flows: Vec<Counted> = flows.into_values().collect();
+        flows.sort_by(|x, y| y.len.cmp(&x.len).then(y.packets.cmp(&x.packets)));
+        return render_flows(format, opts, &flows);
+    }
+    let mut lines = Vec::new();
+    if format == Format::Text {
+        let len: u64 = total.iter().sum();
+        lines.push(format!("  {} of {} packets, {} bytes of payload", kept, seen, len));
+    }
+    lines.extend(report::render(format, &Report {
+        counts: &total, meta: None, hashes: Vec::new(), profile: None, regions: None, segments: None, runs: None, first_last: None, dispersion: None, rare: None, rate: None, huffman: None,
+        bars: opts.bars, ci: opts.ci, truncated: false,
+    }));
+    lines
+}
+
+pub fn protocol_number(name: &str) -> Option<u8> {
+    PROTOCOLS.iter().find(|(n, _)| *n == name).map(|(_, p)| *p).or_else(|| name.parse().ok())
+}
+
+fn protocol_name(n: u8) -> String {
+    PROTOCOLS.iter().find(|(_, p)| *p == n).map_or(format!("ip {}", n), |(name, _)| String::from(*name))
+}
+
+// a port of 0 is a protocol without ports
+fn endpoint(end: (IpAddr, u16)) -> String {
+    match end {
+        (addr, 0) => addr.to_string(),
+        (IpAddr::V6(addr), port) => format!("[{}]:{}", addr, port),
+        (addr, port) => format!("{}:{}", addr, port),
+    }
+}
+
+// calls each with the link type and bytes of every frame in the capture,
+// or says it isn't one. a capture cut off partway ends at the cut
+fn frames<R: Read, F: FnMut(u32, &[u8])>(mut r: R, mut each: F) -> io::Result<bool> {
+    let mut magic = [0u8; 4];
+    if read_full(&mut r, &mut magic)? < 4 { return Ok(false); }
+    match magic {
+        [0xd4, 0xc3, 0xb2, 0xa1] | [0x4d, 0x3c, 0xb2, 0xa1] => pcap(r, false, &mut each)?,
+        [0xa1, 0xb2, 0xc3, 0xd4] | [0xa1, 0xb2, 0x3c, 0x4d] => pcap(r, true, &mut each)?,
+        [0x0a, 0x0d, 0x0d, 0x0a] => pcapng(r, &mut each)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn pcap<R: Read, F: FnMut(u32, &[u8])>(mut r: R, big: bool, each: &mut F) -> io::Result<()> {
+    let mut header = [0u8; 20];
+    if read_full(&mut r, &mut header)? < 20 { return Ok(()); }
+    // the top bits can hold the fcs length
+    let linktype = u32_at(&header, 16, big) & 0xffff;
+    let mut record = [0u8; 16];
+    let mut frame = Vec::new();
+    loop {
+        if read_full(&mut r, &mut record)? < 16 { return Ok(()); }
+        let len = u32_at(&record, 8, big) as usize;
+        if len > MAX_PACKET { return Err(bad("a packet longer than 16M")); }
+        frame.resize(len, 0);
+        if read_full(&mut r, &mut frame)? < len { return Ok(()); }
+        each(linktype, &frame);
+    }
+}
+
+fn pcapng<R: Read, F: FnMut(u32, &[u8])>(mut r: R, each: &mut F) -> io::Result<()> {
+    // the section header's type is already read, and its byte order magic
+    // decides how the rest of the section reads
+    let mut block_type = 0x0a0d0d0a;
+    let mut big = false;
+    let mut linktypes: Vec<u32> = Vec::new();
+    let mut body = Vec::new();
+    loop {
+        let mut head = [0u8; 4];
+        if read_full(&mut r, &mut head)? < 4 { return Ok(()); }
+        if block_type == 0x0a0d0d0a {
+            let mut magic = [0u8; 4];
+            if read_full(&mut r, &mut magic)? < 4 { return Ok(()); }
+            big = match magic {
+                [0x1a, 0x2b, 0x3c, 0x4d] => true,
+                [0x4d, 0x3c, 0x2b, 0x1a] => false,
+                _ => return Err(bad("a section header with no byte order")),
+            };
+            linktypes.clear();
+        }
+        let len = u32_at(&head, 0, big) as usize;
+        let read_already = if block_type == 0x0a0d0d0a { 12 } else { 8 };
+        if len < read_already + 4 || len > MAX_PACKET { return Err(bad("a block of impossible length")); }
+        body.resize(len - read_already, 0);
+        if read_full(&mut r, &mut body)? < body.len() { return Ok(()); }
+        // the block's length again comes last
+        let body = &body[..body.len() - 4];
+        match block_type {
+            // interface description
+            1 if body.len() >= 2 => linktypes.push(u16_at(body, 0, big) as u32),
+            // enhanced and obsolete packets
+            6 | 2 if body.len() >= 20 => {
+                let interface = if block_type == 6 { u32_at(body, 0, big) as usize } else { u16_at(body, 0, big) as usize };
+                let caplen = (u32_at(body, 12, big) as usize).min(body.len() - 20);
+                if let Some(linktype) = linktypes.get(interface) { each(*linktype, &body[20..20 + caplen]); }
+            },
+            // simple packets, always on the first interface
+            3 if body.len() >= 4 => {
+                let caplen = (u32_at(body, 0, big) as usize).min(body.len() - 4);
+                if let Some(linktype) = linktypes.first() { each(*linktype, &body[4..4 + caplen]); }
+            },
+            _ => (),
+        }
+        let mut next = [0u8; 4];
+        if read_full(&mut r, &mut next)? < 4 { return Ok(()); }
+        block_type = if next == [0x0a, 0x0d, 0x0d, 0x0a] { 0x0a0d0d0a } else { u32_at(&next, 0, big) };
+    }
+}
+
+// the ip packet inside a frame, whatever the link
+fn packet(linktype: u32, frame: &[u8]) -> Option<Packet<'_>> {
+    match linktype {
+        // ethernet
+        1 => {
+            let mut at = 12;
+            let mut ethertype = u16_at(frame.get(..at + 2)?, at, true);
+            while ethertype == 0x8100 || ethertype == 0x88a8 {
+                at += 4;
+                ethertype = u16_at(frame.get(..at + 2)?, at, true);
+            }
+            ip(frame.get(at + 2..)?)
+        },
+        // bsd loopback, the family in the capturing host's byte order or
+        // in network order
+        0 | 108 => match frame.get(..4)? {
+            [2, 0, 0, 0] | [0, 0, 0, 2] => ip(&frame[4..]),
+            [24, 0, 0, 0] | [28, 0, 0, 0] | [30, 0, 0, 0] | [0, 0, 0, 24] | [0, 0, 0, 28] | [0, 0, 0, 30] => ip(&frame[4..]),
+            _ => None,
+        },
+        // raw ip, and raw ipv4 and ipv6
+        12 | 14 | 101 | 228 | 229 => ip(frame),
+        // linux cooked
+        113 => ip(frame.get(16..)?),
+        276 => ip(frame.get(20..)?),
+        _ => None,
+    }
+}
+
+fn ip(data: &[u8]) -> Option<Packet<'_>> {
+    match data.first()? >> 4 {
+        4 => {
+            let ihl = (data[0] & 0xf) as usize * 4;
+            if ihl < 20 || data.len() < ihl { return None; }
+            if u16_at(data, 6, true) & 0x1fff != 0 { return None; }
+            // ethernet pads short packets past the ip length
+            let end = (u16_at(data, 2, true) as usize).clamp(ihl, data.len());
+            let src = IpAddr::V4(Ipv4Addr::new(data[12], data[13], data[14], data[15]));
+            let dst = IpAddr::V4(Ipv4Addr::new(data[16], data[17], data[18], data[19]));
+            transport(data[9], src, dst, &data[ihl..end])
+        },
+        6 => {
+            if data.len() < 40 { return None; }
+            let end = (40 + u16_at(data, 4, true) as usize).min(data.len());
+            let src: [u8; 16] = data[8..24].try_into().ok()?;
+            let dst: [u8; 16] = data[24..40].try_into().ok()?;
+            let (src, dst) = (IpAddr::V6(Ipv6Addr::from(src)), IpAddr::V6(Ipv6Addr::from(dst)));
+            let (mut next, mut at) = (data[6], 40);
+            // hop by hop, routing, fragment, authentication and destination
+            // options headers come before the protocol's
+            loop {
+                match next {
+                    0 | 43 | 60 => {
+                        let h = data.get(at..at + 2)?;
+                        next = h[0];
+                        at += (h[1] as usize + 1) * 8;
+                    },
+                    44 => {
+                        let h = data.get(at..at + 8)?;
+                        if u16_at(h, 2, true) & 0xfff8 != 0 { return None; }
+                        next = h[0];
+                        at += 8;
+                    },
+                    51 => {
+                        let h = data.get(at..at + 2)?;
+                        next = h[0];
+                        at += (h[1] as usize + 2) * 4;
+                    },
+                    _ => break,
+                }
+            }
+            transport(next, src, dst, data.get(at..end)?)
+        },
+        _ => None,
+    }
+}
+
+fn transport(protocol: u8, src: IpAddr, dst: IpAddr, segment: &[u8]) -> Option<Packet<'_>> {
+    // too short for the ports, never mind the rest of the header
+    if matches!(protocol, 6 | 17 | 132) && segment.len() < 4 { return None; }
+    let port = |at: usize| u16_at(segment, at, true);
+    let (sport, dport, payload) = match protocol {
+        6 => {
+            let offset = (*segment.get(12)? >> 4) as usize * 4;
+            (port(0), port(2), segment.get(offset.max(20)..)?)
+        },
+        17 => (port(0), port(2), segment.get(8..)?),
+        // sctp's common header, the chunks after it taken as they are
+        132 => (port(0), port(2), segment.get(12..)?),
+        _ => (0, 0, segment),
+    };
+    Some(Packet { protocol, src: (src, sport), dst: (dst, dport), payload })
+}
+
+fn render_flows(format: Format, opts: &Options, flows: &[Counted]) -> Vec<String> {
+    let encrypted_above = opts.encrypted_above.unwrap_or(triage::DEFAULT_ENCRYPTED_ABOVE);
+    let entropy = |c: &Counted| entropy::shannon(&c.counts, c.len);
+    let verdict = |c: &Counted| {
+        if c.len >= MIN_VERDICT_BYTES && entropy(c) >= encrypted_above { "encrypted?" }
+        else if c.len > 0 && stats::printable_ratio(&c.counts) >= 0.95 { "text" }
+        else { "" }
+    };
+    let name = |c: &Counted| format!("{} <-> {}", endpoint(c.flow.a), endpoint(c.flow.b));
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("protocol,a,b,packets,bytes,entropy,printable,verdict")];
+            for c in flows {
+                lines.push(format!(
+                    "{},{},{},{},{},{:.6},{:.6},{}",
+                    protocol_name(c.flow.protocol), csv_field(&endpoint(c.flow.a)), csv_field(&endpoint(c.flow.b)),
+                    c.packets, c.len, entropy(c), stats::printable_ratio(&c.counts), verdict(c)
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = flows.iter()
+                .map(|c| format!(
+                    "    {{\"protocol\": {}, \"a\": {}, \"b\": {}, \"packets\": {}, \"bytes\": {}, \"entropy\": {:.6}, \"printable\": {:.6}, \"verdict\": {}}}",
+                    json_str(&protocol_name(c.flow.protocol)), json_str(&endpoint(c.flow.a)), json_str(&endpoint(c.flow.b)),
+                    c.packets, c.len, entropy(c), stats::printable_ratio(&c.counts), json_str(verdict(c))
+                ))
+                .collect();
+            vec![format!("{{\n  \"flows\": [\n{}\n  ]\n}}", entries.join(",\n"))]
+        },
+        _ => {
+            let width = flows.iter().map(|c| name(c).chars().count()).max().unwrap_or(0).max(4);
+            let mut lines = vec![
+                format!("  {} flows", flows.len()),
+                format!("  {:<8}  {:<w$}  {:>8}  {:>12}  {:>7}  {:>9}", "protocol", "flow", "packets", "bytes", "entropy", "printable", w = width),
+            ];
+            for c in flows {
+                lines.push(format!(
+                    "  {:<8}  {:<w$}  {:>8}  {:>12}  {:>7.4}  {:>8.1}%  {}",
+                    protocol_name(c.flow.protocol), name(c), c.packets, c.len, entropy(c),
+                    stats::printable_ratio(&c.counts) * 100.0, verdict(c), w = width
+                ));
+            }
+            lines
+        },
+    }
+}
+
+// fills buf as far as the reader goes, for telling the end from a cut
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut got = 0;
+    while got < buf.len() {
+        let n = r.read(&mut buf[got..])?;
+        if n == 0 { break; }
+        got += n;
+    }
+    Ok(got)
+}
+
+fn u16_at(b: &[u8], at: usize, big: bool) -> u16 {
+    let bytes = [b[at], b[at + 1]];
+    if big { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+}
+
+fn u32_at(b: &[u8], at: usize, big: bool) -> u32 {
+    let bytes = [b[at], b[at + 1], b[at + 2], b[at + 3]];
+    if big { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("the capture has {}", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_segments() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        for protocol in [6, 17, 132] {
+            for len in 0..4 {
+                assert!(transport(protocol, ip, ip, &[0; 4][..len]).is_none());
+            }
+        }
+        // no ports to read for the others
+        assert!(transport(1, ip, ip, &[]).is_some());
+    }
+}
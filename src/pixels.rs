@@ -0,0 +1,367 @@
+/*
+    --pixels: for png, bmp and the netpbm formats (pgm, ppm), decodes the
+    image and counts the values of each channel instead of the bytes of the
+    file, which for a png are mostly deflate output. Every channel gets its
+    own table of 0 to 255; deeper samples are cut to their top 8 bits and
+    smaller ones (1, 2 or 4 bit gray, netpbm's maxval) scaled up to it.
+*/
+
+use std::fs;
+use std::io;
+
+use entropy;
+use args::Options;
+use inflate;
+use report::{json_str, sparkline, Format};
+
+struct Image {
+    format: &'static str,
+    width: u64,
+    height: u64,
+    names: &'static [&'static str],
+    channels: Vec<[u64; 256]>,
+}
+
+impl Image {
+    fn new(format: &'static str, width: u64, height: u64, names: &'static [&'static str]) -> Image {
+        Image { format, width, height, names, channels: vec![[0; 256]; names.len()] }
+    }
+
+    fn pixel(&mut self, values: &[u8]) {
+        for (c, v) in self.channels.iter_mut().zip(values.iter()) { c[*v as usize] += 1; }
+    }
+}
+
+const GRAY: &[&str] = &["gray"];
+const GRAY_ALPHA: &[&str] = &["gray", "alpha"];
+const RGB: &[&str] = &["red", "green", "blue"];
+const RGBA: &[&str] = &["red", "green", "blue", "alpha"];
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let data = match fs::read(path) {
+        Ok(d) => d,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let image = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png(&data)
+    } else if data.starts_with(b"BM") {
+        bmp(&data)
+    } else if data.len() > 2 && data[0] == b'P' && (b'1'..=b'6').contains(&data[1]) {
+        netpbm(&data)
+    } else {
+        panic!("{} isn't a png, bmp, pgm or ppm image", path)
+    };
+    match image {
+        Ok(image) => render(format, &image),
+        Err(e) => panic!("Could not decode {}: {}", path, e),
+    }
+}
+
+fn png(data: &[u8]) -> io::Result<Image> {
+    let (mut at, mut ihdr, mut palette, mut alpha, mut idat) = (8, None, Vec::new(), Vec::new(), Vec::new());
+    while at + 8 <= data.len() {
+        let len = be32(data, at) as usize;
+        let body = data.get(at + 8..at + 8 + len).ok_or_else(|| bad("a chunk runs past the end"))?;
+        match &data[at + 4..at + 8] {
+            b"IHDR" if len >= 13 => ihdr = Some(body.to_vec()),
+            b"PLTE" => palette = body.to_vec(),
+            b"tRNS" => alpha = body.to_vec(),
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            _ => (),
+        }
+        // the crc comes after the data
+        at += 12 + len;
+    }
+    let ihdr = ihdr.ok_or_else(|| bad("no IHDR chunk"))?;
+    let (width, height) = (be32(&ihdr, 0) as u64, be32(&ihdr, 4) as u64);
+    let (depth, color, interlaced) = (ihdr[8] as u64, ihdr[9], ihdr[12] == 1);
+    let (names, samples) = match color {
+        0 => (GRAY, 1),
+        2 => (RGB, 3),
+        3 if !alpha.is_empty() => (RGBA, 1),
+        3 => (RGB, 1),
+        4 => (GRAY_ALPHA, 2),
+        6 => (RGBA, 4),
+        _ => return Err(bad("an unknown color type")),
+    };
+    let valid = match color {
+        0 => [1, 2, 4, 8, 16].contains(&depth),
+        3 => [1, 2, 4, 8].contains(&depth),
+        _ => [8, 16].contains(&depth),
+    };
+    if !valid { return Err(bad("a bit depth the color type can't have")); }
+    if width == 0 || height == 0 || width * samples * depth > 1 << 32 { return Err(bad("impossible dimensions")); }
+
+    let mut image = Image::new("png", width, height, names);
+    let mut rows = Rows::new(width, height, samples * depth, interlaced);
+    let mut zlib = idat.get(2..).ok_or_else(|| bad("no image data"))?;
+    inflate::inflate(&mut zlib, &mut |chunk| rows.feed(chunk, &mut |row, pixels| {
+        let mut values = [0u8; 4];
+        for x in 0..pixels {
+            let sample = |i: u64| -> u8 {
+                match depth {
+                    8 => row[(x * samples + i) as usize],
+                    16 => row[((x * samples + i) * 2) as usize],
+                    _ => {
+                        let bit = x * depth;
+                        let v = row[(bit / 8) as usize] >> (8 - depth - bit % 8) & ((1 << depth) - 1) as u8;
+                        if color == 0 { (v as u64 * 255 / ((1 << depth) - 1)) as u8 } else { v }
+                    },
+                }
+            };
+            if color == 3 {
+                let index = sample(0) as usize;
+                let rgb = palette.get(index * 3..index * 3 + 3).unwrap_or(&[0, 0, 0]);
+                values[..3].copy_from_slice(rgb);
+                values[3] = alpha.get(index).cloned().unwrap_or(255);
+            } else {
+                for i in 0..samples { values[i as usize] = sample(i); }
+            }
+            image.pixel(&values);
+        }
+    }))?;
+    if let Some(e) = rows.error { return Err(bad(e)); }
+    Ok(image)
+}
+
+// undoes png's filters a scanline at a time, over each pass of an
+// interlaced image in turn
+struct Rows {
+    passes: Vec<(u64, u64)>,    // pixels across and rows of each pass
+    bits: u64,                  // per pixel
+    pass: usize,
+    row: u64,
+    pending: Vec<u8>,
+    previous: Vec<u8>,
+    error: Option<&'static str>,
+}
+
+impl Rows {
+    fn new(width: u64, height: u64, bits: u64, interlaced: bool) -> Rows {
+        let passes = if interlaced {
+            // adam7: where each pass starts and how far apart its pixels are
+            [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)].iter()
+                .map(|&(x, y, dx, dy)| ((width + dx - 1 - x) / dx, (height + dy - 1 - y) / dy))
+                .collect()
+        } else {
+            vec![(width, height)]
+        };
+        Rows { passes, bits, pass: 0, row: 0, pending: Vec::new(), previous: Vec::new(), error: None }
+    }
+
+    fn feed(&mut self, chunk: &[u8], each: &mut dyn FnMut(&[u8], u64)) {
+        self.pending.extend_from_slice(chunk);
+        let mut used = 0;
+        loop {
+            // passes can be empty in small images
+            while self.pass < self.passes.len() && (self.passes[self.pass].0 == 0 || self.row == self.passes[self.pass].1) {
+                self.pass += 1;
+                self.row = 0;
+                self.previous.clear();
+            }
+            if self.pass == self.passes.len() || self.error.is_some() { break; }
+            let pixels = self.passes[self.pass].0;
+            let len = (pixels * self.bits).div_ceil(8) as usize;
+            if self.pending.len() - used < len + 1 { break; }
+            let filter = self.pending[used];
+            let mut row = self.pending[used + 1..used + 1 + len].to_vec();
+            used += len + 1;
+            if self.previous.len() != len { self.previous = vec![0; len]; }
+            let bpp = self.bits.div_ceil(8) as usize;
+            for i in 0..len {
+                let a = if i >= bpp { row[i - bpp] as i16 } else { 0 };
+                let b = self.previous[i] as i16;
+                let c = if i >= bpp { self.previous[i - bpp] as i16 } else { 0 };
+                let add = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => (a + b) / 2,
+                    4 => {
+                        let p = a + b - c;
+                        let (pa, pb, pc) = ((p - a).abs(), (p - b).abs(), (p - c).abs());
+                        if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+                    },
+                    _ => { self.error = Some("an unknown filter"); 0 },
+                };
+                row[i] = row[i].wrapping_add(add as u8);
+            }
+            each(&row, pixels);
+            self.previous = row;
+            self.row += 1;
+        }
+        self.pending.drain(..used);
+    }
+}
+
+fn bmp(data: &[u8]) -> io::Result<Image> {
+    if data.len() < 54 { return Err(bad("a short header")); }
+    let offset = le32(data, 10) as usize;
+    let header = le32(data, 14) as usize;
+    let width = le32(data, 18) as i32;
+    let height = le32(data, 22) as i32;
+    let bits = u16::from_le_bytes([data[28], data[29]]) as u64;
+    let compression = le32(data, 30);
+    if width <= 0 || height == 0 { return Err(bad("impossible dimensions")); }
+    // 32 bit pixels only have alpha when bitfields say so
+    let alpha = compression == 3 && bits == 32 && header >= 56 && data.len() >= 70
+        && [le32(data, 54), le32(data, 58), le32(data, 62), le32(data, 66)] == [0xff0000, 0xff00, 0xff, 0xff000000];
+    if compression != 0 && !(compression == 3 && bits == 32) { return Err(bad("compression, only uncompressed bmp is read")); }
+    let (width, height) = (width as u64, height.unsigned_abs() as u64);
+    let names = if alpha { RGBA } else { RGB };
+    let palette = data.get(14 + header..offset).unwrap_or(&[]);
+    // rows are padded to 4 bytes
+    let stride = ((width * bits).div_ceil(32) * 4) as usize;
+    let mut image = Image::new("bmp", width, height, names);
+    for y in 0..height as usize {
+        let row = data.get(offset + y * stride..offset + (y + 1) * stride).ok_or_else(|| bad("pixels past the end"))?;
+        for x in 0..width as usize {
+            let values = match bits {
+                24 | 32 => {
+                    let p = &row[x * bits as usize / 8..];
+                    [p[2], p[1], p[0], if bits == 32 { p[3] } else { 255 }]
+                },
+                1 | 4 | 8 => {
+                    let bit = x * bits as usize;
+                    let index = (row[bit / 8] >> (8 - bits as usize - bit % 8) & ((1u16 << bits) - 1) as u8) as usize;
+                    let p = palette.get(index * 4..index * 4 + 4).ok_or_else(|| bad("an index past its palette"))?;
+                    [p[2], p[1], p[0], 255]
+                },
+                _ => return Err(bad("a bit depth other than 1, 4, 8, 24 or 32")),
+            };
+            image.pixel(&values);
+        }
+    }
+    Ok(image)
+}
+
+// pbm, pgm and ppm, in ascii or binary
+fn netpbm(data: &[u8]) -> io::Result<Image> {
+    let kind = data[1];
+    let mut at = 2;
+    let number = |at: &mut usize| -> io::Result<u64> {
+        loop {
+            match data.get(*at) {
+                Some(b'#') => while data.get(*at).is_some_and(|b| *b != b'\n') { *at += 1; },
+                Some(b) if b.is_ascii_whitespace() => *at += 1,
+                _ => break,
+            }
+        }
+        let start = *at;
+        while data.get(*at).is_some_and(|b| b.is_ascii_digit()) { *at += 1; }
+        std::str::from_utf8(&data[start..*at]).ok().and_then(|s| s.parse().ok()).ok_or_else(|| bad("a broken header"))
+    };
+    let (width, height) = (number(&mut at)?, number(&mut at)?);
+    let maxval = if kind == b'1' || kind == b'4' { 1 } else { number(&mut at)? };
+    if width == 0 || height == 0 || maxval == 0 || maxval > 65535 { return Err(bad("impossible dimensions")); }
+    let (format, names) = match kind {
+        b'1' | b'4' => ("pbm", GRAY),
+        b'2' | b'5' => ("pgm", GRAY),
+        _ => ("ppm", RGB),
+    };
+    let mut image = Image::new(format, width, height, names);
+    let samples = width * height * names.len() as u64;
+    // pbm's 1 is black
+    let scale = |v: u64| if kind == b'1' || kind == b'4' { (1 - v.min(1)) as u8 * 255 } else { (v.min(maxval) * 255 / maxval) as u8 };
+    let mut values = Vec::with_capacity(samples as usize);
+    match kind {
+        // pbm's ascii pixels need no space between them
+        b'1' => for _ in 0..samples {
+            while data.get(at).is_some_and(|b| b.is_ascii_whitespace()) { at += 1; }
+            let bit = data.get(at).filter(|b| **b == b'0' || **b == b'1').ok_or_else(|| bad("pixels past the end"))?;
+            values.push(scale((*bit - b'0') as u64));
+            at += 1;
+        },
+        b'2' | b'3' => for _ in 0..samples { values.push(scale(number(&mut at)?)); },
+        b'4' => {
+            at += 1;
+            let stride = width.div_ceil(8) as usize;
+            for y in 0..height as usize {
+                let row = data.get(at + y * stride..at + (y + 1) * stride).ok_or_else(|| bad("pixels past the end"))?;
+                for x in 0..width as usize { values.push(scale((row[x / 8] >> (7 - x % 8) & 1) as u64)); }
+            }
+        },
+        _ => {
+            // a single whitespace byte ends the header
+            at += 1;
+            let wide = maxval > 255;
+            let body = data.get(at..at + samples as usize * if wide { 2 } else { 1 }).ok_or_else(|| bad("pixels past the end"))?;
+            if wide {
+                for s in body.chunks(2) { values.push(scale(u16::from_be_bytes([s[0], s[1]]) as u64)); }
+            } else {
+                for s in body { values.push(scale(*s as u64)); }
+            }
+        },
+    }
+    for pixel in values.chunks(names.len()) { image.pixel(pixel); }
+    Ok(image)
+}
+
+fn render(format: Format, image: &Image) -> Vec<String> {
+    let pixels = image.width * image.height;
+    let mean = |c: &[u64; 256]| c.iter().enumerate().map(|(v, n)| v as f64 * *n as f64).sum::<f64>() / pixels as f64;
+    let min = |c: &[u64; 256]| c.iter().position(|n| *n > 0).unwrap_or(0);
+    let max = |c: &[u64; 256]| c.iter().rposition(|n| *n > 0).unwrap_or(0);
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("channel,pixels,mean,min,max,entropy")];
+            for (name, c) in image.names.iter().zip(image.channels.iter()) {
+                lines.push(format!("{},{},{:.6},{},{},{:.6}", name, pixels, mean(c), min(c), max(c), entropy::shannon(c, pixels)));
+            }
+            // then every channel's counts, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("channel,value,count"));
+            for (name, c) in image.names.iter().zip(image.channels.iter()) {
+                for (v, n) in c.iter().enumerate() {
+                    if *n != 0 { lines.push(format!("{},{},{}", name, v, n)); }
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = image.names.iter().zip(image.channels.iter())
+                .map(|(name, c)| {
+                    let values: Vec<String> = c.iter().enumerate()
+                        .filter(|&(_, n)| *n != 0)
+                        .map(|(v, n)| format!("{{\"value\": {}, \"count\": {}}}", v, n))
+                        .collect();
+                    format!(
+                        "    {{\"channel\": {}, \"mean\": {:.6}, \"min\": {}, \"max\": {}, \"entropy\": {:.6}, \"values\": [{}]}}",
+                        json_str(name), mean(c), min(c), max(c), entropy::shannon(c, pixels), values.join(", ")
+                    )
+                })
+                .collect();
+            vec![format!(
+                "{{\n  \"format\": \"{}\",\n  \"width\": {},\n  \"height\": {},\n  \"channels\": [\n{}\n  ]\n}}",
+                image.format, image.width, image.height, entries.join(",\n")
+            )]
+        },
+        _ => {
+            let mut lines = vec![
+                format!("  {} image, {} x {}, {} pixels", image.format, image.width, image.height, pixels),
+                format!("  {:<7}  {:>8}  {:>4}  {:>4}  {:>7}", "channel", "mean", "min", "max", "entropy"),
+            ];
+            for (name, c) in image.names.iter().zip(image.channels.iter()) {
+                lines.push(format!(
+                    "  {:<7}  {:>8.3}  {:>4}  {:>4}  {:>7.4}  {}",
+                    name, mean(c), min(c), max(c), entropy::shannon(c, pixels), sparkline(c)
+                ));
+            }
+            lines
+        },
+    }
+}
+
+fn be32(b: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn le32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("it has {}", what))
+}
@@ -0,0 +1,68 @@
+/*
+    Minimal PNG encoder. Only writes 8-bit RGB images, and doesn't bother
+    compressing anything (the zlib stream is made of stored blocks), which
+    keeps things dependency free at the cost of some file size.
+*/
+
+use std::io::{self, Write};
+
+use digest::{Adler32, Crc32};
+use outfile;
+
+// pixels are tightly packed rgb triplets, row by row
+pub fn write_rgb(path: &str, width: u32, height: u32, pixels: &[u8], force: bool) -> io::Result<()> {
+    // every scanline gets prefixed with filter type 0 (none)
+    let row = width as usize * 3;
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for line in pixels.chunks(row) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // bit depth, colour type (rgb), compression, filter, interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut f = outfile::create(path, force)?;
+    f.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(&mut f, b"IHDR", &ihdr)?;
+    write_chunk(&mut f, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(&mut f, b"IEND", &[])?;
+    f.finish()
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    w.write_all(&crc.finish().to_be_bytes())
+}
+
+// wraps data in a zlib stream without actually compressing it
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 0xffff * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    if data.is_empty() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    let mut blocks = data.chunks(0xffff).peekable();
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8); // BFINAL on the last block
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    let mut adler = Adler32::new();
+    adler.update(data);
+    out.extend_from_slice(&adler.finish().to_be_bytes());
+    out
+}
@@ -0,0 +1,154 @@
+/*
+    Bundled byte distributions of common kinds of content, for classify.
+    Each table is in parts per million, averaged over a handful of real
+    samples of that kind:
+
+        english text    license texts (gpl-3, apache-2.0, gfdl-1.3, mpl-2.0)
+        source code     c headers, python modules and rust, weighted evenly
+        x86-64 code     .text sections of a few glibc-linked binaries
+        jpeg            photos and diagrams
+        zip             deflated archives of text and binaries
+        pdf             a couple of text heavy manuals
+
+    Random (or encrypted) data needs no table, it's uniform.
+*/
+
+pub struct ContentProfile {
+    pub name: &'static str,
+    ppm: Option<&'static [u32; 256]>,   // None for uniform
+}
+
+impl ContentProfile {
+    pub fn proportions(&self) -> [f64; 256] {
+        let mut p = [1.0 / 256.0; 256];
+        if let Some(table) = self.ppm {
+            let total: u32 = table.iter().sum();
+            for (byte, c) in table.iter().enumerate() { p[byte] = *c as f64 / total as f64; }
+        }
+        p
+    }
+}
+
+pub const PROFILES: [ContentProfile; 7] = [
+    ContentProfile { name: "random / encrypted", ppm: None },
+    ContentProfile { name: "english text", ppm: Some(&ENGLISH) },
+    ContentProfile { name: "source code", ppm: Some(&SOURCE) },
+    ContentProfile { name: "x86-64 code", ppm: Some(&X86_64) },
+    ContentProfile { name: "jpeg image", ppm: Some(&JPEG) },
+    ContentProfile { name: "zip archive", ppm: Some(&ZIP) },
+    ContentProfile { name: "pdf document", ppm: Some(&PDF) },
+];
+
+const ENGLISH: [u32; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 19727, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    183561, 22, 3107, 0, 0, 37, 0, 370, 1703, 1810, 5381, 0, 9973, 6066, 7202, 535,
+    769, 1027, 774, 543, 243, 273, 160, 146, 110, 124, 402, 401, 82, 508, 82, 0,
+    0, 2042, 475, 3714, 2262, 2277, 1381, 909, 663, 3015, 51, 87, 4127, 1138, 2131, 1700,
+    1396, 32, 1359, 3599, 3045, 874, 560, 1655, 152, 2283, 65, 66, 0, 66, 0, 0,
+    28, 47889, 11149, 28885, 24354, 84512, 17768, 10353, 27878, 61825, 590, 3806, 23343, 16778, 53570, 66079,
+    14649, 829, 52352, 43705, 68308, 22091, 7805, 8650, 2078, 14211, 256, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+const SOURCE: [u32; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 4586, 27427, 0, 6, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    241650, 890, 6863, 4127, 61, 399, 1463, 4224, 13616, 13631, 7651, 802, 12431, 4083, 11198, 6239,
+    4463, 3794, 2800, 1441, 2033, 1206, 2598, 640, 1150, 726, 7843, 4215, 1411, 5705, 2920, 241,
+    123, 4337, 1213, 4354, 2901, 7154, 2253, 1793, 1461, 3888, 290, 551, 3282, 2830, 4879, 3987,
+    3286, 117, 5578, 6501, 5211, 2590, 1118, 1078, 1364, 766, 216, 1558, 1567, 1549, 113, 22099,
+    244, 30416, 8258, 17693, 18581, 62345, 19507, 8364, 11820, 35491, 902, 2858, 22697, 14220, 36326, 30639,
+    15458, 1011, 33158, 34585, 45340, 15016, 4240, 5136, 4676, 6724, 1502, 3569, 1089, 3569, 19, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+    0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+const X86_64: [u32; 256] = [
+    135468, 19718, 6834, 3923, 6981, 9068, 2739, 2767, 9655, 2460, 1828, 1442, 2512, 2246, 1259, 37958,
+    6711, 1484, 803, 861, 2084, 2078, 984, 953, 4347, 778, 583, 577, 1053, 1223, 570, 10728,
+    4102, 709, 616, 570, 23180, 2571, 516, 612, 4666, 3257, 568, 1475, 1052, 1151, 2401, 1000,
+    3048, 8892, 532, 563, 1090, 2558, 486, 523, 2550, 4771, 755, 1233, 1884, 3831, 628, 1043,
+    5368, 14394, 1118, 2080, 15333, 5002, 982, 1489, 64432, 9972, 937, 813, 15898, 4149, 672, 702,
+    2164, 607, 473, 2392, 4039, 2936, 1278, 1281, 1364, 479, 434, 2636, 3031, 3414, 1297, 1192,
+    1236, 571, 768, 2027, 2645, 590, 9239, 548, 1104, 643, 527, 638, 1375, 623, 714, 1893,
+    1332, 412, 878, 1385, 9293, 5343, 883, 1018, 1761, 604, 441, 882, 3052, 1250, 1275, 1556,
+    5748, 2162, 811, 15518, 12886, 15772, 899, 1205, 2392, 37592, 401, 26111, 724, 11656, 880, 825,
+    2814, 393, 520, 569, 1570, 938, 434, 535, 983, 410, 383, 343, 794, 490, 388, 608,
+    780, 479, 380, 452, 488, 429, 341, 365, 1044, 481, 473, 496, 724, 412, 413, 484,
+    738, 432, 384, 424, 678, 547, 3874, 668, 2324, 1190, 2551, 847, 979, 1012, 2238, 1854,
+    12160, 3949, 3129, 5596, 3476, 3069, 3797, 6227, 2099, 2476, 1179, 719, 752, 748, 838, 852,
+    2550, 1268, 3064, 1048, 692, 888, 856, 1068, 1702, 972, 945, 1974, 626, 786, 1529, 2482,
+    2007, 1221, 1494, 798, 1210, 846, 1305, 1675, 17644, 8967, 1522, 3838, 2347, 2161, 1746, 2845,
+    2239, 1182, 1239, 2092, 1187, 1325, 4261, 2744, 4206, 2407, 3324, 3099, 2807, 4206, 7209, 60220,
+];
+
+const JPEG: [u32; 256] = [
+    25724, 3951, 3320, 3585, 3152, 3460, 3004, 3831, 3337, 3109, 3893, 2931, 3274, 3383, 3523, 3575,
+    2831, 3618, 3317, 3286, 5580, 4020, 3016, 3116, 3943, 3732, 3684, 3631, 4269, 3778, 3640, 4285,
+    8329, 2900, 3321, 4335, 3711, 3573, 3341, 3750, 5682, 3547, 3845, 3882, 3490, 3454, 3622, 3406,
+    3282, 3942, 3842, 3299, 3821, 4100, 3555, 3306, 3932, 4307, 3842, 3459, 4159, 3558, 3771, 4975,
+    3601, 3278, 3194, 3481, 3175, 5403, 4050, 4474, 3740, 3859, 3518, 3768, 2985, 3627, 3760, 4310,
+    3862, 5603, 3782, 3717, 3615, 3717, 4074, 3967, 3527, 3448, 4074, 4275, 4085, 3626, 3088, 3576,
+    3055, 3379, 3457, 4445, 3465, 3772, 3255, 3589, 3568, 4064, 3853, 4192, 3382, 4226, 3711, 4057,
+    3449, 4467, 3937, 4295, 3707, 3788, 3472, 3681, 3963, 3902, 3420, 3660, 3351, 3540, 3244, 5936,
+    3799, 2931, 3421, 3237, 3319, 3354, 3659, 3260, 3386, 3527, 5675, 3246, 4537, 4008, 4516, 4418,
+    3348, 4204, 3967, 3452, 3716, 3877, 3833, 3681, 3345, 3128, 4050, 3271, 3815, 3449, 4012, 4144,
+    3680, 3300, 5059, 4080, 3799, 4130, 3404, 4059, 3594, 3882, 3480, 3749, 3569, 4564, 4391, 3531,
+    3150, 3689, 3535, 3441, 4102, 4074, 3987, 4130, 3769, 4134, 3722, 3521, 3397, 3345, 3127, 4135,
+    2915, 3385, 3603, 3639, 3740, 3700, 4393, 4404, 3608, 3557, 3938, 3844, 3433, 3727, 3702, 4019,
+    3415, 3707, 3954, 4014, 3903, 3507, 4067, 3665, 3473, 3495, 3974, 3815, 3764, 3588, 3690, 3749,
+    3419, 3614, 4102, 4625, 3875, 4029, 3948, 4198, 3359, 3756, 3676, 3673, 3569, 3808, 3619, 3548,
+    3833, 4110, 3850, 3831, 3634, 3741, 3968, 3889, 4098, 4081, 3712, 4177, 4780, 4319, 5948, 11039,
+];
+
+const ZIP: [u32; 256] = [
+    14072, 4337, 3825, 4668, 4534, 3860, 3674, 3815, 3889, 3854, 3558, 4039, 3537, 3685, 3724, 3942,
+    3525, 3935, 3642, 3639, 4028, 3566, 3673, 3723, 3800, 3592, 3544, 3723, 3827, 3731, 3968, 4009,
+    3402, 3503, 3607, 3579, 3551, 3606, 3586, 3707, 3550, 3665, 3568, 3606, 3635, 4742, 4426, 5998,
+    3851, 3596, 3482, 3525, 3614, 3504, 3734, 3759, 3777, 3587, 3634, 3693, 3930, 3794, 3804, 4366,
+    3404, 3509, 3527, 3523, 3434, 3686, 3652, 3739, 3487, 3595, 3651, 4150, 3589, 3610, 3634, 3800,
+    3908, 3596, 3588, 3662, 3858, 4201, 3645, 3742, 3612, 3560, 4082, 3680, 3685, 3721, 3737, 4549,
+    3642, 5309, 4409, 5152, 4414, 6494, 4061, 5775, 5611, 5389, 3624, 3773, 4705, 4643, 4804, 7594,
+    4679, 3743, 6674, 4812, 5462, 4997, 4476, 3884, 4461, 3885, 3698, 3871, 3906, 4170, 3998, 3995,
+    3538, 3864, 3768, 3775, 3462, 3602, 3548, 3827, 3562, 3609, 3630, 3678, 3625, 3724, 3643, 3875,
+    3467, 3580, 3590, 3700, 3606, 3552, 3605, 3659, 3555, 3587, 3523, 3689, 3719, 3687, 3822, 3858,
+    3601, 3663, 3577, 3704, 3768, 3642, 3446, 3792, 3425, 3560, 3486, 3726, 3536, 3662, 3620, 3772,
+    3657, 3707, 3581, 3703, 3677, 3642, 3609, 3743, 3633, 3690, 4021, 3712, 3759, 3801, 3930, 3979,
+    3647, 3709, 3747, 3809, 3675, 3737, 3727, 3744, 3552, 3663, 3555, 3603, 3547, 3638, 3657, 3905,
+    3736, 3697, 3619, 3706, 4038, 3584, 3601, 3779, 3701, 4071, 3672, 3838, 3654, 3746, 3857, 4035,
+    3860, 3838, 3880, 3880, 3797, 3630, 3588, 3907, 3652, 3652, 3636, 3717, 3756, 3774, 3706, 3978,
+    4044, 3916, 3814, 3814, 3811, 3697, 3766, 4002, 3983, 3866, 3834, 3872, 4036, 3986, 4020, 4044,
+];
+
+const PDF: [u32; 256] = [
+    3666, 3666, 3580, 3794, 3862, 3792, 3598, 3840, 3773, 3702, 6172, 3665, 3893, 3655, 3696, 3936,
+    3742, 3753, 3789, 3642, 3923, 3588, 3762, 4055, 3760, 3574, 3796, 3890, 3760, 3597, 3708, 3801,
+    6720, 3465, 3721, 3611, 3999, 3713, 3666, 3779, 3647, 3726, 3457, 3697, 3824, 3794, 3740, 5130,
+    4714, 4464, 4044, 4243, 4162, 4037, 4393, 4069, 4012, 3977, 3706, 3884, 4258, 3774, 4427, 3973,
+    3473, 3776, 3647, 3852, 3983, 3907, 4363, 3762, 3854, 3838, 3800, 3635, 3991, 3972, 3718, 3835,
+    3662, 3779, 3875, 3922, 3687, 3837, 3559, 3862, 3884, 3779, 3741, 3814, 4056, 3652, 3791, 3927,
+    3666, 4474, 4284, 4096, 4522, 6502, 3863, 4218, 4165, 4108, 4178, 3829, 4366, 4432, 4876, 4834,
+    3826, 3962, 4601, 4558, 5373, 3824, 3914, 3802, 4159, 3832, 3765, 3720, 3858, 3931, 4102, 4018,
+    3647, 3759, 3828, 3921, 3759, 3612, 3713, 3777, 3807, 3698, 3881, 3985, 3786, 3754, 3762, 3909,
+    3630, 3723, 3690, 3778, 3573, 3883, 3798, 3820, 3947, 3529, 3606, 3916, 3903, 3733, 3987, 3804,
+    3791, 3788, 3723, 3834, 3499, 3454, 3706, 3779, 3560, 3894, 3873, 3891, 3572, 3837, 3703, 3981,
+    3772, 3667, 3926, 3776, 3727, 4036, 3981, 3905, 3976, 3890, 3836, 4003, 3854, 3743, 3993, 3985,
+    3881, 3715, 3853, 3933, 3709, 3819, 3895, 3901, 3661, 3762, 3751, 3768, 3816, 3982, 3948, 3841,
+    3650, 3735, 3804, 3837, 3743, 3851, 3717, 3992, 3772, 3926, 3945, 4097, 3830, 3924, 3843, 4065,
+    3734, 3856, 3898, 4048, 3706, 3865, 3825, 3827, 3693, 3917, 3648, 3987, 3916, 4124, 3989, 3844,
+    3614, 3878, 3877, 3942, 3601, 3836, 3879, 4052, 3783, 3733, 3919, 4008, 3916, 3895, 4082, 4122,
+];
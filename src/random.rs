@@ -0,0 +1,297 @@
+/*
+    freqs random: every randomness test freqs has, in one report with an
+    overall verdict. The chi-square (or G, with --gof g) of the byte counts
+    against a uniform distribution, the wald-wolfowitz runs tests (of the
+    bits, and of bytes above and below the median) and the nist tests have
+    p-values, each
+    passing at the --significance level; the fips tests have fixed bounds of their own
+    and are left out for files shorter than their 20,000 bits.
+
+    With --block-size it maps the file instead, testing the byte counts of
+    each block on their own, to show which parts of a stream stop looking
+    random. Chance alone fails about one block in 1/significance, so the
+    map only fails as a whole when clearly more blocks do.
+*/
+
+use std::fs;
+use std::io::{self, Read};
+
+use analysis;
+use args::Options;
+use fips;
+use nist::{self, Outcome};
+use report::{csv_field, json_str, Format};
+use special;
+use stats::Gof;
+
+struct Row {
+    test: String,
+    statistic: f64,
+    p: Option<f64>,     // the fips tests have none
+    passed: bool,
+}
+
+// the lines to print, and whether the file passed every test
+pub fn run(opts: &Options, format: Format) -> (Vec<String>, bool) {
+    let path = &opts.inputs[0];
+    let significance = opts.significance.unwrap_or(nist::DEFAULT_SIGNIFICANCE);
+    if let Some(size) = opts.block_size { return blocks(opts, format, size, significance); }
+    let (a, nist_outcomes, fips) = match (analysis::analyze(path, opts, false), nist::test(path), fips::test(path)) {
+        (Ok(a), Ok(n), Ok(f)) => (a, n, f),
+        _ => panic!("Could not open {}. Bad file or path?", path),
+    };
+    let runs = match runs_tests(path, median(&a.counts, a.len)) {
+        Ok(r) => r,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    let nist_outcomes = match nist_outcomes {
+        Some(o) => o,
+        None => panic!("The tests need at least {} bytes, {} is shorter", nist::MIN_BYTES, path),
+    };
+
+    let mut outcomes = vec![uniformity(Gof::parse(&opts.gof_name).unwrap_or(Gof::ChiSquare), &a.counts, a.len)];
+    outcomes.extend(runs);
+    outcomes.extend(nist_outcomes);
+    let mut rows: Vec<Row> = outcomes.into_iter()
+        .map(|o| Row { test: String::from(o.test), statistic: o.statistic, p: Some(o.p), passed: o.p >= significance })
+        .collect();
+    if let Some(f) = fips {
+        let passed = f.stream_passed();
+        for (i, test) in fips::TESTS.iter().enumerate() {
+            rows.push(Row { test: format!("fips {}, blocks failed", test), statistic: f.failed[i] as f64, p: None, passed: passed[i] });
+        }
+    }
+    let failed: Vec<&str> = rows.iter().filter(|r| !r.passed).map(|r| r.test.as_str()).collect();
+    let ok = failed.is_empty();
+    let verdict = if ok {
+        format!("looks random, passed all {} tests", rows.len())
+    } else {
+        format!("doesn't look random, failed {} of {}: {}", failed.len(), rows.len(), failed.join(", "))
+    };
+    let p_text = |r: &Row| r.p.map_or(String::new(), |p| format!("{:.6}", p));
+
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("test,statistic,p_value,passed")];
+            for r in &rows {
+                lines.push(format!("{},{:.6},{},{}", csv_field(&r.test), r.statistic, p_text(r), r.passed));
+            }
+            lines.push(String::from(""));
+            lines.push(String::from("significance,passed,verdict"));
+            lines.push(format!("{},{},{}", significance, ok, csv_field(&verdict)));
+            lines
+        },
+        Format::Json => {
+            let tests: Vec<String> = rows.iter()
+                .map(|r| format!(
+                    "    {{\"test\": {}, \"statistic\": {:.6}, \"p_value\": {}, \"passed\": {}}}",
+                    json_str(&r.test), r.statistic, r.p.map_or(String::from("null"), |p| format!("{:.6}", p)), r.passed
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"path\": {},", json_str(path)),
+                format!("  \"significance\": {},", significance),
+                format!("  \"tests\": [\n{}\n  ],", tests.join(",\n")),
+                format!("  \"passed\": {},", ok),
+                format!("  \"verdict\": {}", json_str(&verdict)),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  randomness tests of {} (significance {})", path, significance),
+                String::from(""),
+                format!("  {:<28}  {:>14}  {:>8}", "test", "statistic", "p-value"),
+            ];
+            for r in &rows {
+                lines.push(format!(
+                    "  {:<28}  {:>14.4}  {:>8}  {}",
+                    r.test, r.statistic, p_text(r), if r.passed { "pass" } else { "FAIL" }
+                ));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}", verdict));
+            lines
+        },
+    };
+    (lines, ok)
+}
+
+// how well the byte counts fit all 256 values being equally likely. G
+// follows the chi-square distribution too
+fn uniformity(gof: Gof, counts: &[u64; 256], len: u64) -> Outcome {
+    let expected = len as f64 / 256.0;
+    let (test, statistic) = match gof {
+        Gof::ChiSquare => ("byte chi-square", counts.iter().map(|c| (*c as f64 - expected).powi(2) / expected).sum()),
+        Gof::G => ("byte G-test", counts.iter().filter(|c| **c > 0).map(|c| 2.0 * *c as f64 * (*c as f64 / expected).ln()).sum()),
+    };
+    Outcome { test, statistic, p: special::chi_square_p(statistic, 255.0) }
+}
+
+// the byte value half the bytes are at or below
+fn median(counts: &[u64; 256], len: u64) -> u8 {
+    let mut seen = 0;
+    for (b, c) in counts.iter().enumerate() {
+        seen += c;
+        if seen * 2 >= len { return b as u8; }
+    }
+    255
+}
+
+// of the bits, and of the bytes as above or below the median, leaving out
+// those right on it
+fn runs_tests(path: &str, median: u8) -> io::Result<[Outcome; 2]> {
+    let mut f = fs::File::open(path)?;
+    let mut chunk = vec![0u8; 1024 * 128];
+    let (mut bits, mut bytes) = (Runs::default(), Runs::default());
+    loop {
+        let n = f.read(&mut chunk)?;
+        if n == 0 { break; }
+        for b in &chunk[..n] {
+            for i in (0..8).rev() { bits.push(b >> i & 1 == 1); }
+            if *b != median { bytes.push(*b > median); }
+        }
+    }
+    Ok([bits.outcome("wald-wolfowitz runs, bits"), bytes.outcome("wald-wolfowitz runs, bytes")])
+}
+
+#[derive(Default)]
+struct Runs {
+    above: u64,
+    below: u64,
+    runs: u64,
+    last: Option<bool>,
+}
+
+impl Runs {
+    fn push(&mut self, above: bool) {
+        if above { self.above += 1; } else { self.below += 1; }
+        if self.last != Some(above) { self.runs += 1; }
+        self.last = Some(above);
+    }
+
+    // the statistic is the z-score of the number of runs; all of one kind
+    // is as far from random as it gets
+    fn outcome(&self, test: &'static str) -> Outcome {
+        let (n1, n2) = (self.above as f64, self.below as f64);
+        let n = n1 + n2;
+        let mean = 2.0 * n1 * n2 / n + 1.0;
+        let variance = (mean - 1.0) * (mean - 2.0) / (n - 1.0);
+        if self.above == 0 || self.below == 0 || variance <= 0.0 {
+            return Outcome { test, statistic: 0.0, p: 0.0 };
+        }
+        let z = (self.runs as f64 - mean) / variance.sqrt();
+        Outcome { test, statistic: z, p: special::erfc(z.abs() / 2f64.sqrt()) }
+    }
+}
+
+struct Block {
+    offset: u64,
+    len: u64,
+    outcome: Outcome,
+}
+
+// the --block-size map
+fn blocks(opts: &Options, format: Format, size: u64, significance: f64) -> (Vec<String>, bool) {
+    let path = &opts.inputs[0];
+    let gof = Gof::parse(&opts.gof_name).unwrap_or(Gof::ChiSquare);
+    let blocks = match test_blocks(path, size, gof) {
+        Ok(b) => b,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    if blocks.is_empty() { panic!("Nothing to test, {} is empty", path); }
+    let failed = blocks.iter().filter(|b| b.outcome.p < significance).count();
+    // more failures than three standard deviations past what chance gives
+    let expected = blocks.len() as f64 * significance;
+    let limit = expected + 3.0 * (expected * (1.0 - significance)).sqrt();
+    let ok = failed as f64 <= limit.max(1.0);
+    let verdict = format!(
+        "{} of {} blocks failed, about {:.1} would by chance: {}",
+        failed, blocks.len(), expected, if ok { "looks random throughout" } else { "parts don't look random" }
+    );
+
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("offset,length,statistic,p_value,passed")];
+            for b in &blocks {
+                lines.push(format!("{},{},{:.6},{:.6},{}", b.offset, b.len, b.outcome.statistic, b.outcome.p, b.outcome.p >= significance));
+            }
+            lines.push(String::from(""));
+            lines.push(String::from("significance,blocks_failed,passed,verdict"));
+            lines.push(format!("{},{},{},{}", significance, failed, ok, csv_field(&verdict)));
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = blocks.iter()
+                .map(|b| format!(
+                    "    {{\"offset\": {}, \"length\": {}, \"statistic\": {:.6}, \"p_value\": {:.6}, \"passed\": {}}}",
+                    b.offset, b.len, b.outcome.statistic, b.outcome.p, b.outcome.p >= significance
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"path\": {},", json_str(path)),
+                format!("  \"test\": {},", json_str(blocks[0].outcome.test)),
+                format!("  \"block_size\": {},", size),
+                format!("  \"significance\": {},", significance),
+                format!("  \"blocks\": [\n{}\n  ],", entries.join(",\n")),
+                format!("  \"blocks_failed\": {},", failed),
+                format!("  \"passed\": {},", ok),
+                format!("  \"verdict\": {}", json_str(&verdict)),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  {} of {} in blocks of {} bytes (significance {})", blocks[0].outcome.test, path, size, significance),
+                format!("  . passes, X fails, {} blocks a line", MAP_WIDTH),
+                String::from(""),
+            ];
+            for row in blocks.chunks(MAP_WIDTH) {
+                let marks: String = row.iter().map(|b| if b.outcome.p >= significance { '.' } else { 'X' }).collect();
+                lines.push(format!("  {:#010x}  {}", row[0].offset, marks));
+            }
+            if failed > 0 {
+                lines.push(String::from(""));
+                lines.push(format!("  {:<10}  {:>10}  {:>14}  {:>8}", "offset", "length", "statistic", "p-value"));
+                for b in blocks.iter().filter(|b| b.outcome.p < significance) {
+                    lines.push(format!("  {:#010x}  {:>10}  {:>14.4}  {:>8.6}", b.offset, b.len, b.outcome.statistic, b.outcome.p));
+                }
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}", verdict));
+            lines
+        },
+    };
+    (lines, ok)
+}
+
+// blocks a line of the text map
+const MAP_WIDTH: usize = 64;
+
+// the last block may be short
+fn test_blocks(path: &str, size: u64, gof: Gof) -> io::Result<Vec<Block>> {
+    let mut f = fs::File::open(path)?;
+    let mut buf = vec![0u8; size.min(1 << 20) as usize];
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut counts = [0u64; 256];
+        let mut len = 0;
+        while len < size {
+            let want = (size - len).min(buf.len() as u64) as usize;
+            let n = f.read(&mut buf[..want])?;
+            if n == 0 { break; }
+            for b in &buf[..n] { counts[*b as usize] += 1; }
+            len += n as u64;
+        }
+        if len == 0 { break; }
+        blocks.push(Block { offset, len, outcome: uniformity(gof, &counts, len) });
+        offset += len;
+        if len < size { break; }
+    }
+    Ok(blocks)
+}
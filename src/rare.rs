@@ -0,0 +1,79 @@
+/*
+    --context: where the odd bytes out are, and what's around them. Which
+    byte values are rare is only known once everything is counted, so the
+    first few times every value turns up are kept, with --context bytes
+    either side, and at the end only the values counted fewer than
+    --rare-below times are reported. Like a line of a hex dump, centred on
+    the byte.
+*/
+
+use std::collections::VecDeque;
+
+pub const DEFAULT_BELOW: u64 = 10;
+pub const MAX_CONTEXT: usize = 64;
+// kept per byte value
+const SAMPLES: usize = 3;
+
+pub struct Rare {
+    pub below: u64,
+    pub each_side: usize,
+    pub samples: Vec<Sample>,  // in byte value order, then by offset
+}
+
+pub struct Sample {
+    pub byte: u8,
+    pub offset: u64,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+pub struct RareTracker {
+    below: u64,
+    each_side: usize,
+    window: VecDeque<u8>,      // the bytes just before
+    samples: Vec<Vec<Sample>>, // per byte value
+    open: Vec<(u8, usize)>,    // samples still waiting on bytes after
+    offset: u64,               // of the next byte
+}
+
+impl RareTracker {
+    pub fn new(each_side: usize, below: u64) -> RareTracker {
+        RareTracker {
+            below,
+            each_side,
+            window: VecDeque::with_capacity(each_side + 1),
+            samples: (0..256).map(|_| Vec::new()).collect(),
+            open: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for byte in chunk {
+            let each_side = self.each_side;
+            let samples = &mut self.samples;
+            self.open.retain(|&(b, i)| {
+                let s = &mut samples[b as usize][i];
+                s.after.push(*byte);
+                s.after.len() < each_side
+            });
+            let kept = &mut self.samples[*byte as usize];
+            if kept.len() < SAMPLES {
+                kept.push(Sample { byte: *byte, offset: self.offset, before: self.window.iter().copied().collect(), after: Vec::new() });
+                self.open.push((*byte, kept.len() - 1));
+            }
+            self.window.push_back(*byte);
+            if self.window.len() > self.each_side { self.window.pop_front(); }
+            self.offset += 1;
+        }
+    }
+
+    pub fn finish(self, counts: &[u64; 256]) -> Rare {
+        let below = self.below;
+        let samples = self.samples.into_iter().enumerate()
+            .filter(|&(byte, _)| counts[byte] != 0 && counts[byte] < below)
+            .flat_map(|(_, s)| s)
+            .collect();
+        Rare { below, each_side: self.each_side, samples }
+    }
+}
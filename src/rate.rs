@@ -0,0 +1,93 @@
+/*
+    --rate: how fast the bytes came in, and what they looked like, over
+    time. Meant for pipes, devices and downloads, where a stall upstream or
+    a change in what's being sent would otherwise be lost in the totals.
+    Every --rate seconds gets the bytes that arrived in it and their
+    entropy; seconds where nothing came at all still get a row, with
+    nothing in it, so a stall shows up as such.
+*/
+
+use std::time::{Duration, Instant};
+
+use entropy;
+
+pub struct Rate {
+    pub interval: u64,          // seconds
+    pub seconds: f64,           // from the start of the read to the end
+    pub intervals: Vec<Interval>,
+}
+
+pub struct Interval {
+    pub start: f64,             // seconds in
+    pub seconds: f64,           // the last one can be short
+    pub offset: u64,            // of its first byte
+    pub bytes: u64,
+    pub entropy: f64,           // bits per byte
+}
+
+impl Interval {
+    pub fn per_second(&self) -> f64 {
+        if self.seconds > 0.0 { self.bytes as f64 / self.seconds } else { 0.0 }
+    }
+}
+
+pub struct RateTracker {
+    started: Instant,
+    interval: Duration,
+    current: u32,               // the interval being filled in
+    counts: [u64; 256],
+    bytes: u64,
+    offset: u64,
+    intervals: Vec<Interval>,
+}
+
+impl RateTracker {
+    pub fn new(interval: u64) -> RateTracker {
+        RateTracker {
+            started: Instant::now(),
+            interval: Duration::from_secs(interval),
+            current: 0,
+            counts: [0; 256],
+            bytes: 0,
+            offset: 0,
+            intervals: Vec::new(),
+        }
+    }
+
+    // the whole chunk counts for the moment it arrived
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let now = (self.started.elapsed().as_secs_f64() / self.interval.as_secs_f64()) as u32;
+        while self.current < now { self.close(self.interval.as_secs_f64()); }
+        for byte in chunk { self.counts[*byte as usize] += 1; }
+        self.bytes += chunk.len() as u64;
+    }
+
+    fn close(&mut self, seconds: f64) {
+        self.intervals.push(Interval {
+            start: self.current as f64 * self.interval.as_secs_f64(),
+            seconds,
+            offset: self.offset,
+            bytes: self.bytes,
+            entropy: entropy::shannon(&self.counts, self.bytes),
+        });
+        self.offset += self.bytes;
+        self.counts = [0; 256];
+        self.bytes = 0;
+        self.current += 1;
+    }
+
+    pub fn finish(mut self) -> Rate {
+        let seconds = self.started.elapsed().as_secs_f64();
+        let interval = self.interval.as_secs_f64();
+        while (self.current as f64 + 1.0) * interval < seconds { self.close(interval); }
+        let last = seconds - self.current as f64 * interval;
+        self.close(last.max(0.0));
+        Rate { interval: self.interval.as_secs(), seconds, intervals: self.intervals }
+    }
+}
+
+// bytes a second overall
+pub fn overall(rate: &Rate) -> f64 {
+    let bytes: u64 = rate.intervals.iter().map(|i| i.bytes).sum();
+    if rate.seconds > 0.0 { bytes as f64 / rate.seconds } else { 0.0 }
+}
@@ -0,0 +1,111 @@
+/*
+    Block-wise mini reports: the file is cut into fixed size blocks and each
+    gets its own entropy, distinct byte count and most common bytes. Handy
+    for files made of very different parts, like installers or disk images,
+    where the overall histogram averages everything away.
+
+    With --content-defined the blocks end where a rolling hash of the last
+    bytes says so instead (a gear hash, as in FastCDC), about block size
+    apart on average. Inserting a few bytes then only changes the blocks
+    around them, the ones after line up with the same content as before.
+*/
+
+use entropy;
+use rng::Rng;
+use stats;
+
+// how many of the most common bytes each region lists
+pub const TOP_BYTES: usize = 3;
+
+pub struct Region {
+    pub offset: u64,
+    pub len: u64,
+    pub entropy: f64,
+    pub distinct: usize,
+    pub top: Vec<(u8, u64)>,    // most common bytes first
+}
+
+pub struct Regions {
+    block_size: u64,
+    counts: [u64; 256],
+    offset: u64,
+    filled: u64,
+    regions: Vec<Region>,
+    gear: Vec<u64>,     // a random number per byte value, content defined only
+    hash: u64,
+    mask: u64,          // a boundary where the hash has these bits clear
+}
+
+impl Regions {
+    pub fn new(block_size: u64) -> Regions {
+        Regions {
+            block_size,
+            counts: [0u64; 256],
+            offset: 0,
+            filled: 0,
+            regions: Vec::new(),
+            gear: Vec::new(),
+            hash: 0,
+            mask: 0,
+        }
+    }
+
+    // blocks of about average bytes, from a quarter of that to four times
+    pub fn content_defined(average: u64) -> Regions {
+        let mut rng = Rng::new(0x6765_6172);
+        let bits = 63 - (average * 3 / 4).leading_zeros();
+        Regions {
+            gear: (0..256).map(|_| rng.next_u64()).collect(),
+            // the top bits of the hash depend on the most bytes
+            mask: ((1u64 << bits) - 1) << (64 - bits),
+            ..Regions::new(average)
+        }
+    }
+
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        if !self.gear.is_empty() { return self.feed_content_defined(chunk); }
+        while !chunk.is_empty() {
+            let take = ((self.block_size - self.filled) as usize).min(chunk.len());
+            for byte in &chunk[..take] { self.counts[*byte as usize] += 1; }
+            self.filled += take as u64;
+            chunk = &chunk[take..];
+            if self.filled == self.block_size { self.end_block(); }
+        }
+    }
+
+    fn feed_content_defined(&mut self, chunk: &[u8]) {
+        let (min, max) = (self.block_size / 4, self.block_size * 4);
+        for byte in chunk {
+            self.counts[*byte as usize] += 1;
+            self.filled += 1;
+            self.hash = (self.hash << 1).wrapping_add(self.gear[*byte as usize]);
+            if (self.filled >= min && self.hash & self.mask == 0) || self.filled >= max { self.end_block(); }
+        }
+    }
+
+    // flushes a trailing partial block, if any, and hands back the results
+    pub fn finish(mut self) -> Vec<Region> {
+        if self.filled > 0 { self.end_block(); }
+        self.regions
+    }
+
+    fn end_block(&mut self) {
+        let mut top: Vec<(u8, u64)> = self.counts.iter().enumerate()
+            .filter(|&(_, c)| *c != 0)
+            .map(|(byte, c)| (byte as u8, *c))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        top.truncate(TOP_BYTES);
+
+        self.regions.push(Region {
+            offset: self.offset,
+            len: self.filled,
+            entropy: entropy::shannon(&self.counts, self.filled),
+            distinct: stats::distinct(&self.counts),
+            top,
+        });
+        self.offset += self.filled;
+        self.counts = [0u64; 256];
+        self.filled = 0;
+    }
+}
@@ -0,0 +1,142 @@
+/*
+    http:// and https:// inputs, so remote artifacts can be counted without
+    downloading them first. There's no tls in std, so curl does the
+    fetching, the way decompression leans on xz and friends; the body is
+    counted as it streams out of it. Content-Length, when the server sends
+    one, gives the progress something to go by. Only the part of the body
+    that's counted is asked for, with a range request, and s3:// urls go
+    the same way once signed (see s3.rs).
+*/
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+#[cfg(feature = "s3")]
+use s3;
+
+pub struct Remote {
+    child: Child,
+    body: BufReader<ChildStdout>,
+    pub length: Option<u64>,    // of the whole thing, not just the range
+    pub start: u64,             // where in it the body starts
+}
+
+// what curl is handed for a url
+pub struct Request {
+    pub url: String,
+    pub args: Vec<String>,
+    pub config: Option<String>,     // fed on stdin, for secrets
+}
+
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://") || input.starts_with("s3://")
+}
+
+#[cfg(feature = "s3")]
+fn s3_request(url: &str) -> io::Result<Request> { s3::request(url) }
+
+#[cfg(not(feature = "s3"))]
+fn s3_request(_: &str) -> io::Result<Request> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "s3:// urls need freqs built with --features s3"))
+}
+
+// range is the start and end of what's wanted, the end past the last byte
+pub fn open(url: &str, range: Option<(u64, u64)>) -> io::Result<Remote> {
+    let request = if url.starts_with("s3://") {
+        s3_request(url)?
+    } else {
+        Request { url: url.to_string(), args: Vec::new(), config: None }
+    };
+
+    // headers come first on stdout, one block per redirect followed
+    let mut curl = Command::new("curl");
+    curl.args(["--silent", "--show-error", "--fail", "--location", "--dump-header", "-"]).args(&request.args);
+    if let Some((start, end)) = range {
+        if end == u64::MAX { curl.arg("--range").arg(format!("{}-", start)); }
+        else { curl.arg("--range").arg(format!("{}-{}", start, end - 1)); }
+    }
+    if request.config.is_some() { curl.args(["--config", "-"]); }
+    let stdin = if request.config.is_some() { Stdio::piped() } else { Stdio::null() };
+    let mut child = match curl.arg("--").arg(&request.url)
+        .stdin(stdin).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(c) => c,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "curl isn't installed, it's needed to fetch urls"));
+        },
+        Err(e) => return Err(e),
+    };
+    if let (Some(config), Some(mut stdin)) = (request.config, child.stdin.take()) {
+        stdin.write_all(config.as_bytes())?;
+    }
+    let mut body = BufReader::new(child.stdout.take().unwrap());
+
+    let mut length = None;
+    let mut partial = None;     // the start and total length of a range
+    let mut line = String::new();
+    loop {
+        // the status line of the next response, if any
+        line.clear();
+        if body.read_line(&mut line)? == 0 { break; }
+        let status: u16 = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+        length = None;
+        partial = None;
+        loop {
+            line.clear();
+            if body.read_line(&mut line)? == 0 { break; }
+            let header = line.trim_end();
+            if header.is_empty() { break; }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") { length = value.trim().parse().ok(); }
+                // bytes 100-199/5000, the total might be a *
+                if name.eq_ignore_ascii_case("content-range") {
+                    let value = value.trim().trim_start_matches("bytes").trim();
+                    if let Some((from, rest)) = value.split_once('-') {
+                        let total = rest.split_once('/').and_then(|(_, t)| t.parse::<u64>().ok());
+                        partial = Some((from.parse().unwrap_or(0), total));
+                    }
+                }
+            }
+        }
+        // redirects and 100 continue are followed by more headers
+        if !(100..200).contains(&status) && !(300..400).contains(&status) { break; }
+    }
+    // a server that ignores the range sends it all from the start
+    let (start, length) = match partial {
+        Some((start, total)) => (start, total),
+        None => (0, length),
+    };
+    Ok(Remote { child, body, length, start })
+}
+
+impl Read for Remote {
+    // whole chunks, like from a file, as the pipe only hands out a bit at
+    // a time. at the end of the body, curl's exit status says if it all came
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.body.read(&mut buf[n..])? {
+                0 => break,
+                got => n += got,
+            }
+        }
+        if n < buf.len() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                let mut message = String::new();
+                if let Some(mut err) = self.child.stderr.take() { let _ = err.read_to_string(&mut message); }
+                return Err(io::Error::other(format!("curl failed ({}): {}", status, message.trim())));
+            }
+        }
+        Ok(n)
+    }
+}
+
+// not read to the end, like with --max-bytes
+impl Drop for Remote {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,257 @@
+/*
+    freqs repl: one analysis after another in the same session, for poking
+    around a pile of files without starting over for every question. Each
+    file analyzed is kept, so asking about it again (top, compare,
+    classify) doesn't read it again unless it's changed since, and a model
+    loaded once stays loaded. A command a line:
+
+        analyze <path>      the byte table, like freqs <path>
+        top [n]             the n most common bytes of the last file, 10 by default
+        compare <a> <b>     how alike the distributions of two files are
+        classify [path]     what kind of data, like freqs classify
+        model <model.json>  a model, from freqs model, to generate from
+        generate <size>     that much from the model, like 1k
+        files               the files kept so far
+        forget [path]       drop one of them, or all of them
+        format <name>       text, csv or json from then on
+        help
+        quit
+
+    The options repl was run with (--offset, --decompress, --cache...)
+    apply to every file. Paths with spaces in them can be quoted. A file
+    that can't be opened is only reported, but the session goes on.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::SystemTime;
+
+use analysis::{self, Analysis};
+use args::{self, Options};
+use classify;
+use generate;
+use model::{self, Model};
+use report::{self, byte_label, json_str, Format};
+use rng::Rng;
+use similarity;
+use stats;
+
+const PROMPT: &str = "freqs> ";
+const DEFAULT_TOP: usize = 10;
+
+const HELP: [&str; 11] = [
+    "  analyze <path>      the byte table",
+    "  top [n]             the n most common bytes of the last file",
+    "  compare <a> <b>     how alike two files are",
+    "  classify [path]     what kind of data, the last file if none",
+    "  model <model.json>  load a model to generate from",
+    "  generate <size>     that much from the model, like 1k",
+    "  files               the files kept so far",
+    "  forget [path]       drop one of them, or all of them",
+    "  format <name>       text, csv or json",
+    "  help                this",
+    "  quit                done",
+];
+
+struct Kept {
+    analysis: Analysis,
+    stamp: (u64, Option<SystemTime>),   // size and modification time when analyzed
+}
+
+struct Session<'a> {
+    opts: &'a Options,
+    format: Format,
+    kept: HashMap<String, Kept>,
+    last: Option<String>,
+    model: Option<(String, Model)>,
+}
+
+// writes straight to stdout, like samples
+pub fn run(opts: &Options, format: Format) {
+    let mut session = Session { opts, format, kept: HashMap::new(), last: None, model: None };
+    let interactive = io::stdin().is_terminal();
+    if interactive { println!("freqs repl, help for the commands"); }
+    let mut input = io::stdin().lock();
+    loop {
+        if interactive {
+            print!("{}", PROMPT);
+            let _ = io::stdout().flush();
+        }
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => (),
+        }
+        let words = split(&line);
+        if words.is_empty() { continue; }
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+        if words[0] == "quit" || words[0] == "exit" { break; }
+        match session.command(&words) {
+            Ok(lines) => for l in lines { println!("{}", l); },
+            Err(message) => println!("{}", message),
+        }
+    }
+    // the prompt is still on the last line after ctrl-d
+    if interactive { println!(); }
+}
+
+impl<'a> Session<'a> {
+    fn command(&mut self, words: &[&str]) -> Result<Vec<String>, String> {
+        match words {
+            ["analyze", path] => {
+                let bars = self.opts.bars;
+                Ok(report::render_counts(self.format, &self.analyzed(path)?.counts, bars))
+            },
+            ["top"] => self.top(DEFAULT_TOP),
+            ["top", n] => match n.parse() {
+                Ok(n) if n > 0 => self.top(n),
+                _ => Err(String::from("top takes how many, like top 10")),
+            },
+            ["compare", a, b] => {
+                let p = stats::proportions(&self.analyzed(a)?.counts);
+                let q = stats::proportions(&self.analyzed(b)?.counts);
+                Ok(similarity::render(self.format, a, b, &stats::similarity(&p, &q)))
+            },
+            ["classify"] => {
+                let path = self.last.clone().ok_or_else(|| String::from("Nothing analyzed yet, try classify <path>"))?;
+                self.classify(&path)
+            },
+            ["classify", path] => self.classify(path),
+            ["model", path] => {
+                let m = model::load(path).map_err(|e| format!("Could not load model {}: {}", path, e))?;
+                if m.contexts.is_empty() { return Err(String::from("The model has nothing to go on, it was made from less than its order")); }
+                let line = format!("  {}: order {}, {} contexts of {}", path, m.order, m.contexts.len(), if m.chars { "chars" } else { "bytes" });
+                self.model = Some((path.to_string(), m));
+                Ok(vec![line])
+            },
+            ["generate", size] => {
+                let bytes = args::parse_size(size).filter(|b| *b > 0).ok_or_else(|| String::from("generate takes a size, like generate 1k"))?;
+                let (_, ref m) = *self.model.as_ref().ok_or_else(|| String::from("No model yet, try model <model.json>"))?;
+                let mut out = io::stdout().lock();
+                generate::write(m, bytes, &mut Rng::new(generate::clock()), &mut out)
+                    .and_then(|_| writeln!(out))
+                    .map_err(|e| format!("Could not write the generated data: {}", e))?;
+                Ok(Vec::new())
+            },
+            ["files"] => Ok(self.files()),
+            ["forget"] => {
+                self.kept.clear();
+                self.last = None;
+                Ok(Vec::new())
+            },
+            ["forget", path] => {
+                if self.kept.remove(*path).is_none() { return Err(format!("{} isn't kept", path)); }
+                if self.last.as_deref() == Some(*path) { self.last = None; }
+                Ok(Vec::new())
+            },
+            ["format", name] => match Format::parse(name) {
+                Some(f @ (Format::Text | Format::Csv | Format::Json)) => {
+                    self.format = f;
+                    Ok(Vec::new())
+                },
+                _ => Err(String::from("The formats here are text, csv and json")),
+            },
+            ["help"] => Ok(HELP.iter().map(|h| h.to_string()).collect()),
+            [command, ..] if HELP.iter().any(|h| h.split_whitespace().next() == Some(*command)) => {
+                Err(format!("That isn't how {} goes, try help", command))
+            },
+            [command, ..] => Err(format!("unknown command {}, try help", command)),
+            [] => Ok(Vec::new()),
+        }
+    }
+
+    // the file's analysis, done again only if it's been changed since
+    fn analyzed(&mut self, path: &str) -> Result<&Analysis, String> {
+        if path == "-" { return Err(String::from("stdin is where the commands come from, so it can't be analyzed")); }
+        let stamp = fs::metadata(path).ok().map(|m| (m.len(), m.modified().ok()));
+        let fresh = match (self.kept.get(path), stamp) {
+            (Some(k), Some(s)) => k.stamp == s,
+            _ => false,
+        };
+        if !fresh {
+            let a = analysis::analyze(path, self.opts, false)
+                .map_err(|_| format!("Could not open {}. Bad file or path?", path))?;
+            // urls and the like have no size or time to go by, so they're read every time
+            self.kept.insert(path.to_string(), Kept { analysis: a, stamp: stamp.unwrap_or((0, None)) });
+        }
+        self.last = Some(path.to_string());
+        Ok(&self.kept[path].analysis)
+    }
+
+    fn classify(&mut self, path: &str) -> Result<Vec<String>, String> {
+        let format = self.format;
+        let a = self.analyzed(path)?;
+        if a.len == 0 { return Err(format!("Nothing to classify, {} is empty", path)); }
+        Ok(classify::render(format, path, a))
+    }
+
+    fn top(&self, n: usize) -> Result<Vec<String>, String> {
+        let path = self.last.as_ref().ok_or_else(|| String::from("Nothing analyzed yet, try analyze <path>"))?;
+        let counts = &self.kept[path].analysis.counts;
+        let total: u64 = counts.iter().sum();
+        let mut bytes: Vec<usize> = (0..256).filter(|b| counts[*b] > 0).collect();
+        bytes.sort_by(|a, b| counts[*b].cmp(&counts[*a]).then(a.cmp(b)));
+        bytes.truncate(n);
+        let share = |b: usize| counts[b] as f64 / total as f64 * 100.0;
+
+        Ok(match self.format {
+            Format::Csv => {
+                let mut lines = vec![String::from("byte,count,percent")];
+                lines.extend(bytes.iter().map(|b| format!("{},{},{:.4}", b, counts[*b], share(*b))));
+                lines
+            },
+            Format::Json => {
+                let entries: Vec<String> = bytes.iter()
+                    .map(|b| format!("    {{\"byte\": {}, \"count\": {}, \"percent\": {:.4}}}", b, counts[*b], share(*b)))
+                    .collect();
+                vec![
+                    String::from("{"),
+                    format!("  \"path\": {},", json_str(path)),
+                    format!("  \"top\": [\n{}\n  ]", entries.join(",\n")),
+                    String::from("}"),
+                ]
+            },
+            _ => {
+                let mut lines = vec![String::from(""), format!("  {}, {} bytes", path, total), String::from("")];
+                lines.extend(bytes.iter().map(|b| format!("  {:<3}: {}: {} ({:.2}%)", format!("{:x}", b), counts[*b], byte_label(*b as u8), share(*b))));
+                lines
+            },
+        })
+    }
+
+    fn files(&self) -> Vec<String> {
+        let mut paths: Vec<&String> = self.kept.keys().collect();
+        paths.sort();
+        let mut lines = Vec::new();
+        for path in paths {
+            let a = &self.kept[path].analysis;
+            lines.push(format!("  {}: {} bytes", path, a.len));
+        }
+        if let Some((ref path, _)) = self.model { lines.push(format!("  model {}", path)); }
+        if lines.is_empty() { lines.push(String::from("Nothing kept yet")); }
+        lines
+    }
+}
+
+// the words of a line, with "quoted" or 'quoted' ones kept together
+fn split(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut started = false;
+    for c in line.trim().chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => { quote = Some(c); started = true; },
+            (None, c) if c.is_whitespace() => {
+                if started { words.push(std::mem::take(&mut word)); }
+                started = false;
+            },
+            (None, c) => { word.push(c); started = true; },
+        }
+    }
+    if started { words.push(word); }
+    words
+}
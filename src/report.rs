@@ -0,0 +1,931 @@
+/*
+    Turns analysis results into output lines, in whichever format was asked
+    for. Everything ends up as a list of lines so the caller can send them
+    to a file or stdout the same way regardless of format.
+*/
+
+use analysis::Summary;
+use dispersion;
+use entropy::{self, Point};
+use history;
+use huffman::Huffman;
+use regions::{Region, TOP_BYTES};
+use rare::Rare;
+use rate::{self, Rate};
+use runs::{self, Runs};
+use segments::Segment;
+use special;
+use stats;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+    Gnuplot,
+    Vega,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            "gnuplot" => Some(Format::Gnuplot),
+            "vega" => Some(Format::Vega),
+            _ => None,
+        }
+    }
+}
+
+pub struct Profile {
+    pub window: usize,
+    pub step: usize,
+    pub points: Vec<Point>,
+}
+
+pub struct Regions {
+    pub block_size: u64,
+    pub content_defined: bool,  // then block_size is the average
+    pub regions: Vec<Region>,
+}
+
+// what was analyzed and when, for reports that describe themselves
+pub struct Meta {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<u64>,     // seconds since the epoch
+    pub analyzed: u64,
+}
+
+pub struct Report<'a> {
+    pub counts: &'a [u64; 256],
+    pub meta: Option<Meta>,
+    pub hashes: Vec<(&'static str, String)>,
+    pub profile: Option<Profile>,
+    pub regions: Option<Regions>,
+    pub segments: Option<Vec<Segment>>,
+    pub runs: Option<Runs>,
+    pub first_last: Option<Vec<(u64, u64)>>,    // by byte value
+    pub dispersion: Option<Vec<Option<f64>>>,   // by byte value
+    pub rare: Option<Rare>,
+    pub rate: Option<Rate>,
+    pub huffman: Option<Huffman>,
+    pub bars: bool,     // bar column in the text table
+    pub ci: Option<f64>,    // confidence level of the byte intervals, in percent
+    pub truncated: bool,
+}
+
+// width of the entropy bars in the text profile, at 8 bits per byte
+const BAR_WIDTH: f64 = 40.0;
+// width of the count bars in the text table, at the most common byte
+const COUNT_BAR_WIDTH: f64 = 50.0;
+
+pub fn render(format: Format, report: &Report) -> Vec<String> {
+    match format {
+        Format::Text => text(report),
+        Format::Csv => csv(report),
+        Format::Json => json(report),
+        Format::Gnuplot => gnuplot(report),
+        Format::Vega => vega(report),
+    }
+}
+
+// just the byte table, for the modes that redraw it while still counting
+pub fn render_counts(format: Format, counts: &[u64; 256], bars: bool) -> Vec<String> {
+    render(format, &Report { counts, meta: None, hashes: Vec::new(), profile: None, regions: None, segments: None, runs: None, first_last: None, dispersion: None, rare: None, rate: None, huffman: None, bars, ci: None, truncated: false })
+}
+
+fn modified(meta: &Meta) -> Option<String> {
+    meta.modified.map(history::timestamp)
+}
+
+// a byte's share in percent and the wilson interval around it, for --ci
+fn interval(report: &Report, count: u64) -> Option<(f64, f64, f64)> {
+    let level = report.ci?;
+    let total: u64 = report.counts.iter().sum();
+    let z = special::normal_quantile(1.0 - (1.0 - level / 100.0) / 2.0);
+    let (low, high) = stats::wilson(count, total, z);
+    Some((count as f64 / total as f64 * 100.0, low * 100.0, high * 100.0))
+}
+
+fn interval_text(report: &Report, count: u64) -> String {
+    interval(report, count).map_or(String::new(), |(pct, low, high)| {
+        format!("  {:>8.4}% ({:.4}% to {:.4}%)", pct, low, high)
+    })
+}
+
+fn first_last_text(report: &Report, byte: usize) -> String {
+    report.first_last.as_ref().map_or(String::new(), |f| {
+        format!("  first {:#010x}  last {:#010x}", f[byte].0, f[byte].1)
+    }) + &dispersion_text(report, byte)
+}
+
+fn dispersion_text(report: &Report, byte: usize) -> String {
+    report.dispersion.as_ref().map_or(String::new(), |d| match d[byte] {
+        Some(cv) => format!("  gaps cv {:.3} {}", cv, dispersion::label(cv)),
+        None => String::from("  gaps cv -"),
+    })
+}
+
+// what the reports say when --max-bytes or --timeout cut the count short
+fn truncated_note(report: &Report) -> String {
+    let len: u64 = report.counts.iter().sum();
+    format!("truncated: stopped early, after {} bytes", len)
+}
+
+fn text(report: &Report) -> Vec<String> {
+    let mut lines = vec![String::from("")];
+    if let Some(ref m) = report.meta {
+        lines.push(format!("  file: {}", m.path));
+        lines.push(format!("  size: {} bytes, modified {}", m.size, modified(m).unwrap_or_else(|| String::from("unknown"))));
+        lines.push(format!("  analyzed: {}", history::timestamp(m.analyzed)));
+        lines.push(String::from(""));
+    }
+    for (name, value) in &report.hashes { lines.push(format!("  {}: {}", name, value)); }
+    if !report.hashes.is_empty() { lines.push(String::from("")); }
+    if report.truncated { lines.push(format!("  {}", truncated_note(report))); }
+    if report.bars {
+        // the bars only line up if the columns before them do too
+        let max = *report.counts.iter().max().unwrap();
+        let count_width = max.to_string().len();
+        let label_width = (0..256)
+            .filter(|b| report.counts[*b] != 0)
+            .map(|b| byte_label(b as u8).chars().count())
+            .max().unwrap_or(0);
+        for (byte, byte_count) in report.counts.iter().enumerate() {
+            if *byte_count != 0 {
+                let bar = (*byte_count as f64 / max as f64 * COUNT_BAR_WIDTH).ceil() as usize;
+                lines.push(format!(
+                    "  {0: <3}: {1: <5$}: {2: <6$}{3}{7}  {4}",
+                    format!("{:x}", byte as u8),
+                    byte_count,
+                    byte_label(byte as u8),
+                    interval_text(report, *byte_count),
+                    "#".repeat(bar),
+                    count_width,
+                    label_width,
+                    first_last_text(report, byte)
+                ));
+            }
+        }
+    } else {
+        for (byte, byte_count) in report.counts.iter().enumerate() {
+            if *byte_count != 0 {
+                lines.push(format!(
+                    "  {0: <3}: {1}: {2}{3}{4}",
+                    format!("{:x}", byte as u8),
+                    byte_count,
+                    byte_label(byte as u8),
+                    interval_text(report, *byte_count),
+                    first_last_text(report, byte)
+                ));
+            }
+        }
+    }
+
+    if let Some(ref p) = report.profile {
+        lines.push(String::from(""));
+        lines.push(format!("  entropy profile (window {}, step {})", p.window, p.step));
+        for point in &p.points {
+            let bar = (point.bits / 8.0 * BAR_WIDTH).round() as usize;
+            lines.push(format!("  {:#010x}: {:.4}: {}", point.offset, point.bits, "#".repeat(bar)));
+        }
+    }
+
+    if let Some(ref r) = report.regions {
+        lines.push(String::from(""));
+        if r.content_defined {
+            lines.push(format!("  content defined blocks of about {} bytes", r.block_size));
+            lines.push(String::from("  offset          length  entropy  distinct  most common"));
+        } else {
+            lines.push(format!("  blocks of {} bytes", r.block_size));
+            lines.push(String::from("  offset      entropy  distinct  most common"));
+        }
+        for region in &r.regions {
+            let top: Vec<String> = region.top.iter()
+                .map(|&(byte, c)| format!("{:02x} {:.1}%", byte, c as f64 / region.len as f64 * 100.0))
+                .collect();
+            // their lengths vary, so they're shown
+            let length = if r.content_defined { format!("  {:>10}", region.len) } else { String::new() };
+            lines.push(format!(
+                "  {:#010x}{}  {:>7.4}  {:>8}  {}",
+                region.offset, length, region.entropy, region.distinct, top.join(", ")
+            ));
+        }
+    }
+
+    if let Some(ref segments) = report.segments {
+        lines.push(String::from(""));
+        lines.push(String::from("  segments"));
+        lines.push(String::from("  offset      size          entropy"));
+        for s in segments {
+            lines.push(format!("  {:#010x}  {:<12}  {:>7.4}", s.offset, s.len, s.entropy));
+        }
+    }
+
+    if let Some(ref r) = report.runs {
+        lines.push(String::from(""));
+        lines.push(String::from("  longest runs"));
+        lines.push(String::from("  byte  length        offset"));
+        for byte in runs::prominent(report.counts) {
+            let (len, offset) = r.longest[byte as usize];
+            lines.push(format!("  {:02x}    {:<12}  {:#010x}", byte, len, offset));
+        }
+        lines.push(String::from(""));
+        lines.push(format!("  padding (runs of at least {} bytes)", r.min_padding));
+        for p in &r.padding {
+            lines.push(format!("  {:02x}: {} regions, {} bytes", p.byte, p.regions, p.bytes));
+        }
+    }
+
+    if let Some(ref r) = report.rare {
+        lines.push(String::from(""));
+        lines.push(format!("  rare bytes, under {} times, with {} bytes either side", r.below, r.each_side));
+        let width = r.each_side * 3 - 1;
+        for s in &r.samples {
+            let before: Vec<String> = s.before.iter().map(|b| format!("{:02x}", b)).collect();
+            let after: Vec<String> = s.after.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = s.before.iter().chain([s.byte].iter()).chain(s.after.iter())
+                .map(|b| if (0x20..0x7f).contains(b) { *b as char } else { '.' })
+                .collect();
+            lines.push(format!(
+                "  {:02x}  {:#010x}  {:>w$} [{:02x}] {:<w$}  {}",
+                s.byte, s.offset, before.join(" "), s.byte, after.join(" "), ascii, w = width
+            ));
+        }
+    }
+
+    if let Some(ref r) = report.rate {
+        lines.push(String::from(""));
+        lines.push(format!("  rate, every {}s: {:.0} bytes a second over {:.1}s", r.interval, rate::overall(r), r.seconds));
+        lines.push(String::from("  time     offset           bytes/s  entropy"));
+        // bars against the fastest interval
+        let fastest = r.intervals.iter().map(|i| i.per_second()).fold(0.0, f64::max);
+        for i in &r.intervals {
+            let bar = if fastest > 0.0 { (i.per_second() / fastest * BAR_WIDTH).round() as usize } else { 0 };
+            lines.push(format!(
+                "  {:>6.0}s  {:#010x}  {:>12.0}  {:>7.4}  {}",
+                i.start, i.offset, i.per_second(), i.entropy, "#".repeat(bar)
+            ));
+        }
+    }
+
+    if let Some(ref h) = report.huffman {
+        let total: u64 = report.counts.iter().sum();
+        lines.push(String::from(""));
+        lines.push(String::from("  huffman code"));
+        lines.push(format!("  {:.4} bits per byte, {:.4} over the entropy", h.bits_per_byte, h.bits_per_byte - h.entropy));
+        lines.push(format!(
+            "  {} bytes coded, {:.2}% of {}, before the code table",
+            h.bytes(), if total == 0 { 0.0 } else { h.bytes() as f64 / total as f64 * 100.0 }, total
+        ));
+        lines.push(format!("  codes of {} to {} bits", h.shortest(), h.longest()));
+        if h.table {
+            lines.push(String::from(""));
+            lines.push(String::from("  byte  count         length  code"));
+            for (byte, code) in h.codes() {
+                lines.push(format!("  {:02x}    {:<12}  {:>6}  {}", byte, report.counts[byte as usize], code.len(), code));
+            }
+        }
+    }
+    lines
+}
+
+fn csv(report: &Report) -> Vec<String> {
+    let mut lines = Vec::new();
+    // first table, before the byte counts
+    if let Some(ref m) = report.meta {
+        lines.push(String::from("path,size,modified,analyzed"));
+        lines.push(format!(
+            "{},{},{},{}", csv_field(&m.path), m.size, modified(m).unwrap_or_default(), history::timestamp(m.analyzed)
+        ));
+        lines.push(String::from(""));
+    }
+    let mut header = String::from(if report.ci.is_some() { "byte,count,label,percent,ci_low,ci_high" } else { "byte,count,label" });
+    if report.first_last.is_some() { header.push_str(",first,last"); }
+    if report.dispersion.is_some() { header.push_str(",gap_cv,spread"); }
+    lines.push(header);
+    for (byte, byte_count) in report.counts.iter().enumerate() {
+        if *byte_count != 0 {
+            let mut line = format!("{},{},{}", byte, byte_count, csv_field(&byte_label(byte as u8)));
+            if let Some((pct, low, high)) = interval(report, *byte_count) {
+                line.push_str(&format!(",{:.6},{:.6},{:.6}", pct, low, high));
+            }
+            if let Some(ref f) = report.first_last { line.push_str(&format!(",{},{}", f[byte].0, f[byte].1)); }
+            if let Some(ref d) = report.dispersion {
+                line.push_str(&d[byte].map_or(String::from(","), |cv| format!(",{:.6},{}", cv, dispersion::label(cv))));
+            }
+            lines.push(line);
+        }
+    }
+
+    // second table, separated by a blank line
+    if let Some(ref p) = report.profile {
+        lines.push(String::from(""));
+        lines.push(String::from("offset,length,entropy"));
+        for point in &p.points {
+            lines.push(format!("{},{},{:.6}", point.offset, point.len, point.bits));
+        }
+    }
+
+    if let Some(ref r) = report.regions {
+        lines.push(String::from(""));
+        let mut header = String::from("offset,length,entropy,distinct");
+        for n in 1..=TOP_BYTES { header.push_str(&format!(",top{0},top{0}_count", n)); }
+        lines.push(header);
+        for region in &r.regions {
+            let mut line = format!("{},{},{:.6},{}", region.offset, region.len, region.entropy, region.distinct);
+            for n in 0..TOP_BYTES {
+                match region.top.get(n) {
+                    Some(&(byte, c)) => line.push_str(&format!(",{},{}", byte, c)),
+                    None => line.push_str(",,"),
+                }
+            }
+            lines.push(line);
+        }
+    }
+
+    if let Some(ref segments) = report.segments {
+        lines.push(String::from(""));
+        lines.push(String::from("offset,length,entropy"));
+        for s in segments {
+            lines.push(format!("{},{},{:.6}", s.offset, s.len, s.entropy));
+        }
+    }
+
+    if let Some(ref r) = report.runs {
+        lines.push(String::from(""));
+        lines.push(String::from("byte,longest_run,offset"));
+        for byte in runs::prominent(report.counts) {
+            let (len, offset) = r.longest[byte as usize];
+            lines.push(format!("{},{},{}", byte, len, offset));
+        }
+        lines.push(String::from(""));
+        lines.push(String::from("padding_byte,min_length,regions,bytes"));
+        for p in &r.padding {
+            lines.push(format!("{},{},{},{}", p.byte, r.min_padding, p.regions, p.bytes));
+        }
+    }
+
+    if let Some(ref r) = report.rare {
+        lines.push(String::from(""));
+        lines.push(String::from("rare_byte,offset,before,after"));
+        for s in &r.samples {
+            lines.push(format!("{},{},{},{}", s.byte, s.offset, hex(&s.before), hex(&s.after)));
+        }
+    }
+
+    if let Some(ref r) = report.rate {
+        lines.push(String::from(""));
+        lines.push(String::from("seconds_in,seconds,offset,bytes,bytes_per_second,entropy"));
+        for i in &r.intervals {
+            lines.push(format!("{:.3},{:.3},{},{},{:.1},{:.6}", i.start, i.seconds, i.offset, i.bytes, i.per_second(), i.entropy));
+        }
+    }
+
+    if let Some(ref h) = report.huffman {
+        lines.push(String::from(""));
+        lines.push(String::from("huffman_bits_per_byte,entropy,coded_bytes,shortest_code,longest_code"));
+        lines.push(format!("{:.6},{:.6},{},{},{}", h.bits_per_byte, h.entropy, h.bytes(), h.shortest(), h.longest()));
+        if h.table {
+            lines.push(String::from(""));
+            lines.push(String::from("byte,count,length,code"));
+            for (byte, code) in h.codes() {
+                lines.push(format!("{},{},{},{}", byte, report.counts[byte as usize], code.len(), code));
+            }
+        }
+    }
+
+    if !report.hashes.is_empty() {
+        lines.push(String::from(""));
+        lines.push(String::from("hash,value"));
+        for (name, value) in &report.hashes { lines.push(format!("{},{}", name, value)); }
+    }
+
+    if report.truncated {
+        lines.push(String::from(""));
+        lines.push(String::from("truncated_after"));
+        lines.push(report.counts.iter().sum::<u64>().to_string());
+    }
+    lines
+}
+
+fn json(report: &Report) -> Vec<String> {
+    // each top level key is rendered on its own, then they're all joined up
+    let mut sections = Vec::new();
+
+    if let Some(ref m) = report.meta {
+        sections.push(format!("  \"file\": {}", meta_json(m)));
+    }
+
+    let bytes: Vec<String> = report.counts.iter().enumerate()
+        .filter(|&(_, c)| *c != 0)
+        .map(|(byte, c)| {
+            let ci = interval(report, *c).map_or(String::new(), |(pct, low, high)| {
+                format!(", \"percent\": {:.6}, \"ci_low\": {:.6}, \"ci_high\": {:.6}", pct, low, high)
+            });
+            let first_last = report.first_last.as_ref().map_or(String::new(), |f| format!(", \"first\": {}, \"last\": {}", f[byte].0, f[byte].1));
+            let spread = report.dispersion.as_ref().map_or(String::new(), |d| match d[byte] {
+                Some(cv) => format!(", \"gap_cv\": {:.6}, \"spread\": {}", cv, json_str(dispersion::label(cv))),
+                None => String::from(", \"gap_cv\": null, \"spread\": null"),
+            });
+            format!("    {{\"byte\": {}, \"count\": {}, \"label\": {}{}{}{}}}", byte, c, json_str(&byte_label(byte as u8)), ci, first_last, spread)
+        })
+        .collect();
+    if let Some(level) = report.ci { sections.push(format!("  \"confidence\": {}", level)); }
+    sections.push(format!("  \"bytes\": [\n{}\n  ]", bytes.join(",\n")));
+
+    if let Some(ref p) = report.profile {
+        let points: Vec<String> = p.points.iter()
+            .map(|point| format!(
+                "      {{\"offset\": {}, \"length\": {}, \"entropy\": {:.6}}}",
+                point.offset, point.len, point.bits
+            ))
+            .collect();
+        sections.push(format!(
+            "  \"entropy_profile\": {{\n    \"window\": {},\n    \"step\": {},\n    \"points\": [\n{}\n    ]\n  }}",
+            p.window, p.step, points.join(",\n")
+        ));
+    }
+
+    if let Some(ref r) = report.regions {
+        let blocks: Vec<String> = r.regions.iter()
+            .map(|region| {
+                let top: Vec<String> = region.top.iter()
+                    .map(|&(byte, c)| format!("{{\"byte\": {}, \"count\": {}}}", byte, c))
+                    .collect();
+                format!(
+                    "      {{\"offset\": {}, \"length\": {}, \"entropy\": {:.6}, \"distinct\": {}, \"top\": [{}]}}",
+                    region.offset, region.len, region.entropy, region.distinct, top.join(", ")
+                )
+            })
+            .collect();
+        sections.push(format!(
+            "  \"blocks\": {{\n    \"block_size\": {},\n    \"content_defined\": {},\n    \"blocks\": [\n{}\n    ]\n  }}",
+            r.block_size, r.content_defined, blocks.join(",\n")
+        ));
+    }
+
+    if let Some(ref segments) = report.segments {
+        let segments: Vec<String> = segments.iter()
+            .map(|s| format!("    {{\"offset\": {}, \"length\": {}, \"entropy\": {:.6}}}", s.offset, s.len, s.entropy))
+            .collect();
+        sections.push(format!("  \"segments\": [\n{}\n  ]", segments.join(",\n")));
+    }
+
+    if let Some(ref r) = report.runs {
+        let longest: Vec<String> = runs::prominent(report.counts).iter()
+            .map(|byte| {
+                let (len, offset) = r.longest[*byte as usize];
+                format!("      {{\"byte\": {}, \"length\": {}, \"offset\": {}}}", byte, len, offset)
+            })
+            .collect();
+        let padding: Vec<String> = r.padding.iter()
+            .map(|p| format!("      {{\"byte\": {}, \"regions\": {}, \"bytes\": {}}}", p.byte, p.regions, p.bytes))
+            .collect();
+        sections.push(format!(
+            "  \"runs\": {{\n    \"longest\": [\n{}\n    ],\n    \"min_padding\": {},\n    \"padding\": [\n{}\n    ]\n  }}",
+            longest.join(",\n"), r.min_padding, padding.join(",\n")
+        ));
+    }
+
+    if let Some(ref r) = report.rare {
+        let samples: Vec<String> = r.samples.iter()
+            .map(|s| format!(
+                "      {{\"byte\": {}, \"offset\": {}, \"before\": \"{}\", \"after\": \"{}\"}}",
+                s.byte, s.offset, hex(&s.before), hex(&s.after)
+            ))
+            .collect();
+        sections.push(format!(
+            "  \"rare\": {{\n    \"below\": {},\n    \"each_side\": {},\n    \"samples\": [\n{}\n    ]\n  }}",
+            r.below, r.each_side, samples.join(",\n")
+        ));
+    }
+
+    if let Some(ref r) = report.rate {
+        let intervals: Vec<String> = r.intervals.iter()
+            .map(|i| format!(
+                "      {{\"start\": {:.3}, \"seconds\": {:.3}, \"offset\": {}, \"bytes\": {}, \"bytes_per_second\": {:.1}, \"entropy\": {:.6}}}",
+                i.start, i.seconds, i.offset, i.bytes, i.per_second(), i.entropy
+            ))
+            .collect();
+        sections.push(format!(
+            "  \"rate\": {{\n    \"interval\": {},\n    \"seconds\": {:.3},\n    \"bytes_per_second\": {:.1},\n    \"intervals\": [\n{}\n    ]\n  }}",
+            r.interval, r.seconds, rate::overall(r), intervals.join(",\n")
+        ));
+    }
+
+    if let Some(ref h) = report.huffman {
+        let codes = if h.table {
+            let codes: Vec<String> = h.codes().iter()
+                .map(|(byte, code)| format!("      {{\"byte\": {}, \"length\": {}, \"code\": \"{}\"}}", byte, code.len(), code))
+                .collect();
+            format!(",\n    \"codes\": [\n{}\n    ]", codes.join(",\n"))
+        } else {
+            String::new()
+        };
+        sections.push(format!(
+            "  \"huffman\": {{\n    \"bits_per_byte\": {:.6},\n    \"entropy\": {:.6},\n    \"coded_bytes\": {},\n    \"shortest_code\": {},\n    \"longest_code\": {}{}\n  }}",
+            h.bits_per_byte, h.entropy, h.bytes(), h.shortest(), h.longest(), codes
+        ));
+    }
+
+    if !report.hashes.is_empty() {
+        sections.push(format!("  \"hashes\": {}", hashes_json(&report.hashes)));
+    }
+
+    if report.truncated {
+        sections.push(format!("  \"truncated\": true,\n  \"truncated_after\": {}", report.counts.iter().sum::<u64>()));
+    }
+
+    vec![String::from("{"), sections.join(",\n"), String::from("}")]
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hashes_json(hashes: &[(&'static str, String)]) -> String {
+    let fields: Vec<String> = hashes.iter().map(|(name, value)| format!("\"{}\": \"{}\"", name, value)).collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn meta_json(m: &Meta) -> String {
+    format!(
+        "{{\"path\": {}, \"size\": {}, \"modified\": {}, \"analyzed\": {}}}",
+        json_str(&m.path), m.size, modified(m).map_or(String::from("null"), |t| json_str(&t)), json_str(&history::timestamp(m.analyzed))
+    )
+}
+
+// inline data blocks followed by a script plotting them, so the output can
+// be fed straight to gnuplot (needs gnuplot 5 for the data blocks)
+fn gnuplot(report: &Report) -> Vec<String> {
+    let mut lines = vec![
+        String::from("# generated by freqs. run with: gnuplot -p <this file>"),
+        String::from("$bytes << EOD"),
+        String::from("# byte count"),
+    ];
+    if report.truncated { lines.insert(1, format!("# {}", truncated_note(report))); }
+    if let Some(ref m) = report.meta {
+        lines.splice(1..1, [
+            format!("# file: {}", m.path),
+            format!("# size: {} bytes, modified {}", m.size, modified(m).unwrap_or_else(|| String::from("unknown"))),
+            format!("# analyzed: {}", history::timestamp(m.analyzed)),
+        ]);
+    }
+    let at = lines.len() - 2;
+    lines.splice(at..at, report.hashes.iter().map(|(name, value)| format!("# {}: {}", name, value)));
+    for (byte, byte_count) in report.counts.iter().enumerate() {
+        lines.push(format!("{} {}", byte, byte_count));
+    }
+    lines.push(String::from("EOD"));
+
+    if let Some(ref p) = report.profile {
+        lines.push(String::from("$entropy << EOD"));
+        lines.push(String::from("# offset entropy"));
+        for point in &p.points {
+            lines.push(format!("{} {:.6}", point.offset, point.bits));
+        }
+        lines.push(String::from("EOD"));
+    }
+
+    lines.push(String::from(""));
+    if report.profile.is_some() { lines.push(String::from("set multiplot layout 2,1")); }
+    lines.extend([
+        "set title \"byte frequencies\"",
+        "set xlabel \"byte\"",
+        "set ylabel \"count\"",
+        "set xrange [-1:256]",
+        "set xtics (\"0x00\" 0, \"0x40\" 64, \"0x80\" 128, \"0xc0\" 192, \"0xff\" 255)",
+        "set style fill solid",
+        "set boxwidth 0.8",
+        "plot $bytes using 1:2 with boxes notitle",
+    ].iter().map(|l| l.to_string()));
+
+    if let Some(ref p) = report.profile {
+        lines.extend([
+            format!("set title \"entropy profile (window {}, step {})\"", p.window, p.step),
+            String::from("set xlabel \"offset\""),
+            String::from("set ylabel \"bits per byte\""),
+            String::from("set autoscale x"),
+            String::from("set xtics auto"),
+            String::from("set yrange [0:8]"),
+            String::from("plot $entropy using 1:2 with lines notitle"),
+            String::from("unset multiplot"),
+        ].iter().cloned());
+    }
+    lines
+}
+
+// a vega-lite spec with the data inlined. the histogram and entropy
+// profile (if any) are stacked on top of each other.
+fn vega(report: &Report) -> Vec<String> {
+    let values: Vec<String> = report.counts.iter().enumerate()
+        .map(|(byte, c)| format!(
+            "          {{\"byte\": {}, \"hex\": \"{:02x}\", \"count\": {}, \"label\": {}}}",
+            byte, byte, c, json_str(&byte_label(byte as u8))
+        ))
+        .collect();
+    let mut charts = vec![format!(
+        r#"    {{
+      "title": "byte frequencies",
+      "width": 800,
+      "data": {{
+        "values": [
+{}
+        ]
+      }},
+      "mark": "bar",
+      "encoding": {{
+        "x": {{"field": "byte", "type": "quantitative", "scale": {{"domain": [0, 255]}}}},
+        "y": {{"field": "count", "type": "quantitative"}},
+        "tooltip": [
+          {{"field": "hex", "type": "nominal"}},
+          {{"field": "label", "type": "nominal"}},
+          {{"field": "count", "type": "quantitative"}}
+        ]
+      }}
+    }}"#,
+        values.join(",\n")
+    )];
+
+    if let Some(ref p) = report.profile {
+        let points: Vec<String> = p.points.iter()
+            .map(|point| format!(
+                "          {{\"offset\": {}, \"length\": {}, \"entropy\": {:.6}}}",
+                point.offset, point.len, point.bits
+            ))
+            .collect();
+        charts.push(format!(
+            r#"    {{
+      "title": "entropy profile (window {}, step {})",
+      "width": 800,
+      "data": {{
+        "values": [
+{}
+        ]
+      }},
+      "mark": "line",
+      "encoding": {{
+        "x": {{"field": "offset", "type": "quantitative"}},
+        "y": {{"field": "entropy", "type": "quantitative", "scale": {{"domain": [0, 8]}}, "title": "bits per byte"}},
+        "tooltip": [
+          {{"field": "offset", "type": "quantitative"}},
+          {{"field": "entropy", "type": "quantitative"}}
+        ]
+      }}
+    }}"#,
+            p.window, p.step, points.join(",\n")
+        ));
+    }
+
+    let mut lines = vec![
+        String::from("{"),
+        String::from("  \"$schema\": \"https://vega.github.io/schema/vega-lite/v5.json\","),
+        String::from("  \"description\": \"generated by freqs\","),
+    ];
+    let mut usermeta = Vec::new();
+    if let Some(ref m) = report.meta { usermeta.push(format!("\"file\": {}", meta_json(m))); }
+    if !report.hashes.is_empty() { usermeta.push(format!("\"hashes\": {}", hashes_json(&report.hashes))); }
+    if !usermeta.is_empty() { lines.push(format!("  \"usermeta\": {{{}}},", usermeta.join(", "))); }
+    lines.extend([
+        String::from("  \"vconcat\": ["),
+        charts.join(",\n"),
+        String::from("  ]"),
+        String::from("}"),
+    ]);
+    lines
+}
+
+// one line per file, with a sparkline of its histogram in text mode or the
+// bucket counts themselves otherwise
+// skipped are the files left out by size, listed after the others
+pub fn render_summaries(format: Format, summaries: &[Summary], skipped: &[(String, u64)]) -> Vec<String> {
+    let printable = |s: &Summary| stats::printable_ratio(&s.analysis.counts) * 100.0;
+    match format {
+        Format::Csv => {
+            let mut header = String::from("path,size,entropy,distinct,top_byte,printable_percent,mean,stddev,skewness,kurtosis");
+            for b in 0..16 { header.push_str(&format!(",{:#04x}", b * 16)); }
+            // every file has the same digests
+            for (name, _) in summaries.first().map_or(&[][..], |s| &s.analysis.hashes[..]) { header.push_str(&format!(",{}", name)); }
+            let mut lines = vec![header];
+            for s in summaries {
+                let buckets: Vec<String> = buckets(&s.analysis.counts).iter().map(|b| b.to_string()).collect();
+                let hashes: String = s.analysis.hashes.iter().map(|(_, value)| format!(",{}", value)).collect();
+                let m = stats::moments(&s.analysis.counts);
+                lines.push(format!(
+                    "{},{},{:.6},{},{},{:.4},{:.6},{:.6},{:.6},{:.6},{}{}",
+                    csv_field(&s.path), s.analysis.len, summary_entropy(s), stats::distinct(&s.analysis.counts),
+                    top_byte(&s.analysis.counts).map_or(String::new(), |b| format!("{:#04x}", b)), printable(s),
+                    m.mean, m.stddev, m.skewness, m.kurtosis, buckets.join(","), hashes
+                ));
+            }
+            if !skipped.is_empty() {
+                lines.push(String::from(""));
+                lines.push(String::from("skipped,size"));
+                for (path, size) in skipped { lines.push(format!("{},{}", csv_field(path), size)); }
+            }
+            lines
+        },
+        Format::Json => {
+            let files: Vec<String> = summaries.iter()
+                .map(|s| {
+                    let buckets: Vec<String> = buckets(&s.analysis.counts).iter().map(|b| b.to_string()).collect();
+                    let hashes = if s.analysis.hashes.is_empty() { String::new() } else {
+                        format!(", \"hashes\": {}", hashes_json(&s.analysis.hashes))
+                    };
+                    let m = stats::moments(&s.analysis.counts);
+                    format!(
+                        "    {{\"path\": {}, \"size\": {}, \"entropy\": {:.6}, \"distinct\": {}, \"top\": {}, \"printable_percent\": {:.4}, {}, \"buckets\": [{}]{}{}}}",
+                        json_str(&s.path), s.analysis.len, summary_entropy(s), stats::distinct(&s.analysis.counts),
+                        top_byte(&s.analysis.counts).map_or(String::from("null"), |b| b.to_string()), printable(s),
+                        moments_json(&m), buckets.join(", "), hashes,
+                        if s.analysis.truncated { ", \"truncated\": true" } else { "" }
+                    )
+                })
+                .collect();
+            if skipped.is_empty() { return vec![format!("{{\n  \"files\": [\n{}\n  ]\n}}", files.join(",\n"))]; }
+            let skipped: Vec<String> = skipped.iter()
+                .map(|(path, size)| format!("    {{\"path\": {}, \"size\": {}}}", json_str(path), size))
+                .collect();
+            vec![format!("{{\n  \"files\": [\n{}\n  ],\n  \"skipped\": [\n{}\n  ]\n}}", files.join(",\n"), skipped.join(",\n"))]
+        },
+        _ => {
+            let width = summaries.iter().map(|s| s.path.chars().count()).max().unwrap_or(0).max(4);
+            let mut lines = vec![format!(
+                "  {:<w$}  {:>12}  {:>7}  {:>8}  {:>4}  {:>9}  {:>6}  {:>6}  {:>6}  {:>7}",
+                "path", "size", "entropy", "distinct", "top", "printable", "mean", "stddev", "skew", "kurt", w = width
+            )];
+            for s in summaries {
+                let m = stats::moments(&s.analysis.counts);
+                lines.push(format!(
+                    "  {:<w$}  {:>12}  {:>7.4}  {:>8}  {:>4}  {:>8.2}%  {:>6.2}  {:>6.2}  {:>6.2}  {:>7.2}  {}{}{}",
+                    s.path, s.analysis.len, summary_entropy(s), stats::distinct(&s.analysis.counts),
+                    top_byte(&s.analysis.counts).map_or(String::from("-"), |b| format!("{:#04x}", b)), printable(s),
+                    m.mean, m.stddev, m.skewness, m.kurtosis, sparkline(&s.analysis.counts),
+                    s.analysis.hashes.iter().map(|(_, value)| format!("  {}", value)).collect::<String>(),
+                    if s.analysis.truncated { "  (truncated)" } else { "" }, w = width
+                ));
+            }
+            if !skipped.is_empty() {
+                let width = skipped.iter().map(|(path, _)| path.chars().count()).max().unwrap_or(0);
+                lines.push(String::from(""));
+                lines.push(format!("  {} skipped, outside the size limits", skipped.len()));
+                for (path, size) in skipped { lines.push(format!("  {:<w$}  {:>12}", path, size, w = width)); }
+            }
+            lines
+        },
+    }
+}
+
+pub const SORT_KEYS: [&str; 5] = ["path", "size", "entropy", "distinct", "printable"];
+
+// for --sort-by, the biggest first, except for paths
+pub fn sort_summaries(summaries: &mut [Summary], key: &str) {
+    let value = |s: &Summary| match key {
+        "size" => s.analysis.len as f64,
+        "entropy" => summary_entropy(s),
+        "distinct" => stats::distinct(&s.analysis.counts) as f64,
+        _ => stats::printable_ratio(&s.analysis.counts),
+    };
+    match key {
+        "path" => summaries.sort_by(|a, b| a.path.cmp(&b.path)),
+        _ => summaries.sort_by(|a, b| value(b).total_cmp(&value(a)).then(a.path.cmp(&b.path))),
+    }
+}
+
+// --summary-line: one line per file and nothing else, no header, so runs
+// over batches of files (from xargs) put together make one table. tab
+// separated, or csv, or a json object per line
+pub fn render_summary_lines(format: Format, summaries: &[Summary]) -> Vec<String> {
+    summaries.iter()
+        .map(|s| {
+            let counts = &s.analysis.counts;
+            let top = top_byte(counts);
+            let entropy = summary_entropy(s);
+            let distinct = stats::distinct(counts);
+            let m = stats::moments(counts);
+            match format {
+                Format::Csv => format!(
+                    "{},{},{:.6},{},{},{:.6},{:.6},{:.6},{:.6}{}",
+                    csv_field(&s.path), s.analysis.len, entropy, distinct, top.map_or(String::new(), |b| format!("{:#04x}", b)),
+                    m.mean, m.stddev, m.skewness, m.kurtosis,
+                    s.analysis.hashes.iter().map(|(_, value)| format!(",{}", value)).collect::<String>()
+                ),
+                Format::Json => format!(
+                    "{{\"path\": {}, \"size\": {}, \"entropy\": {:.6}, \"distinct\": {}, \"top\": {}, {}{}}}",
+                    json_str(&s.path), s.analysis.len, entropy, distinct, top.map_or(String::from("null"), |b| b.to_string()), moments_json(&m),
+                    if s.analysis.hashes.is_empty() { String::new() } else { format!(", \"hashes\": {}", hashes_json(&s.analysis.hashes)) }
+                ),
+                _ => format!(
+                    "{}\t{}\t{:.6}\t{}\t{}\t{:.6}\t{:.6}\t{:.6}\t{:.6}{}",
+                    s.path.replace(['\t', '\n'], " "), s.analysis.len, entropy, distinct, top.map_or(String::from("-"), |b| format!("{:#04x}", b)),
+                    m.mean, m.stddev, m.skewness, m.kurtosis,
+                    s.analysis.hashes.iter().map(|(_, value)| format!("\t{}", value)).collect::<String>()
+                ),
+            }
+        })
+        .collect()
+}
+
+// the most common byte, the lowest on a tie, none for an empty file
+fn top_byte(counts: &[u64; 256]) -> Option<usize> {
+    (0..256).filter(|&b| counts[b] > 0).max_by_key(|&b| (counts[b], 255 - b))
+}
+
+// the four of them as json fields, to go inside an object
+fn moments_json(m: &stats::Moments) -> String {
+    format!("\"mean\": {:.6}, \"stddev\": {:.6}, \"skewness\": {:.6}, \"kurtosis\": {:.6}", m.mean, m.stddev, m.skewness, m.kurtosis)
+}
+
+fn summary_entropy(s: &Summary) -> f64 {
+    entropy::shannon(&s.analysis.counts, s.analysis.len)
+}
+
+// counts summed over 16 buckets of 16 byte values each
+fn buckets(counts: &[u64; 256]) -> [u64; 16] {
+    let mut buckets = [0u64; 16];
+    for (byte, c) in counts.iter().enumerate() { buckets[byte >> 4] += *c; }
+    buckets
+}
+
+pub const SPARKS: [char; 8] = ['\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}', '\u{2588}'];
+
+// empty buckets are left blank so they stand out from merely small ones
+pub fn sparkline(counts: &[u64; 256]) -> String {
+    let buckets = buckets(counts);
+    let max = *buckets.iter().max().unwrap();
+    buckets.iter()
+        .map(|b| if *b == 0 { ' ' } else {
+            SPARKS[(*b as f64 / max as f64 * (SPARKS.len() - 1) as f64).round() as usize]
+        })
+        .collect()
+}
+
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else { s.to_string() }
+}
+
+pub fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn byte_label(byte: u8) -> String {
+    match byte {
+        // gross-ass to_string()s on all of these because I got
+        // tired of fucking around trying to get a str from
+        // format!() to live long enough.
+        0x00 => "<NULL>".to_string(),
+        0x01 => "<SOH>".to_string(),
+        0x02 => "<STX>".to_string(),
+        0x03 => "<ETX>".to_string(),
+        0x04 => "<EOT>".to_string(),
+        0x05 => "<ENQ>".to_string(),
+        0x06 => "<ACK>".to_string(),
+        0x07 => "<BEL".to_string(),
+        0x08 => "<BS>".to_string(),
+        0x09 => "<TAB>".to_string(),
+        0x0a => "\\n".to_string(),
+        0x0b => "<VT>".to_string(),
+        0x0c => "<FF>".to_string(),
+        0x0d => "\\r".to_string(),
+        0x0e => "<SO>".to_string(),
+        0x0f => "<SI>".to_string(),
+        0x10 => "<DLE>".to_string(),
+        0x11 => "<DC1>".to_string(),
+        0x12 => "<DC2>".to_string(),
+        0x13 => "<DC3>".to_string(),
+        0x14 => "<DC4>".to_string(),
+        0x15 => "<NAK>".to_string(),
+        0x16 => "<SYN>".to_string(),
+        0x17 => "<ETB>".to_string(),
+        0x18 => "<EM>".to_string(),
+        0x19 => "<SUB>".to_string(),
+        0x1a => "<SUB>".to_string(),
+        0x1b => "<ESC>".to_string(),
+        0x1c => "<FS>".to_string(),
+        0x1d => "<GS>".to_string(),
+        0x1e => "<RS>".to_string(),
+        0x1f => "<US>".to_string(),
+        0x20 => "<space>".to_string(),
+        0x7f => "<DEL>".to_string(),
+        0xa0 => "<non break space>".to_string(),
+        0xad => "<soft hyphen>".to_string(),
+
+        b => format!("{}", b as char)
+    }
+}
@@ -0,0 +1,89 @@
+/*
+    --stats: what the run cost, printed to stderr once it's over, for
+    comparing ways of running the same thing (a thread per core over a
+    directory or one file at a time, --cache or not, decompressing on the
+    fly...) without reaching for a profiler. Wall time, cpu time and the
+    peak resident memory come from getrusage, where there is one; the
+    bytes, chunks and files are counted by the counting pass as it goes.
+*/
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static CHUNKS: AtomicU64 = AtomicU64::new(0);
+static FILES: AtomicU64 = AtomicU64::new(0);
+static CACHED: AtomicU64 = AtomicU64::new(0);
+static THREADS: AtomicU64 = AtomicU64::new(1);
+
+pub fn chunk(bytes: usize) {
+    BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+    CHUNKS.fetch_add(1, Ordering::Relaxed);
+}
+
+// an input read through, or its results taken from the cache instead
+pub fn file(cached: bool) {
+    FILES.fetch_add(1, Ordering::Relaxed);
+    if cached { CACHED.fetch_add(1, Ordering::Relaxed); }
+}
+
+pub fn threads(n: usize) {
+    THREADS.fetch_max(n as u64, Ordering::Relaxed);
+}
+
+struct Usage {
+    user: f64,      // seconds
+    system: f64,
+    peak: u64,      // bytes resident at most
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn usage() -> Option<Usage> {
+    use std::os::raw::{c_int, c_long};
+
+    const RUSAGE_SELF: c_int = 0;
+
+    #[repr(C)]
+    struct Timeval {
+        sec: c_long,
+        usec: c_long,
+    }
+
+    // only the times and the peak are looked at, the rest is padding
+    #[repr(C)]
+    struct Rusage {
+        utime: Timeval,
+        stime: Timeval,
+        maxrss: c_long,     // in kilobytes
+        rest: [c_long; 13],
+    }
+
+    extern "C" {
+        fn getrusage(who: c_int, usage: *mut Rusage) -> c_int;
+    }
+    let mut r = Rusage { utime: Timeval { sec: 0, usec: 0 }, stime: Timeval { sec: 0, usec: 0 }, maxrss: 0, rest: [0; 13] };
+    // safe: r is laid out as the kernel's struct rusage, and ours to write
+    if unsafe { getrusage(RUSAGE_SELF, &mut r) } != 0 { return None; }
+    let seconds = |t: &Timeval| t.sec as f64 + t.usec as f64 / 1e6;
+    Some(Usage { user: seconds(&r.utime), system: seconds(&r.stime), peak: r.maxrss as u64 * 1024 })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn usage() -> Option<Usage> { None }
+
+pub fn print(started: Instant) {
+    let wall = started.elapsed().as_secs_f64();
+    let (bytes, chunks) = (BYTES.load(Ordering::Relaxed), CHUNKS.load(Ordering::Relaxed));
+    let (files, cached) = (FILES.load(Ordering::Relaxed), CACHED.load(Ordering::Relaxed));
+    eprintln!("  wall time    {:.3}s", wall);
+    match usage() {
+        Some(u) => {
+            eprintln!("  cpu time     {:.3}s (user {:.3}s, system {:.3}s)", u.user + u.system, u.user, u.system);
+            eprintln!("  peak memory  {} bytes", u.peak);
+        },
+        None => eprintln!("  cpu time and peak memory aren't known here"),
+    }
+    eprintln!("  threads      {}", THREADS.load(Ordering::Relaxed));
+    eprintln!("  read         {} bytes in {} chunks, from {} inputs ({} from the cache)", bytes, chunks, files, cached);
+    if wall > 0.0 { eprintln!("  throughput   {:.0} bytes a second", bytes as f64 / wall); }
+}
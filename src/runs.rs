@@ -0,0 +1,88 @@
+/*
+    Runs of the same byte: the longest one of each byte value and where it
+    starts, and padding, meaning long runs of 0x00 or 0xff. Firmware and
+    flash dumps are full of the latter, and how much there is says a lot
+    about how full the image really is.
+*/
+
+// runs of 0x00 or 0xff at least this long count as padding
+pub const DEFAULT_MIN_PADDING: u64 = 512;
+pub const PADDING_BYTES: [u8; 2] = [0x00, 0xff];
+
+pub struct Runs {
+    pub longest: Vec<(u64, u64)>,   // length and offset, per byte value
+    pub min_padding: u64,
+    pub padding: Vec<Padding>,      // one per entry of PADDING_BYTES
+}
+
+pub struct Padding {
+    pub byte: u8,
+    pub regions: u64,
+    pub bytes: u64,
+}
+
+pub struct RunTracker {
+    runs: Runs,
+    byte: u8,       // the run in progress
+    start: u64,
+    len: u64,
+    offset: u64,    // of the next byte
+}
+
+impl RunTracker {
+    pub fn new(min_padding: u64) -> RunTracker {
+        RunTracker {
+            runs: Runs {
+                longest: vec![(0, 0); 256],
+                min_padding,
+                padding: PADDING_BYTES.iter().map(|b| Padding { byte: *b, regions: 0, bytes: 0 }).collect(),
+            },
+            byte: 0,
+            start: 0,
+            len: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for byte in chunk {
+            if *byte != self.byte || self.len == 0 {
+                self.end_run();
+                self.byte = *byte;
+                self.start = self.offset;
+            }
+            self.len += 1;
+            self.offset += 1;
+        }
+    }
+
+    pub fn finish(mut self) -> Runs {
+        self.end_run();
+        self.runs
+    }
+
+    fn end_run(&mut self) {
+        if self.len == 0 { return; }
+        let (byte, len) = (self.byte, self.len);
+        let longest = &mut self.runs.longest[self.byte as usize];
+        if self.len > longest.0 { *longest = (self.len, self.start); }
+        if self.len >= self.runs.min_padding {
+            if let Some(p) = self.runs.padding.iter_mut().find(|p| p.byte == byte) {
+                p.regions += 1;
+                p.bytes += len;
+            }
+        }
+        self.len = 0;
+    }
+}
+
+// how many of the most common bytes get their longest run reported
+pub const PROMINENT: usize = 8;
+
+// the most common byte values, most common first
+pub fn prominent(counts: &[u64; 256]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = (0..=255u8).filter(|b| counts[*b as usize] != 0).collect();
+    bytes.sort_by(|a, b| counts[*b as usize].cmp(&counts[*a as usize]));
+    bytes.truncate(PROMINENT);
+    bytes
+}
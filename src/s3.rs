@@ -0,0 +1,64 @@
+/*
+    s3://bucket/key inputs, built in with --features s3. The object is
+    fetched over https like any other url, with curl signing the request
+    (sigv4) from the usual AWS_* variables, so nothing new is needed for
+    the keys. AWS_ENDPOINT_URL points it at minio, ceph and other stores
+    that talk s3. Without keys the request goes unsigned, for public buckets.
+*/
+
+use std::env;
+use std::io;
+
+use remote::Request;
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+pub fn request(url: &str) -> io::Result<Request> {
+    let (bucket, key) = match url["s3://".len()..].split_once('/') {
+        Some((b, k)) if !b.is_empty() && !k.is_empty() => (b, k),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "s3 urls go s3://bucket/key")),
+    };
+    let region = var("AWS_REGION").or_else(|| var("AWS_DEFAULT_REGION")).unwrap_or_else(|| String::from("us-east-1"));
+    let key = encode(key);
+
+    // other stores take the bucket in the path, as do buckets with dots
+    // in their names, which would break the certificate on aws
+    let url = match var("AWS_ENDPOINT_URL_S3").or_else(|| var("AWS_ENDPOINT_URL")) {
+        Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+        None if bucket.contains('.') => format!("https://s3.{}.amazonaws.com/{}/{}", region, bucket, key),
+        None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key),
+    };
+
+    let mut args = Vec::new();
+    let mut config = None;
+    if let (Some(id), Some(secret)) = (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+        args.push(String::from("--aws-sigv4"));
+        args.push(format!("aws:amz:{}:s3", region));
+        // on curl's stdin, so the secrets don't show up in the process list
+        let mut text = format!("user = \"{}:{}\"\n", quote(&id), quote(&secret));
+        if let Some(token) = var("AWS_SESSION_TOKEN") {
+            text.push_str(&format!("header = \"x-amz-security-token: {}\"\n", quote(&token)));
+        }
+        config = Some(text);
+    }
+    Ok(Request { url, args, config })
+}
+
+// keys are percent encoded by the segment, as sigv4 wants them
+fn encode(key: &str) -> String {
+    let mut out = String::new();
+    for b in key.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+// for a double quoted value in a curl config file
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
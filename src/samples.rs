@@ -0,0 +1,66 @@
+/*
+    freqs samples: the file as numbers for other rng test suites to read.
+    dieharder's file_input generator (-g 202) takes unsigned 32 bit words
+    in decimal under a short header; TestU01's text files and most other
+    harnesses take uniforms in [0, 1), one to a line. Words are read little
+    endian, as dieharder reads the raw file with -g 201, so both agree.
+*/
+
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use args::Options;
+use outfile;
+
+pub const KINDS: [&str; 2] = ["dieharder", "uniform"];
+
+// writes straight to the outfile or stdout, the samples of a big file
+// being far too many to keep around as lines
+pub fn run(opts: &Options) {
+    let path = &opts.inputs[0];
+    let written = match opts.out_path {
+        Some(ref out_path) => outfile::create(out_path, opts.force).and_then(|mut out| {
+            write(path, &opts.samples_name, &mut out)?;
+            out.finish()
+        }),
+        None => write(path, &opts.samples_name, &mut BufWriter::new(io::stdout().lock())),
+    };
+    match written {
+        // whatever was reading them, head and the like, has all it wants
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(e) => panic!("Could not write the samples of {}: {}", path, e),
+        Ok(()) => (),
+    }
+}
+
+// a last word short of 4 bytes is left out
+fn write<W: Write>(path: &str, kind: &str, out: &mut W) -> io::Result<()> {
+    let f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    };
+    let words = f.metadata()?.len() / 4;
+    if words == 0 { panic!("Nothing to sample, {} is shorter than one 32 bit word", path); }
+    if kind == "dieharder" {
+        writeln!(out, "#==================================================================")?;
+        writeln!(out, "# generator freqs  file {}", path)?;
+        writeln!(out, "#==================================================================")?;
+        writeln!(out, "type: d")?;
+        writeln!(out, "count: {}", words)?;
+        writeln!(out, "numbit: 32")?;
+    }
+
+    let mut reader = BufReader::new(f).take(words * 4);
+    let mut word = [0u8; 4];
+    for _ in 0..words {
+        reader.read_exact(&mut word)?;
+        let n = u32::from_le_bytes(word);
+        if kind == "dieharder" {
+            writeln!(out, "{}", n)?;
+        } else {
+            // 2^-32 apart, which 12 places still tell apart
+            writeln!(out, "{:.12}", n as f64 / 4294967296.0)?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,296 @@
+/*
+    --sections: for executables, counts each section on its own instead of
+    the file as one blob, since code, data, resources and so on have very
+    different distributions. Understands ELF (32 and 64 bit, either byte
+    order), PE and thin Mach-O. Sections that take no room in the file, like
+    .bss, are left out.
+*/
+
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use args::Options;
+use entropy;
+use report::{csv_field, json_str, sparkline, Format};
+
+const CHUNKSIZE: usize = 1024 * 128;
+
+pub struct Section {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+}
+
+struct Counted {
+    section: Section,
+    counts: [u64; 256],
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let mut f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let (kind, sections) = match find(&mut f) {
+        Ok(Some(found)) => found,
+        Ok(None) => panic!("{} isn't an ELF, PE or Mach-O file", path),
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    };
+
+    let mut counted = Vec::new();
+    for section in sections {
+        let counts = match count_range(&mut f, section.offset, section.size) {
+            Ok(c) => c,
+            Err(e) => panic!("Could not read section {}: {}", section.name, e),
+        };
+        counted.push(Counted { section, counts });
+    }
+    render(format, kind, &counted)
+}
+
+// the format and sections of an executable, or None if it isn't one
+pub fn find(f: &mut fs::File) -> io::Result<Option<(&'static str, Vec<Section>)>> {
+    let file_len = f.metadata()?.len();
+    let head = read_at(f, 0, 64)?;
+    let mut sections = if head.starts_with(b"\x7fELF") {
+        ("elf", elf(f, &head)?)
+    } else if head.starts_with(b"MZ") {
+        match pe(f, &head)? {
+            Some(s) => ("pe", s),
+            None => return Ok(None),
+        }
+    } else if head.len() >= 4 && macho_magic(&head).is_some() {
+        ("mach-o", macho(f, &head)?)
+    } else {
+        return Ok(None);
+    };
+
+    // headers can claim anything, only keep what's actually in the file
+    sections.1.retain(|s| s.size > 0 && s.offset < file_len);
+    for s in &mut sections.1 { s.size = s.size.min(file_len - s.offset); }
+    Ok(Some(sections))
+}
+
+fn elf(f: &mut fs::File, head: &[u8]) -> io::Result<Vec<Section>> {
+    let wide = head.get(4) == Some(&2);
+    let e = Fields { data: head, big: head.get(5) == Some(&2) };
+    let (shoff, shentsize, shnum, shstrndx) = if wide {
+        (e.u64(0x28), e.u16(0x3a), e.u16(0x3c), e.u16(0x3e))
+    } else {
+        (e.u32(0x20).map(u64::from), e.u16(0x2e), e.u16(0x30), e.u16(0x32))
+    };
+    let (shoff, shentsize, shnum, shstrndx) = match (shoff, shentsize, shnum, shstrndx) {
+        (Some(a), Some(b), Some(c), Some(d)) => (a, b as u64, c as u64, d as u64),
+        _ => return Err(bad("truncated elf header")),
+    };
+
+    let table = read_at(f, shoff, shentsize * shnum)?;
+    let t = Fields { data: &table, big: e.big };
+    // name offset, type, file offset and size of each section header
+    let headers: Vec<(u32, u32, u64, u64)> = (0..shnum)
+        .filter_map(|n| {
+            let at = (n * shentsize) as usize;
+            let (offset, size) = if wide {
+                (t.u64(at + 0x18), t.u64(at + 0x20))
+            } else {
+                (t.u32(at + 0x10).map(u64::from), t.u32(at + 0x14).map(u64::from))
+            };
+            Some((t.u32(at)?, t.u32(at + 4)?, offset?, size?))
+        })
+        .collect();
+
+    let names = match headers.get(shstrndx as usize) {
+        Some(&(_, _, offset, size)) => read_at(f, offset, size)?,
+        None => Vec::new(),
+    };
+    // skipping the null section and ones without file contents (nobits)
+    Ok(headers.iter()
+        .filter(|h| h.1 != 0 && h.1 != 8)
+        .map(|&(name, _, offset, size)| Section { name: c_str(names.get(name as usize..).unwrap_or(&[])), offset, size })
+        .collect())
+}
+
+fn pe(f: &mut fs::File, head: &[u8]) -> io::Result<Option<Vec<Section>>> {
+    let h = Fields { data: head, big: false };
+    let pe_at = match h.u32(0x3c) { Some(n) => n as u64, None => return Ok(None) };
+    let coff = read_at(f, pe_at, 24)?;
+    if !coff.starts_with(b"PE\0\0") { return Ok(None); }
+    let c = Fields { data: &coff, big: false };
+    let (count, optional) = match (c.u16(6), c.u16(20)) {
+        (Some(a), Some(b)) => (a as u64, b as u64),
+        _ => return Err(bad("truncated pe header")),
+    };
+
+    let table = read_at(f, pe_at + 24 + optional, count * 40)?;
+    let t = Fields { data: &table, big: false };
+    Ok(Some((0..count as usize)
+        .filter_map(|n| {
+            let at = n * 40;
+            Some(Section {
+                name: c_str(table.get(at..at + 8)?),
+                size: t.u32(at + 16)? as u64,
+                offset: t.u32(at + 20)? as u64,
+            })
+        })
+        .collect()))
+}
+
+// whether the magic is big endian, and whether it's the 64 bit flavour
+fn macho_magic(head: &[u8]) -> Option<(bool, bool)> {
+    match [head[0], head[1], head[2], head[3]] {
+        [0xce, 0xfa, 0xed, 0xfe] => Some((false, false)),
+        [0xcf, 0xfa, 0xed, 0xfe] => Some((false, true)),
+        [0xfe, 0xed, 0xfa, 0xce] => Some((true, false)),
+        [0xfe, 0xed, 0xfa, 0xcf] => Some((true, true)),
+        _ => None,
+    }
+}
+
+fn macho(f: &mut fs::File, head: &[u8]) -> io::Result<Vec<Section>> {
+    let (big, wide) = macho_magic(head).unwrap();
+    let h = Fields { data: head, big };
+    let (ncmds, cmds_size) = match (h.u32(16), h.u32(20)) {
+        (Some(a), Some(b)) => (a, b as u64),
+        _ => return Err(bad("truncated mach-o header")),
+    };
+    let cmds = read_at(f, if wide { 32 } else { 28 }, cmds_size)?;
+    let c = Fields { data: &cmds, big };
+
+    let mut sections = Vec::new();
+    let mut at = 0usize;
+    for _ in 0..ncmds {
+        let (cmd, cmd_size) = match (c.u32(at), c.u32(at + 4)) {
+            (Some(a), Some(b)) if b > 0 => (a, b as usize),
+            _ => break,
+        };
+        // lc_segment and lc_segment_64, each followed by its sections
+        let layout = match cmd {
+            0x01 if !wide => Some((48, 56, 68)),
+            0x19 if wide => Some((64, 72, 80)),
+            _ => None,
+        };
+        if let Some((nsects_at, first, stride)) = layout {
+            for n in 0..c.u32(at + nsects_at).unwrap_or(0) as usize {
+                let s = at + first + n * stride;
+                let (size, offset, flags) = if wide {
+                    (c.u64(s + 40), c.u32(s + 48), c.u32(s + 64))
+                } else {
+                    (c.u32(s + 36).map(u64::from), c.u32(s + 40), c.u32(s + 56))
+                };
+                let (size, offset, flags) = match (size, offset, flags) {
+                    (Some(size), Some(offset), Some(flags)) => (size, offset as u64, flags),
+                    _ => break,
+                };
+                // zerofill sections have no file contents
+                if [0x01, 0x0c, 0x12].contains(&(flags & 0xff)) { continue; }
+                let name = format!("{},{}", c_str(&cmds[s + 16..s + 32]), c_str(&cmds[s..s + 16]));
+                sections.push(Section { name, offset, size });
+            }
+        }
+        at += cmd_size;
+    }
+    Ok(sections)
+}
+
+// integers at an offset into a header, in its byte order
+struct Fields<'a> {
+    data: &'a [u8],
+    big: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn bytes<const N: usize>(&self, at: usize) -> Option<[u8; N]> {
+        let mut b: [u8; N] = self.data.get(at..at.checked_add(N)?)?.try_into().ok()?;
+        if !self.big { b.reverse(); }
+        Some(b)
+    }
+    fn u16(&self, at: usize) -> Option<u16> { self.bytes(at).map(u16::from_be_bytes) }
+    fn u32(&self, at: usize) -> Option<u32> { self.bytes(at).map(u32::from_be_bytes) }
+    fn u64(&self, at: usize) -> Option<u64> { self.bytes(at).map(u64::from_be_bytes) }
+}
+
+// a nul terminated (or padded) name
+fn c_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what)
+}
+
+// up to len bytes from offset, fewer if the file ends first
+pub fn read_at(f: &mut fs::File, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    f.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    f.take(len).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+pub fn count_range(f: &mut fs::File, offset: u64, len: u64) -> io::Result<[u64; 256]> {
+    f.seek(SeekFrom::Start(offset))?;
+    let mut reader = f.take(len);
+    let mut counts = [0u64; 256];
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if n == 0 { break; }
+        for byte in &chunk[..n] { counts[*byte as usize] += 1; }
+    }
+    Ok(counts)
+}
+
+fn render(format: Format, kind: &str, counted: &[Counted]) -> Vec<String> {
+    let entropy = |c: &Counted| entropy::shannon(&c.counts, c.section.size);
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("section,offset,size,entropy")];
+            for c in counted {
+                lines.push(format!(
+                    "{},{},{},{:.6}",
+                    csv_field(&c.section.name), c.section.offset, c.section.size, entropy(c)
+                ));
+            }
+            // then every section's counts, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("section,byte,count"));
+            for c in counted {
+                for (byte, n) in c.counts.iter().enumerate() {
+                    if *n != 0 { lines.push(format!("{},{},{}", csv_field(&c.section.name), byte, n)); }
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let sections: Vec<String> = counted.iter()
+                .map(|c| {
+                    let bytes: Vec<String> = c.counts.iter().enumerate()
+                        .filter(|&(_, n)| *n != 0)
+                        .map(|(byte, n)| format!("{{\"byte\": {}, \"count\": {}}}", byte, n))
+                        .collect();
+                    format!(
+                        "    {{\"name\": {}, \"offset\": {}, \"size\": {}, \"entropy\": {:.6}, \"bytes\": [{}]}}",
+                        json_str(&c.section.name), c.section.offset, c.section.size, entropy(c), bytes.join(", ")
+                    )
+                })
+                .collect();
+            vec![format!("{{\n  \"format\": \"{}\",\n  \"sections\": [\n{}\n  ]\n}}", kind, sections.join(",\n"))]
+        },
+        _ => {
+            let width = counted.iter().map(|c| c.section.name.chars().count()).max().unwrap_or(0).max(7);
+            let mut lines = vec![
+                format!("  {} sections", kind),
+                format!("  {:<w$}  {:<10}  {:>12}  entropy", "section", "offset", "size", w = width),
+            ];
+            for c in counted {
+                lines.push(format!(
+                    "  {:<w$}  {:#010x}  {:>12}  {:>7.4}  {}",
+                    c.section.name, c.section.offset, c.section.size, entropy(c), sparkline(&c.counts), w = width
+                ));
+            }
+            lines
+        },
+    }
+}
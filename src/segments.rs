@@ -0,0 +1,96 @@
+/*
+    Splits a file into segments where its byte distribution changes. The
+    stream is looked at a block at a time, and each block is compared to
+    the segment so far; when it's too far off, a new segment starts there.
+    Only the running segment is kept, so this works in one pass over files
+    of any size.
+*/
+
+use entropy;
+use stats;
+
+// blocks never get smaller than this, below it the noise in a block's own
+// histogram makes everything look like a change
+pub const MIN_BLOCK: u64 = 4096;
+// and beyond this many the blocks get bigger instead
+const BLOCKS_PER_FILE: u64 = 4096;
+// jensen-shannon distance to the segment so far from which a block starts
+// a new one
+pub const DEFAULT_THRESHOLD: f64 = 0.45;
+
+pub struct Segment {
+    pub offset: u64,
+    pub len: u64,
+    pub entropy: f64,
+}
+
+pub struct Segmenter {
+    block_size: u64,
+    threshold: f64,
+    block: [u64; 256],
+    filled: u64,
+    segment: [u64; 256],
+    offset: u64,
+    len: u64,
+    segments: Vec<Segment>,
+}
+
+pub fn block_size_for(file_len: u64) -> u64 {
+    file_len.div_ceil(BLOCKS_PER_FILE).max(MIN_BLOCK)
+}
+
+impl Segmenter {
+    pub fn new(block_size: u64, threshold: f64) -> Segmenter {
+        Segmenter {
+            block_size,
+            threshold,
+            block: [0u64; 256],
+            filled: 0,
+            segment: [0u64; 256],
+            offset: 0,
+            len: 0,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        while !chunk.is_empty() {
+            let take = ((self.block_size - self.filled) as usize).min(chunk.len());
+            for byte in &chunk[..take] { self.block[*byte as usize] += 1; }
+            self.filled += take as u64;
+            chunk = &chunk[take..];
+            if self.filled == self.block_size { self.end_block(); }
+        }
+    }
+
+    // a trailing partial block is too small to judge, so it just joins the
+    // last segment
+    pub fn finish(mut self) -> Vec<Segment> {
+        for (s, b) in self.segment.iter_mut().zip(self.block.iter()) { *s += *b; }
+        self.len += self.filled;
+        if self.len > 0 { self.end_segment(); }
+        self.segments
+    }
+
+    fn end_block(&mut self) {
+        if self.len > 0 {
+            let d = stats::js_divergence(&stats::proportions(&self.segment), &stats::proportions(&self.block));
+            if d.sqrt() > self.threshold { self.end_segment(); }
+        }
+        for (s, b) in self.segment.iter_mut().zip(self.block.iter()) { *s += *b; }
+        self.len += self.filled;
+        self.block = [0u64; 256];
+        self.filled = 0;
+    }
+
+    fn end_segment(&mut self) {
+        self.segments.push(Segment {
+            offset: self.offset,
+            len: self.len,
+            entropy: entropy::shannon(&self.segment, self.len),
+        });
+        self.offset += self.len;
+        self.segment = [0u64; 256];
+        self.len = 0;
+    }
+}
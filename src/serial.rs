@@ -0,0 +1,129 @@
+/*
+    --serial: opens a serial port for the counting pass, set to raw mode,
+    so nothing is translated or held back waiting for a line end, and to
+    the --baud rate if there's one. Reads give up after a tenth of a second
+    of quiet, so a silent line still stops when the time is up.
+*/
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::time::Instant;
+
+// the standard rates, in the order termios numbers them
+pub const BAUDS: [u32; 30] = [
+    50, 75, 110, 134, 150, 200, 300, 600, 1200, 1800, 2400, 4800, 9600, 19200, 38400,
+    57600, 115200, 230400, 460800, 500000, 576000, 921600, 1000000, 1152000, 1500000,
+    2000000, 2500000, 3000000, 3500000, 4000000,
+];
+
+pub struct Port {
+    file: File,
+    deadline: Option<Instant>,
+}
+
+pub fn open(path: &str, baud: Option<u32>, deadline: Option<Instant>) -> io::Result<Port> {
+    Ok(Port { file: tty::open(path, baud)?, deadline })
+}
+
+// a quiet line reads as nothing, which would end the count, so reads are
+// tried again until there's something or the time is up
+impl Read for Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.deadline.is_some_and(|d| Instant::now() >= d) { return Ok(0); }
+            let n = self.file.read(buf)?;
+            if n > 0 { return Ok(n); }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod tty {
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::raw::{c_int, c_uchar, c_uint};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    use super::BAUDS;
+
+    const O_NOCTTY: c_int = 0o400;
+    const O_NONBLOCK: c_int = 0o4000;
+    const F_SETFL: c_int = 4;
+    const TCSANOW: c_int = 0;
+    const TCIFLUSH: c_int = 0;
+    const CLOCAL: c_uint = 0o4000;
+    const CREAD: c_uint = 0o200;
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+
+    // glibc's layout
+    #[repr(C)]
+    #[derive(Default)]
+    struct Termios {
+        iflag: c_uint,
+        oflag: c_uint,
+        cflag: c_uint,
+        lflag: c_uint,
+        line: c_uchar,
+        cc: [c_uchar; 32],
+        ispeed: c_uint,
+        ospeed: c_uint,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, when: c_int, termios: *const Termios) -> c_int;
+        fn cfmakeraw(termios: *mut Termios);
+        fn cfsetispeed(termios: *mut Termios, speed: c_uint) -> c_int;
+        fn cfsetospeed(termios: *mut Termios, speed: c_uint) -> c_int;
+        fn tcflush(fd: c_int, queue: c_int) -> c_int;
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    }
+
+    // B50 is 1 up to B38400 at 15, the faster ones carry on from 0o10001
+    fn speed(baud: u32) -> Option<c_uint> {
+        let i = BAUDS.iter().position(|b| *b == baud)? as c_uint;
+        Some(if i < 15 { i + 1 } else { 0o10001 + i - 15 })
+    }
+
+    fn check(result: c_int) -> io::Result<()> {
+        if result < 0 { Err(io::Error::last_os_error()) } else { Ok(()) }
+    }
+
+    pub fn open(path: &str, baud: Option<u32>) -> io::Result<File> {
+        // without O_NONBLOCK, opening can wait on the modem lines
+        let file = OpenOptions::new().read(true).custom_flags(O_NOCTTY | O_NONBLOCK).open(path)?;
+        let fd = file.as_raw_fd();
+        let mut t = Termios::default();
+        // safe: t is as big as glibc's termios, and the fd stays open
+        unsafe {
+            check(tcgetattr(fd, &mut t))
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "it isn't a serial port"))?;
+            cfmakeraw(&mut t);
+            t.cflag |= CLOCAL | CREAD;
+            t.cc[VMIN] = 0;
+            t.cc[VTIME] = 1;
+            if let Some(baud) = baud {
+                let s = speed(baud).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the baud rate isn't a standard one"))?;
+                check(cfsetispeed(&mut t, s))?;
+                check(cfsetospeed(&mut t, s))?;
+            }
+            check(tcsetattr(fd, TCSANOW, &t))?;
+            check(tcflush(fd, TCIFLUSH))?;
+            // VTIME does the waiting from here on
+            check(fcntl(fd, F_SETFL, 0))?;
+        }
+        Ok(file)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod tty {
+    use std::fs::File;
+    use std::io;
+
+    pub fn open(_path: &str, _baud: Option<u32>) -> io::Result<File> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "serial ports are only set up on linux, try stty and reading the device as a file"))
+    }
+}
@@ -0,0 +1,318 @@
+/*
+    freqs serve: a small http service, so other tools can have data
+    analyzed without spawning a process each time. Data (or the path of a
+    file on the server) is POSTed to /analyses and the results come back as
+    json; earlier results are listed at /analyses and fetched again from
+    /analyses/<id>. Results are only kept in memory, the latest KEPT.
+    /metrics has the latest of each source for prometheus.
+
+    Files on the server can only be named with --root, and only regular
+    files under it, so a client can't read whatever the server can or tie
+    it up on /dev/zero. A request line or header longer than MAX_LINE is
+    refused, and past MAX_CONNECTIONS at once the rest are turned away.
+*/
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use analysis::{self, HEAD_LEN};
+use args::Options;
+use decode::Decoder;
+use entropy;
+use log;
+use magic;
+use metrics::Board;
+use report::{self, json_str, Format};
+
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+const KEPT: usize = 1000;
+const CHUNKSIZE: usize = 1024 * 128;
+// a request head longer than this is refused
+const MAX_HEADER_LINES: usize = 100;
+const MAX_LINE: u64 = 8 * 1024;
+pub const MAX_CONNECTIONS: usize = 64;
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+const JSON_TYPE: &str = "application/json";
+pub const METRICS_TYPE: &str = "text/plain; version=0.0.4";
+
+struct Entry {
+    id: u64,
+    source: String,     // the path, or upload
+    len: u64,
+    counts: [u64; 256],
+    kind: Option<&'static str>,
+}
+
+struct State {
+    next_id: u64,
+    entries: VecDeque<Entry>,
+    board: Board,
+}
+
+pub struct Request {
+    method: String,
+    pub path: String,
+    query: Vec<(String, String)>,
+    length: Option<u64>,
+    chunked: bool,
+    expect_continue: bool,
+}
+
+pub fn run(opts: &Options) -> ! {
+    let addr = opts.listen.as_deref().unwrap_or(DEFAULT_LISTEN);
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| panic!("Could not listen on {}: {}", addr, e));
+    log::info(&format!("listening on http://{}", addr));
+
+    let state = Arc::new(Mutex::new(State { next_id: 1, entries: VecDeque::new(), board: Board::new() }));
+    let open = Arc::new(AtomicUsize::new(0));
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(_) => continue,
+        };
+        let slot = match Slot::take(&open) {
+            Some(slot) => slot,
+            None => {
+                let _ = stream.set_write_timeout(Some(TIMEOUT));
+                let _ = respond(&mut &stream, 503, JSON_TYPE, &error("too many connections, try again later"));
+                continue;
+            },
+        };
+        let (state, opts) = (state.clone(), opts.clone());
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = handle(stream, &opts, &state) { log::warn(&format!("request failed: {}", e)); }
+        });
+    }
+}
+
+// one of the MAX_CONNECTIONS, given back when dropped
+pub struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    pub fn take(open: &Arc<AtomicUsize>) -> Option<Slot> {
+        if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            open.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Slot(open.clone()))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) { self.0.fetch_sub(1, Ordering::SeqCst); }
+}
+
+// a file a client named, as long as it's a regular one under --root
+pub fn local_path(opts: &Options, path: &str) -> Result<String, String> {
+    let root = opts.root.as_ref().ok_or_else(|| String::from("files on the server can't be named, unless it's run with --root <dir>"))?;
+    let root = fs::canonicalize(root).map_err(|e| format!("Could not read the --root: {}", e))?;
+    // an absolute path replaces the root here, and is then checked like any other
+    let full = fs::canonicalize(root.join(path)).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    if !full.starts_with(&root) { return Err(format!("{} isn't under the --root", path)); }
+    if !fs::metadata(&full).is_ok_and(|m| m.is_file()) { return Err(format!("{} isn't a regular file", path)); }
+    Ok(full.to_string_lossy().into_owned())
+}
+
+fn handle(stream: TcpStream, opts: &Options, state: &Mutex<State>) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::with_capacity(CHUNKSIZE, &stream);
+    let mut out = &stream;
+    let request = match read_head(&mut reader)? {
+        Some(r) => r,
+        None => return respond(&mut out, 400, JSON_TYPE, &error("malformed request")),
+    };
+
+    if request.path == "/metrics" {
+        let body = state.lock().unwrap().board.render();
+        return respond(&mut out, 200, METRICS_TYPE, &body);
+    }
+    let (status, body) = route(&request, &mut reader, &mut out, opts, state)?;
+    log::info(&format!("{} {} {}", request.method, request.path, status));
+    respond(&mut out, status, JSON_TYPE, &body)
+}
+
+fn route<R: BufRead, W: Write>(
+    request: &Request, body: &mut R, out: &mut W, opts: &Options, state: &Mutex<State>
+) -> io::Result<(u16, String)> {
+    let method = request.method.as_str();
+    match request.path.as_str() {
+        "/analyses" if method == "POST" => {
+            let entry = match request.query.iter().find(|(k, _)| k == "path") {
+                Some((_, path)) => {
+                    let local = match local_path(opts, path) {
+                        Ok(local) => local,
+                        Err(e) => return Ok((403, error(&e))),
+                    };
+                    match analysis::analyze(&local, opts, false) {
+                        Ok(a) => Entry { id: 0, source: path.clone(), len: a.len, counts: a.counts, kind: magic::detect(&a.head).map(|s| s.name) },
+                        Err(e) => return Ok((400, error(&format!("Could not read {}: {}", path, e)))),
+                    }
+                },
+                None => {
+                    if request.chunked { return Ok((411, error("send a Content-Length, chunked uploads aren't supported"))); }
+                    let len = match request.length {
+                        Some(len) => len,
+                        None => return Ok((411, error("uploads need a Content-Length"))),
+                    };
+                    if request.expect_continue {
+                        out.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+                        out.flush()?;
+                    }
+                    upload(body, len)?
+                },
+            };
+            Ok((201, entry_json(&store(state, entry))))
+        },
+        "/analyses" if method == "GET" => {
+            let state = state.lock().unwrap();
+            Ok((200, list_json(&state.entries)))
+        },
+        "/analyses" => Ok((405, error("use GET to list analyses, POST to submit one"))),
+        path if path.starts_with("/analyses/") => {
+            if method != "GET" { return Ok((405, error("use GET to fetch an analysis"))); }
+            let state = state.lock().unwrap();
+            match path["/analyses/".len()..].parse::<u64>().ok().and_then(|id| state.entries.iter().find(|e| e.id == id)) {
+                Some(entry) => Ok((200, entry_json(entry))),
+                None => Ok((404, error("no such analysis"))),
+            }
+        },
+        _ => Ok((404, error("not found, try /analyses or /metrics"))),
+    }
+}
+
+// counts an uploaded body as it comes in
+fn upload<R: BufRead>(body: &mut R, len: u64) -> io::Result<Entry> {
+    let mut counts = [0u64; 256];
+    let mut head = Vec::with_capacity(HEAD_LEN);
+    let mut left = len;
+    while left > 0 {
+        let chunk = body.fill_buf()?;
+        if chunk.is_empty() { return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "upload cut short")); }
+        let take = (chunk.len() as u64).min(left) as usize;
+        for byte in &chunk[..take] { counts[*byte as usize] += 1; }
+        if head.len() < HEAD_LEN {
+            let n = (HEAD_LEN - head.len()).min(take);
+            head.extend_from_slice(&chunk[..n]);
+        }
+        body.consume(take);
+        left -= take as u64;
+    }
+    Ok(Entry { id: 0, source: String::from("upload"), len, counts, kind: magic::detect(&head).map(|s| s.name) })
+}
+
+// gives the entry its id and keeps it, dropping the oldest past KEPT
+fn store(state: &Mutex<State>, mut entry: Entry) -> Entry {
+    let mut state = state.lock().unwrap();
+    entry.id = state.next_id;
+    state.next_id += 1;
+    state.board.update(&entry.source, entry.len, &entry.counts);
+    let copy = Entry { source: entry.source.clone(), ..entry };
+    state.entries.push_back(entry);
+    if state.entries.len() > KEPT { state.entries.pop_front(); }
+    copy
+}
+
+// a line of the head, false if it's cut off at MAX_LINE or the end
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<bool> {
+    line.clear();
+    reader.by_ref().take(MAX_LINE).read_line(line)?;
+    Ok(line.ends_with('\n'))
+}
+
+pub fn read_head<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if !read_line(reader, &mut line)? { return Ok(None); }
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(m), Some(t)) => (m.to_string(), t.to_string()),
+        _ => return Ok(None),
+    };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query),
+        None => (target.clone(), ""),
+    };
+    let query = query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (unescape(k), unescape(v))
+        })
+        .collect();
+
+    let mut request = Request { method, path, query, length: None, chunked: false, expect_continue: false };
+    for _ in 0..MAX_HEADER_LINES {
+        if !read_line(reader, &mut line)? { return Ok(None); }
+        let header = line.trim_end();
+        if header.is_empty() { return Ok(Some(request)); }
+        let (name, value) = match header.split_once(':') {
+            Some((n, v)) => (n.trim().to_ascii_lowercase(), v.trim()),
+            None => return Ok(None),
+        };
+        match name.as_str() {
+            "content-length" => request.length = value.parse().ok(),
+            "transfer-encoding" => request.chunked = value.eq_ignore_ascii_case("chunked"),
+            "expect" => request.expect_continue = value.eq_ignore_ascii_case("100-continue"),
+            _ => {},
+        }
+    }
+    Ok(None)
+}
+
+// query strings are url encoded, same as --decode url
+fn unescape(text: &str) -> String {
+    let mut out = Vec::new();
+    let mut decoder = Decoder::new("url");
+    decoder.feed(text.as_bytes(), &mut |chunk| out.extend_from_slice(chunk));
+    let _ = decoder.finish(&mut |chunk| out.extend_from_slice(chunk));
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn respond<W: Write>(out: &mut W, status: u16, content_type: &str, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, content_type, body.len(), body
+    )?;
+    out.flush()
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\": {}}}\n", json_str(message))
+}
+
+fn entry_fields(e: &Entry) -> String {
+    format!(
+        "\"id\": {}, \"source\": {}, \"size\": {}, \"entropy\": {:.6}, \"type\": {}",
+        e.id, json_str(&e.source), e.len, entropy::shannon(&e.counts, e.len),
+        e.kind.map_or(String::from("null"), json_str)
+    )
+}
+
+fn entry_json(e: &Entry) -> String {
+    // the usual json report, indented to sit inside
+    let report = report::render_counts(Format::Json, &e.counts, false).join("\n").replace('\n', "\n  ");
+    format!("{{\n  {},\n  \"report\": {}\n}}\n", entry_fields(e), report)
+}
+
+fn list_json(entries: &VecDeque<Entry>) -> String {
+    let items: Vec<String> = entries.iter().map(|e| format!("    {{{}}}", entry_fields(e))).collect();
+    format!("{{\n  \"analyses\": [\n{}\n  ]\n}}\n", items.join(",\n"))
+}
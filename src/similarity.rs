@@ -0,0 +1,51 @@
+/*
+    freqs similarity: a quick "are these the same kind of data?" check,
+    comparing the normalized byte distributions of two files.
+*/
+
+use analysis;
+use args::Options;
+use report::{json_str, Format};
+use stats::{self, Similarity};
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let (path_a, path_b) = (&opts.inputs[0], &opts.inputs[1]);
+    let mut p = Vec::new();
+    for path in &opts.inputs {
+        match analysis::analyze(path, opts, true) {
+            Ok(a) => p.push(stats::proportions(&a.counts)),
+            Err(_) => panic!("Could not open {}. Bad file or path?", path),
+        }
+    }
+    render(format, path_a, path_b, &stats::similarity(&p[0], &p[1]))
+}
+
+pub fn render(format: Format, path_a: &str, path_b: &str, s: &Similarity) -> Vec<String> {
+
+    match format {
+        Format::Csv => vec![
+            String::from("metric,value"),
+            format!("cosine_similarity,{:.6}", s.cosine),
+            format!("js_distance,{:.6}", s.js_distance),
+            format!("histogram_intersection,{:.6}", s.intersection),
+        ],
+        Format::Json => vec![
+            String::from("{"),
+            format!("  \"a\": {},", json_str(path_a)),
+            format!("  \"b\": {},", json_str(path_b)),
+            format!("  \"cosine_similarity\": {:.6},", s.cosine),
+            format!("  \"js_distance\": {:.6},", s.js_distance),
+            format!("  \"histogram_intersection\": {:.6}", s.intersection),
+            String::from("}"),
+        ],
+        _ => vec![
+            String::from(""),
+            format!("  a: {}", path_a),
+            format!("  b: {}", path_b),
+            String::from(""),
+            format!("  cosine similarity:       {:.6}  (1 = same shape)", s.cosine),
+            format!("  jensen-shannon distance: {:.6}  (0 = identical)", s.js_distance),
+            format!("  histogram intersection:  {:.6}  (1 = identical)", s.intersection),
+        ],
+    }
+}
@@ -0,0 +1,137 @@
+/*
+    --snapshot-every: for streams that go on for hours, or forever, like a
+    pipe into stdin or a device. The byte table so far is put out at every
+    interval of time or bytes while counting carries on, and the totals as
+    usual once the stream ends. Reading happens on a thread of its own, so
+    timed snapshots come on time even when the stream stalls.
+*/
+
+use std::fs;
+use std::io::{self, stdout, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use args::{Every, Options};
+use control::Control;
+use dump;
+use entropy;
+use log;
+use metrics;
+use outfile;
+use report::{self, Format};
+
+const CHUNKSIZE: usize = 1024 * 128;
+const WAKE: Duration = Duration::from_secs(1);
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let board = opts.metrics_addr.as_ref().map(|addr| metrics::listen(addr));
+    let path = opts.inputs[0].clone();
+    let control = opts.control_path.as_ref().map(|p| Control::open(p, opts));
+    let mut input: Box<dyn Read + Send> = if path == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(fs::File::open(&path).unwrap_or_else(|_| panic!("Could not open file. Bad file or path?")))
+    };
+
+    let (tx, rx) = mpsc::sync_channel(4);
+    thread::spawn(move || loop {
+        let mut chunk = vec![0u8; CHUNKSIZE];
+        let sent = match input.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => { chunk.truncate(n); tx.send(Ok(chunk)) },
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => tx.send(Err(e)),
+        };
+        if sent.is_err() { break; }
+    });
+
+    let every = opts.snapshot_every.unwrap();
+    let mut counts = [0u64; 256];
+    let mut len = 0u64;
+    let mut taken = 0;
+    let mut deadline = match every {
+        Every::Seconds(s) => Some(Instant::now() + Duration::from_secs(s)),
+        Every::Bytes(_) => None,
+    };
+
+    loop {
+        // woken up at least every second, to answer SIGUSR1 in time
+        let wait = deadline.map_or(WAKE, |d| d.saturating_duration_since(Instant::now()).min(WAKE));
+        let received = rx.recv_timeout(wait);
+        if dump::requested() { dump::write(opts, &counts, len); }
+        match received {
+            Ok(Ok(chunk)) => {
+                let mut rest = &chunk[..];
+                // split chunks at the boundaries, so each snapshot covers
+                // exactly a multiple of the interval
+                while let Every::Bytes(n) = every {
+                    let until_next = n - len % n;
+                    if (rest.len() as u64) < until_next { break; }
+                    let (now, later) = rest.split_at(until_next as usize);
+                    count(&mut counts, &mut len, now);
+                    taken += 1;
+                    emit(opts, format, taken, &counts, len);
+                    rest = later;
+                }
+                count(&mut counts, &mut len, rest);
+                if let Some(ref b) = board { b.update(&path, len, &counts); }
+            },
+            Ok(Err(e)) => panic!("Could not read {}: {}", path, e),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if let Some(ref c) = control {
+            if c.sync(&mut counts, &mut len) { break; }
+        }
+        if let (Some(d), Every::Seconds(s)) = (deadline, every) {
+            if Instant::now() >= d {
+                taken += 1;
+                emit(opts, format, taken, &counts, len);
+                deadline = Some(d + Duration::from_secs(s));
+            }
+        }
+    }
+
+    let mut lines = report::render_counts(format, &counts, opts.bars);
+    if format == Format::Text {
+        lines.insert(0, format!("  {}: {} bytes in all, entropy {:.4}", path, len, entropy::shannon(&counts, len)));
+    }
+    lines
+}
+
+fn count(counts: &mut [u64; 256], len: &mut u64, chunk: &[u8]) {
+    for byte in chunk { counts[*byte as usize] += 1; }
+    *len += chunk.len() as u64;
+}
+
+fn emit(opts: &Options, format: Format, taken: u32, counts: &[u64; 256], len: u64) {
+    let mut lines = report::render_counts(format, counts, opts.bars);
+    if format == Format::Text {
+        lines.insert(0, format!("  snapshot {}: {} bytes so far, entropy {:.4}", taken, len, entropy::shannon(counts, len)));
+    }
+
+    match opts.out_path {
+        Some(ref out) => {
+            let path = numbered(out, taken);
+            let text: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+            if let Err(e) = outfile::write(&path, text.as_bytes(), opts.force) { panic!("Could not write {}: {}", path, e); }
+            log::info(&format!("wrote {}", path));
+        },
+        None => {
+            for line in lines { println!("{}", line); }
+            println!();
+            let _ = stdout().flush();
+        },
+    }
+}
+
+// out.txt becomes out.1.txt, out.2.txt...
+pub fn numbered(out: &str, n: u32) -> String {
+    let path = Path::new(out);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => path.with_file_name(format!("{}.{}.{}", stem.to_string_lossy(), n, ext.to_string_lossy())),
+        _ => path.with_file_name(format!("{}.{}", path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default(), n)),
+    }.to_string_lossy().into_owned()
+}
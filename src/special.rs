@@ -0,0 +1,129 @@
+/*
+    The special functions p-values come from: the regularized incomplete
+    gamma function, for chi-square tails, and erfc, for normal ones. The
+    gamma functions follow cephes, which the nist test suite uses too.
+*/
+
+use std::f64::consts::PI;
+
+const MACHEP: f64 = 1.110_223_024_625_156_5e-16;
+const MAXLOG: f64 = 709.782_712_893_384;
+const BIG: f64 = 4.503599627370496e15;
+const BIGINV: f64 = 2.220_446_049_250_313e-16;
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8, 771.323_428_777_653_1,
+    -176.615_029_162_140_6, 12.507_343_278_686_905, -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+pub fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 { return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x); }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let mut a = LANCZOS[0];
+    for (i, c) in LANCZOS.iter().enumerate().skip(1) { a += c / (x + i as f64); }
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+// the lower regularized incomplete gamma function P(a, x)
+pub fn igam(a: f64, x: f64) -> f64 {
+    if x <= 0.0 || a <= 0.0 { return 0.0; }
+    if x > 1.0 && x > a { return 1.0 - igamc(a, x); }
+    let ax = a * x.ln() - x - ln_gamma(a);
+    if ax < -MAXLOG { return 0.0; }
+    let (mut r, mut c, mut ans) = (a, 1.0, 1.0);
+    loop {
+        r += 1.0;
+        c *= x / r;
+        ans += c;
+        if c / ans <= MACHEP { break; }
+    }
+    ans * ax.exp() / a
+}
+
+// the upper one, Q(a, x) = 1 - P(a, x). the chance of a chi-square
+// statistic of at least x with k degrees of freedom is igamc(k / 2, x / 2)
+pub fn igamc(a: f64, x: f64) -> f64 {
+    if x <= 0.0 || a <= 0.0 { return 1.0; }
+    if x < 1.0 || x < a { return 1.0 - igam(a, x); }
+    let ax = a * x.ln() - x - ln_gamma(a);
+    if ax < -MAXLOG { return 0.0; }
+
+    // continued fraction
+    let (mut y, mut c) = (1.0 - a, 0.0);
+    let mut z = x + y + 1.0;
+    let (mut pkm2, mut qkm2, mut pkm1, mut qkm1) = (1.0, x, x + 1.0, z * x);
+    let mut ans = pkm1 / qkm1;
+    loop {
+        c += 1.0;
+        y += 1.0;
+        z += 2.0;
+        let yc = y * c;
+        let pk = pkm1 * z - pkm2 * yc;
+        let qk = qkm1 * z - qkm2 * yc;
+        let t = if qk != 0.0 {
+            let r = pk / qk;
+            let t = ((ans - r) / r).abs();
+            ans = r;
+            t
+        } else {
+            1.0
+        };
+        pkm2 = pkm1;
+        pkm1 = pk;
+        qkm2 = qkm1;
+        qkm1 = qk;
+        if pk.abs() > BIG {
+            pkm2 *= BIGINV;
+            pkm1 *= BIGINV;
+            qkm2 *= BIGINV;
+            qkm1 *= BIGINV;
+        }
+        if t <= MACHEP { break; }
+    }
+    ans * ax.exp()
+}
+
+pub fn erfc(x: f64) -> f64 {
+    if x < 0.0 { 2.0 - igamc(0.5, x * x) } else { igamc(0.5, x * x) }
+}
+
+// the standard normal distribution function
+pub fn normal(x: f64) -> f64 {
+    0.5 * erfc(-x / 2f64.sqrt())
+}
+
+
+// the inverse of normal, by acklam's approximation and a step of halley's
+// method to polish it off
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const LOW: f64 = 0.02425;
+
+    if p <= 0.0 { return f64::NEG_INFINITY; }
+    if p >= 1.0 { return f64::INFINITY; }
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    let x = if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    };
+    let u = (normal(x) - p) * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x - u / (1.0 + x * u / 2.0)
+}
+
+// the p-value of a chi-square statistic
+pub fn chi_square_p(statistic: f64, degrees_of_freedom: f64) -> f64 {
+    igamc(degrees_of_freedom / 2.0, statistic / 2.0)
+}
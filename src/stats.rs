@@ -0,0 +1,184 @@
+/*
+    Statistics over byte distributions, mostly for comparing two of them.
+*/
+
+// fraction of the total taken by each byte
+pub fn proportions(counts: &[u64; 256]) -> [f64; 256] {
+    let total: u64 = counts.iter().sum();
+    let mut p = [0f64; 256];
+    if total == 0 { return p; }
+    for (byte, c) in counts.iter().enumerate() { p[byte] = *c as f64 / total as f64; }
+    p
+}
+
+// chi-square test of homogeneity between two tables of counts, i.e. how
+// likely it is both were drawn from the same distribution. returns the
+// statistic and its degrees of freedom. bytes absent from both tables don't
+// count towards either.
+pub fn chi_square(a: &[u64; 256], b: &[u64; 256]) -> (f64, usize) {
+    let total_a: u64 = a.iter().sum();
+    let total_b: u64 = b.iter().sum();
+    if total_a == 0 || total_b == 0 { return (0.0, 0); }
+    let total = (total_a + total_b) as f64;
+
+    let mut stat = 0.0;
+    let mut present = 0;
+    for byte in 0..256 {
+        let row = (a[byte] + b[byte]) as f64;
+        if row == 0.0 { continue; }
+        present += 1;
+        let expected_a = row * total_a as f64 / total;
+        let expected_b = row * total_b as f64 / total;
+        stat += (a[byte] as f64 - expected_a).powi(2) / expected_a;
+        stat += (b[byte] as f64 - expected_b).powi(2) / expected_b;
+    }
+    (stat, present.max(1) - 1)
+}
+
+// the likelihood-ratio (G) test of the same, which holds up better when
+// many bytes are rare, as in small files
+pub fn g_test(a: &[u64; 256], b: &[u64; 256]) -> (f64, usize) {
+    let total_a: u64 = a.iter().sum();
+    let total_b: u64 = b.iter().sum();
+    if total_a == 0 || total_b == 0 { return (0.0, 0); }
+    let total = (total_a + total_b) as f64;
+
+    let mut stat = 0.0;
+    let mut present = 0;
+    for byte in 0..256 {
+        let row = (a[byte] + b[byte]) as f64;
+        if row == 0.0 { continue; }
+        present += 1;
+        for (observed, column) in [(a[byte], total_a), (b[byte], total_b)] {
+            if observed == 0 { continue; }
+            let expected = row * column as f64 / total;
+            stat += 2.0 * observed as f64 * (observed as f64 / expected).ln();
+        }
+    }
+    (stat, present.max(1) - 1)
+}
+
+// which of the two --gof picks
+#[derive(Clone, Copy, PartialEq)]
+pub enum Gof {
+    ChiSquare,
+    G,
+}
+
+impl Gof {
+    pub fn parse(name: &str) -> Option<Gof> {
+        match name {
+            "chi2" => Some(Gof::ChiSquare),
+            "g" => Some(Gof::G),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self { Gof::ChiSquare => "chi-square", Gof::G => "G" }
+    }
+
+    // in csv and json
+    pub fn key(self) -> &'static str {
+        match self { Gof::ChiSquare => "chi_square", Gof::G => "g" }
+    }
+
+    pub fn homogeneity(self, a: &[u64; 256], b: &[u64; 256]) -> (f64, usize) {
+        match self { Gof::ChiSquare => chi_square(a, b), Gof::G => g_test(a, b) }
+    }
+}
+
+// jensen-shannon divergence in bits, running from 0 for identical
+// distributions to 1 for ones with no bytes in common
+pub fn js_divergence(p: &[f64; 256], q: &[f64; 256]) -> f64 {
+    let mut d = 0.0;
+    for byte in 0..256 {
+        let m = (p[byte] + q[byte]) / 2.0;
+        if p[byte] > 0.0 { d += 0.5 * p[byte] * (p[byte] / m).log2(); }
+        if q[byte] > 0.0 { d += 0.5 * q[byte] * (q[byte] / m).log2(); }
+    }
+    d.max(0.0)
+}
+
+// cosine of the angle between two distributions taken as vectors; 1 when
+// they're proportional, 0 when they share no bytes
+pub fn cosine(p: &[f64; 256], q: &[f64; 256]) -> f64 {
+    let dot: f64 = p.iter().zip(q.iter()).map(|(a, b)| a * b).sum();
+    let norm = |v: &[f64; 256]| v.iter().map(|a| a * a).sum::<f64>().sqrt();
+    let (np, nq) = (norm(p), norm(q));
+    if np == 0.0 || nq == 0.0 { 0.0 } else { dot / (np * nq) }
+}
+
+// how much of the two distributions overlaps, from 0 to 1
+pub fn intersection(p: &[f64; 256], q: &[f64; 256]) -> f64 {
+    p.iter().zip(q.iter()).map(|(a, b)| a.min(*b)).sum()
+}
+
+pub struct Similarity {
+    pub cosine: f64,
+    pub js_distance: f64,   // square root of the divergence, an actual metric
+    pub intersection: f64,
+}
+
+pub fn similarity(p: &[f64; 256], q: &[f64; 256]) -> Similarity {
+    Similarity {
+        cosine: cosine(p, q),
+        js_distance: js_divergence(p, q).sqrt(),
+        intersection: intersection(p, q),
+    }
+}
+
+// the wilson score interval around the proportion count / total, for the
+// normal quantile z of a confidence level. unlike the plain normal
+// interval it stays within 0 and 1 and makes sense for rare bytes
+pub fn wilson(count: u64, total: u64, z: f64) -> (f64, f64) {
+    if total == 0 { return (0.0, 1.0); }
+    let n = total as f64;
+    let p = count as f64 / n;
+    let z2 = z * z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((centre - half).max(0.0), (centre + half).min(1.0))
+}
+
+// share of bytes that are printable ascii, counting tabs and line breaks
+pub fn printable_ratio(counts: &[u64; 256]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 { return 0.0; }
+    let printable: u64 = counts.iter().enumerate()
+        .filter(|&(byte, _)| (0x20..0x7f).contains(&byte) || byte == 0x09 || byte == 0x0a || byte == 0x0d)
+        .map(|(_, c)| c)
+        .sum();
+    printable as f64 / total as f64
+}
+
+// how many of the 256 byte values occur at all
+pub fn distinct(counts: &[u64; 256]) -> usize {
+    counts.iter().filter(|c| **c != 0).count()
+}
+
+// the moments of the byte values themselves, taken as numbers 0 to 255.
+// kurtosis is the excess over a normal distribution's; a file of a single
+// byte value has no spread, and so no skew or kurtosis either
+pub struct Moments {
+    pub mean: f64,
+    pub stddev: f64,
+    pub skewness: f64,
+    pub kurtosis: f64,
+}
+
+pub fn moments(counts: &[u64; 256]) -> Moments {
+    let total: u64 = counts.iter().sum();
+    if total == 0 { return Moments { mean: 0.0, stddev: 0.0, skewness: 0.0, kurtosis: 0.0 }; }
+    let n = total as f64;
+    let mean = counts.iter().enumerate().map(|(byte, c)| byte as f64 * *c as f64).sum::<f64>() / n;
+    let central = |k: i32| counts.iter().enumerate().map(|(byte, c)| (byte as f64 - mean).powi(k) * *c as f64).sum::<f64>() / n;
+    let variance = central(2);
+    if variance == 0.0 { return Moments { mean, stddev: 0.0, skewness: 0.0, kurtosis: 0.0 }; }
+    Moments {
+        mean,
+        stddev: variance.sqrt(),
+        skewness: central(3) / variance.powf(1.5),
+        kurtosis: central(4) / (variance * variance) - 3.0,
+    }
+}
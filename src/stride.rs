@@ -0,0 +1,195 @@
+/*
+    --stride: counts every Nth byte on its own, the bytes at offsets i mod N
+    for each i below N, which brings out the columns of fixed size records
+    and interleaved channels. A column that's always 0x00 or that only
+    takes a handful of values stands out against the rest.
+
+    --record-size is the same count over whole records, the last one left
+    out if it's cut short, with a guess at the layout from it: runs of
+    neighbouring columns of the same kind, taken together as one field.
+*/
+
+use std::fs;
+use std::io::Read;
+
+use args::Options;
+use entropy;
+use report::{csv_field, json_str, sparkline, Format};
+
+const CHUNKSIZE: usize = 1024 * 128;
+pub const MAX_STRIDE: usize = 1 << 16;
+
+struct Column {
+    counts: [u64; 256],
+    len: u64,
+}
+
+impl Column {
+    fn top(&self) -> (usize, u64) {
+        self.counts.iter().enumerate().max_by_key(|(_, c)| **c).map(|(b, c)| (b, *c)).unwrap_or((0, 0))
+    }
+
+    fn distinct(&self) -> usize {
+        self.counts.iter().filter(|c| **c > 0).count()
+    }
+
+    fn kind(&self) -> &'static str {
+        let distinct = self.distinct();
+        // the most entropy this many bytes could show
+        let most = (self.len.min(256) as f64).log2();
+        if distinct == 1 {
+            "constant"
+        } else if self.counts.iter().enumerate().all(|(b, c)| *c == 0 || (0x20..0x7f).contains(&b)) {
+            "text"
+        } else if distinct <= 16 {
+            "few values"
+        } else if most > 0.0 && entropy::shannon(&self.counts, self.len) > 0.9 * most {
+            "random"
+        } else {
+            ""
+        }
+    }
+}
+
+// neighbouring columns of one kind, first to last
+struct Field {
+    start: usize,
+    end: usize,
+    kind: &'static str,
+}
+
+fn layout(columns: &[Column]) -> Vec<Field> {
+    let mut fields: Vec<Field> = Vec::new();
+    for (i, c) in columns.iter().enumerate() {
+        match fields.last_mut() {
+            Some(f) if f.kind == c.kind() => f.end = i,
+            _ => fields.push(Field { start: i, end: i, kind: c.kind() }),
+        }
+    }
+    fields
+}
+
+// the values of a constant field, or the column kind spelled out
+fn describe(field: &Field, columns: &[Column]) -> String {
+    match field.kind {
+        "constant" => {
+            let bytes: Vec<String> = columns[field.start..=field.end].iter().map(|c| format!("{:02x}", c.top().0)).collect();
+            format!("constant {}", bytes.join(" "))
+        },
+        "" => String::from("varying"),
+        kind => String::from(kind),
+    }
+}
+
+// records are whole, for --record-size
+pub fn run(opts: &Options, format: Format, stride: usize, records: bool) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let size = f.metadata().map(|m| m.len()).unwrap_or(0);
+    let trailing = if records { size % stride as u64 } else { 0 };
+    if records && size < stride as u64 { panic!("{} is shorter than one {} byte record", path, stride); }
+    let mut f = f.take(size - trailing);
+    let mut columns: Vec<Column> = (0..stride).map(|_| Column { counts: [0; 256], len: 0 }).collect();
+    let mut chunk = vec![0u8; CHUNKSIZE];
+    let mut at = 0;
+    loop {
+        let n = match f.read(&mut chunk) {
+            Ok(n) => n,
+            Err(e) => panic!("Could not read {}: {}", path, e),
+        };
+        if n == 0 { break; }
+        for byte in &chunk[..n] {
+            columns[at].counts[*byte as usize] += 1;
+            columns[at].len += 1;
+            at = if at + 1 == stride { 0 } else { at + 1 };
+        }
+    }
+    if columns[0].len == 0 { panic!("Nothing to count, {} is empty", path); }
+    let fields = if records { Some(layout(&columns)) } else { None };
+    render(format, stride, &columns, fields.as_deref(), trailing)
+}
+
+fn render(format: Format, stride: usize, columns: &[Column], fields: Option<&[Field]>, trailing: u64) -> Vec<String> {
+    let entropy = |c: &Column| entropy::shannon(&c.counts, c.len);
+    let share = |c: &Column| if c.len == 0 { 0.0 } else { c.top().1 as f64 / c.len as f64 * 100.0 };
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("column,bytes,entropy,distinct,top_byte,top_percent,kind")];
+            for (i, c) in columns.iter().enumerate() {
+                lines.push(format!(
+                    "{},{},{:.6},{},{},{:.4},{}",
+                    i, c.len, entropy(c), c.distinct(), c.top().0, share(c), csv_field(c.kind())
+                ));
+            }
+            // then every column's counts, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("column,byte,count"));
+            for (i, c) in columns.iter().enumerate() {
+                for (byte, n) in c.counts.iter().enumerate() {
+                    if *n != 0 { lines.push(format!("{},{},{}", i, byte, n)); }
+                }
+            }
+            if let Some(fields) = fields {
+                lines.push(String::from(""));
+                lines.push(String::from("start,end,field"));
+                for field in fields { lines.push(format!("{},{},{}", field.start, field.end, csv_field(&describe(field, columns)))); }
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = columns.iter().enumerate()
+                .map(|(i, c)| {
+                    let bytes: Vec<String> = c.counts.iter().enumerate()
+                        .filter(|&(_, n)| *n != 0)
+                        .map(|(byte, n)| format!("{{\"byte\": {}, \"count\": {}}}", byte, n))
+                        .collect();
+                    format!(
+                        "    {{\"column\": {}, \"bytes\": {}, \"entropy\": {:.6}, \"distinct\": {}, \"top_byte\": {}, \"top_percent\": {:.4}, \"kind\": {}, \"counts\": [{}]}}",
+                        i, c.len, entropy(c), c.distinct(), c.top().0, share(c), json_str(c.kind()), bytes.join(", ")
+                    )
+                })
+                .collect();
+            match fields {
+                Some(fields) => {
+                    let layout: Vec<String> = fields.iter()
+                        .map(|f| format!("    {{\"start\": {}, \"end\": {}, \"field\": {}}}", f.start, f.end, json_str(&describe(f, columns))))
+                        .collect();
+                    vec![format!(
+                        "{{\n  \"record_size\": {},\n  \"records\": {},\n  \"trailing\": {},\n  \"columns\": [\n{}\n  ],\n  \"layout\": [\n{}\n  ]\n}}",
+                        stride, columns[0].len, trailing, entries.join(",\n"), layout.join(",\n")
+                    )]
+                },
+                None => vec![format!("{{\n  \"stride\": {},\n  \"columns\": [\n{}\n  ]\n}}", stride, entries.join(",\n"))],
+            }
+        },
+        _ => {
+            let heading = match fields {
+                Some(_) if trailing > 0 => format!("  {} records of {} bytes, and {} bytes left over", columns[0].len, stride, trailing),
+                Some(_) => format!("  {} records of {} bytes", columns[0].len, stride),
+                None => format!("  every {} bytes, by offset mod {}", stride, stride),
+            };
+            let mut lines = vec![
+                heading,
+                format!("  {:>6}  {:>12}  {:>7}  {:>8}  {:>4}  {:>7}  {:<10}", "column", "bytes", "entropy", "distinct", "top", "top %", "kind"),
+            ];
+            for (i, c) in columns.iter().enumerate() {
+                lines.push(format!(
+                    "  {:>6}  {:>12}  {:>7.4}  {:>8}  0x{:02x}  {:>7.2}  {:<10}  {}",
+                    i, c.len, entropy(c), c.distinct(), c.top().0, share(c), c.kind(), sparkline(&c.counts)
+                ));
+            }
+            if let Some(fields) = fields {
+                lines.push(String::from(""));
+                lines.push(String::from("  layout"));
+                for field in fields {
+                    let columns_text = if field.start == field.end { format!("{}", field.start) } else { format!("{}-{}", field.start, field.end) };
+                    lines.push(format!("  {:>11}  {}", columns_text, describe(field, columns)));
+                }
+            }
+            lines
+        },
+    }
+}
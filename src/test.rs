@@ -0,0 +1,149 @@
+/*
+    freqs test: whether two files' byte distributions differ by more than
+    chance would explain, as a two-sample chi-square and G-test on their
+    counts, with the --significance level to decide at (--gof picks which
+    of the two decides). On big files even a tiny difference is
+    significant, so the effect size goes alongside: cramer's v, which
+    cohen's rules of thumb call small from 0.1, medium from 0.3 and large
+    from 0.5, and the total variation distance, the share of bytes that
+    would have to change to turn one distribution into the other.
+*/
+
+use analysis::{self, Analysis};
+use args::Options;
+use nist;
+use report::{csv_field, json_str, Format};
+use special;
+use stats::{self, Gof};
+
+struct Outcome {
+    gof: Gof,
+    statistic: f64,
+    df: usize,
+    p: f64,
+}
+
+// the lines, and whether the two look alike
+pub fn run(opts: &Options, format: Format) -> (Vec<String>, bool) {
+    let (path_a, path_b) = (&opts.inputs[0], &opts.inputs[1]);
+    let (a, b) = (open(path_a, opts), open(path_b, opts));
+    if a.len == 0 || b.len == 0 { panic!("Can't test an empty file, {} has no bytes", if a.len == 0 { path_a } else { path_b }); }
+    let significance = opts.significance.unwrap_or(nist::DEFAULT_SIGNIFICANCE);
+    let decides = Gof::parse(&opts.gof_name).unwrap_or(Gof::ChiSquare);
+
+    let outcomes: Vec<Outcome> = [Gof::ChiSquare, Gof::G].iter()
+        .map(|gof| {
+            let (statistic, df) = gof.homogeneity(&a.counts, &b.counts);
+            // one byte value between them leaves nothing to differ in
+            let p = if df == 0 { 1.0 } else { special::chi_square_p(statistic, df as f64) };
+            Outcome { gof: *gof, statistic, df, p }
+        })
+        .collect();
+    let p = outcomes.iter().find(|o| o.gof == decides).map_or(1.0, |o| o.p);
+    let differ = p < significance;
+
+    let chi = outcomes[0].statistic;
+    let cramers_v = (chi / (a.len + b.len) as f64).sqrt();
+    let (pa, pb) = (stats::proportions(&a.counts), stats::proportions(&b.counts));
+    let variation = 0.5 * pa.iter().zip(pb.iter()).map(|(x, y)| (x - y).abs()).sum::<f64>();
+    let js = stats::js_divergence(&pa, &pb);
+    let sparse = sparse_share(&a.counts, &b.counts);
+
+    let verdict = if differ {
+        format!("the distributions differ at the {} level ({} effect)", significance, effect(cramers_v))
+    } else {
+        format!("no significant difference at the {} level", significance)
+    };
+    let lines = match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("test,statistic,degrees_of_freedom,p_value,differ")];
+            for o in &outcomes {
+                lines.push(format!("{},{:.6},{},{:.6},{}", o.gof.key(), o.statistic, o.df, o.p, o.p < significance));
+            }
+            lines.push(String::from(""));
+            lines.push(String::from("significance,decided_by,cramers_v,total_variation,js_divergence,sparse_cells,differ,verdict"));
+            lines.push(format!(
+                "{},{},{:.6},{:.6},{:.6},{:.6},{},{}",
+                significance, decides.key(), cramers_v, variation, js, sparse, differ, csv_field(&verdict)
+            ));
+            lines
+        },
+        Format::Json => {
+            let tests: Vec<String> = outcomes.iter()
+                .map(|o| format!(
+                    "    {{\"test\": {}, \"statistic\": {:.6}, \"degrees_of_freedom\": {}, \"p_value\": {:.6}, \"differ\": {}}}",
+                    json_str(o.gof.key()), o.statistic, o.df, o.p, o.p < significance
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"a\": {{\"path\": {}, \"size\": {}}},", json_str(path_a), a.len),
+                format!("  \"b\": {{\"path\": {}, \"size\": {}}},", json_str(path_b), b.len),
+                format!("  \"tests\": [\n{}\n  ],", tests.join(",\n")),
+                format!("  \"significance\": {},", significance),
+                format!("  \"decided_by\": {},", json_str(decides.key())),
+                format!("  \"cramers_v\": {:.6},", cramers_v),
+                format!("  \"total_variation\": {:.6},", variation),
+                format!("  \"js_divergence\": {:.6},", js),
+                format!("  \"sparse_cells\": {:.6},", sparse),
+                format!("  \"differ\": {},", differ),
+                format!("  \"verdict\": {}", json_str(&verdict)),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let mut lines = vec![
+                String::from(""),
+                format!("  a: {} ({} bytes)", path_a, a.len),
+                format!("  b: {} ({} bytes)", path_b, b.len),
+                String::from(""),
+                format!("  {:<10}  {:>14}  {:>4}  {:>10}", "test", "statistic", "df", "p-value"),
+            ];
+            for o in &outcomes {
+                lines.push(format!(
+                    "  {:<10}  {:>14.4}  {:>4}  {:>10.6}{}",
+                    o.gof.label(), o.statistic, o.df, o.p, if o.gof == decides { "  <" } else { "" }
+                ));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  cramer's v       {:.4} ({})", cramers_v, effect(cramers_v)));
+            lines.push(format!("  total variation  {:.4}", variation));
+            lines.push(format!("  js divergence    {:.6} bits", js));
+            if sparse > 0.2 {
+                lines.push(format!("  {:.0}% of the expected counts are under 5, so the chi-square is rough, try --gof g", sparse * 100.0));
+            }
+            lines.push(String::from(""));
+            lines.push(format!("  {}", verdict));
+            lines
+        },
+    };
+    (lines, !differ)
+}
+
+fn open(path: &str, opts: &Options) -> Analysis {
+    match analysis::analyze(path, opts, true) {
+        Ok(a) => a,
+        Err(_) => panic!("Could not open {}. Bad file or path?", path),
+    }
+}
+
+// cohen's thresholds, for a table two columns wide
+fn effect(v: f64) -> &'static str {
+    if v < 0.1 { "negligible" } else if v < 0.3 { "small" } else if v < 0.5 { "medium" } else { "large" }
+}
+
+// the share of cells, among bytes either file has, expected to hold fewer
+// than 5, where the chi-square approximation starts to go wrong
+fn sparse_share(a: &[u64; 256], b: &[u64; 256]) -> f64 {
+    let (total_a, total_b) = (a.iter().sum::<u64>() as f64, b.iter().sum::<u64>() as f64);
+    let (mut cells, mut sparse) = (0, 0);
+    for byte in 0..256 {
+        let row = (a[byte] + b[byte]) as f64;
+        if row == 0.0 { continue; }
+        for column in [total_a, total_b] {
+            cells += 1;
+            if row * column / (total_a + total_b) < 5.0 { sparse += 1; }
+        }
+    }
+    if cells == 0 { 0.0 } else { sparse as f64 / cells as f64 }
+}
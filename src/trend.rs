@@ -0,0 +1,146 @@
+/*
+    freqs trend: how a file (or every file recorded under a --tag) has
+    changed across the runs in the history. Size, entropy and the shares
+    of printable, nul and high bytes per run, with the change in entropy
+    from the run before, as a table or as a chart over time.
+*/
+
+use args::Options;
+use history::{self, Run};
+use report::{csv_field, json_str, Format, SPARKS};
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let store = history::store(opts).unwrap();
+    let target = &opts.inputs[0];
+    let path = history::canonical(target);
+    let runs: Vec<Run> = history::load(&store).into_iter()
+        .filter(|r| r.path == path || r.tag.as_ref() == Some(target))
+        .collect();
+    if runs.is_empty() { panic!("No runs of {} in the history at {}", target, store); }
+    render(format, target, &runs)
+}
+
+// a missing share is from a run recorded before they were
+fn share(value: Option<f64>) -> String {
+    value.map_or(String::from("-"), |v| format!("{:.2}%", v * 100.0))
+}
+
+fn number(value: Option<f64>) -> String {
+    value.map_or(String::new(), |v| format!("{:.6}", v))
+}
+
+fn render(format: Format, target: &str, runs: &[Run]) -> Vec<String> {
+    // from the run of the same file before
+    let deltas: Vec<Option<f64>> = (0..runs.len())
+        .map(|i| runs[..i].iter().rev().find(|r| r.path == runs[i].path).map(|r| runs[i].entropy - r.entropy))
+        .collect();
+    // a tag can take in several files
+    let several = runs.iter().any(|r| r.path != runs[0].path);
+
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("time,path,size,entropy,entropy_change,printable,nul,high")];
+            for (r, d) in runs.iter().zip(&deltas) {
+                lines.push(format!(
+                    "{},{},{},{:.6},{},{},{},{}",
+                    history::timestamp(r.when), csv_field(&r.path), r.size, r.entropy,
+                    number(*d), number(r.printable), number(r.nul), number(r.high)
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let json_number = |v: Option<f64>| v.map_or(String::from("null"), |v| format!("{:.6}", v));
+            let items: Vec<String> = runs.iter().zip(&deltas)
+                .map(|(r, d)| format!(
+                    "    {{\"time\": {}, \"path\": {}, \"size\": {}, \"entropy\": {:.6}, \"entropy_change\": {}, \"printable\": {}, \"nul\": {}, \"high\": {}}}",
+                    json_str(&history::timestamp(r.when)), json_str(&r.path), r.size, r.entropy,
+                    json_number(*d), json_number(r.printable), json_number(r.nul), json_number(r.high)
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"trend\": {},", json_str(target)),
+                format!("  \"runs\": [\n{}\n  ]", items.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        Format::Gnuplot => gnuplot(target, runs),
+        Format::Vega => vega(target, runs),
+        Format::Text => {
+            let sparks: String = runs.iter()
+                .map(|r| SPARKS[((r.entropy / 8.0).clamp(0.0, 1.0) * (SPARKS.len() - 1) as f64).round() as usize])
+                .collect();
+            let mut lines = vec![
+                String::from(""),
+                format!("  trend of {}: {} runs, entropy {}", target, runs.len(), sparks),
+                String::from(""),
+                format!(
+                    "  {:<20}  {:>12}  {:>7}  {:>7}  {:>9}  {:>7}  {:>7}{}",
+                    "time", "size", "entropy", "change", "printable", "nul", "high", if several { "  path" } else { "" }
+                ),
+            ];
+            for (r, d) in runs.iter().zip(&deltas) {
+                lines.push(format!(
+                    "  {:<20}  {:>12}  {:>7.4}  {:>7}  {:>9}  {:>7}  {:>7}{}",
+                    history::timestamp(r.when), r.size, r.entropy, d.map_or(String::new(), |d| format!("{:+.4}", d)),
+                    share(r.printable), share(r.nul), share(r.high),
+                    if several { format!("  {}", r.path) } else { String::new() }
+                ));
+            }
+            lines
+        },
+    }
+}
+
+fn gnuplot(target: &str, runs: &[Run]) -> Vec<String> {
+    let mut lines = vec![
+        String::from("# generated by freqs. run with: gnuplot -p <this file>"),
+        String::from("$runs << EOD"),
+        String::from("# time entropy printable"),
+    ];
+    for r in runs {
+        lines.push(format!("{} {:.6} {}", r.when, r.entropy, r.printable.map_or(String::from("NaN"), |p| format!("{:.6}", p))));
+    }
+    lines.push(String::from("EOD"));
+    lines.push(String::from(""));
+    lines.extend([
+        format!("set title \"trend of {}\"", target.replace('"', "'")),
+        String::from("set xdata time"),
+        String::from("set timefmt \"%s\""),
+        String::from("set format x \"%Y-%m-%d\\n%H:%M\""),
+        String::from("set xlabel \"time (utc)\""),
+        String::from("set ylabel \"bits per byte\""),
+        String::from("set yrange [0:8]"),
+        String::from("set y2label \"printable share\""),
+        String::from("set y2range [0:1]"),
+        String::from("set y2tics"),
+        String::from("plot $runs using 1:2 with linespoints title \"entropy\", $runs using 1:3 axes x1y2 with linespoints title \"printable\""),
+    ]);
+    lines
+}
+
+fn vega(target: &str, runs: &[Run]) -> Vec<String> {
+    let values: Vec<String> = runs.iter()
+        .map(|r| format!(
+            "      {{\"time\": {}, \"path\": {}, \"entropy\": {:.6}}}",
+            json_str(&history::timestamp(r.when)), json_str(&r.path), r.entropy
+        ))
+        .collect();
+    vec![
+        String::from("{"),
+        String::from("  \"$schema\": \"https://vega.github.io/schema/vega-lite/v5.json\","),
+        String::from("  \"description\": \"generated by freqs\","),
+        format!("  \"title\": {},", json_str(&format!("trend of {}", target))),
+        String::from("  \"width\": 800,"),
+        format!("  \"data\": {{\n    \"values\": [\n{}\n    ]\n  }},", values.join(",\n")),
+        String::from("  \"mark\": {\"type\": \"line\", \"point\": true},"),
+        String::from("  \"encoding\": {"),
+        String::from("    \"x\": {\"field\": \"time\", \"type\": \"temporal\"},"),
+        String::from("    \"y\": {\"field\": \"entropy\", \"type\": \"quantitative\", \"scale\": {\"domain\": [0, 8]}, \"title\": \"bits per byte\"},"),
+        String::from("    \"color\": {\"field\": \"path\", \"type\": \"nominal\"},"),
+        String::from("    \"tooltip\": [{\"field\": \"time\", \"type\": \"temporal\"}, {\"field\": \"path\", \"type\": \"nominal\"}, {\"field\": \"entropy\", \"type\": \"quantitative\"}]"),
+        String::from("  }"),
+        String::from("}"),
+    ]
+}
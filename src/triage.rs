@@ -0,0 +1,89 @@
+/*
+    --triage: one line per file with the few numbers that matter when going
+    through a directory of samples (entropy, printable ratio, how many byte
+    values occur) and a verdict on whether the file looks packed or
+    encrypted. Thresholds are in bits per byte and can be tuned.
+*/
+
+use analysis;
+use args::Options;
+use entropy;
+use report::{csv_field, json_str, Format};
+use stats;
+use walk;
+
+pub const DEFAULT_PACKED_ABOVE: f64 = 7.0;
+pub const DEFAULT_ENCRYPTED_ABOVE: f64 = 7.9;
+// text with the odd accented character or box drawing still counts
+const TEXT_PRINTABLE: f64 = 0.95;
+// encrypted and compressed data uses practically every byte value
+const ENCRYPTED_COVERAGE: usize = 250;
+
+struct Row {
+    path: String,
+    size: u64,
+    entropy: f64,
+    printable: f64,
+    distinct: usize,
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let packed_above = opts.packed_above.unwrap_or(DEFAULT_PACKED_ABOVE);
+    let encrypted_above = opts.encrypted_above.unwrap_or(DEFAULT_ENCRYPTED_ABOVE);
+
+    let rows: Vec<Row> = analysis::batch(&walk::expand(&opts.inputs, opts), opts, |a| {
+        (a.len, entropy::shannon(&a.counts, a.len), stats::printable_ratio(&a.counts), stats::distinct(&a.counts))
+    })
+        .into_iter()
+        .map(|(path, (size, entropy, printable, distinct))| Row { path, size, entropy, printable, distinct })
+        .collect();
+
+    let verdict = |r: &Row| {
+        if r.entropy >= encrypted_above && r.distinct >= ENCRYPTED_COVERAGE { "encrypted/compressed" }
+        else if r.entropy >= packed_above { "packed" }
+        else if r.printable >= TEXT_PRINTABLE { "text" }
+        else { "plain" }
+    };
+
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("path,size,entropy,printable,distinct,verdict")];
+            for r in &rows {
+                lines.push(format!(
+                    "{},{},{:.6},{:.6},{},{}",
+                    csv_field(&r.path), r.size, r.entropy, r.printable, r.distinct, verdict(r)
+                ));
+            }
+            lines
+        },
+        Format::Json => {
+            let files: Vec<String> = rows.iter()
+                .map(|r| format!(
+                    "    {{\"path\": {}, \"size\": {}, \"entropy\": {:.6}, \"printable\": {:.6}, \"distinct\": {}, \"verdict\": \"{}\"}}",
+                    json_str(&r.path), r.size, r.entropy, r.printable, r.distinct, verdict(r)
+                ))
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"packed_above\": {},", packed_above),
+                format!("  \"encrypted_above\": {},", encrypted_above),
+                format!("  \"files\": [\n{}\n  ]", files.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let width = rows.iter().map(|r| r.path.chars().count()).max().unwrap_or(0).max(4);
+            let mut lines = vec![format!(
+                "  {:<w$}  {:>12}  {:>7}  {:>9}  {:>8}  verdict",
+                "path", "size", "entropy", "printable", "distinct", w = width
+            )];
+            for r in &rows {
+                lines.push(format!(
+                    "  {:<w$}  {:>12}  {:>7.4}  {:>8.1}%  {:>8}  {}",
+                    r.path, r.size, r.entropy, r.printable * 100.0, r.distinct, verdict(r), w = width
+                ));
+            }
+            lines
+        },
+    }
+}
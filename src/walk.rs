@@ -0,0 +1,182 @@
+/*
+    Expands the inputs given on the command line into a flat list of files,
+    descending into directories. Entries are sorted so that runs over the
+    same tree come out in the same order. Symlinks to directories aren't
+    followed unless --follow-symlinks says so, and then a link back up to a
+    directory being walked is skipped, which keeps us out of cycles.
+    --max-depth and --same-filesystem keep big trees in bounds, and
+    --include and --exclude pick the files in them by pattern, --min-size
+    and --max-size by size. With
+    --respect-gitignore what .gitignore files leave out is skipped, and so
+    are hidden files unless --hidden is given too, like ripgrep. Inputs
+    named outright always count.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use args::Options;
+use glob;
+use ignore::{self, Rule};
+use log;
+use windows;
+
+pub fn expand(inputs: &[String], opts: &Options) -> Vec<String> {
+    expand_sized(inputs, opts).0
+}
+
+// the same, and the files --min-size and --max-size left out, with their sizes
+pub fn expand_sized(inputs: &[String], opts: &Options) -> (Vec<String>, Vec<(String, u64)>) {
+    let mut walker = Walker {
+        opts, rules: Vec::new(), files: Vec::new(), skipped: Vec::new(), open: Vec::new(), device: None, root: PathBuf::new(),
+    };
+    for input in inputs {
+        let path = Path::new(input);
+        walker.root = path.to_path_buf();
+        // rules go by absolute paths, rules from above the input included
+        let abs = if opts.respect_gitignore {
+            walker.rules = ignore::above(path);
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        } else { PathBuf::new() };
+        walker.device = fs::metadata(path).ok().and_then(|m| id(&m)).map(|(dev, _)| dev);
+        walker.visit(path, &abs, 0);
+    }
+    (walker.files, walker.skipped)
+}
+
+// whether an input should be treated as a directory to descend into
+pub fn is_dir(input: &str) -> bool {
+    fs::metadata(input).map(|m| m.is_dir()).unwrap_or(false)
+}
+
+struct Walker<'a> {
+    opts: &'a Options,
+    rules: Vec<Rule>,
+    files: Vec<String>,
+    skipped: Vec<(String, u64)>,
+    open: Vec<(u64, u64)>,  // the directories being walked, to spot cycles
+    device: Option<u64>,    // the filesystem of the input, for --same-filesystem
+    root: PathBuf,          // the input, for --include and --exclude
+}
+
+impl<'a> Walker<'a> {
+    fn visit(&mut self, path: &Path, abs: &Path, depth: usize) {
+        let opts = self.opts;
+        // deep down, windows needs paths written the long way
+        let long = windows::long_path(path);
+        // only follow a symlink if it was named on the command line
+        let mut meta = if depth == 0 { fs::metadata(&long) } else { fs::symlink_metadata(&long) };
+        // or with --follow-symlinks, as long as it leads somewhere
+        if opts.follow_symlinks && meta.as_ref().is_ok_and(|m| m.file_type().is_symlink()) {
+            meta = fs::metadata(&long).or(meta);
+        }
+        match meta {
+            Ok(ref m) if m.is_dir() => {
+                if opts.max_depth.is_some_and(|max| depth >= max) { return; }
+                let id = id(m);
+                if opts.same_filesystem && depth > 0 && id.map(|(dev, _)| dev) != self.device { return; }
+                if id.is_some_and(|id| self.open.contains(&id)) {
+                    log::warn(&format!("skipping {}: it loops back to a directory above it", path.display()));
+                    return;
+                }
+                // named as given, not the long way
+                let mut entries: Vec<_> = match fs::read_dir(&long) {
+                    Ok(dir) => dir.filter_map(|e| e.ok()).map(|e| path.join(e.file_name())).collect(),
+                    Err(e) => {
+                        log::warn(&format!("skipping {}: {}", path.display(), e));
+                        return;
+                    },
+                };
+                entries.sort();
+                let outer = self.rules.len();
+                if opts.respect_gitignore { ignore::read(abs, &mut self.rules); }
+                self.open.extend(id);
+                for entry in entries {
+                    let name = entry.file_name().unwrap_or_default();
+                    let abs = abs.join(name);
+                    if opts.respect_gitignore {
+                        // git's own directory never has anything to count
+                        let hidden = name.to_string_lossy().starts_with('.');
+                        if name == ".git" || (hidden && !opts.hidden) { continue; }
+                        let is_dir = fs::symlink_metadata(&entry).map(|m| m.is_dir()).unwrap_or(false);
+                        if ignore::ignored(&self.rules, &abs, is_dir) { continue; }
+                    }
+                    if self.left_out(&entry) { continue; }
+                    self.visit(&entry, &abs, depth + 1);
+                }
+                if id.is_some() { self.open.pop(); }
+                self.rules.truncate(outer);
+            },
+            Ok(ref m) if m.file_type().is_symlink() => {
+                if let Some(m) = fs::metadata(&long).ok().filter(|m| m.is_file()) { self.file(path, depth, Some(&m)); }
+            },
+            Ok(ref m) if depth == 0 || m.is_file() => self.file(path, depth, Some(m)),
+            // fifos, sockets and devices turned up on the way would hang or
+            // make no sense to read. named on the command line they're read
+            // as given
+            Ok(_) => log::warn(&format!("skipping {}: not a regular file", path.display())),
+            Err(ref e) if depth > 0 => log::warn(&format!("skipping {}: {}", path.display(), e)),
+            // paths we can't stat get passed along so the error shows up
+            // when it's analyzed
+            Err(_) => self.file(path, depth, None),
+        }
+    }
+
+    // files outside --min-size and --max-size are set aside, unless named.
+    // carve has its own use for --min-size
+    fn file(&mut self, path: &Path, depth: usize, meta: Option<&fs::Metadata>) {
+        let name = path.to_string_lossy().into_owned();
+        if let Some(size) = meta.filter(|m| depth > 0 && m.is_file()).map(|m| m.len()) {
+            let min = if self.opts.command.as_deref() == Some("carve") { None } else { self.opts.min_size };
+            if min.is_some_and(|min| size < min) || self.opts.max_size.is_some_and(|max| size > max) {
+                self.skipped.push((name, size));
+                return;
+            }
+        }
+        self.files.push(name);
+    }
+
+    // --exclude leaves out files and whole directories, --include keeps
+    // only the files it names. both go by the path below the input
+    fn left_out(&self, path: &Path) -> bool {
+        let (includes, excludes) = (&self.opts.includes, &self.opts.excludes);
+        if includes.is_empty() && excludes.is_empty() { return false; }
+        let rel: Vec<String> = path.strip_prefix(&self.root).unwrap_or(path).iter()
+            .map(|c| c.to_string_lossy().into_owned())
+            .collect();
+        let hit = |pattern: &String| {
+            if pattern.contains('/') {
+                // at any depth, as if it started with **/
+                let components: Vec<String> = Some("**").into_iter().chain(pattern.split('/').filter(|p| !p.is_empty()))
+                    .map(String::from)
+                    .collect();
+                ignore::matches(&components, &rel)
+            } else {
+                rel.last().is_some_and(|name| glob::matches(pattern, name))
+            }
+        };
+        if excludes.iter().any(hit) { return true; }
+        let is_dir = fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false);
+        !is_dir && !includes.is_empty() && !includes.iter().any(hit)
+    }
+}
+
+// device and inode, where there are such things
+#[cfg(unix)]
+fn id(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn id(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// whether two paths name the same file, as far as we can tell
+pub fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,207 @@
+/*
+    freqs watch: runs the analysis again whenever one of the watched files
+    changes, and redraws the results. Inputs can be glob patterns (quoted,
+    so the shell leaves them alone), expanded anew each time so that files
+    showing up later get picked up. On linux changes are noticed through
+    inotify, elsewhere by checking the files every so often.
+*/
+
+use std::any::Any;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use args::Options;
+use analysis;
+use glob;
+use log;
+use metrics;
+use outfile;
+use report::Format;
+
+// how long to let a burst of writes settle before running again
+const SETTLE: Duration = Duration::from_millis(150);
+// files are checked at least this often, even without notifications
+const RESCAN: Duration = Duration::from_secs(2);
+// and this often where there are no notifications
+const POLL: Duration = Duration::from_millis(500);
+
+// size and modification time of every watched file, None if it's gone
+type Stamps = Vec<(String, Option<(u64, SystemTime)>)>;
+
+pub fn run(opts: &Options, format: Format, analyze: fn(&Options, Format) -> Vec<String>) -> ! {
+    let board = opts.metrics_addr.as_ref().map(|addr| metrics::listen(addr));
+    let mut notifier = notify::Notifier::new();
+    let mut last: Option<Stamps> = None;
+
+    loop {
+        let files = glob::expand(&opts.inputs, opts);
+        let stamps: Stamps = files.iter()
+            .map(|f| (f.clone(), fs::metadata(f).ok().and_then(|m| Some((m.len(), m.modified().ok()?)))))
+            .collect();
+
+        if last.as_ref() != Some(&stamps) {
+            let lines = if files.is_empty() {
+                vec![format!("  waiting for {} to show up", opts.inputs.join(", "))]
+            } else if let Some((f, e)) = files.iter().find_map(|f| fs::File::open(f).err().map(|e| (f, e))) {
+                // what usually goes wrong: a file caught halfway through
+                // being replaced, or not readable yet. shown in place of the
+                // results, not the end of watching
+                vec![format!("  Error: Could not open {}: {}, waiting for changes", f, e)]
+            } else {
+                if board.is_some() { analysis::keep_seen(); }
+                let current = Options { command: None, inputs: files.clone(), ..opts.clone() };
+                // anything else still gets reported as it happens, but
+                // doesn't end the watching either
+                match panic::catch_unwind(|| analyze(&current, format)) {
+                    Ok(lines) => lines,
+                    Err(payload) => vec![format!("  Error: {}, waiting for changes", message(payload))],
+                }
+            };
+            show(opts, &lines);
+            if let Some(ref b) = board {
+                let seen = analysis::take_seen();
+                let current = Options { command: None, inputs: files.clone(), ..opts.clone() };
+                for f in &files {
+                    let mut counted = seen.iter().filter(|(path, _, _)| path == f);
+                    match (counted.next(), counted.next()) {
+                        (Some((_, len, counts)), None) => b.update(f, *len, counts),
+                        // the modes with counting of their own, and --per-range,
+                        // don't leave one analysis of the file to go by
+                        _ => if let Ok(a) = analysis::analyze(f, &current, false) { b.update(f, a.len, &a.counts); },
+                    }
+                }
+                b.retain(&|source| files.iter().any(|f| f == source));
+            }
+            last = Some(stamps);
+        }
+
+        if let Some(ref mut n) = notifier {
+            for dir in dirs(&opts.inputs, &files) { n.watch(&dir); }
+            if n.wait(RESCAN) { thread::sleep(SETTLE); }
+        } else {
+            thread::sleep(POLL);
+        }
+    }
+}
+
+fn message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(s) => s.to_string(),
+        None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+    }
+}
+
+// the outfile is rewritten on every run, the terminal redrawn
+pub fn show(opts: &Options, lines: &[String]) {
+    let text: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+    if let Some(ref path) = opts.out_path {
+        if let Err(e) = outfile::write(path, text.as_bytes(), true) { log::warn(&format!("Could not write {}: {}", path, e)); }
+        return;
+    }
+    let mut out = io::stdout();
+    if out.is_terminal() { print!("\x1b[2J\x1b[H"); }
+    print!("{}", text);
+    let _ = out.flush();
+}
+
+// directories whose changes matter: those holding the files, and those
+// new matches could appear in
+fn dirs(inputs: &[String], files: &[String]) -> Vec<PathBuf> {
+    let parent = |path: &Path| match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut dirs: Vec<PathBuf> = files.iter().map(|f| parent(Path::new(f))).collect();
+    for input in inputs {
+        let path = Path::new(input);
+        dirs.push(if glob::is_pattern(input) {
+            glob::split(input).0
+        } else if path.is_dir() {
+            path.to_path_buf()
+        } else {
+            parent(path)
+        });
+    }
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+#[cfg(target_os = "linux")]
+mod notify {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::raw::{c_char, c_int, c_short, c_ulong};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::Path;
+    use std::time::Duration;
+
+    const IN_CLOEXEC: c_int = 0o2000000;
+    // modify, attrib, close_write, moved_from, moved_to, create, delete
+    const MASK: u32 = 0x002 | 0x004 | 0x008 | 0x040 | 0x080 | 0x100 | 0x200;
+    const POLLIN: c_short = 1;
+
+    #[repr(C)]
+    struct PollFd {
+        fd: c_int,
+        events: c_short,
+        revents: c_short,
+    }
+
+    extern "C" {
+        fn inotify_init1(flags: c_int) -> c_int;
+        fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+        fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    }
+
+    pub struct Notifier {
+        file: File,
+    }
+
+    impl Notifier {
+        pub fn new() -> Option<Notifier> {
+            // safe: no arguments to get wrong, and the fd is ours alone
+            let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+            if fd < 0 { return None; }
+            Some(Notifier { file: unsafe { File::from_raw_fd(fd) } })
+        }
+
+        // watching a directory twice is harmless, inotify keeps one watch
+        pub fn watch(&mut self, dir: &Path) {
+            if let Ok(path) = CString::new(dir.as_os_str().as_bytes()) {
+                unsafe { inotify_add_watch(self.file.as_raw_fd(), path.as_ptr(), MASK); }
+            }
+        }
+
+        // waits for changes, up to timeout. true if there were any
+        pub fn wait(&mut self, timeout: Duration) -> bool {
+            let mut fds = PollFd { fd: self.file.as_raw_fd(), events: POLLIN, revents: 0 };
+            let ready = unsafe { poll(&mut fds, 1, timeout.as_millis() as c_int) };
+            if ready <= 0 { return false; }
+            // what changed doesn't matter, the files are checked afterwards
+            let mut events = [0u8; 4096];
+            let _ = self.file.read(&mut events);
+            true
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod notify {
+    use std::path::Path;
+    use std::time::Duration;
+
+    pub struct Notifier;
+
+    impl Notifier {
+        pub fn new() -> Option<Notifier> { None }
+        pub fn watch(&mut self, _dir: &Path) {}
+        pub fn wait(&mut self, _timeout: Duration) -> bool { false }
+    }
+}
@@ -0,0 +1,195 @@
+/*
+    --audio: for wav files, counts the pcm samples rather than the bytes
+    they're stored in, one table per channel, along with the dc offset,
+    level and how many samples sit at full scale, for checking recordings.
+    8, 16, 24 and 32 bit integer pcm are understood. Samples are binned by
+    their top 8 bits, so every depth gets the same 256 bins, -128 to 127.
+*/
+
+use std::fs;
+use std::io::{self, BufReader, Read};
+
+use args::Options;
+use report::{json_str, sparkline, Format};
+
+const CHUNKSIZE: usize = 1024 * 128;
+
+struct Wav {
+    channels: usize,
+    rate: u32,
+    bits: u32,
+    data_len: u64,
+}
+
+struct Channel {
+    bins: [u64; 256],
+    samples: u64,
+    sum: f64,
+    squares: f64,
+    min: i64,
+    max: i64,
+    clipped: u64,
+}
+
+pub fn run(opts: &Options, format: Format) -> Vec<String> {
+    let path = &opts.inputs[0];
+    let f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(_) => panic!("Could not open file. Bad file or path?"),
+    };
+    let mut r = BufReader::new(f);
+    let wav = match header(&mut r) {
+        Ok(w) => w,
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    };
+    let channels = match count(&mut r, &wav) {
+        Ok(c) => c,
+        Err(e) => panic!("Could not read {}: {}", path, e),
+    };
+    render(format, &wav, &channels)
+}
+
+// reads up to the start of the samples
+fn header<R: Read>(r: &mut R) -> io::Result<Wav> {
+    let mut riff = [0u8; 12];
+    r.read_exact(&mut riff).map_err(|_| bad("isn't a wav file"))?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" { return Err(bad("isn't a wav file")); }
+    let mut fmt = None;
+    loop {
+        let mut chunk = [0u8; 8];
+        r.read_exact(&mut chunk).map_err(|_| bad("has no data chunk"))?;
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        match &chunk[..4] {
+            b"fmt " => {
+                let mut body = vec![0u8; len as usize];
+                r.read_exact(&mut body)?;
+                if len % 2 == 1 { r.read_exact(&mut [0u8])?; }
+                if body.len() < 16 { return Err(bad("has a short fmt chunk")); }
+                let tag = u16::from_le_bytes([body[0], body[1]]);
+                // extensible wav keeps the real format in its subformat guid
+                let pcm = tag == 1 || (tag == 0xfffe && body.len() >= 26 && body[24..26] == [1, 0]);
+                if !pcm { return Err(bad("isn't integer pcm")); }
+                let channels = u16::from_le_bytes([body[2], body[3]]) as usize;
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]) as u32;
+                if channels == 0 || ![8, 16, 24, 32].contains(&bits) {
+                    return Err(bad("has samples other than 8, 16, 24 or 32 bit"));
+                }
+                fmt = Some((channels, rate, bits));
+            },
+            b"data" => {
+                let (channels, rate, bits) = fmt.ok_or_else(|| bad("has its data before its fmt chunk"))?;
+                // streamed recordings leave the length unset
+                return Ok(Wav { channels, rate, bits, data_len: if len == 0xffffffff { u64::MAX } else { len } });
+            },
+            _ => { io::copy(&mut r.take(len + len % 2), &mut io::sink())?; },
+        }
+    }
+}
+
+fn count<R: Read>(r: &mut R, wav: &Wav) -> io::Result<Vec<Channel>> {
+    let width = (wav.bits / 8) as usize;
+    let (lowest, highest) = (-(1i64 << (wav.bits - 1)), (1i64 << (wav.bits - 1)) - 1);
+    let mut channels: Vec<Channel> = (0..wav.channels)
+        .map(|_| Channel { bins: [0; 256], samples: 0, sum: 0.0, squares: 0.0, min: i64::MAX, max: i64::MIN, clipped: 0 })
+        .collect();
+    let mut reader = r.take(wav.data_len);
+    let mut chunk = vec![0u8; CHUNKSIZE - CHUNKSIZE % width];
+    let (mut carry, mut at) = (Vec::new(), 0);
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if n == 0 { break; }
+        carry.extend_from_slice(&chunk[..n]);
+        let whole = carry.len() - carry.len() % width;
+        for sample in carry[..whole].chunks(width) {
+            let s = match width {
+                1 => sample[0] as i64 - 128,
+                2 => i16::from_le_bytes([sample[0], sample[1]]) as i64,
+                3 => (i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) >> 8) as i64,
+                _ => i32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]) as i64,
+            };
+            let c = &mut channels[at];
+            c.bins[((s >> (wav.bits - 8)) + 128) as usize] += 1;
+            c.samples += 1;
+            c.sum += s as f64;
+            c.squares += (s as f64).powi(2);
+            c.min = c.min.min(s);
+            c.max = c.max.max(s);
+            if s == lowest || s == highest { c.clipped += 1; }
+            at = (at + 1) % wav.channels;
+        }
+        carry.drain(..whole);
+    }
+    if channels[0].samples == 0 { return Err(bad("has no samples")); }
+    Ok(channels)
+}
+
+fn render(format: Format, wav: &Wav, channels: &[Channel]) -> Vec<String> {
+    let full = (1i64 << (wav.bits - 1)) as f64;
+    // dc offset as a share of full scale, and the level in dbfs
+    let dc = |c: &Channel| c.sum / c.samples as f64 / full * 100.0;
+    let rms = |c: &Channel| 20.0 * ((c.squares / c.samples as f64).sqrt() / full).log10();
+    let clipped = |c: &Channel| c.clipped as f64 / c.samples as f64 * 100.0;
+    match format {
+        Format::Csv => {
+            let mut lines = vec![String::from("channel,samples,min,max,dc_offset_percent,rms_dbfs,clipped,clipped_percent")];
+            for (i, c) in channels.iter().enumerate() {
+                lines.push(format!(
+                    "{},{},{},{},{:.6},{:.4},{},{:.6}",
+                    i + 1, c.samples, c.min, c.max, dc(c), rms(c), c.clipped, clipped(c)
+                ));
+            }
+            // then every channel's bins, separated by a blank line
+            lines.push(String::from(""));
+            lines.push(String::from("channel,bin,count"));
+            for (i, c) in channels.iter().enumerate() {
+                for (bin, n) in c.bins.iter().enumerate() {
+                    if *n != 0 { lines.push(format!("{},{},{}", i + 1, bin as i64 - 128, n)); }
+                }
+            }
+            lines
+        },
+        Format::Json => {
+            let entries: Vec<String> = channels.iter().enumerate()
+                .map(|(i, c)| {
+                    let bins: Vec<String> = c.bins.iter().enumerate()
+                        .filter(|&(_, n)| *n != 0)
+                        .map(|(bin, n)| format!("{{\"bin\": {}, \"count\": {}}}", bin as i64 - 128, n))
+                        .collect();
+                    format!(
+                        "    {{\"channel\": {}, \"samples\": {}, \"min\": {}, \"max\": {}, \"dc_offset_percent\": {:.6}, \"rms_dbfs\": {:.4}, \"clipped\": {}, \"clipped_percent\": {:.6}, \"bins\": [{}]}}",
+                        i + 1, c.samples, c.min, c.max, dc(c), rms(c), c.clipped, clipped(c), bins.join(", ")
+                    )
+                })
+                .collect();
+            vec![
+                String::from("{"),
+                format!("  \"format\": {},", json_str(&format!("{} bit pcm", wav.bits))),
+                format!("  \"sample_rate\": {},", wav.rate),
+                format!("  \"channels\": [\n{}\n  ]", entries.join(",\n")),
+                String::from("}"),
+            ]
+        },
+        _ => {
+            let samples = channels[0].samples;
+            let mut lines = vec![
+                format!(
+                    "  {} bit pcm, {} channels at {} hz, {:.2} seconds",
+                    wav.bits, wav.channels, wav.rate, samples as f64 / wav.rate.max(1) as f64
+                ),
+                format!("  {:>7}  {:>12}  {:>11}  {:>11}  {:>9}  {:>9}  {:>9}", "channel", "samples", "min", "max", "dc offset", "rms dbfs", "clipped"),
+            ];
+            for (i, c) in channels.iter().enumerate() {
+                lines.push(format!(
+                    "  {:>7}  {:>12}  {:>11}  {:>11}  {:>8.3}%  {:>9.2}  {:>8.3}%  {}",
+                    i + 1, c.samples, c.min, c.max, dc(c), rms(c), clipped(c), sparkline(&c.bins)
+                ));
+            }
+            lines
+        },
+    }
+}
+
+fn bad(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("it {}", what))
+}
@@ -0,0 +1,85 @@
+/*
+    Getting along with Windows consoles and paths. cmd and PowerShell
+    only show escape codes (watch clearing the screen) once a program asks
+    for them, they leave wildcards for the program to expand, and paths
+    over 260 characters, which deep trees get to, only work written the
+    long way, \\?\C:\... Elsewhere all of this does nothing.
+*/
+
+use std::path::{Path, PathBuf};
+
+use glob;
+
+#[cfg(windows)]
+pub fn init() {
+    use std::os::raw::c_void;
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(which: u32) -> *mut c_void;
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+    }
+    for which in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+        // safe: the handle is only used if it turns out to be a console
+        unsafe {
+            let console = GetStdHandle(which);
+            let mut mode = 0;
+            // not a console, but a file or a pipe
+            if console.is_null() || GetConsoleMode(console, &mut mode) == 0 { continue; }
+            SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn init() {}
+
+// the inputs with wildcards swapped for what they match, as a unix shell
+// would have. a pattern that matches nothing stays as it is, and fails
+// like any path that isn't there
+pub fn expand(inputs: &[String]) -> Vec<String> {
+    if !cfg!(windows) { return inputs.to_vec(); }
+    let mut expanded = Vec::new();
+    for input in inputs {
+        let found = if glob::is_pattern(input) { glob::matching(&input.replace('\\', "/")) } else { Vec::new() };
+        if found.is_empty() { expanded.push(input.clone()); } else { expanded.extend(found); }
+    }
+    expanded
+}
+
+// the path to open, written the long way when it's too long for the
+// usual one. that way round nothing is taken apart any more, so it's made
+// absolute and the . and .. resolved first. \\server\share paths are left
+// alone
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::env;
+    use std::path::Component;
+
+    const MAX_PATH: usize = 260;
+    let text = path.to_string_lossy();
+    if text.len() < MAX_PATH || text.starts_with(r"\\") { return path.to_path_buf(); }
+    let abs = match env::current_dir() {
+        Ok(dir) if !path.is_absolute() => dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    let mut resolved = PathBuf::new();
+    for c in abs.components() {
+        match c {
+            Component::CurDir => {},
+            Component::ParentDir => { resolved.pop(); },
+            c => resolved.push(c.as_os_str()),
+        }
+    }
+    PathBuf::from(format!(r"\\?\{}", resolved.display()))
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}